@@ -0,0 +1,36 @@
+pub fn ident(input: &[u8]) -> bool {
+    let mut state: u32 = 0;
+    for &byte in input {
+        state = match state {
+            0 => match byte {
+                b'0' => 1,
+                b'a'..=b'z' => 2,
+                _ => return false,
+            },
+            1 => match byte {
+                b'X' => 3,
+                b'x' => 3,
+                _ => return false,
+            },
+            2 => match byte {
+                b'_' => 4,
+                b'a'..=b'z' => 2,
+                _ => return false,
+            },
+            3 => match byte {
+                b'0'..=b'9' => 5,
+                _ => return false,
+            },
+            4 => match byte {
+                b'a'..=b'z' => 2,
+                _ => return false,
+            },
+            5 => match byte {
+                b'0'..=b'9' => 5,
+                _ => return false,
+            },
+            _ => return false,
+        };
+    }
+    matches!(state, 2 | 4 | 5)
+}
@@ -0,0 +1,21 @@
+pub fn lex(input: &[u8]) -> bool {
+    let mut state: u32 = 0;
+    for &byte in input {
+        state = match state {
+            0 => match byte {
+                b'0'..=b'9' => 2,
+                b'a' => 1,
+                _ => return false,
+            },
+            1 => match byte {
+                0x0a => 1,
+                _ => 0,
+            },
+            2 => match byte {
+                0x00..=0xff => 2,
+            },
+            _ => return false,
+        };
+    }
+    matches!(state, 1 | 2)
+}
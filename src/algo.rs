@@ -1,94 +1,1818 @@
 use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
-use crate::nfa::NFAutoBlueprint;
-use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::nfa::{
+    next_state_set, ConnType, NFAutoBlueprint, NFAutoBuilder, Predicate, StateLimitExceeded,
+};
+use crate::range::{cmp_cut, Cut, TransRange};
+use crate::re::Re;
+use crate::view::{MapView, SetView};
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
 use std::hash::Hash;
 
-pub fn determinize<S, T>(nfa: &NFAutoBlueprint<S, T>) -> DFAutoBlueprint<BTreeSet<S>, T>
+/// DFA over the sets of NFA states reachable by the same input.
+///
+/// Fails on predicate connections, which need `determinize_over`.
+pub fn determinize<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+) -> Result<DFAutoBlueprint<BTreeSet<S>, T>, PredicateWithoutAlphabet>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    determinize_with(nfa, DeterminizeOptions::default())
+}
+
+/// Options of `determinize_with`. The default options build the same DFA as
+/// `determinize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeterminizeOptions {
+    /// Leave NFA states from which no accept state is reachable out of the
+    /// state sets, so that state sets made only of them are not built. The
+    /// empty state set is still built when it overrides a fallback.
+    pub elide_dead: bool,
+    /// Give every DFA state a fallback, to the empty state set when it has
+    /// none, so that every transition is taken from every state.
+    pub complete: bool,
+}
+
+/// Same as `determinize`, tuned by `options`.
+pub fn determinize_with<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    options: DeterminizeOptions,
+) -> Result<DFAutoBlueprint<BTreeSet<S>, T>, PredicateWithoutAlphabet>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    check_no_predicate(nfa)?;
+    let (dfa, state_set_list, _) =
+        subset_construction(nfa, &[], usize::MAX, options).unwrap_or_else(|_| unreachable!());
+    Ok(with_state_sets(&dfa, &state_set_list))
+}
+
+/// The NFA has predicate connections, which need `determinize_over`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PredicateWithoutAlphabet;
+
+impl fmt::Display for PredicateWithoutAlphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "predicate connection without alphabet")
+    }
+}
+
+impl Error for PredicateWithoutAlphabet {}
+
+/// Reason of a failed `determinize_bounded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeterminizeError {
+    StateLimitExceeded(StateLimitExceeded),
+    PredicateWithoutAlphabet(PredicateWithoutAlphabet),
+}
+
+impl From<StateLimitExceeded> for DeterminizeError {
+    fn from(err: StateLimitExceeded) -> Self {
+        DeterminizeError::StateLimitExceeded(err)
+    }
+}
+
+impl From<PredicateWithoutAlphabet> for DeterminizeError {
+    fn from(err: PredicateWithoutAlphabet) -> Self {
+        DeterminizeError::PredicateWithoutAlphabet(err)
+    }
+}
+
+impl fmt::Display for DeterminizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeterminizeError::StateLimitExceeded(err) => err.fmt(f),
+            DeterminizeError::PredicateWithoutAlphabet(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for DeterminizeError {}
+
+/// Same as `determinize`, giving up once more than `max_states` DFA states
+/// are found.
+pub fn determinize_bounded<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    max_states: usize,
+) -> Result<DFAutoBlueprint<BTreeSet<S>, T>, DeterminizeError>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    check_no_predicate(nfa)?;
+    let (dfa, state_set_list, _) =
+        subset_construction(nfa, &[], max_states, DeterminizeOptions::default())?;
+    Ok(with_state_sets(&dfa, &state_set_list))
+}
+
+// fail if `nfa` has predicate connections needing an alphabet to expand
+fn check_no_predicate<S, T>(nfa: &NFAutoBlueprint<S, T>) -> Result<(), PredicateWithoutAlphabet>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    let has_predicate = nfa.iterate_connections().any(|(_, conn, _)| match conn {
+        ConnType::Predicate(pred) => pred.as_except().is_none(),
+        _ => false,
+    });
+    if has_predicate {
+        Err(PredicateWithoutAlphabet)
+    } else {
+        Ok(())
+    }
+}
+
+// the DFA with renumbered states, and the NFA states making up each of them
+type RenumberedDeterminization<S, T> = (DFAutoBlueprint<u64, T>, Vec<BTreeSet<S>>);
+
+/// Same as `determinize`, with states numbered from 0 in breadth-first order
+/// from the start state as in `DFAutoBlueprint::renumbered`. Item `i` of the
+/// returned list is the set of NFA states making up DFA state `i`.
+pub fn determinize_renumbered<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+) -> Result<RenumberedDeterminization<S, T>, PredicateWithoutAlphabet>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    check_no_predicate(nfa)?;
+    let (dfa, state_set_list, _) =
+        subset_construction(nfa, &[], usize::MAX, DeterminizeOptions::default())
+            .unwrap_or_else(|_| unreachable!());
+    // numbered as the blueprint over state sets would be
+    let (renumbered, id_list) = dfa.renumber_by(|id| &state_set_list[*id as usize]);
+    let renumbered_state_set_list = id_list
+        .into_iter()
+        .map(|id| state_set_list[*id as usize].clone())
+        .collect();
+    Ok((renumbered, renumbered_state_set_list))
+}
+
+/// Same as `determinize`, expanding predicate connections into plain
+/// transitions for the symbols of `alphabet` passing the predicate.
+pub fn determinize_over<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    alphabet: &[T],
+) -> DFAutoBlueprint<BTreeSet<S>, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    let (dfa, state_set_list, _) =
+        subset_construction(nfa, alphabet, usize::MAX, DeterminizeOptions::default())
+            .unwrap_or_else(|_| unreachable!());
+    with_state_sets(&dfa, &state_set_list)
+}
+
+// the DFA over ids of state sets, the state set of every id, and the number of
+// state sets expanded to build it
+type SubsetConstruction<S, T> = (DFAutoBlueprint<u64, T>, Vec<BTreeSet<S>>, usize);
+
+fn subset_construction<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    alphabet: &[T],
+    max_states: usize,
+    options: DeterminizeOptions,
+) -> Result<SubsetConstruction<S, T>, StateLimitExceeded>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    let live_state_set = if options.elide_dead {
+        Some(live_state_set(nfa))
+    } else {
+        None
+    };
+    let resolve = |state_set: HashSet<S>| -> BTreeSet<S> {
+        state_set
+            .into_iter()
+            .filter(|state| {
+                live_state_set
+                    .as_ref()
+                    .is_none_or(|live_state_set| live_state_set.contains(state))
+            })
+            .collect()
+    };
+    // state sets get their id when first found, so that each one is expanded
+    // once even if found again before its expansion
+    let mut interner = StateSetInterner::default();
+    let mut unresolved_id_list = Vec::new();
+    let start_id = interner.intern(
+        resolve(extend_state_set(nfa, nfa.start_state_set())),
+        &mut unresolved_id_list,
+    );
+    let mut builder = DFAutoBuilder::start(start_id);
+    let mut expansion_count = 0;
+    while let Some(from_id) = unresolved_id_list.pop() {
+        expansion_count += 1;
+        let state_set = interner.state_set_list[from_id as usize].clone();
+        let mut aggregated_connections: HashMap<_, HashSet<_>> = HashMap::new();
+        let mut aggregated_wildcard_connections = HashSet::new();
+        let mut aggregated_range_connections = Vec::new();
+        let mut aggregated_except_connections = Vec::new();
+        for state in state_set.iter() {
+            if nfa.accept_state_set().contains(state) {
+                builder = builder.accept(from_id);
+            }
+            for tag in nfa.accept_tags(state).iter() {
+                builder = builder.accept_with(from_id, *tag);
+            }
+
+            let connections = nfa.connections_from(state);
+            for (trans, to_set) in connections.plain.iter() {
+                if !aggregated_connections.contains_key(trans) {
+                    aggregated_connections.insert(trans.clone(), HashSet::new());
+                }
+                aggregated_connections
+                    .get_mut(trans)
+                    .unwrap()
+                    .extend(extend_state_set(nfa, to_set));
+            }
+            for (pred, to) in connections.predicate {
+                // wildcards except on transitions and ranges need no alphabet
+                if let Some((excluded, range_list)) = pred.as_except() {
+                    aggregated_except_connections.push((
+                        excluded,
+                        range_list,
+                        extend_state_set(nfa, vec![to]),
+                    ));
+                    continue;
+                }
+                for trans in alphabet.iter().filter(|trans| pred.test(trans)) {
+                    aggregated_connections
+                        .entry(trans.clone())
+                        .or_default()
+                        .extend(extend_state_set(nfa, vec![to]));
+                }
+            }
+            for (range, to) in connections.range {
+                aggregated_range_connections.push((range, extend_state_set(nfa, vec![to])));
+            }
+            if !connections.wildcard.is_empty() {
+                aggregated_wildcard_connections
+                    .extend(extend_state_set(nfa, connections.wildcard.iter()));
+            }
+            for (to, excluded) in connections.wildcard_except.iter() {
+                aggregated_except_connections.push((
+                    excluded,
+                    &[],
+                    extend_state_set(nfa, vec![to]),
+                ));
+            }
+        }
+        // excluded transitions leave the fallback, so they need their own
+        // connections, and excluded ranges their own range connections
+        for (excluded, range_list, _) in aggregated_except_connections.iter() {
+            for range in range_list.iter() {
+                aggregated_range_connections.push((range, HashSet::new()));
+            }
+            for trans in excluded.iter() {
+                if !aggregated_connections.contains_key(trans) {
+                    aggregated_connections
+                        .insert(trans.clone(), aggregated_wildcard_connections.clone());
+                }
+            }
+        }
+        for (trans, to_set) in aggregated_connections.iter_mut() {
+            for (excluded, range_list, except_to_set) in aggregated_except_connections.iter() {
+                if !excluded.contains(trans)
+                    && !range_list.iter().any(|range| range.contains(trans))
+                {
+                    to_set.extend(except_to_set.iter().cloned());
+                }
+            }
+        }
+        // wildcards are taken on every transition, not only on those falling
+        // back
+        for to_set in aggregated_connections.values_mut() {
+            to_set.extend(aggregated_wildcard_connections.iter().cloned());
+        }
+        let mut fallback_connections = aggregated_wildcard_connections.clone();
+        for (_, _, except_to_set) in aggregated_except_connections.iter() {
+            fallback_connections.extend(except_to_set.iter().cloned());
+        }
+        for (trans, to_set) in aggregated_connections.iter_mut() {
+            for (range, range_to_set) in aggregated_range_connections.iter() {
+                if range.contains(trans) {
+                    to_set.extend(range_to_set.iter().cloned());
+                }
+            }
+        }
+        let range_connections =
+            split_range_list(&aggregated_range_connections, &aggregated_connections);
+        let wildcard_to = resolve(fallback_connections);
+        // an empty target is only needed to override the fallback
+        for (range, mut to_hashset) in range_connections {
+            to_hashset.extend(aggregated_wildcard_connections.iter().cloned());
+            // split ranges lie either inside or outside of every excluded
+            // range
+            for (_, range_list, except_to_set) in aggregated_except_connections.iter() {
+                if !range_list.iter().any(|excluded| excluded.overlaps(&range)) {
+                    to_hashset.extend(except_to_set.iter().cloned());
+                }
+            }
+            let to_btreeset = resolve(to_hashset);
+            if to_btreeset.is_empty() && wildcard_to.is_empty() {
+                continue;
+            }
+            let to_id = interner.intern(to_btreeset, &mut unresolved_id_list);
+            builder = builder.connect_trans_range(from_id, range, to_id);
+        }
+        for (trans, to_hashset) in aggregated_connections {
+            let to_btreeset = resolve(to_hashset);
+            if to_btreeset.is_empty() && wildcard_to.is_empty() {
+                continue;
+            }
+            let to_id = interner.intern(to_btreeset, &mut unresolved_id_list);
+            builder = builder.connect(from_id, trans, to_id);
+        }
+        if !wildcard_to.is_empty() || options.complete {
+            let to_id = interner.intern(wildcard_to, &mut unresolved_id_list);
+            builder = builder.connect_fallback(from_id, to_id);
+        }
+        if interner.state_set_list.len() > max_states {
+            return Err(StateLimitExceeded {
+                size: interner.state_set_list.len(),
+            });
+        }
+    }
+    Ok((builder.finalize(), interner.state_set_list, expansion_count))
+}
+
+// ids given to state sets in the order they are found
+struct StateSetInterner<S> {
+    id_map: HashMap<BTreeSet<S>, u64>,
+    state_set_list: Vec<BTreeSet<S>>,
+}
+
+impl<S> Default for StateSetInterner<S> {
+    fn default() -> Self {
+        Self {
+            id_map: HashMap::new(),
+            state_set_list: Vec::new(),
+        }
+    }
+}
+
+impl<S> StateSetInterner<S>
 where
     S: Hash + Eq + Ord + Clone,
+{
+    // the id of `state_set`, pushed to `unresolved_id_list` if new
+    fn intern(&mut self, state_set: BTreeSet<S>, unresolved_id_list: &mut Vec<u64>) -> u64 {
+        if let Some(id) = self.id_map.get(&state_set) {
+            return *id;
+        }
+        let id = self.state_set_list.len() as u64;
+        self.id_map.insert(state_set.clone(), id);
+        self.state_set_list.push(state_set);
+        unresolved_id_list.push(id);
+        id
+    }
+}
+
+// the DFA with every id replaced by its state set
+fn with_state_sets<S, T>(
+    dfa: &DFAutoBlueprint<u64, T>,
+    state_set_list: &[BTreeSet<S>],
+) -> DFAutoBlueprint<BTreeSet<S>, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    let state_set = |id: &u64| state_set_list[*id as usize].clone();
+    let mut builder = DFAutoBuilder::start(state_set(dfa.start_state()));
+    for (id, _) in state_set_list.iter().enumerate() {
+        let id = id as u64;
+        if dfa.accept_state_set().contains(&id) {
+            builder = builder.accept(state_set(&id));
+        }
+        for tag in dfa.accept_tags(&id).iter() {
+            builder = builder.accept_with(state_set(&id), *tag);
+        }
+        let connections = dfa.connections_from(&id);
+        for (trans, to) in connections.plain.iter() {
+            builder = builder.connect(state_set(&id), trans.clone(), state_set(to));
+        }
+        for (range, to) in connections.range {
+            builder = builder.connect_trans_range(state_set(&id), range.clone(), state_set(to));
+        }
+        if let Some(to) = connections.fallback {
+            builder = builder.connect_fallback(state_set(&id), state_set(to));
+        }
+    }
+    builder.finalize()
+}
+
+// states from which an accept state is reachable
+fn live_state_set<S, T>(nfa: &NFAutoBlueprint<S, T>) -> HashSet<S>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    let mut reverse_graph: HashMap<_, Vec<_>> = HashMap::new();
+    for (from, _, to) in nfa.iterate_connections() {
+        reverse_graph.entry(to).or_default().push(from);
+    }
+    let mut live_state_set = HashSet::new();
+    let mut unresolved: Vec<_> = nfa.accept_state_set().iter().collect();
+    while let Some(state) = unresolved.pop() {
+        if live_state_set.insert(state.clone()) {
+            if let Some(from_list) = reverse_graph.get(state) {
+                unresolved.extend(from_list.iter().copied());
+            }
+        }
+    }
+    live_state_set
+}
+
+/// Build the DFA accepting exactly the strings over `alphabet` that `dfa`
+/// rejects. States are renumbered from 0 for the start state, and missing
+/// transitions of `dfa` lead to a rejecting sink. Transitions outside
+/// `alphabet` are not accepted by either.
+pub fn complement<S, T>(dfa: &DFAutoBlueprint<S, T>, alphabet: &[T]) -> DFAutoBlueprint<u64, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    let mut number_map: HashMap<Option<&S>, u64> = HashMap::new();
+    number_map.insert(Some(dfa.start_state()), 0);
+    let mut unresolved = vec![Some(dfa.start_state())];
+    let mut builder = DFAutoBuilder::start(0);
+    while let Some(state) = unresolved.pop() {
+        let from = number_map[&state];
+        if !state.is_some_and(|state| dfa.accept_state_set().contains(state)) {
+            builder = builder.accept(from);
+        }
+        for trans in alphabet {
+            let next = state.and_then(|state| dfa.next_state(state, trans));
+            let count = number_map.len() as u64;
+            let to = *number_map.entry(next).or_insert_with(|| {
+                unresolved.push(next);
+                count
+            });
+            builder = builder.connect(from, trans.clone(), to);
+        }
+    }
+    builder.finalize()
+}
+
+/// Check that `nfa` and `dfa` accept the same inputs over `alphabet`, by
+/// running them side by side on every input in breadth-first order until a
+/// pair of state set and state repeats. Returns the shortest input accepted
+/// by only one of them otherwise.
+pub fn nfa_equivalent_dfa<S1, S2, T>(
+    nfa: &NFAutoBlueprint<S1, T>,
+    dfa: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> Result<(), Vec<T>>
+where
+    S1: Hash + Eq + Ord + Clone,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    let start_state_set: BTreeSet<_> = extend_state_set(nfa, nfa.start_state_set())
+        .into_iter()
+        .collect();
+    let start = (start_state_set, Some(dfa.start_state()));
+    let mut parent_map: HashMap<_, Option<(_, &T)>> = HashMap::new();
+    parent_map.insert(start.clone(), None);
+    let mut unresolved = VecDeque::new();
+    unresolved.push_back(start);
+    while let Some(pair) = unresolved.pop_front() {
+        let (state_set, state) = &pair;
+        let nfa_accepted = state_set
+            .iter()
+            .any(|state| nfa.accept_state_set().contains(state));
+        let dfa_accepted = state.is_some_and(|state| dfa.accept_state_set().contains(state));
+        if nfa_accepted != dfa_accepted {
+            let mut witness = Vec::new();
+            let mut pair = &pair;
+            while let Some((parent, trans)) = &parent_map[pair] {
+                witness.push((*trans).clone());
+                pair = parent;
+            }
+            witness.reverse();
+            return Err(witness);
+        }
+        for trans in alphabet {
+            let next = (
+                next_state_set(nfa, state_set, trans).into_iter().collect(),
+                state.and_then(|state| dfa.next_state(state, trans)),
+            );
+            if let Entry::Vacant(entry) = parent_map.entry(next.clone()) {
+                entry.insert(Some((pair.clone(), trans)));
+                unresolved.push_back(next);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Number of inputs over `alphabet` accepted by `dfa` for each length from 0
+/// to `max_len`, counted without listing the inputs. Counts too large for a
+/// `u128` saturate at `u128::MAX`.
+pub fn count_by_length<S, T>(
+    dfa: &DFAutoBlueprint<S, T>,
+    alphabet: &[T],
+    max_len: usize,
+) -> Vec<u128>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    // states reachable on `alphabet`, numbered in the order they are found
+    let mut index_map = HashMap::new();
+    index_map.insert(dfa.start_state(), 0);
+    let mut state_list = vec![dfa.start_state()];
+    // number of transitions of `alphabet` between each pair of states
+    let mut count_map: HashMap<(usize, usize), u128> = HashMap::new();
+    let mut index = 0;
+    while index < state_list.len() {
+        let state = state_list[index];
+        for trans in alphabet {
+            if let Some(to) = dfa.next_state(state, trans) {
+                let to_index = *index_map.entry(to).or_insert_with(|| {
+                    state_list.push(to);
+                    state_list.len() - 1
+                });
+                *count_map.entry((index, to_index)).or_default() += 1;
+            }
+        }
+        index += 1;
+    }
+
+    let mut count_list = Vec::new();
+    let mut path_count_list = vec![0u128; state_list.len()];
+    path_count_list[0] = 1;
+    for len in 0..=max_len {
+        count_list.push(
+            state_list
+                .iter()
+                .zip(path_count_list.iter())
+                .filter(|(state, _)| dfa.accept_state_set().contains(state))
+                .fold(0u128, |total, (_, count)| total.saturating_add(*count)),
+        );
+        if len == max_len {
+            break;
+        }
+        let mut next_path_count_list = vec![0u128; state_list.len()];
+        for ((from, to), count) in count_map.iter() {
+            next_path_count_list[*to] = next_path_count_list[*to]
+                .saturating_add(path_count_list[*from].saturating_mul(*count));
+        }
+        path_count_list = next_path_count_list;
+    }
+    count_list
+}
+
+/// Transitions of an alphabet a DFA has no connection for, found by
+/// `validate_total`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotalityReport<S, T> {
+    /// Reachable states with a transition of the alphabet taking neither a
+    /// plain, range nor fallback connection.
+    pub missing: Vec<(S, T)>,
+    /// Reachable states with the number of transitions of the alphabet taking
+    /// their fallback, for states with a fallback.
+    pub fallback_count: Vec<(S, usize)>,
+}
+
+impl<S, T> TotalityReport<S, T> {
+    pub fn is_total(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// States taking their fallback on more than `max_count` transitions of
+    /// the alphabet.
+    pub fn fallback_heavy(&self, max_count: usize) -> impl Iterator<Item = &S> {
+        self.fallback_count
+            .iter()
+            .filter(move |(_, count)| *count > max_count)
+            .map(|(state, _)| state)
+    }
+}
+
+impl<S, T> fmt::Display for TotalityReport<S, T>
+where
+    S: fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_total() {
+            return write!(f, "every transition is connected");
+        }
+        for (index, (state, trans)) in self.missing.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "no connection on {:?} from {:?}", trans, state)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that every state of `dfa` reachable on `alphabet` has a connection
+/// for every transition of `alphabet`, listing states and transitions in the
+/// order they are found.
+pub fn validate_total<S, T>(dfa: &DFAutoBlueprint<S, T>, alphabet: &[T]) -> TotalityReport<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut seen: HashSet<_> = vec![dfa.start_state()].into_iter().collect();
+    let mut unresolved = VecDeque::new();
+    unresolved.push_back(dfa.start_state());
+    let mut missing = Vec::new();
+    let mut fallback_count = Vec::new();
+    while let Some(state) = unresolved.pop_front() {
+        let connections = dfa.connections_from(state);
+        let mut count = 0;
+        for trans in alphabet {
+            let to = if let Some(to) = connections.plain.get(trans) {
+                to
+            } else if let Some((_, to)) = connections
+                .range
+                .iter()
+                .find(|(range, _)| range.contains(trans))
+            {
+                to
+            } else if let Some(to) = connections.fallback {
+                count += 1;
+                to
+            } else {
+                missing.push((state.clone(), trans.clone()));
+                continue;
+            };
+            if seen.insert(to) {
+                unresolved.push_back(to);
+            }
+        }
+        if connections.fallback.is_some() {
+            fallback_count.push((state.clone(), count));
+        }
+    }
+    TotalityReport {
+        missing,
+        fallback_count,
+    }
+}
+
+/// No common input was found by `intersects_checked`, but both blueprints
+/// can move together on transitions that were not tried, such as a wildcard
+/// of the NFA meeting a fallback of the DFA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UntriedTransitions;
+
+impl fmt::Display for UntriedTransitions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "languages may overlap on transitions not tried")
+    }
+}
+
+impl Error for UntriedTransitions {}
+
+/// Shortest input accepted by both `nfa` and `dfa`, or `None` if none is
+/// found. Only transitions appearing in either of them are tried, that is
+/// plain transitions and range bounds, so wildcards of the NFA only advance
+/// on transitions the DFA has a connection or a fallback for, and the
+/// languages may still overlap on other transitions. See
+/// `intersects_checked` to tell these cases apart.
+pub fn intersects<S1, S2, T>(
+    nfa: &NFAutoBlueprint<S1, T>,
+    dfa: &DFAutoBlueprint<S2, T>,
+) -> Option<Vec<T>>
+where
+    S1: Hash + Eq + Ord + Clone,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    intersects_checked(nfa, dfa).unwrap_or(None)
+}
+
+/// Same as `intersects`, failing instead of returning `None` when the
+/// languages could overlap on transitions that were not tried, so that `None`
+/// means they are disjoint.
+pub fn intersects_checked<S1, S2, T>(
+    nfa: &NFAutoBlueprint<S1, T>,
+    dfa: &DFAutoBlueprint<S2, T>,
+) -> Result<Option<Vec<T>>, UntriedTransitions>
+where
+    S1: Hash + Eq + Ord + Clone,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    let mut untried_move = false;
+    let start_state_set: BTreeSet<_> = extend_state_set(nfa, nfa.start_state_set())
+        .into_iter()
+        .collect();
+    let start = (start_state_set, dfa.start_state());
+    let mut parent_map: HashMap<_, Option<(_, &T)>> = HashMap::new();
+    parent_map.insert(start.clone(), None);
+    let mut unresolved = VecDeque::new();
+    unresolved.push_back(start);
+    while let Some(pair) = unresolved.pop_front() {
+        let (state_set, state) = &pair;
+        if dfa.accept_state_set().contains(state)
+            && state_set
+                .iter()
+                .any(|state| nfa.accept_state_set().contains(state))
+        {
+            let mut witness = Vec::new();
+            let mut pair = &pair;
+            while let Some((parent, trans)) = &parent_map[pair] {
+                witness.push((*trans).clone());
+                pair = parent;
+            }
+            witness.reverse();
+            return Ok(Some(witness));
+        }
+        let connections = dfa.connections_from(state);
+        // transitions not tried move the DFA through fallbacks and ranges, and
+        // the NFA through any connection but plain ones
+        let dfa_untried = connections.fallback.is_some() || !connections.range.is_empty();
+        let nfa_untried = state_set.iter().any(|state| {
+            let connections = nfa.connections_from(state);
+            !connections.wildcard.is_empty()
+                || !connections.wildcard_except.is_empty()
+                || !connections.range.is_empty()
+                || !connections.predicate.is_empty()
+        });
+        untried_move |= dfa_untried && nfa_untried;
+        let mut trans_list: Vec<_> = connections.plain.iter().map(|(trans, _)| trans).collect();
+        for range in connections.range.iter().map(|(range, _)| range) {
+            trans_list.extend(
+                vec![range.start(), range.end()]
+                    .into_iter()
+                    .filter(|trans| range.contains(trans)),
+            );
+        }
+        for state in state_set.iter() {
+            let connections = nfa.connections_from(state);
+            trans_list.extend(connections.plain.iter().map(|(trans, _)| trans));
+            for (range, _) in connections.range {
+                trans_list.extend(
+                    vec![range.start(), range.end()]
+                        .into_iter()
+                        .filter(|trans| range.contains(trans)),
+                );
+            }
+        }
+        let mut tried_set = HashSet::new();
+        for trans in trans_list {
+            if !tried_set.insert(trans) {
+                continue;
+            }
+            let next_state_set: BTreeSet<_> =
+                next_state_set(nfa, state_set, trans).into_iter().collect();
+            let next_state = match dfa.next_state(state, trans) {
+                Some(next_state) if !next_state_set.is_empty() => next_state,
+                _ => continue,
+            };
+            let next = (next_state_set, next_state);
+            if let Entry::Vacant(entry) = parent_map.entry(next.clone()) {
+                entry.insert(Some((pair.clone(), trans)));
+                unresolved.push_back(next);
+            }
+        }
+    }
+    if untried_move {
+        Err(UntriedTransitions)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Shortest input with two different accepting runs through `nfa`, or `None`
+/// if none is found. Runs differing only in their void connections count as
+/// the same run. Only transitions appearing in `nfa` are tried, that is plain
+/// transitions, range bounds and the transitions excluded by wildcards, on
+/// which wildcards are paired with every other connection. Runs only told
+/// apart on other transitions have no transition to show in the input, and
+/// are not reported.
+pub fn is_ambiguous<S, T>(nfa: &NFAutoBlueprint<S, T>) -> Option<Vec<T>>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let nfa = nfa.remove_void();
+    let mut trans_list = Vec::new();
+    for (_, conn, _) in nfa.iterate_connections() {
+        match conn {
+            ConnType::Plain(trans) => trans_list.push(trans),
+            ConnType::Range(range) => trans_list.extend(
+                vec![range.start(), range.end()]
+                    .into_iter()
+                    .filter(|trans| range.contains(trans)),
+            ),
+            ConnType::WildcardExcept(excluded) => trans_list.extend(excluded.iter()),
+            _ => {}
+        }
+    }
+    let mut tried_set = HashSet::new();
+    trans_list.retain(|trans| tried_set.insert(*trans));
+
+    // pairs of states reached by the same input, with whether the two runs
+    // have parted
+    let mut parent_map: HashMap<_, Option<(_, &T)>> = HashMap::new();
+    let mut unresolved = VecDeque::new();
+    for first in nfa.start_state_set() {
+        for second in nfa.start_state_set() {
+            let start = (first.clone(), second.clone(), first != second);
+            if let Entry::Vacant(entry) = parent_map.entry(start.clone()) {
+                entry.insert(None);
+                unresolved.push_back(start);
+            }
+        }
+    }
+    while let Some(triple) = unresolved.pop_front() {
+        let (first, second, parted) = &triple;
+        if *parted
+            && nfa.accept_state_set().contains(first)
+            && nfa.accept_state_set().contains(second)
+        {
+            let mut witness = Vec::new();
+            let mut triple = &triple;
+            while let Some((parent, trans)) = &parent_map[triple] {
+                witness.push((*trans).clone());
+                triple = parent;
+            }
+            witness.reverse();
+            return Some(witness);
+        }
+        for trans in trans_list.iter().copied() {
+            let first_to_set = next_state_set(&nfa, vec![first], trans);
+            let second_to_set = next_state_set(&nfa, vec![second], trans);
+            for first_to in first_to_set.iter() {
+                for second_to in second_to_set.iter() {
+                    let next = (
+                        first_to.clone(),
+                        second_to.clone(),
+                        *parted || first_to != second_to,
+                    );
+                    if let Entry::Vacant(entry) = parent_map.entry(next.clone()) {
+                        entry.insert(Some((triple.clone(), trans)));
+                        unresolved.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// DFA running `first` and `second` side by side on the transitions of
+/// `alphabet`, and accepting when `accept` holds for whether each of them
+/// accepts. Only pairs reachable from the start states are built, `None`
+/// standing for a DFA without transition to take.
+pub fn product<S1, S2, T, F>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+    accept: F,
+) -> DFAutoBlueprint<(Option<S1>, Option<S2>), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+    F: Fn(bool, bool) -> bool,
+{
+    let start = (Some(first.start_state()), Some(second.start_state()));
+    let owned = |(state1, state2): (Option<&S1>, Option<&S2>)| (state1.cloned(), state2.cloned());
+    let mut builder = DFAutoBuilder::start(owned(start));
+    let mut seen = HashSet::new();
+    seen.insert(start);
+    let mut unresolved = vec![start];
+    while let Some(pair) = unresolved.pop() {
+        let (state1, state2) = pair;
+        let accepted1 = state1.is_some_and(|state| first.accept_state_set().contains(state));
+        let accepted2 = state2.is_some_and(|state| second.accept_state_set().contains(state));
+        if accept(accepted1, accepted2) {
+            builder = builder.accept(owned(pair));
+        }
+        for trans in alphabet {
+            let next = (
+                state1.and_then(|state| first.next_state(state, trans)),
+                state2.and_then(|state| second.next_state(state, trans)),
+            );
+            builder = builder.connect(owned(pair), trans.clone(), owned(next));
+            if seen.insert(next) {
+                unresolved.push(next);
+            }
+        }
+    }
+    builder.finalize()
+}
+
+/// DFA accepting the inputs over `alphabet` accepted by both DFAs.
+pub fn dfa_intersect<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> DFAutoBlueprint<(Option<S1>, Option<S2>), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    product(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 && accepted2
+    })
+}
+
+/// DFA accepting the inputs over `alphabet` accepted by either DFA.
+pub fn dfa_union<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> DFAutoBlueprint<(Option<S1>, Option<S2>), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    product(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 || accepted2
+    })
+}
+
+/// DFA accepting the inputs over `alphabet` accepted by `first` but not by
+/// `second`.
+pub fn dfa_difference<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> DFAutoBlueprint<(Option<S1>, Option<S2>), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    product(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 && !accepted2
+    })
+}
+
+/// DFA accepting the inputs over `alphabet` accepted by exactly one DFA.
+pub fn dfa_symmetric_difference<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> DFAutoBlueprint<(Option<S1>, Option<S2>), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    product(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 != accepted2
+    })
+}
+
+/// Shortest input over `alphabet` accepted by exactly one of the DFAs, or
+/// `None` if they agree on every input over `alphabet`.
+pub fn dfa_difference_witness<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> Option<Vec<T>>
+where
+    S1: Hash + Eq,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    product_witness(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 != accepted2
+    })
+}
+
+/// Shortest input over `alphabet` accepted by `second` but not by `first`, or
+/// `None` if `first` accepts every input over `alphabet` that `second` accepts.
+pub fn dfa_inclusion_witness<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> Option<Vec<T>>
+where
+    S1: Hash + Eq,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    product_witness(first, second, alphabet, |accepted1, accepted2| {
+        !accepted1 && accepted2
+    })
+}
+
+/// Shortest input over `alphabet` accepted by `first` but not by `second`, or
+/// `None` if there is none. Swap the arguments for the other direction, or see
+/// `dfa_difference_witness` for both at once.
+pub fn difference_witness<S1, S2, T>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+) -> Option<Vec<T>>
+where
+    S1: Hash + Eq,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    product_witness(first, second, alphabet, |accepted1, accepted2| {
+        accepted1 && !accepted2
+    })
+}
+
+// breadth-first search over pairs of states, where `None` is the dead state,
+// for the shortest input reaching a pair whose acceptance satisfies `found`
+fn product_witness<S1, S2, T, F>(
+    first: &DFAutoBlueprint<S1, T>,
+    second: &DFAutoBlueprint<S2, T>,
+    alphabet: &[T],
+    found: F,
+) -> Option<Vec<T>>
+where
+    S1: Hash + Eq,
+    S2: Hash + Eq,
+    T: Hash + Eq + Clone,
+    F: Fn(bool, bool) -> bool,
+{
+    let start = (Some(first.start_state()), Some(second.start_state()));
+    let mut parent_map: HashMap<_, Option<(_, &T)>> = HashMap::new();
+    parent_map.insert(start, None);
+    let mut unresolved = VecDeque::new();
+    unresolved.push_back(start);
+    while let Some(pair) = unresolved.pop_front() {
+        let (state1, state2) = pair;
+        let accepted1 = state1.is_some_and(|state| first.accept_state_set().contains(state));
+        let accepted2 = state2.is_some_and(|state| second.accept_state_set().contains(state));
+        if found(accepted1, accepted2) {
+            let mut witness = Vec::new();
+            let mut pair = pair;
+            while let Some((parent, trans)) = parent_map[&pair] {
+                witness.push(trans.clone());
+                pair = parent;
+            }
+            witness.reverse();
+            return Some(witness);
+        }
+        for trans in alphabet {
+            let next = (
+                state1.and_then(|state| first.next_state(state, trans)),
+                state2.and_then(|state| second.next_state(state, trans)),
+            );
+            if let Entry::Vacant(entry) = parent_map.entry(next) {
+                entry.insert(Some((pair, trans)));
+                unresolved.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Shortest input accepted by `nfa`, searched on the NFA itself without
+/// determinizing it, or `None` if no input is found. Wildcard and predicate
+/// steps take `default`, and range steps take their start, their end or
+/// `default`. Steps none of these symbols can take, such as a wildcard
+/// excluding `default`, are left out of the search.
+pub fn nfa_example_accepted<S, T>(nfa: &NFAutoBlueprint<S, T>, default: &T) -> Option<Vec<T>>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    // every found state, with the state and the transition (`None` for a void
+    // connection) it was first found from
+    let mut parent_map: HashMap<&S, Option<(&S, Option<T>)>> = HashMap::new();
+    let mut layer = Vec::new();
+    for state in nfa.start_state_set() {
+        if let Entry::Vacant(entry) = parent_map.entry(state) {
+            entry.insert(None);
+            layer.push(state);
+        }
+    }
+    while !layer.is_empty() {
+        // void connections take no input, so they stay in the same layer
+        let mut index = 0;
+        while index < layer.len() {
+            let state = layer[index];
+            index += 1;
+            for to in nfa.connections_from(state).void.iter() {
+                if let Entry::Vacant(entry) = parent_map.entry(to) {
+                    entry.insert(Some((state, None)));
+                    layer.push(to);
+                }
+            }
+        }
+        if let Some(mut state) = layer
+            .iter()
+            .copied()
+            .find(|state| nfa.accept_state_set().contains(state))
+        {
+            let mut input = Vec::new();
+            while let Some((from, trans)) = &parent_map[state] {
+                input.extend(trans.iter().cloned());
+                state = from;
+            }
+            input.reverse();
+            return Some(input);
+        }
+        let mut next_layer = Vec::new();
+        for state in layer {
+            let connections = nfa.connections_from(state);
+            let step_list = connections
+                .plain
+                .iter()
+                .flat_map(|(trans, to_set)| to_set.iter().map(move |to| (trans.clone(), to)))
+                .chain(connections.wildcard.iter().map(|to| (default.clone(), to)))
+                .chain(
+                    connections
+                        .wildcard_except
+                        .iter()
+                        .filter(|(_, excluded)| !excluded.contains(default))
+                        .map(|(to, _)| (default.clone(), to)),
+                )
+                .chain(connections.range.iter().filter_map(|(range, to)| {
+                    vec![range.start(), range.end(), default]
+                        .into_iter()
+                        .find(|trans| range.contains(trans))
+                        .map(|trans| (trans.clone(), to))
+                }))
+                .chain(
+                    connections
+                        .predicate
+                        .iter()
+                        .filter(|(pred, _)| pred.test(default))
+                        .map(|(_, to)| (default.clone(), to)),
+                );
+            for (trans, to) in step_list {
+                if let Entry::Vacant(entry) = parent_map.entry(to) {
+                    entry.insert(Some((state, Some(trans))));
+                    next_layer.push(to);
+                }
+            }
+        }
+        layer = next_layer;
+    }
+    None
+}
+
+/// Several NFAs run side by side on the same transitions, each identified by
+/// its index in the slice the runner is created from. Machines without
+/// current state are dead and skipped by later transitions.
+#[derive(Debug, Clone)]
+pub struct MultiRunner<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    blueprint_list: Vec<&'b NFAutoBlueprint<S, T>>,
+    state_set_list: Vec<HashSet<S>>,
+}
+
+impl<'b, S, T> MultiRunner<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    pub fn new(blueprint_list: &[&'b NFAutoBlueprint<S, T>]) -> Self {
+        let state_set_list = blueprint_list
+            .iter()
+            .map(|nfa| extend_state_set(nfa, nfa.start_state_set()))
+            .collect();
+        Self {
+            blueprint_list: blueprint_list.to_vec(),
+            state_set_list,
+        }
+    }
+
+    pub fn trigger(&mut self, trans: &T) {
+        for (nfa, state_set) in self
+            .blueprint_list
+            .iter()
+            .zip(self.state_set_list.iter_mut())
+        {
+            if !state_set.is_empty() {
+                *state_set = next_state_set(nfa, state_set.iter(), trans);
+            }
+        }
+    }
+
+    pub fn accepted_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blueprint_list
+            .iter()
+            .zip(self.state_set_list.iter())
+            .enumerate()
+            .filter(|(_, (nfa, state_set))| {
+                state_set
+                    .iter()
+                    .any(|state| nfa.accept_state_set().contains(state))
+            })
+            .map(|(index, _)| index)
+    }
+
+    pub fn dead_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.state_set_list
+            .iter()
+            .enumerate()
+            .filter(|(_, state_set)| state_set.is_empty())
+            .map(|(index, _)| index)
+    }
+
+    /// Whether every machine is dead, so that no later transition can make
+    /// any of them accept.
+    pub fn all_dead(&self) -> bool {
+        self.state_set_list
+            .iter()
+            .all(|state_set| state_set.is_empty())
+    }
+}
+
+// split possibly overlapping ranges at every boundary into disjoint ranges,
+// each leading to the targets of all ranges covering it, leaving out the plain
+// transitions which already take the ranges' targets. Ranges without targets
+// still make split ranges, leading nowhere unless covered by other ranges.
+fn split_range_list<S, T>(
+    range_to_list: &[(&TransRange<T>, HashSet<S>)],
+    trans_to_map: &HashMap<T, HashSet<S>>,
+) -> Vec<(TransRange<T>, HashSet<S>)>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let cmp = if let Some((range, _)) = range_to_list.first() {
+        range.cmp_fn()
+    } else {
+        return Vec::new();
+    };
+    let mut cut_list: Vec<_> = range_to_list
+        .iter()
+        .flat_map(|(range, _)| vec![range.start_cut(), range.end_cut()])
+        .chain(
+            trans_to_map
+                .keys()
+                .flat_map(|trans| vec![Cut(trans, false), Cut(trans, true)]),
+        )
+        .collect();
+    cut_list.sort_by(|first, second| cmp_cut(cmp, *first, *second));
+    cut_list.dedup_by(|first, second| cmp_cut(cmp, *first, *second) == Ordering::Equal);
+    let mut split_list = Vec::new();
+    for window in cut_list.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        if !start.1 && end.1 && trans_to_map.contains_key(start.0) {
+            continue;
+        }
+        let mut to_set = HashSet::new();
+        let mut covered = false;
+        for (range, range_to_set) in range_to_list {
+            if cmp_cut(cmp, range.start_cut(), start) != Ordering::Greater
+                && cmp_cut(cmp, end, range.end_cut()) != Ordering::Greater
+            {
+                to_set.extend(range_to_set.iter().cloned());
+                covered = true;
+            }
+        }
+        if covered {
+            split_list.push((TransRange::between(start, end, cmp), to_set));
+        }
+    }
+    split_list
+}
+
+/// States reachable from `states` through void connections, `states`
+/// included.
+pub fn epsilon_closure<'s, S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    states: impl IntoIterator<Item = &'s S>,
+) -> HashSet<S>
+where
+    S: Hash + Eq + Clone + 's,
+    T: Hash + Eq,
+{
+    let mut state_set = HashSet::new();
+    let mut unresolved: Vec<_> = states.into_iter().collect();
+    while let Some(state) = unresolved.pop() {
+        if state_set.insert(state.clone()) {
+            unresolved.extend(nfa.connections_from(state).void.iter());
+        }
+    }
+    state_set
+}
+
+pub(crate) use self::epsilon_closure as extend_state_set;
+
+/// NFA accepting what `first` or `second` accepts, with their state numbers.
+pub fn nfa_union<S1, S2, T>(
+    first: &NFAutoBlueprint<S1, T>,
+    second: &NFAutoBlueprint<S2, T>,
+) -> (NFAutoBlueprint<u64, T>, HashMap<S1, u64>, HashMap<S2, u64>)
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut counter = 2;
+    let builder = NFAutoBuilder::start(0).accept(1);
+    let (builder, first_map) = embed(first, builder, &mut counter, 0, 1);
+    let (builder, second_map) = embed(second, builder, &mut counter, 0, 1);
+    (builder.finalize(), first_map, second_map)
+}
+
+/// NFA accepting `first` then `second`, with their state numbers.
+pub fn nfa_concat<S1, S2, T>(
+    first: &NFAutoBlueprint<S1, T>,
+    second: &NFAutoBlueprint<S2, T>,
+) -> (NFAutoBlueprint<u64, T>, HashMap<S1, u64>, HashMap<S2, u64>)
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut counter = 3;
+    let builder = NFAutoBuilder::start(0).accept(1);
+    let (builder, first_map) = embed(first, builder, &mut counter, 0, 2);
+    let (builder, second_map) = embed(second, builder, &mut counter, 2, 1);
+    (builder.finalize(), first_map, second_map)
+}
+
+/// NFA accepting repetitions of `inner`, with its state numbers.
+pub fn nfa_star<S, T>(inner: &NFAutoBlueprint<S, T>) -> (NFAutoBlueprint<u64, T>, HashMap<S, u64>)
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut counter = 2;
+    let builder = NFAutoBuilder::start(0)
+        .accept(1)
+        .connect_void(0, 1)
+        .connect_void(1, 0);
+    let (builder, map) = embed(inner, builder, &mut counter, 0, 1);
+    (builder.finalize(), map)
+}
+
+/// Build a single automaton out of several patterns. The accept state of each
+/// pattern is tagged with the position of the pattern, which is also its
+/// priority; the returned list maps the tags back to the pattern names.
+pub fn combine_patterns<A, T>(
+    patterns: impl IntoIterator<Item = (A, Re<T>)>,
+) -> (NFAutoBlueprint<u64, T>, Vec<A>)
+where
+    T: Hash + Eq + Clone,
+{
+    let mut counter = 1;
+    let mut builder = NFAutoBuilder::start(0);
+    let mut name_list = Vec::new();
+    for (tag, (name, re)) in patterns.into_iter().enumerate() {
+        let accept = counter;
+        counter += 1;
+        builder = builder.accept_with(accept, tag);
+        builder = embed(&re.compile(), builder, &mut counter, 0, accept).0;
+        name_list.push(name);
+    }
+    (builder.finalize(), name_list)
+}
+
+enum Paired<'t, T> {
+    Plain(&'t T),
+    Symbolic(Symbolic<T>),
+}
+
+// connections matching transitions by a rule rather than by equality
+enum Symbolic<T> {
+    Wildcard,
+    WildcardExcept(HashSet<T>),
+    Range(TransRange<T>),
+    Predicate(Predicate<T>),
+}
+
+impl<T> Symbolic<T>
+where
+    T: Hash + Eq + Clone,
+{
+    fn admits(&self, trans: &T) -> bool {
+        match self {
+            Symbolic::Wildcard => true,
+            Symbolic::WildcardExcept(excluded) => !excluded.contains(trans),
+            Symbolic::Range(range) => range.contains(trans),
+            Symbolic::Predicate(pred) => pred.test(trans),
+        }
+    }
+
+    fn into_predicate(self) -> Predicate<T> {
+        match self {
+            Symbolic::Wildcard => Predicate::new(|_| true),
+            Symbolic::WildcardExcept(excluded) => Predicate::except(excluded),
+            Symbolic::Range(range) => Predicate::in_range(range),
+            Symbolic::Predicate(pred) => pred,
+        }
+    }
+
+    // the connections admitting together exactly what both admit
+    fn combine(&self, other: &Self) -> Vec<Self> {
+        match (self, other) {
+            (Symbolic::Wildcard, _) => vec![other.clone()],
+            (_, Symbolic::Wildcard) => vec![self.clone()],
+            (Symbolic::WildcardExcept(excluded), Symbolic::WildcardExcept(other_excluded)) => {
+                vec![Symbolic::WildcardExcept(excluded | other_excluded)]
+            }
+            (Symbolic::WildcardExcept(excluded), Symbolic::Range(range))
+            | (Symbolic::Range(range), Symbolic::WildcardExcept(excluded)) => range
+                .without(excluded.iter())
+                .into_iter()
+                .map(Symbolic::Range)
+                .collect(),
+            (Symbolic::Range(range), Symbolic::Range(other_range)) => range
+                .intersection(other_range)
+                .map(Symbolic::Range)
+                .into_iter()
+                .collect(),
+            _ => vec![Symbolic::Predicate(Predicate::both(
+                self.clone().into_predicate(),
+                other.clone().into_predicate(),
+            ))],
+        }
+    }
+}
+
+impl<T> Clone for Symbolic<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Symbolic::Wildcard => Symbolic::Wildcard,
+            Symbolic::WildcardExcept(excluded) => Symbolic::WildcardExcept(excluded.clone()),
+            Symbolic::Range(range) => Symbolic::Range(range.clone()),
+            Symbolic::Predicate(pred) => Symbolic::Predicate(pred.clone()),
+        }
+    }
+}
+
+fn symbolic_connections<'c, S, T>(
+    wildcard: &SetView<'c, S>,
+    wildcard_except: &MapView<'c, S, HashSet<T>>,
+    range: &'c [(TransRange<T>, S)],
+    predicate: &'c [(Predicate<T>, S)],
+) -> Vec<(Symbolic<T>, &'c S)>
+where
+    S: Hash + Eq,
+    T: Clone,
+{
+    wildcard
+        .iter()
+        .map(|to| (Symbolic::Wildcard, to))
+        .chain(
+            wildcard_except
+                .iter()
+                .map(|(to, excluded)| (Symbolic::WildcardExcept(excluded.clone()), to)),
+        )
+        .chain(
+            range
+                .iter()
+                .map(|(range, to)| (Symbolic::Range(range.clone()), to)),
+        )
+        .chain(
+            predicate
+                .iter()
+                .map(|(pred, to)| (Symbolic::Predicate(pred.clone()), to)),
+        )
+        .collect()
+}
+
+/// Build the product automaton accepting strings accepted by both `first` and
+/// `second`, exploring only pairs reachable from the start pairs.
+///
+/// A plain connection pairs with a plain connection on the same transition or
+/// a wildcard, range or predicate connection admitting it on the other side,
+/// producing a plain connection. Two wildcard connections produce a wildcard
+/// connection, a wildcard connection paired with a range or predicate
+/// connection produces that connection, two ranges produce their intersection,
+/// two wildcard-except connections exclude the transitions of both, a range
+/// paired with a wildcard-except connection produces the range split around
+/// the excluded transitions, and other pairings produce a predicate connection
+/// testing both. A void connection on either side advances that side only,
+/// while the other side stays put.
+pub fn nfa_intersect<S1, S2, T>(
+    first: &NFAutoBlueprint<S1, T>,
+    second: &NFAutoBlueprint<S2, T>,
+) -> NFAutoBlueprint<(S1, S2), T>
+where
+    S1: Hash + Eq + Clone,
+    S2: Hash + Eq + Clone,
     T: Hash + Eq + Clone,
 {
-    let start_state_set: BTreeSet<_> =
-        extend_state_set(nfa, &vec![nfa.start_state().clone()].into_iter().collect())
-            .into_iter()
-            .collect();
-    let mut builder = DFAutoBuilder::start(start_state_set.clone());
-    let mut unresolved_state_set_list = vec![start_state_set];
-    let mut resolved_state_set_set: HashSet<BTreeSet<_>> = HashSet::new();
-    while let Some(state_set) = unresolved_state_set_list.pop() {
-        let mut aggregated_connections: HashMap<_, HashSet<_>> = HashMap::new();
-        let mut aggregated_wildcard_connections = HashSet::new();
-        for state in state_set.iter() {
-            if nfa.accept_state_set().contains(&state) {
-                builder = builder.accept(state_set.clone());
+    let start_list: Vec<_> = first
+        .start_state_set()
+        .flat_map(|s1| {
+            second
+                .start_state_set()
+                .map(move |s2| (s1.clone(), s2.clone()))
+        })
+        .collect();
+    let mut builder = start_list
+        .iter()
+        .skip(1)
+        .fold(NFAutoBuilder::start(start_list[0].clone()), |b, state| {
+            b.also_start(state.clone())
+        });
+    let mut seen: HashSet<_> = start_list.iter().cloned().collect();
+    let mut unresolved = start_list;
+    while let Some((s1, s2)) = unresolved.pop() {
+        if first.accept_state_set().contains(&s1) && second.accept_state_set().contains(&s2) {
+            builder = builder.accept((s1.clone(), s2.clone()));
+        }
+        let (c1, c2) = (first.connections_from(&s1), second.connections_from(&s2));
+        let symbolic1 =
+            symbolic_connections(&c1.wildcard, &c1.wildcard_except, c1.range, c1.predicate);
+        let symbolic2 =
+            symbolic_connections(&c2.wildcard, &c2.wildcard_except, c2.range, c2.predicate);
+        let mut connected = Vec::new();
+        for (trans, to1_set) in c1.plain.iter() {
+            let to2_list: Vec<_> = c2
+                .plain
+                .get(trans)
+                .into_iter()
+                .flatten()
+                .chain(
+                    symbolic2
+                        .iter()
+                        .filter(|(symbolic, _)| symbolic.admits(trans))
+                        .map(|(_, to2)| *to2),
+                )
+                .collect();
+            for to1 in to1_set {
+                for to2 in to2_list.iter() {
+                    connected.push((Paired::Plain(trans), to1.clone(), (*to2).clone()));
+                }
             }
-
-            let connections = nfa.connections_from(state);
-            let (option_trans_to_set, option_wildcard_to_set) =
-                (connections.plain, connections.wildcard);
-            if let Some(trans_to_set) = option_trans_to_set {
-                for (trans, to_set) in trans_to_set.iter() {
-                    if !aggregated_connections.contains_key(trans) {
-                        aggregated_connections.insert(trans.clone(), HashSet::new());
-                    }
-                    aggregated_connections
-                        .get_mut(trans)
-                        .unwrap()
-                        .extend(extend_state_set(nfa, to_set));
+        }
+        for (trans, to2_set) in c2.plain.iter() {
+            for (_, to1) in symbolic1
+                .iter()
+                .filter(|(symbolic, _)| symbolic.admits(trans))
+            {
+                for to2 in to2_set {
+                    connected.push((Paired::Plain(trans), (*to1).clone(), to2.clone()));
                 }
             }
-            if let Some(wildcard_to_set) = option_wildcard_to_set {
-                aggregated_wildcard_connections.extend(extend_state_set(nfa, wildcard_to_set));
+        }
+        for (symbolic1, to1) in symbolic1.iter() {
+            for (symbolic2, to2) in symbolic2.iter() {
+                for symbolic in symbolic1.combine(symbolic2) {
+                    connected.push((Paired::Symbolic(symbolic), (*to1).clone(), (*to2).clone()));
+                }
             }
         }
-        for (trans, to_hashset) in aggregated_connections {
-            let to_btreeset: BTreeSet<_> = to_hashset.clone().into_iter().collect();
-            builder = builder.connect(state_set.clone(), trans, to_btreeset.clone());
-            if !resolved_state_set_set.contains(&to_btreeset) {
-                unresolved_state_set_list.push(to_btreeset);
+        let mut void_connected = Vec::new();
+        for to1 in c1.void.iter() {
+            void_connected.push((to1.clone(), s2.clone()));
+        }
+        for to2 in c2.void.iter() {
+            void_connected.push((s1.clone(), to2.clone()));
+        }
+        let from = (s1, s2);
+        for (paired, to1, to2) in connected {
+            let to = (to1, to2);
+            builder = match paired {
+                Paired::Plain(trans) => builder.connect(from.clone(), trans.clone(), to.clone()),
+                Paired::Symbolic(Symbolic::Wildcard) => {
+                    builder.connect_wildcard(from.clone(), to.clone())
+                }
+                Paired::Symbolic(Symbolic::WildcardExcept(excluded)) => {
+                    builder.connect_wildcard_except(from.clone(), excluded, to.clone())
+                }
+                Paired::Symbolic(Symbolic::Range(range)) => {
+                    builder.connect_trans_range(from.clone(), range, to.clone())
+                }
+                Paired::Symbolic(Symbolic::Predicate(pred)) => {
+                    builder.connect_predicate(from.clone(), pred, to.clone())
+                }
+            };
+            if seen.insert(to.clone()) {
+                unresolved.push(to);
             }
         }
-        if !aggregated_wildcard_connections.is_empty() {
-            let wildcard_to: BTreeSet<_> = aggregated_wildcard_connections.into_iter().collect();
-            builder = builder.connect_fallback(state_set.clone(), wildcard_to.clone());
-            if !resolved_state_set_set.contains(&wildcard_to) {
-                unresolved_state_set_list.push(wildcard_to);
+        for to in void_connected {
+            builder = builder.connect_void(from.clone(), to.clone());
+            if seen.insert(to.clone()) {
+                unresolved.push(to);
             }
         }
-        resolved_state_set_set.insert(state_set);
     }
     builder.finalize()
 }
 
-pub(crate) fn extend_state_set<S, T>(
-    nfa: &NFAutoBlueprint<S, T>,
-    state_set: &HashSet<S>,
-) -> HashSet<S>
+/// Strongly connected components of the states of `dfa` reachable from its
+/// start state, see `DFAutoBlueprint::strongly_connected_components`.
+pub fn strongly_connected_components<S, T>(dfa: &DFAutoBlueprint<S, T>) -> Vec<Vec<&S>>
 where
-    S: Hash + Eq + Clone,
+    S: Hash + Eq,
     T: Hash + Eq,
 {
-    let mut state_set = state_set.clone();
-    let fallback = HashSet::new();
-    loop {
-        let void_reachable: HashSet<_> = state_set
-            .iter()
-            .flat_map(|state| nfa.connections_from(state).void.unwrap_or(&fallback))
-            .cloned()
-            .collect();
-        let extended = &state_set | &void_reachable;
-        if extended.len() == state_set.len() {
-            return state_set;
+    dfa.strongly_connected_components()
+}
+
+/// Minimal DFA accepting the same inputs as `dfa`, with states numbered from
+/// 0 as in `DFAutoBlueprint::renumbered`, built by determinizing the reversed
+/// automaton twice. Each determinization may take exponential time and space
+/// in the number of states.
+pub fn minimize_brzozowski<S, T>(dfa: &DFAutoBlueprint<S, T>) -> DFAutoBlueprint<u64, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    // fallbacks next to ranges become except predicates, which need no
+    // alphabet
+    let reversed = determinize(&dfa_to_nfa(dfa).reverse())
+        .unwrap_or_else(|_| unreachable!())
+        .renumbered();
+    determinize(&dfa_to_nfa(&reversed).reverse())
+        .unwrap_or_else(|_| unreachable!())
+        .renumbered()
+}
+
+/// A fallback connection of a state with range connections, which an NFA
+/// cannot express.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackWithRange<S> {
+    pub state: S,
+}
+
+impl<S> fmt::Display for FallbackWithRange<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fallback alongside range connections on {:?}",
+            self.state
+        )
+    }
+}
+
+impl<S> Error for FallbackWithRange<S> where S: fmt::Debug {}
+
+/// NFA with the states reachable in `dfa` and the same connections. A
+/// fallback connection becomes a wildcard connection except on the plain
+/// transitions of its state, so the NFA accepts exactly the same inputs. On a
+/// state with range connections, which a wildcard connection cannot leave
+/// out, the fallback becomes a predicate connection excluding the ranges too,
+/// which `determinize` still expands without alphabet.
+pub fn dfa_to_nfa<S, T>(dfa: &DFAutoBlueprint<S, T>) -> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    embed_dfa(dfa, true).unwrap_or_else(|_| unreachable!())
+}
+
+/// Same as `dfa_to_nfa`, failing on a state with both range and fallback
+/// connections instead of making a predicate connection, so that the NFA
+/// only has connections of the other kinds.
+pub fn try_dfa_to_nfa<S, T>(
+    dfa: &DFAutoBlueprint<S, T>,
+) -> Result<NFAutoBlueprint<S, T>, FallbackWithRange<S>>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    embed_dfa(dfa, false)
+}
+
+// the NFA of `dfa_to_nfa`, failing on a fallback alongside ranges unless
+// `with_predicate` is set
+fn embed_dfa<S, T>(
+    dfa: &DFAutoBlueprint<S, T>,
+    with_predicate: bool,
+) -> Result<NFAutoBlueprint<S, T>, FallbackWithRange<S>>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut builder = NFAutoBuilder::start(dfa.start_state().clone());
+    let mut resolved = HashSet::new();
+    let mut unresolved = vec![dfa.start_state()];
+    while let Some(state) = unresolved.pop() {
+        if !resolved.insert(state) {
+            continue;
+        }
+        if dfa.accept_state_set().contains(state) {
+            builder = builder.accept(state.clone());
+        }
+        for tag in dfa.accept_tags(state).iter() {
+            builder = builder.accept_with(state.clone(), *tag);
         }
-        state_set = extended;
+        let connections = dfa.connections_from(state);
+        for (trans, to) in connections.plain.iter() {
+            builder = builder.connect(state.clone(), trans.clone(), to.clone());
+            unresolved.push(to);
+        }
+        for (range, to) in connections.range {
+            builder = builder.connect_trans_range(state.clone(), range.clone(), to.clone());
+            unresolved.push(to);
+        }
+        if let Some(to) = connections.fallback {
+            let excluded = connections.plain.iter().map(|(trans, _)| trans.clone());
+            builder = if connections.range.is_empty() {
+                builder.connect_wildcard_except(state.clone(), excluded, to.clone())
+            } else if with_predicate {
+                let range_list = connections
+                    .range
+                    .iter()
+                    .map(|(range, _)| range.clone())
+                    .collect();
+                let pred = Predicate::except_ranges(excluded.collect(), range_list);
+                builder.connect_predicate(state.clone(), pred, to.clone())
+            } else {
+                return Err(FallbackWithRange {
+                    state: state.clone(),
+                });
+            };
+            unresolved.push(to);
+        }
+    }
+    Ok(builder.finalize())
+}
+
+/// A fallback connection of a DFA, rejected by `dfa_to_nfa_strict`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackConnection<S> {
+    pub state: S,
+}
+
+impl<S> fmt::Display for FallbackConnection<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fallback connection on {:?}", self.state)
+    }
+}
+
+impl<S> Error for FallbackConnection<S> where S: fmt::Debug {}
+
+/// Same as `dfa_to_nfa`, failing on any fallback connection instead of
+/// turning it into a wildcard connection, for callers whose fallbacks do not
+/// stand for "any other symbol".
+pub fn dfa_to_nfa_strict<S, T>(
+    dfa: &DFAutoBlueprint<S, T>,
+) -> Result<NFAutoBlueprint<S, T>, FallbackConnection<S>>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    if let Some((state, _)) = dfa.iterate_fallback_connections().next() {
+        return Err(FallbackConnection {
+            state: state.clone(),
+        });
+    }
+    // without fallbacks there is no fallback alongside ranges
+    Ok(try_dfa_to_nfa(dfa).unwrap_or_else(|_| unreachable!()))
+}
+
+// copy every connection of `nfa` into `builder` with freshly numbered states,
+// wired between `left` and `right` through void connections
+fn embed<S, T>(
+    nfa: &NFAutoBlueprint<S, T>,
+    mut builder: NFAutoBuilder<u64, T>,
+    counter: &mut u64,
+    left: u64,
+    right: u64,
+) -> (NFAutoBuilder<u64, T>, HashMap<S, u64>)
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    let mut map = HashMap::new();
+    let mut rename = |state: &S| {
+        *map.entry(state.clone()).or_insert_with(|| {
+            *counter += 1;
+            *counter - 1
+        })
+    };
+    for start in nfa.start_state_set() {
+        builder = builder.connect_void(left, rename(start));
+    }
+    for accept in nfa.accept_state_set() {
+        builder = builder.connect_void(rename(accept), right);
+    }
+    for (from, conn, to) in nfa.iterate_connections() {
+        builder = builder.connect_as(rename(from), conn, rename(to));
     }
+    (builder, map)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::auto::Auto;
-    use crate::re::Re;
+    use crate::lcg::Lcg;
+    use crate::nfa::NFAuto;
+    use std::convert::TryFrom;
 
     #[test]
     fn correct_auto() {
@@ -99,9 +1823,899 @@ mod tests {
                 Re::concat(Re::wildcard(), Re::either(Re::plain('c'), Re::plain('d'))),
             )
             .compile(),
-        );
+        )
+        .unwrap();
         assert!(auto.create().test("abababb&c".chars()));
         assert!(auto.create().test("ababbba?d".chars()));
         assert!(!auto.create().test("ababbbe-d".chars()));
     }
+
+    #[test]
+    fn expand_state_sets_once() {
+        // (a|b)*.(c|d)
+        let nfa = Re::concat(
+            Re::zero_or_more(Re::either(Re::plain('a'), Re::plain('b'))),
+            Re::concat(Re::wildcard(), Re::either(Re::plain('c'), Re::plain('d'))),
+        )
+        .compile();
+        let (dfa, state_set_list, expansion_count) =
+            subset_construction(&nfa, &[], usize::MAX, DeterminizeOptions::default()).unwrap();
+        assert_eq!(
+            with_state_sets(&dfa, &state_set_list),
+            determinize(&nfa).unwrap()
+        );
+        assert_eq!(expansion_count, state_count(&dfa));
+        assert_eq!(expansion_count, state_set_list.len());
+
+        // many paths reaching the same state sets
+        let nfa = Re::parse("(a|b|c)*a(a|b|c)(a|b|c)(a|b|c)(a|b|c)")
+            .unwrap()
+            .compile();
+        let (dfa, _, expansion_count) =
+            subset_construction(&nfa, &[], usize::MAX, DeterminizeOptions::default()).unwrap();
+        assert_eq!(expansion_count, state_count(&dfa));
+    }
+
+    #[test]
+    fn state_sets_follow_nfa() {
+        let pattern_list = [
+            "(a|b)*.(c|d)",
+            "(a|b|c)*a(a|b|c)(a|b|c)",
+            "[^a]b|[^bc]a|c",
+            "([a-c]x|b.)*[b-d]",
+            "(ab&cd)e?",
+        ];
+        for pattern in pattern_list.iter() {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let dfa = determinize(&nfa).unwrap();
+            assert_eq!(
+                dfa.start_state(),
+                &extend_state_set(&nfa, nfa.start_state_set())
+                    .into_iter()
+                    .collect()
+            );
+            let (renumbered, state_set_list) = determinize_renumbered(&nfa).unwrap();
+            assert_eq!(state_count(&dfa), state_set_list.len());
+            for (number, state_set) in state_set_list.iter().enumerate() {
+                assert_eq!(
+                    dfa.accept_state_set().contains(state_set),
+                    state_set
+                        .iter()
+                        .any(|state| nfa.accept_state_set().contains(state))
+                );
+                assert_eq!(
+                    renumbered.accept_state_set().contains(&(number as u64)),
+                    dfa.accept_state_set().contains(state_set)
+                );
+                for trans in "abcdex".chars() {
+                    let expected: BTreeSet<_> = next_state_set(&nfa, state_set, &trans)
+                        .into_iter()
+                        .collect();
+                    assert_eq!(
+                        dfa.next_state(state_set, &trans)
+                            .cloned()
+                            .unwrap_or_default(),
+                        expected,
+                        "{} {:?} {}",
+                        pattern,
+                        state_set,
+                        trans
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn determinize_options() {
+        // (a|b)*.(c|d)
+        let nfa = Re::parse("(a|b)*.(c|d)").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        assert_eq!(
+            determinize_with(&nfa, DeterminizeOptions::default()).unwrap(),
+            dfa
+        );
+        let with = |elide_dead, complete| {
+            determinize_with(
+                &nfa,
+                DeterminizeOptions {
+                    elide_dead,
+                    complete,
+                },
+            )
+            .unwrap()
+        };
+        let elided = with(true, false);
+        let completed = with(false, true);
+        let both = with(true, true);
+        assert_eq!(state_count(&dfa), 8);
+        assert_eq!(state_count(&elided), 8);
+        // the empty state set is added as the sink
+        assert_eq!(state_count(&completed), 9);
+        assert_eq!(state_count(&both), 9);
+        for other in [&completed, &both].iter() {
+            assert_eq!(
+                other.next_state(&BTreeSet::new(), &'a'),
+                Some(&BTreeSet::new())
+            );
+        }
+        for input in strings_up_to("abcd?", 5) {
+            let expected = dfa.create().test(input.chars());
+            for other in [&elided, &completed, &both].iter() {
+                assert_eq!(other.create().test(input.chars()), expected, "{}", input);
+            }
+        }
+
+        // a[]|b, where the `a` branch can never accept
+        let nfa = ((Re::plain('a') + Re::never()) | Re::plain('b')).compile();
+        let dfa = determinize(&nfa).unwrap();
+        let elided = determinize_with(
+            &nfa,
+            DeterminizeOptions {
+                elide_dead: true,
+                complete: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(state_count(&dfa), 3);
+        assert_eq!(state_count(&elided), 2);
+        assert_eq!(elided.next_state(elided.start_state(), &'a'), None);
+        for input in strings_up_to("abc", 3) {
+            assert_eq!(
+                elided.create().test(input.chars()),
+                dfa.create().test(input.chars()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn example_accepted() {
+        let example = |nfa: &NFAutoBlueprint<u64, char>| {
+            nfa_example_accepted(nfa, &'?').map(|input| input.into_iter().collect::<String>())
+        };
+        let nfa = Re::parse("(a|b)*c").unwrap().compile();
+        assert_eq!(example(&nfa), Some("c".to_string()));
+        let nfa = Re::parse("(abb|c.)(d|[e-f])").unwrap().compile();
+        assert_eq!(example(&nfa), Some("c?d".to_string()));
+        let nfa = Re::parse("[^?]x|[e-f]yy").unwrap().compile();
+        assert_eq!(example(&nfa), Some("eyy".to_string()));
+        assert_eq!(
+            example(&Re::parse("a*").unwrap().compile()),
+            Some("".to_string())
+        );
+
+        // a[]b
+        let nfa = (Re::plain('a') + Re::never() + Re::plain('b')).compile();
+        assert_eq!(example(&nfa), None);
+    }
+
+    #[test]
+    fn nfa_intersects_dfa() {
+        let keyword_dfa = determinize(&Re::parse("if|else|while").unwrap().compile()).unwrap();
+        let check = |pattern: &str| {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let witness = intersects(&nfa, &keyword_dfa);
+            if let Some(witness) = &witness {
+                assert!(nfa.create().test(witness.iter().cloned()));
+                assert!(keyword_dfa.create().test(witness.iter().cloned()));
+            }
+            assert_eq!(intersects_checked(&nfa, &keyword_dfa), Ok(witness.clone()));
+            witness.map(|witness| witness.into_iter().collect::<String>())
+        };
+        assert_eq!(check("[a-z][a-z]"), Some("if".to_string()));
+        assert_eq!(check("e.*"), Some("else".to_string()));
+        assert_eq!(check("wh[^i]le|[a-z]+"), Some("if".to_string()));
+        assert_eq!(check("[0-9]+"), None);
+        assert_eq!(check("i.f"), None);
+
+        // a wildcard only meets a fallback on transitions not tried
+        let fallback_dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 2)
+            .connect_fallback(0, 1)
+            .accept(1)
+            .finalize();
+        let nfa = Re::parse(".").unwrap().compile();
+        assert_eq!(intersects(&nfa, &fallback_dfa), None);
+        assert_eq!(
+            intersects_checked(&nfa, &fallback_dfa),
+            Err(UntriedTransitions)
+        );
+        let nfa = Re::parse("x|.").unwrap().compile();
+        assert_eq!(intersects(&nfa, &fallback_dfa), Some(vec!['x']));
+        let nfa = Re::parse("a").unwrap().compile();
+        assert_eq!(intersects_checked(&nfa, &fallback_dfa), Ok(None));
+    }
+
+    #[test]
+    fn count_accepted_by_length() {
+        let dfa = determinize(&Re::parse("(a|b)*a.|c").unwrap().compile()).unwrap();
+        let count_list = count_by_length(&dfa, &['a', 'b', 'c'], 6);
+        let mut expected = vec![0u128; 7];
+        for input in strings_up_to("abc", 6) {
+            if dfa.create().test(input.chars()) {
+                expected[input.len()] += 1;
+            }
+        }
+        assert_eq!(count_list, expected);
+        assert_eq!(&count_list[..3], &[0, 1, 3]);
+
+        // 256 ** 16 does not fit
+        let bytes: Vec<u8> = (0..=255).collect();
+        let dfa = determinize(&Re::<u8>::zero_or_more(Re::wildcard()).compile()).unwrap();
+        let count_list = count_by_length(&dfa, &bytes, 17);
+        assert_eq!(count_list[15], 1 << 120);
+        assert_eq!(count_list[16], u128::MAX);
+        assert_eq!(count_list[17], u128::MAX);
+    }
+
+    #[test]
+    fn ambiguity() {
+        let witness = |pattern: &str| {
+            is_ambiguous(&Re::parse(pattern).unwrap().compile())
+                .map(|input| input.into_iter().collect::<String>())
+        };
+        assert_eq!(witness("a|a"), Some("a".to_string()));
+        assert_eq!(witness("a*a*"), Some("a".to_string()));
+        assert_eq!(witness("(a|ab)(c|bc)"), Some("abc".to_string()));
+        assert_eq!(witness("x[a-c]|xb"), Some("xb".to_string()));
+        assert_eq!(witness("(a|b)*c"), None);
+        assert_eq!(witness("ab|ac"), None);
+        assert_eq!(witness(".|a"), Some("a".to_string()));
+        assert_eq!(witness("a.|.b"), Some("ab".to_string()));
+        assert_eq!(witness(".*x.*"), Some("xx".to_string()));
+        assert_eq!(witness("x.|x[^y]"), Some("xx".to_string()));
+        assert_eq!(witness("[^a]|a"), None);
+        // no transition appearing in the pattern tells the runs apart
+        assert_eq!(witness(".|."), None);
+
+        let nfa = Re::parse("(a|ab)(c|bc)").unwrap().compile();
+        assert_eq!(is_ambiguous(&dfa_to_nfa(&determinize(&nfa).unwrap())), None);
+    }
+
+    #[test]
+    fn multi_runner() {
+        let nfa_list: Vec<_> = ["(a|b)*c", "ab.*", "a[b-c]"]
+            .iter()
+            .map(|pattern| Re::parse(pattern).unwrap().compile())
+            .collect();
+        let blueprint_list: Vec<_> = nfa_list.iter().collect();
+        let mut runner = MultiRunner::new(&blueprint_list);
+        let mut auto_list: Vec<_> = nfa_list.iter().map(|nfa| nfa.create()).collect();
+        let check = |runner: &MultiRunner<_, _>, auto_list: &[NFAuto<_, _>]| {
+            let expected: Vec<_> = (0..auto_list.len())
+                .filter(|index| auto_list[*index].is_accepted())
+                .collect();
+            assert_eq!(runner.accepted_indices().collect::<Vec<_>>(), expected);
+        };
+        check(&runner, &auto_list);
+        for c in "abcxc".chars() {
+            runner.trigger(&c);
+            for auto in auto_list.iter_mut() {
+                auto.trigger(&c);
+            }
+            check(&runner, &auto_list);
+        }
+        assert_eq!(runner.dead_indices().collect::<Vec<_>>(), vec![0, 2]);
+        assert!(!runner.all_dead());
+
+        let mut runner = MultiRunner::new(&blueprint_list);
+        runner.trigger(&'x');
+        assert!(runner.all_dead());
+        assert_eq!(runner.accepted_indices().count(), 0);
+    }
+
+    #[test]
+    fn one_way_difference_witness() {
+        let dfa = |pattern: &str| determinize(&Re::parse(pattern).unwrap().compile()).unwrap();
+        let witness = |first: &str, second: &str| {
+            difference_witness(&dfa(first), &dfa(second), &['a', 'b', 'c'])
+                .map(|input| input.into_iter().collect::<String>())
+        };
+        assert_eq!(witness("a*", "(aa)*"), Some("a".to_string()));
+        assert_eq!(witness("(aa)*", "a*"), None);
+        assert_eq!(witness("(a|b)*", "(a|b)*b(a|b)*"), Some("".to_string()));
+        assert_eq!(witness("(a|b)*b(a|b)*", "(a|b)*"), None);
+        assert_eq!(witness("a.c", "a[ab]c|a.b"), Some("acc".to_string()));
+        assert_eq!(witness("(ab)*", "(ab)*|abab"), None);
+        assert_eq!(witness("(ab)*", "ab|(abab)*"), Some("ababab".to_string()));
+    }
+
+    #[test]
+    fn totality() {
+        let builder = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_range(0, 'b'..='c', 0)
+            .connect(1, 'a', 1)
+            .connect_fallback(1, 0)
+            .accept(1);
+        let report = validate_total(&builder.clone().finalize(), &['a', 'b', 'c']);
+        assert!(report.is_total());
+        assert_eq!(report.missing, vec![]);
+        assert_eq!(report.fallback_count, vec![(1, 2)]);
+        assert_eq!(report.fallback_heavy(1).collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(report.fallback_heavy(2).count(), 0);
+        assert_eq!(report.to_string(), "every transition is connected");
+
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_range(0, 'b'..='b', 0)
+            .connect(1, 'a', 1)
+            .connect_fallback(1, 0)
+            .accept(1)
+            .finalize();
+        let report = validate_total(&dfa, &['a', 'b', 'c']);
+        assert!(!report.is_total());
+        assert_eq!(report.missing, vec![(0, 'c')]);
+        assert_eq!(report.to_string(), "no connection on 'c' from 0");
+    }
+
+    #[test]
+    fn renumbered_state_sets() {
+        let nfa = Re::parse("(a|b)*.(c|d)").unwrap().compile();
+        let (dfa, state_set_list) = determinize_renumbered(&nfa).unwrap();
+        assert_eq!(
+            (dfa.clone(), state_set_list.clone()),
+            determinize_renumbered(&nfa).unwrap()
+        );
+        assert_eq!(dfa.start_state(), &0);
+        assert_eq!(state_count(&dfa), state_set_list.len());
+
+        // every numbered state behaves as its state set in `determinize`
+        let subset_dfa = determinize(&nfa).unwrap();
+        assert_eq!(&state_set_list[0], subset_dfa.start_state());
+        for (number, state_set) in state_set_list.iter().enumerate() {
+            let number = number as u64;
+            assert_eq!(
+                dfa.accept_state_set().contains(&number),
+                subset_dfa.accept_state_set().contains(state_set)
+            );
+            for trans in "abcde".chars() {
+                assert_eq!(
+                    dfa.next_state(&number, &trans)
+                        .map(|to| &state_set_list[*to as usize]),
+                    subset_dfa.next_state(state_set, &trans)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn determinize_bounded_states() {
+        // (a|b)*a(a|b){n} needs 2^(n+1) DFA states
+        for n in 2..6 {
+            let re = Re::concat(
+                Re::parse("(a|b)*a").unwrap(),
+                Re::repeat(Re::parse("a|b").unwrap(), n),
+            );
+            let nfa = re.compile();
+            match determinize_bounded(&nfa, 1 << n) {
+                Err(DeterminizeError::StateLimitExceeded(err)) => assert!(err.size > 1 << n),
+                result => panic!("unexpected {:?}", result.map(|_| ())),
+            }
+            let dfa = determinize_bounded(&nfa, 1 << (n + 2)).unwrap();
+            assert_eq!(dfa, determinize(&nfa).unwrap());
+            assert!(state_count(&dfa) <= 1 << (n + 2));
+        }
+    }
+
+    #[test]
+    fn minimize() {
+        let dfa = determinize(&Re::parse("(a|b)*c").unwrap().compile()).unwrap();
+        let minimal = minimize_brzozowski(&dfa);
+        assert_eq!(state_count(&minimal), 2);
+        assert!(state_count(&dfa) > 2);
+        for s in strings_up_to("abcd", 4) {
+            assert_eq!(
+                minimal.create().test(s.chars()),
+                dfa.create().test(s.chars()),
+                "{}",
+                s
+            );
+        }
+
+        // fallbacks alongside ranges, also after the first reversal
+        let pattern_list = [
+            "(a|ab)(c|bcd)",
+            ".*ab",
+            "[^a]*a(b|.)",
+            "(ab|a)*",
+            "[a-c]x|.y",
+            "([b-c].|d)*a",
+        ];
+        for pattern in pattern_list.iter() {
+            let dfa = determinize(&Re::parse(pattern).unwrap().compile()).unwrap();
+            let minimal = minimize_brzozowski(&dfa);
+            assert_eq!(minimize_brzozowski(&minimal), minimal, "{}", pattern);
+            for s in strings_up_to("abcdxy", 4) {
+                assert_eq!(
+                    minimal.create().test(s.chars()),
+                    dfa.create().test(s.chars()),
+                    "{} {}",
+                    pattern,
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dfa_to_nfa_round_trip() {
+        let pattern_list = ["(a|b)*c", "a[b-d]+|c", "[^a]b", "(ab)*|.c", "[a-c]x|.y"];
+        for pattern in pattern_list.iter() {
+            let dfa = determinize(&Re::parse(pattern).unwrap().compile()).unwrap();
+            let nfa = dfa_to_nfa(&dfa);
+            let round_trip = determinize(&nfa).unwrap();
+            for s in strings_up_to("abcdxy", 4) {
+                let expected = dfa.create().test(s.chars());
+                assert_eq!(nfa.create().test(s.chars()), expected, "{} {}", pattern, s);
+                assert_eq!(round_trip.create().test(s.chars()), expected);
+            }
+        }
+
+        // the fallback next to [a-c] becomes a predicate connection
+        let dfa = determinize(&Re::parse("[a-c]x|.y").unwrap().compile()).unwrap();
+        assert!(try_dfa_to_nfa(&dfa).is_err());
+        assert!(dfa_to_nfa(&dfa)
+            .iterate_connections()
+            .any(|(_, conn, _)| matches!(conn, ConnType::Predicate(_))));
+
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 0)
+            .accept(1)
+            .finalize();
+        let nfa = NFAutoBlueprint::try_from(&dfa).unwrap();
+        assert!(nfa.is_deterministic());
+        assert_eq!(dfa_to_nfa_strict(&dfa), Ok(nfa.clone()));
+        assert_eq!(nfa.try_into_dfa(), Some(dfa));
+
+        let dfa = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='c', 1)
+            .connect_fallback(0, 0)
+            .finalize();
+        assert_eq!(try_dfa_to_nfa(&dfa), Err(FallbackWithRange { state: 0 }));
+        assert_eq!(
+            NFAutoBlueprint::try_from(&dfa),
+            Err(FallbackWithRange { state: 0 })
+        );
+        assert_eq!(
+            dfa_to_nfa_strict(&dfa),
+            Err(FallbackConnection { state: 0 })
+        );
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_fallback(1, 0)
+            .finalize();
+        assert!(try_dfa_to_nfa(&dfa).is_ok());
+        assert_eq!(
+            dfa_to_nfa_strict(&dfa),
+            Err(FallbackConnection { state: 1 })
+        );
+    }
+
+    #[test]
+    fn product_combiner() {
+        let alphabet: Vec<_> = "abc".chars().collect();
+        let first = determinize(&Re::parse("(a|b)*a").unwrap().compile()).unwrap();
+        let second = determinize(&Re::parse("a(a|c)*").unwrap().compile()).unwrap();
+        let xor = product(&first, &second, &alphabet, |accepted1, accepted2| {
+            accepted1 ^ accepted2
+        });
+        let symmetric_difference = dfa_union(
+            &dfa_difference(&first, &second, &alphabet),
+            &dfa_difference(&second, &first, &alphabet),
+            &alphabet,
+        );
+        let intersect = dfa_intersect(&first, &second, &alphabet);
+        let union = dfa_union(&first, &second, &alphabet);
+        for s in strings_up_to("abc", 5) {
+            let (accepted1, accepted2) = (
+                first.create().test(s.chars()),
+                second.create().test(s.chars()),
+            );
+            assert_eq!(
+                xor.create().test(s.chars()),
+                accepted1 != accepted2,
+                "{}",
+                s
+            );
+            assert_eq!(
+                symmetric_difference.create().test(s.chars()),
+                accepted1 != accepted2
+            );
+            assert_eq!(
+                dfa_symmetric_difference(&first, &second, &alphabet)
+                    .create()
+                    .test(s.chars()),
+                accepted1 != accepted2
+            );
+            assert_eq!(intersect.create().test(s.chars()), accepted1 && accepted2);
+            assert_eq!(union.create().test(s.chars()), accepted1 || accepted2);
+        }
+    }
+
+    #[test]
+    fn nfa_against_dfa() {
+        let alphabet: Vec<_> = "abc".chars().collect();
+        let nfa = Re::parse("(a|b)*.(c|a)").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        assert_eq!(nfa_equivalent_dfa(&nfa, &dfa, &alphabet), Ok(()));
+
+        // same as `a(b|c)*` with the `c` loop missing
+        let full = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 1)
+            .connect(1, 'c', 1)
+            .accept(1)
+            .finalize();
+        let dfa = determinize(&full).unwrap();
+        let broken = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 1)
+            .accept(1)
+            .finalize();
+        assert_eq!(
+            nfa_equivalent_dfa(&broken, &dfa, &alphabet),
+            Err(vec!['a', 'c'])
+        );
+
+        // fallback against wildcard
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_fallback(0, 2)
+            .accept(2)
+            .finalize();
+        let nfa = Re::parse("[^a]").unwrap().compile();
+        assert_eq!(nfa_equivalent_dfa(&nfa, &dfa, &alphabet), Ok(()));
+        let nfa = Re::parse(".").unwrap().compile();
+        assert_eq!(nfa_equivalent_dfa(&nfa, &dfa, &alphabet), Err(vec!['a']));
+    }
+
+    #[test]
+    fn epsilon_closure_of_states() {
+        let nfa = NFAutoBuilder::start(0)
+            .connect_void(0, 1)
+            .connect_void(1, 2)
+            .connect_void(2, 1)
+            .connect(2, 'a', 3)
+            .connect_void(3, 4)
+            .finalize();
+        let closure = epsilon_closure(&nfa, &[0]);
+        assert_eq!(closure, vec![0, 1, 2].into_iter().collect());
+        assert_eq!(epsilon_closure(&nfa, &closure), closure);
+        assert_eq!(
+            epsilon_closure(&nfa, vec![&3, &1]),
+            vec![1, 2, 3, 4].into_iter().collect()
+        );
+        assert!(epsilon_closure(&nfa, &[]).is_empty());
+    }
+
+    fn state_count<S, T>(dfa: &DFAutoBlueprint<S, T>) -> usize
+    where
+        S: Hash + Eq,
+        T: Hash + Eq,
+    {
+        let mut state_set = HashSet::new();
+        let mut unresolved = vec![dfa.start_state()];
+        while let Some(state) = unresolved.pop() {
+            if state_set.insert(state) {
+                let connections = dfa.connections_from(state);
+                unresolved.extend(connections.plain.iter().map(|(_, to)| to));
+                unresolved.extend(connections.range.iter().map(|(_, to)| to));
+                unresolved.extend(connections.fallback);
+            }
+        }
+        state_set.len()
+    }
+
+    fn word(w: &str) -> NFAutoBlueprint<usize, char> {
+        w.chars()
+            .enumerate()
+            .fold(NFAutoBuilder::start(0), |b, (i, c)| b.connect(i, c, i + 1))
+            .accept(w.chars().count())
+            .finalize()
+    }
+
+    fn strings_up_to(alphabet: &str, max_len: usize) -> Vec<String> {
+        let mut strings = vec![String::new()];
+        let mut last = vec![String::new()];
+        for _ in 0..max_len {
+            last = last
+                .iter()
+                .flat_map(|prefix| alphabet.chars().map(move |c| format!("{}{}", prefix, c)))
+                .collect();
+            strings.extend(last.iter().cloned());
+        }
+        strings
+    }
+
+    #[test]
+    fn union_of_words() {
+        let (union, first_map, second_map) = nfa_union(&word("ab"), &word("ba"));
+        assert_eq!(first_map.len(), 3);
+        assert_eq!(second_map.len(), 3);
+        for s in strings_up_to("ab", 4) {
+            assert_eq!(
+                union.create().test(s.chars()),
+                s == "ab" || s == "ba",
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn concat_with_wildcard() {
+        let (concat, _, _) = nfa_concat(&word("a"), &Re::wildcard().compile());
+        for s in strings_up_to("ab", 3) {
+            assert_eq!(
+                concat.create().test(s.chars()),
+                s.len() == 2 && s.starts_with('a'),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn star_of_word() {
+        let (star, _) = nfa_star(&word("ab"));
+        assert!(star.create().test("".chars()));
+        assert!(star.create().test("abab".chars()));
+        assert!(!star.create().test("aba".chars()));
+        assert!(!star.create().test("ba".chars()));
+    }
+
+    #[test]
+    fn intersect() {
+        // a.*a
+        let wildcard = Re::concat(
+            Re::plain('a'),
+            Re::concat(Re::zero_or_more(Re::wildcard()), Re::plain('a')),
+        )
+        .compile();
+        // ab*a
+        let plain = Re::concat(
+            Re::plain('a'),
+            Re::concat(Re::zero_or_more(Re::plain('b')), Re::plain('a')),
+        )
+        .compile();
+        let product = nfa_intersect(&wildcard, &plain);
+        assert!(product.create().test("aba".chars()));
+        assert!(product.create().test("abba".chars()));
+        assert!(product.create().test("aa".chars()));
+        assert!(!product.create().test("aca".chars()));
+        assert!(!product.create().test("ab".chars()));
+        for s in strings_up_to("abc", 5) {
+            assert_eq!(
+                product.create().test(s.chars()),
+                wildcard.create().test(s.chars()) && plain.create().test(s.chars()),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn intersect_wildcards() {
+        // .. against .*b: only the wildcard-wildcard and wildcard-plain pairings apply
+        let two = Re::concat(Re::wildcard(), Re::wildcard()).compile();
+        let ends_with_b = Re::concat(Re::zero_or_more(Re::wildcard()), Re::plain('b')).compile();
+        let product = nfa_intersect(&two, &ends_with_b);
+        assert!(product.create().test("ab".chars()));
+        assert!(product.create().test("cb".chars()));
+        assert!(!product.create().test("ba".chars()));
+        assert!(!product.create().test("b".chars()));
+        assert!(!product.create().test("abb".chars()));
+    }
+
+    #[test]
+    fn predicate_connection() {
+        // [0-9]+
+        let bp = NFAutoBuilder::start(0)
+            .connect_if(0, |c: &char| c.is_ascii_digit(), 1)
+            .connect_void(1, 0)
+            .accept(1)
+            .finalize();
+        assert!(bp.create().test("42".chars()));
+        assert!(bp.create().test("0123456789".chars()));
+        assert!(!bp.create().test("".chars()));
+        assert!(!bp.create().test("4a2".chars()));
+
+        let ascii: Vec<_> = (0..128u8).map(char::from).collect();
+        let dfa = determinize_over(&bp, &ascii);
+        assert!(dfa.create().test("42".chars()));
+        assert!(dfa.create().test("0123456789".chars()));
+        assert!(!dfa.create().test("".chars()));
+        assert!(!dfa.create().test("4a2".chars()));
+    }
+
+    #[test]
+    fn predicate_connection_without_alphabet() {
+        // predicates need not be thread-safe
+        let digits = std::rc::Rc::new("0123456789");
+        let bp = NFAutoBuilder::start(0)
+            .connect_if(0, move |c: &char| digits.contains(*c), 1)
+            .accept(1)
+            .finalize();
+        assert_eq!(
+            determinize_bounded(&bp, usize::MAX),
+            Err(DeterminizeError::PredicateWithoutAlphabet(
+                PredicateWithoutAlphabet
+            ))
+        );
+        assert_eq!(determinize(&bp), Err(PredicateWithoutAlphabet));
+        let options = DeterminizeOptions {
+            elide_dead: true,
+            complete: true,
+        };
+        assert_eq!(
+            determinize_with(&bp, options),
+            Err(PredicateWithoutAlphabet)
+        );
+        assert!(determinize_renumbered(&bp).is_err());
+        assert!(determinize_over(&bp, &['4']).create().test("4".chars()));
+    }
+
+    #[test]
+    fn determinize_ranges() {
+        let bp = NFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 1)
+            .connect_range(0, 'm'..='p', 2)
+            .connect(0, 'n', 3)
+            .connect_range(1, 'x'..='y', 4)
+            .connect(2, 'x', 4)
+            .connect_range(3, 'y'..='z', 4)
+            .accept(1)
+            .accept(4)
+            .finalize();
+        let dfa = determinize(&bp).unwrap();
+        for s in strings_up_to("`almnopqxyz{", 2) {
+            assert_eq!(
+                bp.create().test(s.chars()),
+                dfa.create().test(s.chars()),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn combine_keywords() {
+        let keyword = |w: &str| w.chars().map(Re::plain).reduce(Re::concat).unwrap();
+        let identifier = || {
+            let letter = || {
+                "abcdefghijklmnopqrstuvwxyz"
+                    .chars()
+                    .map(Re::plain)
+                    .reduce(Re::either)
+                    .unwrap()
+            };
+            Re::concat(letter(), Re::zero_or_more(letter()))
+        };
+        let (bp, name_list) = combine_patterns(vec![
+            ("if", keyword("if")),
+            ("else", keyword("else")),
+            ("elif", keyword("elif")),
+            ("identifier", identifier()),
+        ]);
+        assert_eq!(name_list, vec!["if", "else", "elif", "identifier"]);
+        let matched_tags = |input: &str| {
+            let mut auto = bp.create();
+            for c in input.chars() {
+                auto.trigger(&c);
+            }
+            auto.matched_tags().into_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(matched_tags("if"), vec![0, 3]);
+        assert_eq!(matched_tags("elif"), vec![2, 3]);
+        assert_eq!(matched_tags("els"), vec![3]);
+        assert_eq!(matched_tags("foo"), vec![3]);
+        assert_eq!(matched_tags("el1"), Vec::<usize>::new());
+        assert_eq!(matched_tags(""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn determinize_tags() {
+        let keyword = |w: &str| w.chars().map(Re::plain).reduce(Re::concat).unwrap();
+        let (nfa, _) = combine_patterns(vec![
+            ("if", keyword("if")),
+            ("iff", keyword("iff")),
+            ("any", Re::concat(Re::plain('i'), Re::wildcard())),
+        ]);
+        let dfa = determinize(&nfa).unwrap();
+        let matched_tags = |dfa: &DFAutoBlueprint<_, char>, input: &str| {
+            let mut auto = dfa.create();
+            for c in input.chars() {
+                auto.trigger(&c);
+            }
+            auto.matched_tags().into_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(matched_tags(&dfa, "if"), vec![0, 2]);
+        assert_eq!(matched_tags(&dfa, "iff"), vec![1]);
+        assert_eq!(matched_tags(&dfa, "ix"), vec![2]);
+        assert_eq!(matched_tags(&dfa, "i"), Vec::<usize>::new());
+
+        let dfa = dfa.first_tag_only();
+        assert_eq!(matched_tags(&dfa, "if"), vec![0]);
+        assert_eq!(matched_tags(&dfa, "ix"), vec![2]);
+    }
+
+    #[test]
+    fn determinize_wildcard_except() {
+        // [^a]b|[^bc]a|c
+        let nfa = Re::either(
+            Re::concat(Re::none_of(vec!['a']), Re::plain('b')),
+            Re::either(
+                Re::concat(Re::none_of(vec!['b', 'c']), Re::plain('a')),
+                Re::plain('c'),
+            ),
+        )
+        .compile();
+        let dfa = determinize(&nfa).unwrap();
+        for s in strings_up_to("abcd", 3) {
+            assert_eq!(
+                dfa.create().test(s.chars()),
+                nfa.create().test(s.chars()),
+                "{}",
+                s
+            );
+        }
+
+        let other = Re::zero_or_more(Re::none_of(vec!['b'])).compile();
+        let product = nfa_intersect(&nfa, &other);
+        for s in strings_up_to("abcd", 3) {
+            assert_eq!(
+                product.create().test(s.chars()),
+                nfa.create().test(s.chars()) && other.create().test(s.chars()),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn determinize_wildcard_with_plain() {
+        // a(ab|.c), where the wildcard must also fire on 'a'
+        let nfa = Re::parse("a(ab|.c)").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        assert!(nfa.create().test("aac".chars()));
+        assert!(dfa.create().test("aac".chars()));
+        assert!(dfa.create().test("aab".chars()));
+        assert!(dfa.create().test("abc".chars()));
+        assert!(!dfa.create().test("abb".chars()));
+    }
+
+    #[test]
+    fn determinize_differential() {
+        let pattern_list = [
+            "a(ab|.c)",
+            "(a|.)*b",
+            ".*a.b",
+            "([a-c]|.a)*[^b]",
+            "(.|b)(a|[b-d])+.?",
+            "([^a]c|.b|a)*d",
+        ];
+        let mut rng = Lcg::new(42);
+        let alphabet: Vec<_> = "abcde".chars().collect();
+        for pattern in pattern_list.iter() {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let dfa = determinize(&nfa).unwrap();
+            for _ in 0..500 {
+                let len = rng.next(8);
+                let input: String = (0..len)
+                    .map(|_| alphabet[rng.next(alphabet.len())])
+                    .collect();
+                assert_eq!(
+                    dfa.create().test(input.chars()),
+                    nfa.create().test(input.chars()),
+                    "{} {}",
+                    pattern,
+                    input
+                );
+            }
+        }
+    }
 }
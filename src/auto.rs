@@ -1,5 +1,22 @@
 use std::borrow::Borrow;
-use std::iter::Iterator;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, Read};
+use std::iter::{IntoIterator, Peekable};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+mod any;
+mod registry;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "tracing")]
+mod traced;
+
+pub use any::{AnyAuto, AnyBlueprint};
+pub use registry::Registry;
+#[cfg(feature = "tracing")]
+pub use traced::Traced;
 
 pub trait Auto {
     type Trans;
@@ -10,26 +27,47 @@ pub trait Auto {
 
     fn is_accepted(&self) -> bool;
 
+    /// Whether the automaton can never accept again, whatever comes next.
+    /// `false` unless the implementation can tell.
+    fn is_dead(&self) -> bool {
+        false
+    }
+
+    /// Whether the automaton accepts after triggering every item of `iter`.
+    /// No item is consumed after the automaton dies.
     fn test<I>(&mut self, iter: I) -> bool
     where
-        I: Iterator,
+        Self: Sized,
+        I: IntoIterator,
         I::Item: Borrow<Self::Trans>,
     {
+        if self.is_dead() {
+            return false;
+        }
         for trans in iter {
             if !self.test_trigger(trans.borrow()) {
                 return false;
             }
             self.trigger(trans.borrow());
+            if self.is_dead() {
+                return false;
+            }
         }
         self.is_accepted()
     }
 
+    /// Whether the automaton accepts at some point while triggering the items
+    /// of `iter`. No item is consumed after the automaton dies.
     fn search<I>(&mut self, iter: I) -> bool
     where
-        I: Iterator,
+        Self: Sized,
+        I: IntoIterator,
         I::Item: Borrow<Self::Trans>,
     {
         let mut accepted = false;
+        if self.is_dead() {
+            return false;
+        }
         for trans in iter {
             if self.is_accepted() {
                 accepted = true;
@@ -38,7 +76,1618 @@ pub trait Auto {
                 return accepted;
             }
             self.trigger(trans.borrow());
+            if self.is_dead() {
+                return accepted;
+            }
         }
         accepted || self.is_accepted()
     }
+
+    /// Same verdict as `search`, also giving back the rest of `iter`. Only the
+    /// items which have been triggered are consumed: the returned iterator
+    /// starts with the item `test_trigger` failed on, or is exhausted if every
+    /// item was triggered.
+    fn search_lazy<I>(&mut self, iter: I) -> (bool, Peekable<I::IntoIter>)
+    where
+        Self: Sized,
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        let mut iter = iter.into_iter().peekable();
+        let mut accepted = false;
+        loop {
+            if self.is_accepted() {
+                accepted = true;
+            }
+            match iter.next_if(|trans| self.test_trigger(trans.borrow())) {
+                Some(trans) => self.trigger(trans.borrow()),
+                None => return (accepted, iter),
+            }
+        }
+    }
+
+    /// Every length of a prefix of `iter` which is accepted, 0 for the empty
+    /// prefix, stopping at the first transition that cannot be taken.
+    fn search_all<I>(&mut self, iter: I) -> Vec<usize>
+    where
+        Self: Sized,
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        let mut len_list = Vec::new();
+        if self.is_accepted() {
+            len_list.push(0);
+        }
+        for (index, trans) in iter.into_iter().enumerate() {
+            if !self.test_trigger(trans.borrow()) {
+                break;
+            }
+            self.trigger(trans.borrow());
+            if self.is_accepted() {
+                len_list.push(index + 1);
+            }
+        }
+        len_list
+    }
+
+    /// Position of the first item of `iter` for which `test_trigger` fails,
+    /// after triggering every item before it, or `None` if every item can be
+    /// triggered.
+    fn find_reject_index<I>(&mut self, iter: I) -> Option<usize>
+    where
+        Self: Sized,
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        for (index, trans) in iter.into_iter().enumerate() {
+            if !self.test_trigger(trans.borrow()) {
+                return Some(index);
+            }
+            self.trigger(trans.borrow());
+        }
+        None
+    }
+}
+
+/// Blueprints creating runners, implemented by both DFA and NFA blueprints.
+pub trait Blueprint {
+    type State;
+    type Trans;
+    type Runner<'a>: Auto<Trans = Self::Trans>
+    where
+        Self: 'a;
+
+    fn create(&self) -> Self::Runner<'_>;
+
+    fn start_state(&self) -> &Self::State;
+
+    fn accept_state_set(&self) -> &HashSet<Self::State>;
+
+    /// Whether a fresh runner accepts `input`.
+    fn accepts<I>(&self, input: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        self.create().test(input)
+    }
+}
+
+/// Automaton verdicts over iterators, without collecting them first.
+pub trait AutoIteratorExt: Iterator + Sized {
+    /// Item index ranges of the leftmost longest matches of `blueprint`, not
+    /// overlapping each other. After an empty match the search goes on from
+    /// the next item, and an empty match may follow right after a match.
+    fn match_spans<B>(self, blueprint: &B) -> MatchSpans<'_, Self, B>
+    where
+        B: Blueprint,
+        Self::Item: Borrow<B::Trans>,
+    {
+        MatchSpans {
+            iter: self,
+            blueprint,
+            index: 0,
+            next_start: 0,
+            run_list: VecDeque::new(),
+            ended: false,
+        }
+    }
+
+    /// Whether the prefix ending at each item is accepted by `blueprint`.
+    fn accepted_prefixes<B>(self, blueprint: &B) -> AcceptedPrefixes<'_, Self, B>
+    where
+        B: Blueprint,
+        Self::Item: Borrow<B::Trans>,
+    {
+        AcceptedPrefixes {
+            iter: self,
+            auto: blueprint.create(),
+            dead: false,
+        }
+    }
+}
+
+impl<I> AutoIteratorExt for I where I: Iterator {}
+
+// a match attempt started at `start`, with the end of its longest match so far
+struct Run<A> {
+    start: usize,
+    auto: A,
+    end: Option<usize>,
+    alive: bool,
+}
+
+/// Iterator built by `AutoIteratorExt::match_spans`.
+pub struct MatchSpans<'b, I, B>
+where
+    B: Blueprint + 'b,
+{
+    iter: I,
+    blueprint: &'b B,
+    index: usize,
+    next_start: usize,
+    // runs in the order of their starts
+    run_list: VecDeque<Run<B::Runner<'b>>>,
+    ended: bool,
+}
+
+impl<'b, I, B> MatchSpans<'b, I, B>
+where
+    I: Iterator,
+    I::Item: Borrow<B::Trans>,
+    B: Blueprint,
+{
+    fn start_run(&mut self) {
+        if self.index < self.next_start {
+            return;
+        }
+        let auto = self.blueprint.create();
+        let end = if auto.is_accepted() {
+            Some(self.index)
+        } else {
+            None
+        };
+        self.run_list.push_back(Run {
+            start: self.index,
+            auto,
+            end,
+            alive: true,
+        });
+    }
+}
+
+impl<'b, I, B> Iterator for MatchSpans<'b, I, B>
+where
+    I: Iterator,
+    I::Item: Borrow<B::Trans>,
+    B: Blueprint,
+{
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // the leftmost run decides once it cannot go further
+            while self
+                .run_list
+                .front()
+                .is_some_and(|run| self.ended || !run.alive)
+            {
+                let run = self.run_list.pop_front().unwrap();
+                if let Some(end) = run.end {
+                    self.next_start = end.max(run.start + 1);
+                    let next_start = self.next_start;
+                    self.run_list.retain(|run| run.start >= next_start);
+                    return Some(run.start..end);
+                }
+            }
+            if self.ended {
+                return None;
+            }
+            match self.iter.next() {
+                None => {
+                    self.ended = true;
+                    self.start_run();
+                }
+                Some(item) => {
+                    self.start_run();
+                    let trans = item.borrow();
+                    for run in self.run_list.iter_mut().filter(|run| run.alive) {
+                        if run.auto.test_trigger(trans) {
+                            run.auto.trigger(trans);
+                            if run.auto.is_accepted() {
+                                run.end = Some(self.index + 1);
+                            }
+                        } else {
+                            run.alive = false;
+                        }
+                    }
+                    self.run_list.retain(|run| run.alive || run.end.is_some());
+                    self.index += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Iterator built by `AutoIteratorExt::accepted_prefixes`.
+pub struct AcceptedPrefixes<'b, I, B>
+where
+    B: Blueprint + 'b,
+{
+    iter: I,
+    auto: B::Runner<'b>,
+    dead: bool,
+}
+
+impl<'b, I, B> Iterator for AcceptedPrefixes<'b, I, B>
+where
+    I: Iterator,
+    I::Item: Borrow<B::Trans>,
+    B: Blueprint,
+{
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        let item = self.iter.next()?;
+        if !self.dead && self.auto.test_trigger(item.borrow()) {
+            self.auto.trigger(item.borrow());
+            Some(self.auto.is_accepted())
+        } else {
+            self.dead = true;
+            Some(false)
+        }
+    }
+}
+
+/// One item of `Steps`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// Position of the item in the input.
+    pub index: usize,
+    /// Whether the automaton accepts after the step.
+    pub accepted_after: bool,
+    /// Whether the item could be triggered. The last step is the first one
+    /// which could not.
+    pub consumed: bool,
+}
+
+/// Iterator built by `AutoExt::steps`.
+pub struct Steps<A, I> {
+    auto: A,
+    iter: I,
+    index: usize,
+    done: bool,
+}
+
+impl<A, I> Steps<A, I> {
+    /// The automaton as left by the last step.
+    pub fn auto(&self) -> &A {
+        &self.auto
+    }
+
+    pub fn into_inner(self) -> A {
+        self.auto
+    }
+}
+
+impl<A, I> Iterator for Steps<A, I>
+where
+    A: Auto,
+    I: Iterator,
+    I::Item: Borrow<A::Trans>,
+{
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if self.done {
+            return None;
+        }
+        let trans = self.iter.next()?;
+        let consumed = self.auto.test_trigger(trans.borrow());
+        if consumed {
+            self.auto.trigger(trans.borrow());
+        } else {
+            self.done = true;
+        }
+        let step = Step {
+            index: self.index,
+            accepted_after: self.auto.is_accepted(),
+            consumed,
+        };
+        self.index += 1;
+        Some(step)
+    }
+}
+
+/// Change of verdict reported by `Monitor::feed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorEvent {
+    EnteredAccepting,
+    LeftAccepting,
+    StillAccepting,
+    StillRejecting,
+    /// The transition could not be taken, or the automaton died. Every later
+    /// feed reports it again.
+    Dead,
+}
+
+/// Automaton fed one transition at a time, reporting how its verdict changes
+/// rather than a final verdict.
+pub struct Monitor<'c, A> {
+    auto: A,
+    dead: bool,
+    on_edge: Option<Box<dyn FnMut(MonitorEvent) + 'c>>,
+}
+
+impl<'c, A> Monitor<'c, A>
+where
+    A: Auto,
+{
+    pub fn new(auto: A) -> Self {
+        Self {
+            auto,
+            dead: false,
+            on_edge: None,
+        }
+    }
+
+    /// Call `f` on every edge: when the automaton enters or leaves acceptance,
+    /// and when it dies.
+    pub fn on_edge(mut self, f: impl FnMut(MonitorEvent) + 'c) -> Self {
+        self.on_edge = Some(Box::new(f));
+        self
+    }
+
+    pub fn feed(&mut self, trans: &A::Trans) -> MonitorEvent {
+        if self.dead {
+            return MonitorEvent::Dead;
+        }
+        let was_accepted = self.auto.is_accepted();
+        let event = if !self.auto.test_trigger(trans) {
+            MonitorEvent::Dead
+        } else {
+            self.auto.trigger(trans);
+            match (was_accepted, self.auto.is_accepted()) {
+                _ if self.auto.is_dead() => MonitorEvent::Dead,
+                (false, true) => MonitorEvent::EnteredAccepting,
+                (true, false) => MonitorEvent::LeftAccepting,
+                (true, true) => MonitorEvent::StillAccepting,
+                (false, false) => MonitorEvent::StillRejecting,
+            }
+        };
+        self.dead = event == MonitorEvent::Dead;
+        match event {
+            MonitorEvent::StillAccepting | MonitorEvent::StillRejecting => {}
+            _ => {
+                if let Some(on_edge) = &mut self.on_edge {
+                    on_edge(event);
+                }
+            }
+        }
+        event
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        !self.dead && self.auto.is_accepted()
+    }
+
+    pub fn into_inner(self) -> A {
+        self.auto
+    }
+}
+
+/// Automata whose progress can be saved and gone back to, e.g. to backtrack.
+pub trait Checkpointable {
+    type Checkpoint;
+
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Go back to where `checkpoint` was taken.
+    fn restore(&mut self, checkpoint: &Self::Checkpoint);
+}
+
+impl<A> Checkpointable for &mut A
+where
+    A: Checkpointable + ?Sized,
+{
+    type Checkpoint = A::Checkpoint;
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (**self).checkpoint()
+    }
+
+    fn restore(&mut self, checkpoint: &Self::Checkpoint) {
+        (**self).restore(checkpoint)
+    }
+}
+
+impl<A> Checkpointable for Box<A>
+where
+    A: Checkpointable + ?Sized,
+{
+    type Checkpoint = A::Checkpoint;
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (**self).checkpoint()
+    }
+
+    fn restore(&mut self, checkpoint: &Self::Checkpoint) {
+        (**self).restore(checkpoint)
+    }
+}
+
+impl<'a, A> Checkpointable for ByRef<'a, A>
+where
+    A: Checkpointable,
+{
+    type Checkpoint = A::Checkpoint;
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.0.checkpoint()
+    }
+
+    fn restore(&mut self, checkpoint: &Self::Checkpoint) {
+        self.0.restore(checkpoint)
+    }
+}
+
+/// Automata giving an output for some of their transitions.
+pub trait Transduce: Auto {
+    type Output;
+
+    /// Take the transition, as `trigger`, returning its output if any.
+    fn trigger_output(&mut self, trans: &Self::Trans) -> Option<Self::Output>;
+
+    /// Outputs of every transition of `iter`, as long as the input is accepted
+    /// like with `test`.
+    fn transduce<I>(&mut self, iter: I) -> Result<Vec<Self::Output>, Rejection<Self::Output>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        let mut output_list = Vec::new();
+        let mut len = 0;
+        for trans in iter {
+            if !self.test_trigger(trans.borrow()) {
+                return Err(Rejection {
+                    index: len,
+                    output_list,
+                });
+            }
+            output_list.extend(self.trigger_output(trans.borrow()));
+            len += 1;
+        }
+        if self.is_accepted() {
+            Ok(output_list)
+        } else {
+            Err(Rejection {
+                index: len,
+                output_list,
+            })
+        }
+    }
+}
+
+/// Input rejected by `Transduce::transduce`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection<O> {
+    /// Position of the transition which could not be taken, or length of the
+    /// input if it ended outside of an accept state.
+    pub index: usize,
+    /// Outputs given before the rejection.
+    pub output_list: Vec<O>,
+}
+
+impl<O> fmt::Display for Rejection<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "input rejected at {} after {} outputs",
+            self.index,
+            self.output_list.len()
+        )
+    }
+}
+
+impl<O> std::error::Error for Rejection<O> where O: fmt::Debug {}
+
+/// Shortcuts for automata over `char`, taking `&str` inputs and reporting
+/// byte offsets.
+pub trait TextAuto: Auto<Trans = char> {
+    fn test_str(&mut self, text: &str) -> bool
+    where
+        Self: Sized,
+    {
+        self.test(text.chars())
+    }
+
+    fn search_str(&mut self, text: &str) -> bool
+    where
+        Self: Sized,
+    {
+        self.search(text.chars())
+    }
+
+    /// Byte range of the leftmost longest match in `text`, running a copy of
+    /// the automaton from every char boundary in turn. The automaton is left
+    /// after the match, or unchanged if there is none.
+    fn find_str(&mut self, text: &str) -> Option<Range<usize>>
+    where
+        Self: Clone,
+    {
+        for start in text
+            .char_indices()
+            .map(|(start, _)| start)
+            .chain(Some(text.len()))
+        {
+            let mut auto = self.clone();
+            let mut found = if auto.is_accepted() {
+                Some((start, auto.clone()))
+            } else {
+                None
+            };
+            for (offset, trans) in text[start..].char_indices() {
+                if !auto.test_trigger(&trans) {
+                    break;
+                }
+                auto.trigger(&trans);
+                if auto.is_accepted() {
+                    found = Some((start + offset + trans.len_utf8(), auto.clone()));
+                }
+            }
+            if let Some((end, auto)) = found {
+                *self = auto;
+                return Some(start..end);
+            }
+        }
+        None
+    }
+}
+
+impl<A> TextAuto for A where A: Auto<Trans = char> {}
+
+// size of the chunks read by `BytesAuto::test_reader`
+const READ_CHUNK_SIZE: usize = 1 << 16;
+
+/// Shortcuts for automata over `u8`, taking byte slices and readers.
+pub trait BytesAuto: Auto<Trans = u8> {
+    fn test_bytes(&mut self, bytes: &[u8]) -> bool {
+        for byte in bytes {
+            if !self.test_trigger(byte) {
+                return false;
+            }
+            self.trigger(byte);
+        }
+        self.is_accepted()
+    }
+
+    fn search_bytes(&mut self, bytes: &[u8]) -> bool {
+        for byte in bytes {
+            if self.is_accepted() {
+                return true;
+            }
+            if !self.test_trigger(byte) {
+                return false;
+            }
+            self.trigger(byte);
+        }
+        self.is_accepted()
+    }
+
+    /// Range of the leftmost longest match in `bytes`, as in
+    /// `TextAuto::find_str`.
+    fn find_bytes(&mut self, bytes: &[u8]) -> Option<Range<usize>>
+    where
+        Self: Clone,
+    {
+        for start in 0..=bytes.len() {
+            let mut auto = self.clone();
+            let mut found = if auto.is_accepted() {
+                Some((start, auto.clone()))
+            } else {
+                None
+            };
+            for (offset, byte) in bytes[start..].iter().enumerate() {
+                if !auto.test_trigger(byte) {
+                    break;
+                }
+                auto.trigger(byte);
+                if auto.is_accepted() {
+                    found = Some((start + offset + 1, auto.clone()));
+                }
+            }
+            if let Some((end, auto)) = found {
+                *self = auto;
+                return Some(start..end);
+            }
+        }
+        None
+    }
+
+    /// Same as `test_bytes` on everything `reader` gives, read in chunks and
+    /// stopping early once a byte cannot be triggered.
+    fn test_reader(&mut self, mut reader: impl Read) -> io::Result<bool> {
+        let mut buffer = vec![0; READ_CHUNK_SIZE];
+        loop {
+            let len = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(self.is_accepted()),
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            for byte in &buffer[..len] {
+                if !self.test_trigger(byte) {
+                    return Ok(false);
+                }
+                self.trigger(byte);
+            }
+        }
+    }
+}
+
+impl<A> BytesAuto for A where A: Auto<Trans = u8> {}
+
+/// Adapters over any automaton.
+pub trait AutoExt: Auto + Sized {
+    /// Borrow the automaton as an `Auto` of its own, like `Iterator::by_ref`,
+    /// so it stays usable after being passed by value.
+    fn by_ref(&mut self) -> ByRef<'_, Self> {
+        ByRef(self)
+    }
+
+    /// Run `self` until it accepts, as told by `policy`, then run `next` on
+    /// the rest of the input.
+    fn then<B>(self, next: B, policy: SwitchPolicy) -> Then<Self, B>
+    where
+        B: Auto<Trans = Self::Trans>,
+    {
+        let mut then = Then {
+            first: self,
+            second: next,
+            policy,
+            switched: false,
+        };
+        then.settle();
+        then
+    }
+
+    /// Run the automaton over inputs of type `U`, each projected by `f`.
+    fn map_input<F, U>(self, f: F) -> MapInput<Self, F, U>
+    where
+        F: Fn(&U) -> Self::Trans,
+    {
+        MapInput::new(self, f)
+    }
+
+    /// Same as `map_input` with a projection borrowing from the input, so
+    /// nothing gets cloned.
+    fn map_input_ref<F, U>(self, f: F) -> MapInputRef<Self, F, U>
+    where
+        F: Fn(&U) -> &Self::Trans,
+    {
+        MapInputRef::new(self, f)
+    }
+
+    /// Iterator triggering the items of `iter` one at a time, telling how
+    /// each step went.
+    fn steps<I>(self, iter: I) -> Steps<Self, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        Steps {
+            auto: self,
+            iter: iter.into_iter(),
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// Emit `tracing` events while running, tagged with `target`.
+    #[cfg(feature = "tracing")]
+    fn traced(self, target: &'static str) -> Traced<Self> {
+        Traced::new(self, target)
+    }
+}
+
+impl<A> AutoExt for A where A: Auto {}
+
+/// Automaton behind dynamic dispatch, e.g. to store automata of different
+/// kinds together.
+pub type BoxAuto<'b, T> = Box<dyn Auto<Trans = T> + 'b>;
+
+impl<A> Auto for Box<A>
+where
+    A: Auto + ?Sized,
+{
+    type Trans = A::Trans;
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        (**self).trigger(trans)
+    }
+
+    fn test_trigger(&self, trans: &Self::Trans) -> bool {
+        (**self).test_trigger(trans)
+    }
+
+    fn is_accepted(&self) -> bool {
+        (**self).is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        (**self).is_dead()
+    }
+}
+
+/// Automaton borrowed by `AutoExt::by_ref`.
+pub struct ByRef<'a, A>(&'a mut A);
+
+impl<'a, A> Auto for ByRef<'a, A>
+where
+    A: Auto,
+{
+    type Trans = A::Trans;
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        self.0.trigger(trans)
+    }
+
+    fn test_trigger(&self, trans: &Self::Trans) -> bool {
+        self.0.test_trigger(trans)
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.0.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.0.is_dead()
+    }
+}
+
+/// Automaton over `U` projecting every input before passing it on.
+pub struct MapInput<A, F, U> {
+    auto: A,
+    f: F,
+    marker: PhantomData<fn(&U)>,
+}
+
+impl<A, F, U> MapInput<A, F, U>
+where
+    A: Auto,
+    F: Fn(&U) -> A::Trans,
+{
+    pub fn new(auto: A, f: F) -> Self {
+        Self {
+            auto,
+            f,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.auto
+    }
+}
+
+impl<A, F, U> Clone for MapInput<A, F, U>
+where
+    A: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            auto: self.auto.clone(),
+            f: self.f.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A, F, U> Auto for MapInput<A, F, U>
+where
+    A: Auto,
+    F: Fn(&U) -> A::Trans,
+{
+    type Trans = U;
+
+    fn trigger(&mut self, trans: &U) {
+        self.auto.trigger(&(self.f)(trans))
+    }
+
+    fn test_trigger(&self, trans: &U) -> bool {
+        self.auto.test_trigger(&(self.f)(trans))
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.auto.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.auto.is_dead()
+    }
+}
+
+/// Automaton over `U` projecting every input to a borrowed transition.
+pub struct MapInputRef<A, F, U> {
+    auto: A,
+    f: F,
+    marker: PhantomData<fn(&U)>,
+}
+
+impl<A, F, U> MapInputRef<A, F, U>
+where
+    A: Auto,
+    F: Fn(&U) -> &A::Trans,
+{
+    pub fn new(auto: A, f: F) -> Self {
+        Self {
+            auto,
+            f,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.auto
+    }
+}
+
+impl<A, F, U> Clone for MapInputRef<A, F, U>
+where
+    A: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            auto: self.auto.clone(),
+            f: self.f.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A, F, U> Auto for MapInputRef<A, F, U>
+where
+    A: Auto,
+    F: Fn(&U) -> &A::Trans,
+{
+    type Trans = U;
+
+    fn trigger(&mut self, trans: &U) {
+        self.auto.trigger((self.f)(trans))
+    }
+
+    fn test_trigger(&self, trans: &U) -> bool {
+        self.auto.test_trigger((self.f)(trans))
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.auto.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.auto.is_dead()
+    }
+}
+
+/// When `Then` leaves its first automaton for the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchPolicy {
+    /// As soon as the first automaton accepts.
+    First,
+    /// Only once the first automaton cannot take the next transition, if it
+    /// accepts by then. The choice is greedy: the input is rejected if the
+    /// first automaton goes on past its last acceptance.
+    Longest,
+}
+
+/// Two automata run one after the other, built by `AutoExt::then`.
+#[derive(Debug, Clone)]
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+    policy: SwitchPolicy,
+    switched: bool,
+}
+
+impl<A, B> Then<A, B>
+where
+    A: Auto,
+    B: Auto<Trans = A::Trans>,
+{
+    /// Whether the second automaton is being run.
+    pub fn is_switched(&self) -> bool {
+        self.switched
+    }
+
+    // switch as soon as the first automaton accepts, if told so
+    fn settle(&mut self) {
+        if !self.switched && self.policy == SwitchPolicy::First && self.first.is_accepted() {
+            self.switched = true;
+        }
+    }
+}
+
+impl<A, B> Auto for Then<A, B>
+where
+    A: Auto,
+    B: Auto<Trans = A::Trans>,
+{
+    type Trans = A::Trans;
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        if !self.switched && !self.first.test_trigger(trans) && self.first.is_accepted() {
+            self.switched = true;
+        }
+        if self.switched {
+            self.second.trigger(trans);
+        } else {
+            self.first.trigger(trans);
+            self.settle();
+        }
+    }
+
+    fn test_trigger(&self, trans: &Self::Trans) -> bool {
+        if self.switched {
+            self.second.test_trigger(trans)
+        } else {
+            self.first.test_trigger(trans)
+                || (self.first.is_accepted() && self.second.test_trigger(trans))
+        }
+    }
+
+    fn is_accepted(&self) -> bool {
+        if self.switched {
+            self.second.is_accepted()
+        } else {
+            self.first.is_accepted() && self.second.is_accepted()
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        if self.switched {
+            self.second.is_dead()
+        } else {
+            // a dead first automaton still hands over if it accepts
+            self.first.is_dead() && (!self.first.is_accepted() || self.second.is_dead())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::re::Re;
+
+    #[test]
+    fn test_into_iter() {
+        let nfa = Re::parse("(a|b)*c").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        let input: Vec<_> = "abbc".chars().collect();
+        assert!(nfa.create().test(&input));
+        assert!(nfa.create().test(&input[..]));
+        assert!(nfa.create().test(input.clone()));
+        assert!(nfa.create().test("abbc".chars()));
+        assert!(nfa.create().test(input.iter().skip(2)));
+        assert!(!nfa.create().test(input.iter().take(3)));
+        assert!(dfa.create().test(&input));
+        assert!(dfa
+            .create()
+            .test(input.iter().cloned().filter(|c| *c != 'a')));
+
+        assert!(!nfa.create().search(&input[..1]));
+        assert!(dfa.create().search(vec!['c', 'x']));
+        assert!(nfa.create().search("xcab".chars().skip(1)));
+    }
+
+    #[test]
+    fn search_all() {
+        for (pattern, input, expected) in [
+            ("a(ba)*", "ababab", vec![1, 3, 5]),
+            ("a(ba)*", "abaxa", vec![1, 3]),
+            ("(ab)*", "abab", vec![0, 2, 4]),
+            ("(ab)*", "b", vec![0]),
+            ("a.*c", "abcbc", vec![3, 5]),
+        ]
+        .iter()
+        {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let dfa = determinize(&nfa).unwrap();
+            assert_eq!(&nfa.create().search_all(input.chars()), expected);
+            assert_eq!(&dfa.create().search_all(input.chars()), expected);
+        }
+    }
+
+    #[test]
+    fn find_reject_index() {
+        let nfa = Re::parse("ab*c").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        assert_eq!(dfa.create().find_reject_index("abbc".chars()), None);
+        assert_eq!(dfa.create().find_reject_index("abbxc".chars()), Some(3));
+        assert_eq!(dfa.create().find_reject_index("abcc".chars()), Some(3));
+        let mut auto = nfa.create();
+        assert_eq!(auto.find_reject_index("abbxc".chars()), Some(3));
+        assert!(!auto.is_dead());
+        assert!(auto.test("c".chars()));
+        assert_eq!(nfa.create().find_reject_index("".chars()), None);
+
+        // the fallback takes every transition
+        let dfa = determinize(&Re::parse("a.*").unwrap().compile()).unwrap();
+        assert_eq!(dfa.create().find_reject_index("abbxc".chars()), None);
+        assert_eq!(dfa.create().find_reject_index("xa".chars()), Some(0));
+    }
+
+    #[test]
+    fn text_auto() {
+        let dfa = determinize(&Re::parse("[a-z]+!").unwrap().compile()).unwrap();
+        assert!(!dfa.create().test_str("héllo!"));
+        assert!(dfa.create().test_str("hello!"));
+        assert!(dfa.create().search_str("hi!!"));
+
+        let text = "café crème: abc! ok!";
+        let range = dfa.create().find_str(text).unwrap();
+        assert_eq!(&text[range.clone()], "abc!");
+        assert_eq!(range, 14..18);
+        assert_eq!(dfa.create().find_str("é!"), None);
+
+        // matches right after multi-byte characters
+        let nfa = Re::parse("x+").unwrap().compile();
+        let text = "ééxx€x";
+        let mut auto = nfa.create();
+        let range = auto.find_str(text).unwrap();
+        assert_eq!(range, 4..6);
+        assert_eq!(&text[range], "xx");
+        assert!(auto.is_accepted());
+        let range = nfa.create().find_str(&text[6..]).unwrap();
+        assert_eq!(&text[6..][range], "x");
+
+        let nfa = Re::parse("a*").unwrap().compile();
+        assert_eq!(nfa.create().find_str("éa"), Some(0..0));
+    }
+
+    #[test]
+    fn bytes_auto() {
+        let nfa = (Re::literal(b"GET /".iter().cloned())
+            + Re::range(b'a'..=b'z').star()
+            + Re::literal(b"\r\n".iter().cloned()))
+        .compile();
+        let dfa = determinize(&nfa).unwrap();
+        for input in [
+            &b"GET /index\r\n"[..],
+            b"GET /\r\n",
+            b"GET /Index\r\n",
+            b"POST /\r\n",
+            b"GET /a\r\nx",
+            b"",
+        ]
+        .iter()
+        {
+            let expected = dfa.create().test(input.iter());
+            assert_eq!(dfa.create().test_bytes(input), expected);
+            assert_eq!(nfa.create().test_bytes(input), expected);
+            assert_eq!(dfa.create().test_reader(*input).unwrap(), expected);
+            assert_eq!(
+                dfa.create().search_bytes(input),
+                dfa.create().search(input.iter())
+            );
+        }
+        let input = b"xxGET /ab\r\nGET /\r\n";
+        assert_eq!(dfa.create().find_bytes(input), Some(2..11));
+        assert_eq!(nfa.create().find_bytes(&input[3..]), Some(8..15));
+        assert_eq!(dfa.create().find_bytes(b"GET /A\r\n"), None);
+    }
+
+    #[test]
+    fn large_bytes() {
+        let dfa = determinize(&(Re::range(b'a'..=b'z').star() + Re::plain(b'!')).compile())
+            .unwrap()
+            .renumbered();
+        // 10 MB, cut down without optimizations to keep the suite quick
+        let len = if cfg!(debug_assertions) {
+            1 << 18
+        } else {
+            10 << 20
+        };
+        let mut input = vec![b'q'; len];
+        input.push(b'!');
+        assert!(dfa.create().test_bytes(&input));
+        assert!(dfa.create().test_reader(&input[..]).unwrap());
+        input[len / 2] = b'?';
+        assert!(!dfa.create().test_bytes(&input));
+        assert!(!dfa.create().test_reader(&input[..]).unwrap());
+    }
+
+    #[test]
+    fn by_ref() {
+        fn feed(mut auto: impl Auto<Trans = char>, input: &str) -> bool {
+            auto.test(input.chars())
+        }
+
+        let dfa = determinize(&Re::parse("ab*c").unwrap().compile()).unwrap();
+        let mut auto = dfa.create();
+        assert!(!feed(auto.by_ref(), "abb"));
+        assert!(!auto.is_accepted());
+        assert!(feed(auto.by_ref(), "bc"));
+        assert!(auto.is_accepted());
+    }
+
+    #[test]
+    fn then() {
+        let header = determinize(&Re::parse("[A-Z]+:").unwrap().compile()).unwrap();
+        let body = determinize(&Re::parse("[a-z]*;").unwrap().compile()).unwrap();
+        for policy in [SwitchPolicy::First, SwitchPolicy::Longest].iter() {
+            let mut auto = header.create().then(body.create(), *policy);
+            assert!(auto.test("HOST:abc;".chars()));
+            assert!(auto.is_switched());
+            assert!(header
+                .create()
+                .then(body.create(), *policy)
+                .test("X:;".chars()));
+            assert!(!header
+                .create()
+                .then(body.create(), *policy)
+                .test("HOST:abc".chars()));
+            assert!(!header
+                .create()
+                .then(body.create(), *policy)
+                .test("HOST;".chars()));
+        }
+
+        // the header accepts after every `a`
+        let header = determinize(&Re::parse("a+").unwrap().compile()).unwrap();
+        let body = determinize(&Re::parse("ab").unwrap().compile()).unwrap();
+        assert!(header
+            .create()
+            .then(body.create(), SwitchPolicy::First)
+            .test("aab".chars()));
+        assert!(!header
+            .create()
+            .then(body.create(), SwitchPolicy::Longest)
+            .test("aab".chars()));
+        let body = determinize(&Re::parse("b*c").unwrap().compile()).unwrap();
+        assert!(!header
+            .create()
+            .then(body.create(), SwitchPolicy::First)
+            .test("aabbc".chars()));
+        let mut auto = header.create().then(body.create(), SwitchPolicy::Longest);
+        assert!(auto.test("aabbc".chars()));
+        assert_eq!(
+            header
+                .create()
+                .then(body.create(), SwitchPolicy::Longest)
+                .search_all("aaac".chars()),
+            vec![4]
+        );
+
+        // a first automaton accepting the empty input hands over at once
+        let header = determinize(&Re::parse("a*").unwrap().compile()).unwrap();
+        let auto = header.create().then(body.create(), SwitchPolicy::First);
+        assert!(auto.is_switched());
+        assert!(header
+            .create()
+            .then(body.create(), SwitchPolicy::Longest)
+            .test("aac".chars()));
+    }
+
+    #[test]
+    fn map_input() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        enum EventKind {
+            Open,
+            Write,
+            Close,
+        }
+
+        struct Event {
+            kind: EventKind,
+            #[allow(dead_code)]
+            payload: Vec<u8>,
+        }
+
+        use EventKind::*;
+        // opened, written any number of times, then closed
+        let dfa =
+            determinize(&(Re::plain(Open) + Re::plain(Write).star() + Re::plain(Close)).compile())
+                .unwrap();
+        let event_list: Vec<_> = [Open, Write, Write, Close]
+            .iter()
+            .map(|kind| Event {
+                kind: *kind,
+                payload: vec![0; 16],
+            })
+            .collect();
+        assert!(dfa
+            .create()
+            .map_input(|event: &Event| event.kind)
+            .test(&event_list));
+        assert!(dfa
+            .create()
+            .map_input_ref(|event: &Event| &event.kind)
+            .test(&event_list));
+        assert!(!dfa
+            .create()
+            .map_input_ref(|event: &Event| &event.kind)
+            .test(&event_list[1..]));
+        let mut auto = dfa.create().map_input(|event: &Event| event.kind);
+        assert_eq!(auto.find_reject_index(event_list.iter().rev()), Some(0));
+        assert!(auto.into_inner().test(&[Open, Close]));
+    }
+
+    #[test]
+    fn transduce() {
+        use crate::dfa::{DFAuto, DFAutoBlueprint, DFAutoBuilder};
+        use std::collections::HashMap;
+
+        // a DFA runner with an action on some of its connections
+        struct Protocol<'b> {
+            auto: DFAuto<'b, u8, &'static str>,
+            action_map: &'b HashMap<(u8, &'static str), &'static str>,
+        }
+
+        impl<'b> Auto for Protocol<'b> {
+            type Trans = &'static str;
+
+            fn trigger(&mut self, trans: &Self::Trans) {
+                self.auto.trigger(trans)
+            }
+
+            fn test_trigger(&self, trans: &Self::Trans) -> bool {
+                self.auto.test_trigger(trans)
+            }
+
+            fn is_accepted(&self) -> bool {
+                self.auto.is_accepted()
+            }
+        }
+
+        impl<'b> Transduce for Protocol<'b> {
+            type Output = &'static str;
+
+            fn trigger_output(&mut self, trans: &Self::Trans) -> Option<Self::Output> {
+                let action = self
+                    .action_map
+                    .get(&(*self.auto.current_state(), *trans))
+                    .copied();
+                self.auto.trigger(trans);
+                action
+            }
+        }
+
+        let dfa: DFAutoBlueprint<u8, &str> = DFAutoBuilder::start(0)
+            .connect(0, "connect", 1)
+            .connect(1, "data", 1)
+            .connect(1, "close", 2)
+            .accept(2)
+            .finalize();
+        let action_map: HashMap<_, _> = [
+            ((0, "connect"), "handshake"),
+            ((1, "data"), "store"),
+            ((1, "close"), "flush"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let protocol = || Protocol {
+            auto: dfa.create(),
+            action_map: &action_map,
+        };
+
+        assert_eq!(
+            protocol().transduce(&["connect", "data", "data", "close"]),
+            Ok(vec!["handshake", "store", "store", "flush"])
+        );
+        assert_eq!(
+            protocol().transduce(&["connect", "data", "connect", "close"]),
+            Err(Rejection {
+                index: 2,
+                output_list: vec!["handshake", "store"],
+            })
+        );
+        let rejection = protocol().transduce(&["connect", "data"]).unwrap_err();
+        assert_eq!(rejection.index, 2);
+        assert_eq!(rejection.output_list, vec!["handshake", "store"]);
+        assert!(protocol().test(&["connect", "close"]));
+    }
+
+    #[test]
+    fn blueprint() {
+        fn validate<B>(blueprint: &B, accepted: &[&str], rejected: &[&str]) -> usize
+        where
+            B: Blueprint<Trans = char>,
+        {
+            for input in accepted {
+                assert!(blueprint.accepts(input.chars()), "{}", input);
+            }
+            for input in rejected {
+                assert!(!blueprint.create().test(input.chars()), "{}", input);
+            }
+            blueprint.accept_state_set().len()
+        }
+
+        let nfa = Re::parse("a(b|c)*d").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        let accepted = ["ad", "abcbd", "accd"];
+        let rejected = ["", "a", "abd!", "bd"];
+        assert_eq!(validate(&nfa, &accepted, &rejected), 1);
+        assert_eq!(validate(&dfa, &accepted, &rejected), 1);
+        assert!(Blueprint::start_state(&dfa).contains(Blueprint::start_state(&nfa)));
+    }
+
+    #[test]
+    fn iterator_ext() {
+        use std::io::{BufRead, Cursor};
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        enum Event {
+            Login,
+            Action,
+            Logout,
+        }
+
+        let parse = |line: io::Result<String>| match line.unwrap().trim() {
+            "login" => Event::Login,
+            "logout" => Event::Logout,
+            _ => Event::Action,
+        };
+        use Event::*;
+        // a whole session
+        let dfa = determinize(
+            &(Re::plain(Login) + Re::plain(Action).star() + Re::plain(Logout)).compile(),
+        )
+        .unwrap();
+        let log = "action\nlogin\naction\naction\nlogout\nlogout\nlogin\nlogout\nlogin\n";
+        let span_list: Vec<_> = Cursor::new(log)
+            .lines()
+            .map(parse)
+            .match_spans(&dfa)
+            .collect();
+        assert_eq!(span_list, vec![1..5, 6..8]);
+        let verdict_list: Vec<_> = Cursor::new(log)
+            .lines()
+            .map(parse)
+            .skip(1)
+            .accepted_prefixes(&dfa)
+            .collect();
+        assert_eq!(
+            verdict_list,
+            vec![false, false, false, true, false, false, false, false]
+        );
+
+        // leftmost first, then longest
+        let nfa = Re::parse("ab|abcd|bc").unwrap().compile();
+        let span_list: Vec<_> = "xabcdabc".chars().match_spans(&nfa).collect();
+        assert_eq!(span_list, vec![1..5, 5..7]);
+        let nfa = Re::parse("a*").unwrap().compile();
+        let span_list: Vec<_> = "baab".chars().match_spans(&nfa).collect();
+        assert_eq!(span_list, vec![0..0, 1..3, 3..3, 4..4]);
+        assert_eq!("".chars().match_spans(&nfa).count(), 1);
+        assert_eq!(
+            "aab".chars().accepted_prefixes(&nfa).collect::<Vec<_>>(),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn search_lazy() {
+        let dfa = determinize(&Re::parse("ab+").unwrap().compile()).unwrap();
+        let (accepted, mut rest) = dfa.create().search_lazy("axabbc".chars());
+        assert!(!accepted);
+        // the rejected item is still there
+        assert_eq!(rest.next(), Some('x'));
+        let (accepted, rest) = dfa.create().search_lazy(rest);
+        assert!(accepted);
+        assert_eq!(rest.collect::<String>(), "c");
+
+        let (accepted, mut rest) = dfa.create().search_lazy("abb".chars());
+        assert!(accepted);
+        assert_eq!(rest.next(), None);
+        let (accepted, rest) = dfa.create().search_lazy(vec!['b', 'a']);
+        assert!(!accepted);
+        assert_eq!(rest.count(), 2);
+        for input in ["", "abc", "xab", "abab", "aab"].iter() {
+            assert_eq!(
+                dfa.create().search_lazy(input.chars()).0,
+                dfa.create().search(input.chars()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn boxed() {
+        use crate::dfa::DFAutoBuilder;
+        use std::collections::HashMap;
+
+        let digits = determinize(&Re::parse("[0-9]+").unwrap().compile()).unwrap();
+        let word = Re::parse("[a-z]+").unwrap().compile();
+        let yes_no = DFAutoBuilder::start(0)
+            .connect(0, 'y', 1)
+            .connect(0, 'n', 1)
+            .accept(1)
+            .finalize();
+        let hex = Re::parse("0x[0-9a-f]+").unwrap().compile();
+        let mut validator_map: HashMap<String, BoxAuto<char>> = HashMap::new();
+        validator_map.insert("digits".to_string(), digits.create_boxed());
+        validator_map.insert("word".to_string(), word.create_boxed());
+        validator_map.insert("yes_no".to_string(), yes_no.create_boxed());
+        validator_map.insert("hex".to_string(), Box::new(hex.create_lazy_dfa()));
+
+        for (key, input, expected) in [
+            ("digits", "0042", true),
+            ("word", "hello", true),
+            ("yes_no", "y", true),
+            ("hex", "0x1f", true),
+            ("word", "Hello", false),
+        ]
+        .iter()
+        {
+            let auto = validator_map.get_mut(*key).unwrap();
+            assert_eq!(auto.test_str(input), *expected, "{} {}", key, input);
+        }
+        let auto = validator_map.get_mut("digits").unwrap();
+        assert!(auto.search("12a".chars()));
+        let auto: &mut dyn Auto<Trans = char> = &mut **auto;
+        assert!(auto.test_trigger(&'3'));
+    }
+
+    #[test]
+    fn stop_when_dead() {
+        use crate::dfa::DFAutoBuilder;
+        use std::cell::Cell;
+
+        // `x` leads to a trap state
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 0)
+            .connect(0, 'b', 1)
+            .connect(0, 'x', 2)
+            .accept(1)
+            .finalize();
+        let consumed = Cell::new(0);
+        let counted = |input: &'static str| {
+            consumed.set(0);
+            input.chars().inspect(|_| consumed.set(consumed.get() + 1))
+        };
+        let mut auto = dfa.create();
+        assert!(!auto.test(counted("aaxaab")));
+        assert_eq!(consumed.get(), 3);
+        assert!(auto.is_dead());
+        assert!(!dfa.create().search(counted("axab")));
+        assert_eq!(consumed.get(), 2);
+        assert!(dfa.create().search(counted("abxb")));
+        assert!(dfa.create().test(counted("aab")));
+        assert_eq!(consumed.get(), 3);
+        // an accept state without connections is not dead
+        let mut auto = dfa.create();
+        auto.trigger(&'b');
+        assert!(!auto.is_dead());
+
+        // NFAs die once no state is left
+        let nfa = Re::parse("ab*").unwrap().compile();
+        assert!(!nfa.create().test(counted("ba")));
+        assert_eq!(consumed.get(), 1);
+        let boxed: BoxAuto<char> = Box::new(nfa.create());
+        let mut mapped = boxed.map_input(|c: &char| c.to_ascii_lowercase());
+        assert!(mapped.test(counted("AB")));
+        assert!(!mapped.is_dead());
+        mapped.trigger(&'C');
+        assert!(mapped.is_dead());
+    }
+
+    #[test]
+    fn monitor() {
+        use std::cell::RefCell;
+
+        // every request `r` is granted `g` eventually, `i` being idle
+        let nfa = Re::parse("([ig]|r[ir]*g)*").unwrap().compile();
+        let edge_list = RefCell::new(Vec::new());
+        let mut monitor =
+            Monitor::new(nfa.create()).on_edge(|event| edge_list.borrow_mut().push(event));
+        assert!(monitor.is_accepted());
+        let event_list: Vec<_> = "irigrggxi".chars().map(|c| monitor.feed(&c)).collect();
+        use MonitorEvent::*;
+        assert_eq!(
+            event_list,
+            vec![
+                StillAccepting,
+                LeftAccepting,
+                StillRejecting,
+                EnteredAccepting,
+                LeftAccepting,
+                EnteredAccepting,
+                StillAccepting,
+                Dead,
+                Dead,
+            ]
+        );
+        assert!(!monitor.is_accepted());
+        drop(monitor);
+        assert_eq!(
+            edge_list.into_inner(),
+            vec![
+                LeftAccepting,
+                EnteredAccepting,
+                LeftAccepting,
+                EnteredAccepting,
+                Dead
+            ]
+        );
+
+        // a DFA dies in its trap state
+        let dfa = determinize(&Re::parse("ab").unwrap().compile()).unwrap();
+        let mut monitor = Monitor::new(dfa.create());
+        assert_eq!(monitor.feed(&'a'), StillRejecting);
+        assert_eq!(monitor.feed(&'b'), EnteredAccepting);
+        assert_eq!(monitor.feed(&'b'), Dead);
+    }
+
+    #[test]
+    fn checkpoint() {
+        // expansions of `input` where `?` stands for `a` or `b`, found by
+        // backtracking, in order
+        fn expansions<A>(auto: &mut A, input: &[char], prefix: &mut String, found: &mut Vec<String>)
+        where
+            A: Auto<Trans = char> + Checkpointable,
+        {
+            let (first, rest) = match input.split_first() {
+                Some(split) => split,
+                None => {
+                    if auto.is_accepted() {
+                        found.push(prefix.clone());
+                    }
+                    return;
+                }
+            };
+            let choice_list = if *first == '?' {
+                vec!['a', 'b']
+            } else {
+                vec![*first]
+            };
+            for c in choice_list {
+                if !auto.test_trigger(&c) {
+                    continue;
+                }
+                let checkpoint = auto.checkpoint();
+                auto.trigger(&c);
+                prefix.push(c);
+                expansions(auto, rest, prefix, found);
+                prefix.pop();
+                auto.restore(&checkpoint);
+            }
+        }
+
+        let nfa = Re::parse("(a|b)*ab(a|b)").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        for input in ["??b?", "????", "a?", "ab?"].iter() {
+            let input: Vec<_> = input.chars().collect();
+            let mut expected = Vec::new();
+            expansions(&mut nfa.create(), &input, &mut String::new(), &mut expected);
+            let mut found = Vec::new();
+            expansions(&mut dfa.create(), &input, &mut String::new(), &mut found);
+            assert_eq!(found, expected);
+            let mut found = Vec::new();
+            expansions(
+                &mut Box::new(dfa.create()),
+                &input,
+                &mut String::new(),
+                &mut found,
+            );
+            assert_eq!(found, expected);
+            let mut found = Vec::new();
+            let mut auto = nfa.create();
+            expansions(&mut auto.by_ref(), &input, &mut String::new(), &mut found);
+            assert_eq!(found, expected);
+            assert!(!auto.is_accepted());
+        }
+        let mut expected = Vec::new();
+        expansions(
+            &mut nfa.create(),
+            &['?', 'b', '?'],
+            &mut String::new(),
+            &mut expected,
+        );
+        assert_eq!(expected, vec!["aba", "abb"]);
+    }
 }
@@ -0,0 +1,175 @@
+use super::Auto;
+use crate::dfa::{DFAuto, DFAutoBlueprint};
+use crate::nfa::{NFAuto, NFAutoBlueprint};
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+/// Either kind of blueprint, to store both together.
+#[derive(Debug, Clone)]
+pub enum AnyBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    Dfa(DFAutoBlueprint<S, T>),
+    Nfa(NFAutoBlueprint<S, T>),
+}
+
+impl<S, T> AnyBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    pub fn create(&self) -> AnyAuto<'_, S, T> {
+        match self {
+            AnyBlueprint::Dfa(dfa) => AnyAuto::Dfa(dfa.create()),
+            AnyBlueprint::Nfa(nfa) => AnyAuto::Nfa(nfa.create()),
+        }
+    }
+
+    /// Whether a fresh runner accepts `input`.
+    pub fn accepts<I>(&self, input: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        self.create().test(input)
+    }
+}
+
+impl<S, T> From<DFAutoBlueprint<S, T>> for AnyBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn from(dfa: DFAutoBlueprint<S, T>) -> Self {
+        AnyBlueprint::Dfa(dfa)
+    }
+}
+
+impl<S, T> From<NFAutoBlueprint<S, T>> for AnyBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn from(nfa: NFAutoBlueprint<S, T>) -> Self {
+        AnyBlueprint::Nfa(nfa)
+    }
+}
+
+/// Either kind of runner, without the indirection of `BoxAuto`.
+#[derive(Debug, Clone)]
+pub enum AnyAuto<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    Dfa(DFAuto<'b, S, T>),
+    Nfa(NFAuto<'b, S, T>),
+}
+
+impl<'b, S, T> From<DFAuto<'b, S, T>> for AnyAuto<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn from(auto: DFAuto<'b, S, T>) -> Self {
+        AnyAuto::Dfa(auto)
+    }
+}
+
+impl<'b, S, T> From<NFAuto<'b, S, T>> for AnyAuto<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn from(auto: NFAuto<'b, S, T>) -> Self {
+        AnyAuto::Nfa(auto)
+    }
+}
+
+impl<'b, S, T> Auto for AnyAuto<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    type Trans = T;
+
+    fn trigger(&mut self, trans: &T) {
+        match self {
+            AnyAuto::Dfa(auto) => auto.trigger(trans),
+            AnyAuto::Nfa(auto) => auto.trigger(trans),
+        }
+    }
+
+    fn test_trigger(&self, trans: &T) -> bool {
+        match self {
+            AnyAuto::Dfa(auto) => auto.test_trigger(trans),
+            AnyAuto::Nfa(auto) => auto.test_trigger(trans),
+        }
+    }
+
+    fn is_accepted(&self) -> bool {
+        match self {
+            AnyAuto::Dfa(auto) => auto.is_accepted(),
+            AnyAuto::Nfa(auto) => auto.is_accepted(),
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        match self {
+            AnyAuto::Dfa(auto) => auto.is_dead(),
+            AnyAuto::Nfa(auto) => auto.is_dead(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::re::Re;
+
+    // the same loop for every kind of runner
+    fn drive(mut auto: AnyAuto<'_, u64, char>, input: &str) -> (bool, bool, usize) {
+        let mut triggered = 0;
+        for c in input.chars() {
+            if !auto.test_trigger(&c) {
+                break;
+            }
+            auto.trigger(&c);
+            triggered += 1;
+        }
+        (auto.is_accepted(), auto.is_dead(), triggered)
+    }
+
+    #[test]
+    fn same_verdicts() {
+        for pattern in ["a(b|c)*d", "(ab)*", "x?y+"].iter() {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let dfa: AnyBlueprint<_, _> = determinize(&nfa).unwrap().renumbered().into();
+            let nfa: AnyBlueprint<_, _> = nfa.into();
+            for input in ["", "ad", "abcbd", "abab", "aba", "yyy", "xy", "xx", "adx"].iter() {
+                let expected = drive(nfa.create(), input);
+                let (accepted, _, triggered) = drive(dfa.create(), input);
+                assert_eq!(
+                    (accepted, triggered),
+                    (expected.0, expected.2),
+                    "{} {}",
+                    pattern,
+                    input
+                );
+                assert_eq!(dfa.accepts(input.chars()), nfa.accepts(input.chars()));
+            }
+        }
+
+        let nfa = Re::parse("ab").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap().renumbered();
+        let auto_list: Vec<AnyAuto<_, _>> = vec![dfa.create().into(), nfa.create().into()];
+        for mut auto in auto_list {
+            assert!(!auto.test_trigger(&'b'));
+            auto.trigger(&'a');
+            assert!(auto.test("b".chars()));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use super::AnyBlueprint;
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+#[cfg(feature = "rayon")]
+use super::Auto;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use std::collections::HashSet;
+
+/// Blueprints of both kinds stored under keys, e.g. rule ids, to find every
+/// one accepting an input.
+#[derive(Debug, Clone)]
+pub struct Registry<K, T, S = u64>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    // in insertion order
+    entry_list: Vec<(K, AnyBlueprint<S, T>)>,
+}
+
+impl<K, T, S> Default for Registry<K, T, S>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self {
+            entry_list: Vec::new(),
+        }
+    }
+}
+
+impl<K, T, S> Registry<K, T, S>
+where
+    K: Eq,
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `blueprint` under `key`, returning the blueprint it replaces.
+    /// A replaced blueprint keeps its place in the order of the keys.
+    pub fn insert(
+        &mut self,
+        key: K,
+        blueprint: impl Into<AnyBlueprint<S, T>>,
+    ) -> Option<AnyBlueprint<S, T>> {
+        let blueprint = blueprint.into();
+        match self.entry_list.iter_mut().find(|(other, _)| other == &key) {
+            Some((_, old)) => Some(std::mem::replace(old, blueprint)),
+            None => {
+                self.entry_list.push((key, blueprint));
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entry_list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry_list.is_empty()
+    }
+
+    /// Keys of the blueprints accepting `input`, in insertion order. Every
+    /// blueprint runs over its own clone of `input`.
+    pub fn matching_keys<I>(&self, input: I) -> Vec<&K>
+    where
+        I: IntoIterator + Clone,
+        I::Item: Borrow<T>,
+    {
+        self.entry_list
+            .iter()
+            .filter(|(_, blueprint)| blueprint.accepts(input.clone()))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Same as `matching_keys`, running the DFA blueprints in parallel. NFA
+    /// blueprints run on the calling thread, as their predicate connections
+    /// need not be thread-safe.
+    #[cfg(feature = "rayon")]
+    pub fn matching_keys_parallel<I>(&self, input: I) -> Vec<&K>
+    where
+        S: Send + Sync,
+        T: Send + Sync,
+        I: IntoIterator + Clone + Sync,
+        I::Item: Borrow<T>,
+    {
+        let dfa_list: Vec<_> = self
+            .entry_list
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, blueprint))| match blueprint {
+                AnyBlueprint::Dfa(dfa) => Some((index, dfa)),
+                AnyBlueprint::Nfa(_) => None,
+            })
+            .collect();
+        let accepted_set: HashSet<_> = dfa_list
+            .par_iter()
+            .filter(|(_, dfa)| dfa.create().test(input.clone()))
+            .map(|(index, _)| *index)
+            .collect();
+        self.entry_list
+            .iter()
+            .enumerate()
+            .filter(|(index, (_, blueprint))| match blueprint {
+                AnyBlueprint::Dfa(_) => accepted_set.contains(index),
+                AnyBlueprint::Nfa(nfa) => nfa.create().test(input.clone()),
+            })
+            .map(|(_, (key, _))| key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::dfa::DFAutoBuilder;
+    use crate::re::Re;
+
+    fn rule_registry() -> Registry<&'static str, char> {
+        let mut registry = Registry::new();
+        registry.insert("digits", Re::parse("[0-9]+").unwrap().compile());
+        registry.insert(
+            "even",
+            determinize(&Re::parse("[0-9]*[02468]").unwrap().compile())
+                .unwrap()
+                .renumbered(),
+        );
+        registry.insert("word", Re::parse("[a-z]+").unwrap().compile());
+        registry.insert(
+            "short",
+            DFAutoBuilder::start(0)
+                .connect_range(0, '\0'..=char::MAX, 1)
+                .connect_range(1, '\0'..=char::MAX, 2)
+                .accept(1)
+                .accept(2)
+                .finalize(),
+        );
+        registry
+    }
+
+    #[test]
+    fn matching_keys() {
+        let registry = rule_registry();
+        assert_eq!(registry.len(), 4);
+        assert_eq!(
+            registry.matching_keys("1234".chars()),
+            vec![&"digits", &"even"]
+        );
+        assert_eq!(
+            registry.matching_keys("12".chars()),
+            vec![&"digits", &"even", &"short"]
+        );
+        assert_eq!(
+            registry.matching_keys("ab".chars()),
+            vec![&"word", &"short"]
+        );
+        let input: Vec<_> = "a1".chars().collect();
+        assert_eq!(registry.matching_keys(&input), vec![&"short"]);
+        assert!(registry.matching_keys("a1b2c".chars()).is_empty());
+
+        // replacing keeps the order
+        let mut registry = registry;
+        assert!(registry
+            .insert("digits", Re::parse("[0-9]").unwrap().compile())
+            .is_some());
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.matching_keys("1234".chars()), vec![&"even"]);
+        assert_eq!(
+            registry.matching_keys("7".chars()),
+            vec![&"digits", &"short"]
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn matching_keys_parallel() {
+        let registry = rule_registry();
+        for input in ["1234", "12", "ab", "a1", ""].iter() {
+            assert_eq!(
+                registry.matching_keys_parallel(input.chars()),
+                registry.matching_keys(input.chars())
+            );
+        }
+    }
+}
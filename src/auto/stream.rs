@@ -0,0 +1,96 @@
+use super::Auto;
+use futures_core::Stream;
+use std::borrow::Borrow;
+use std::future::poll_fn;
+use std::pin::pin;
+
+/// Same as `Auto::test` over the items of `stream`. Each item is triggered as
+/// soon as it is polled, so dropping the future before it completes leaves
+/// the automaton right after the last item taken from the stream.
+pub async fn test_stream<A, St>(auto: &mut A, stream: St) -> bool
+where
+    A: Auto + ?Sized,
+    St: Stream,
+    St::Item: Borrow<A::Trans>,
+{
+    let mut stream = pin!(stream);
+    while let Some(trans) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        if !auto.test_trigger(trans.borrow()) {
+            return false;
+        }
+        auto.trigger(trans.borrow());
+    }
+    auto.is_accepted()
+}
+
+/// Same as `Auto::search` over the items of `stream`, and as cancellation
+/// safe as `test_stream`.
+pub async fn search_stream<A, St>(auto: &mut A, stream: St) -> bool
+where
+    A: Auto + ?Sized,
+    St: Stream,
+    St::Item: Borrow<A::Trans>,
+{
+    let mut stream = pin!(stream);
+    let mut accepted = false;
+    while let Some(trans) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        if auto.is_accepted() {
+            accepted = true;
+        }
+        if !auto.test_trigger(trans.borrow()) {
+            return accepted;
+        }
+        auto.trigger(trans.borrow());
+    }
+    accepted || auto.is_accepted()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::re::Re;
+    use futures::executor::block_on;
+    use futures::stream::{self, StreamExt};
+    use futures::FutureExt;
+
+    #[test]
+    fn drive_from_stream() {
+        let dfa = determinize(&Re::parse("a(b|c)*d").unwrap().compile()).unwrap();
+        for input in ["abcd", "ad", "abc", "abxd", "xad", ""].iter() {
+            let expected = dfa.create().test(input.chars());
+            assert_eq!(
+                block_on(test_stream(&mut dfa.create(), stream::iter(input.chars()))),
+                expected,
+                "{}",
+                input
+            );
+            let expected = dfa.create().search(input.chars());
+            assert_eq!(
+                block_on(search_stream(
+                    &mut dfa.create(),
+                    stream::iter(input.chars())
+                )),
+                expected,
+                "{}",
+                input
+            );
+        }
+        let input: Vec<_> = "abbd".chars().collect();
+        assert!(block_on(test_stream(
+            &mut dfa.create(),
+            stream::iter(&input)
+        )));
+    }
+
+    #[test]
+    fn cancel_mid_stream() {
+        let dfa = determinize(&Re::parse("abc").unwrap().compile()).unwrap();
+        let mut auto = dfa.create();
+        // the third item never comes
+        let stream = stream::iter("ab".chars()).chain(stream::pending());
+        assert_eq!(test_stream(&mut auto, stream).now_or_never(), None);
+        assert!(!auto.is_accepted());
+        assert!(auto.test("c".chars()));
+    }
+}
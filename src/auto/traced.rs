@@ -0,0 +1,214 @@
+use super::{Auto, AutoExt};
+use crate::dfa::DFAuto;
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::hash::Hash;
+use tracing::field::Empty;
+use tracing::{trace, trace_span};
+
+/// Automaton emitting a `tracing` event on every trigger, with the transition
+/// and whether the automaton accepts after it, and a span around every `test`
+/// and `search` recording the verdict. Since `tracing` targets are fixed at
+/// compile time, `target` is emitted as the `auto` field.
+pub struct Traced<A>
+where
+    A: Auto,
+{
+    auto: A,
+    target: &'static str,
+    describe_state: Option<fn(&A) -> String>,
+}
+
+impl<A> Traced<A>
+where
+    A: Auto,
+{
+    pub fn new(auto: A, target: &'static str) -> Self {
+        Self {
+            auto,
+            target,
+            describe_state: None,
+        }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.auto
+    }
+}
+
+impl<'b, S, T> Traced<DFAuto<'b, S, T>>
+where
+    S: Eq + Hash + Clone + Debug,
+    T: Eq + Hash,
+{
+    /// Also emit the state reached by every trigger.
+    pub fn with_state(mut self) -> Self {
+        self.describe_state = Some(|auto| format!("{:?}", auto.current_state()));
+        self
+    }
+}
+
+impl<A> Auto for Traced<A>
+where
+    A: Auto,
+    A::Trans: Debug,
+{
+    type Trans = A::Trans;
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        self.auto.trigger(trans);
+        let accepted = self.auto.is_accepted();
+        match self.describe_state {
+            Some(describe_state) => trace!(
+                auto = self.target,
+                trans = ?trans,
+                accepted,
+                state = %describe_state(&self.auto),
+            ),
+            None => trace!(auto = self.target, trans = ?trans, accepted),
+        }
+    }
+
+    fn test_trigger(&self, trans: &Self::Trans) -> bool {
+        self.auto.test_trigger(trans)
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.auto.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.auto.is_dead()
+    }
+
+    fn test<I>(&mut self, iter: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        let span = trace_span!("test", auto = self.target, verdict = Empty);
+        let _entered = span.enter();
+        let verdict = self.by_ref().test(iter);
+        span.record("verdict", verdict);
+        verdict
+    }
+
+    fn search<I>(&mut self, iter: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self::Trans>,
+    {
+        let span = trace_span!("search", auto = self.target, verdict = Empty);
+        let _entered = span.enter();
+        let verdict = self.by_ref().search(iter);
+        span.record("verdict", verdict);
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::re::Re;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    // every event and span record, as lists of field names and values
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<FieldList>>>);
+
+    struct FieldList(Vec<(String, String)>);
+
+    impl Capture {
+        fn record_list(&self) -> Vec<Vec<(String, String)>> {
+            let record_list = self.0.lock().unwrap();
+            record_list
+                .iter()
+                .map(|field_list| field_list.0.clone())
+                .collect()
+        }
+    }
+
+    impl Visit for FieldList {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl<C> Layer<C> for Capture
+    where
+        C: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_event(&self, event: &Event<'_>, _: Context<'_, C>) {
+            let mut field_list = FieldList(Vec::new());
+            event.record(&mut field_list);
+            self.0.lock().unwrap().push(field_list);
+        }
+
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, C>) {
+            let mut field_list = FieldList(vec![field("span", attrs.metadata().name())]);
+            attrs.record(&mut field_list);
+            self.0.lock().unwrap().push(field_list);
+        }
+
+        fn on_record(&self, _: &Id, values: &Record<'_>, _: Context<'_, C>) {
+            let mut field_list = FieldList(Vec::new());
+            values.record(&mut field_list);
+            self.0.lock().unwrap().push(field_list);
+        }
+    }
+
+    fn field(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn emit_events() {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let dfa = determinize(&Re::parse("ab").unwrap().compile())
+            .unwrap()
+            .renumbered();
+        let verdict = tracing::subscriber::with_default(subscriber, || {
+            dfa.create().traced("login").with_state().test("ab".chars())
+        });
+        assert!(verdict);
+        let record_list = capture.record_list();
+        assert_eq!(record_list.len(), 4);
+        assert_eq!(record_list[0][0], field("span", "test"));
+        assert_eq!(record_list[0][1], field("auto", "\"login\""));
+        assert_eq!(
+            record_list[1][..3],
+            [
+                field("auto", "\"login\""),
+                field("trans", "'a'"),
+                field("accepted", "false")
+            ]
+        );
+        assert_eq!(record_list[1][3].0, "state");
+        assert_eq!(record_list[2][2], field("accepted", "true"));
+        assert_eq!(record_list[3], vec![field("verdict", "true")]);
+
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let nfa = Re::parse("a+").unwrap().compile();
+        let verdict = tracing::subscriber::with_default(subscriber, || {
+            nfa.create().traced("repeat").search("aab".chars())
+        });
+        assert!(verdict);
+        let record_list = capture.record_list();
+        // no event for the transition which cannot be taken
+        assert_eq!(record_list.len(), 4);
+        assert_eq!(record_list[0][0], field("span", "search"));
+        assert_eq!(record_list[2].len(), 3);
+        assert_eq!(record_list[3], vec![field("verdict", "true")]);
+    }
+}
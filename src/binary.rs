@@ -0,0 +1,412 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"AUTD";
+const FORMAT_VERSION: u8 = 1;
+// magic, version and checksum
+const MIN_LEN: usize = 4 + 1 + 4;
+
+/// Error reading a compiled blueprint.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// The input does not start with the magic number.
+    BadMagic,
+    /// The input was written by a later version of the format.
+    UnsupportedVersion(u8),
+    /// The input ends before its checksum.
+    Truncated,
+    BadChecksum,
+    /// The checksum matches but the content does not describe a blueprint.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::BadMagic => f.write_str("not a compiled blueprint"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            LoadError::Truncated => f.write_str("truncated input"),
+            LoadError::BadChecksum => f.write_str("checksum mismatch"),
+            LoadError::Malformed(message) => write!(f, "malformed blueprint: {}", message),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+// 32-bit FNV-1a
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+// `value` as written, failing instead of truncating values above `u32::MAX`
+fn encoded(value: usize, what: &str) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} {} above u32::MAX", what, value),
+        )
+    })
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+// the content of a blueprint after the header, consumed from the front
+struct Body<'b>(&'b [u8]);
+
+impl<'b> Body<'b> {
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        let (byte, rest) = self
+            .0
+            .split_first()
+            .ok_or(LoadError::Malformed("unexpected end"))?;
+        self.0 = rest;
+        Ok(*byte)
+    }
+
+    fn varint(&mut self) -> Result<u32, LoadError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as u32;
+            if shift == 28 && bits > 0x0f {
+                return Err(LoadError::Malformed("varint overflow"));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(LoadError::Malformed("varint overflow"))
+    }
+
+    // a count of items taking at least `item_len` bytes each, checked
+    // against the remaining input before anything is allocated for them
+    fn count(&mut self, item_len: usize) -> Result<usize, LoadError> {
+        let count = self.varint()? as usize;
+        if count.saturating_mul(item_len) > self.0.len() {
+            return Err(LoadError::Malformed("count beyond end"));
+        }
+        Ok(count)
+    }
+}
+
+/// The binary format starts with the magic number `AUTD` and a version byte,
+/// followed by LEB128 varints: the start state, then counted lists of accept
+/// states, accept tags, plain connections, ranges and fallbacks, each sorted.
+/// It ends with a little-endian FNV-1a checksum of everything before it.
+impl DFAutoBlueprint<u32, u8> {
+    pub fn write_compiled(&self, mut w: impl Write) -> io::Result<()> {
+        let mut buf = MAGIC.to_vec();
+        buf.push(FORMAT_VERSION);
+        write_varint(&mut buf, *self.start_state());
+
+        let mut accept_list: Vec<_> = self.accept_state_set().iter().copied().collect();
+        accept_list.sort_unstable();
+        write_varint(&mut buf, encoded(accept_list.len(), "count")?);
+        for state in &accept_list {
+            write_varint(&mut buf, *state);
+        }
+        let mut tag_list = Vec::new();
+        for state in &accept_list {
+            for tag in self.accept_tags(state).iter() {
+                tag_list.push((*state, encoded(*tag, "accept tag")?));
+            }
+        }
+        tag_list.sort_unstable();
+        write_varint(&mut buf, encoded(tag_list.len(), "count")?);
+        for (state, tag) in tag_list {
+            write_varint(&mut buf, state);
+            write_varint(&mut buf, tag);
+        }
+
+        let mut plain_list: Vec<_> = self
+            .iterate_connections()
+            .map(|(from, trans, to)| (*from, *trans, *to))
+            .collect();
+        plain_list.sort_unstable();
+        write_varint(&mut buf, encoded(plain_list.len(), "count")?);
+        for (from, trans, to) in plain_list {
+            write_varint(&mut buf, from);
+            buf.push(trans);
+            write_varint(&mut buf, to);
+        }
+        // bounds made inclusive, leaving out empty ranges
+        let mut range_list: Vec<_> = self
+            .iterate_range_connections()
+            .filter_map(|(from, range, to)| {
+                let start = match range.contains(range.start()) {
+                    true => *range.start(),
+                    false => range.start().checked_add(1)?,
+                };
+                let end = match range.contains(range.end()) {
+                    true => *range.end(),
+                    false => range.end().checked_sub(1)?,
+                };
+                Some((*from, start, end, *to)).filter(|_| start <= end)
+            })
+            .collect();
+        range_list.sort_unstable();
+        write_varint(&mut buf, encoded(range_list.len(), "count")?);
+        for (from, start, end, to) in range_list {
+            write_varint(&mut buf, from);
+            buf.extend(&[start, end]);
+            write_varint(&mut buf, to);
+        }
+        let mut fallback_list: Vec<_> = self
+            .iterate_fallback_connections()
+            .map(|(from, to)| (*from, *to))
+            .collect();
+        fallback_list.sort_unstable();
+        write_varint(&mut buf, encoded(fallback_list.len(), "count")?);
+        for (from, to) in fallback_list {
+            write_varint(&mut buf, from);
+            write_varint(&mut buf, to);
+        }
+
+        let checksum = checksum(&buf);
+        buf.extend(&checksum.to_le_bytes());
+        w.write_all(&buf)
+    }
+
+    /// Read a blueprint written by `write_compiled`, rejecting inputs of a
+    /// later format version, with a bad checksum, or connecting a transition
+    /// twice.
+    pub fn read_compiled(mut r: impl Read) -> Result<Self, LoadError> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        if !buf.starts_with(&MAGIC[..buf.len().min(4)]) {
+            return Err(LoadError::BadMagic);
+        }
+        if buf.len() < MIN_LEN {
+            return Err(LoadError::Truncated);
+        }
+        if buf[4] > FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion(buf[4]));
+        }
+        let (content, expected) = buf.split_at(buf.len() - 4);
+        if checksum(content).to_le_bytes() != expected {
+            return Err(LoadError::BadChecksum);
+        }
+
+        let mut body = Body(&content[5..]);
+        let mut builder = DFAutoBuilder::start(body.varint()?);
+        for _ in 0..body.count(1)? {
+            builder = builder.accept(body.varint()?);
+        }
+        for _ in 0..body.count(2)? {
+            let state = body.varint()?;
+            builder = builder.accept_with(state, body.varint()? as usize);
+        }
+        // target of every transition so far, as the builder panics on
+        // duplicated ones
+        let mut target_map = HashMap::new();
+        let mut connect = |from, trans, to| match target_map.insert((from, trans), to) {
+            Some(known) if known != to => Err(LoadError::Malformed("duplicated transition")),
+            _ => Ok(()),
+        };
+        for _ in 0..body.count(3)? {
+            let (from, trans, to) = (body.varint()?, body.byte()?, body.varint()?);
+            connect(from, trans, to)?;
+            builder = builder.connect(from, trans, to);
+        }
+        for _ in 0..body.count(4)? {
+            let (from, start, end, to) =
+                (body.varint()?, body.byte()?, body.byte()?, body.varint()?);
+            if start > end {
+                return Err(LoadError::Malformed("empty range"));
+            }
+            for trans in start..=end {
+                connect(from, trans, to)?;
+            }
+            builder = builder.connect_range(from, start..=end, to);
+        }
+        let mut fallback_map = HashMap::new();
+        for _ in 0..body.count(2)? {
+            let (from, to) = (body.varint()?, body.varint()?);
+            if fallback_map
+                .insert(from, to)
+                .is_some_and(|known| known != to)
+            {
+                return Err(LoadError::Malformed("duplicated fallback"));
+            }
+            builder = builder.connect_fallback(from, to);
+        }
+        if !body.0.is_empty() {
+            return Err(LoadError::Malformed("trailing bytes"));
+        }
+        Ok(builder.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+    use crate::range::TransRange;
+
+    fn compiled(dfa: &DFAutoBlueprint<u32, u8>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        dfa.write_compiled(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn start_only() {
+        let buf = compiled(&DFAutoBuilder::start(0).finalize());
+        // start state, then five empty lists
+        assert_eq!(&buf[..buf.len() - 4], b"AUTD\x01\x00\x00\x00\x00\x00\x00");
+        assert_eq!(
+            buf[buf.len() - 4..],
+            checksum(&buf[..buf.len() - 4]).to_le_bytes()
+        );
+        let restored = DFAutoBlueprint::read_compiled(buf.as_slice()).unwrap();
+        assert_eq!(restored, DFAutoBuilder::start(0).finalize());
+    }
+
+    #[test]
+    fn varint_states() {
+        let dfa = DFAutoBuilder::start(127)
+            .connect(127, b'a', 128)
+            .connect(128, 0, 70_000)
+            .connect_range(70_000, 0..=255, u32::MAX)
+            .accept_with(u32::MAX, 2)
+            .finalize();
+        // start state followed by an empty list of accept states
+        let start_bytes = |state| compiled(&DFAutoBuilder::start(state).finalize())[5..].to_vec();
+        assert_eq!(start_bytes(127)[..2], [0x7f, 0]);
+        assert_eq!(start_bytes(128)[..3], [0x80, 0x01, 0]);
+        assert_eq!(
+            start_bytes(u32::MAX)[..6],
+            [0xff, 0xff, 0xff, 0xff, 0x0f, 0]
+        );
+        let restored = DFAutoBlueprint::read_compiled(compiled(&dfa).as_slice()).unwrap();
+        assert_eq!(restored, dfa);
+        assert!(restored.create().test(b"a\x00\xff"));
+        assert!(!restored.create().test(b"a\x01"));
+    }
+
+    #[test]
+    fn independent_of_insertion_order() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, b'b', 2)
+            .connect(0, b'a', 1)
+            .connect_range(1, b'0'..=b'9', 1)
+            .connect_range(1, b'a'..=b'z', 2)
+            .connect_fallback(2, 0)
+            .connect_fallback(1, 0)
+            .accept_with(2, 5)
+            .accept_with(2, 3)
+            .accept(1)
+            .finalize();
+        let reversed = DFAutoBuilder::start(0)
+            .accept(1)
+            .accept_with(2, 3)
+            .accept_with(2, 5)
+            .connect_fallback(1, 0)
+            .connect_fallback(2, 0)
+            .connect_range(1, b'a'..=b'z', 2)
+            .connect_range(1, b'0'..=b'9', 1)
+            .connect(0, b'a', 1)
+            .connect(0, b'b', 2)
+            .finalize();
+        assert_eq!(compiled(&dfa), compiled(&reversed));
+    }
+
+    #[test]
+    fn exclusive_bounds() {
+        let exclusive = |start, end| {
+            let range = TransRange::with_cmp(start, false, end, false, u8::cmp);
+            DFAutoBuilder::start(0)
+                .connect_trans_range(0, range, 0)
+                .finalize()
+        };
+        let inclusive = DFAutoBuilder::start(0)
+            .connect_range(0, b'a'..=b'z', 0)
+            .finalize();
+        assert_eq!(compiled(&exclusive(b'`', b'{')), compiled(&inclusive));
+        // empty once made inclusive, so left out
+        let empty = DFAutoBuilder::start(0).finalize();
+        assert_eq!(compiled(&exclusive(b'a', b'b')), compiled(&empty));
+    }
+
+    #[test]
+    fn reject_wide_tag() {
+        // only wider than `u32` on 64-bit targets
+        if let Ok(tag) = usize::try_from(u64::from(u32::MAX) + 1) {
+            let dfa = DFAutoBuilder::start(0).accept_with(0, tag).finalize();
+            let error = dfa.write_compiled(Vec::new()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(error.to_string(), "accept tag 4294967296 above u32::MAX");
+        }
+    }
+
+    #[test]
+    fn reject_damaged() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, b'a', 1)
+            .connect(1, b'b', 300)
+            .connect_range(1, b'0'..=b'9', 1)
+            .connect_fallback(0, 0)
+            .accept_with(1, 2)
+            .finalize();
+        let buf = compiled(&dfa);
+        let read = |bytes: &[u8]| DFAutoBlueprint::read_compiled(bytes).unwrap_err();
+
+        for len in 0..buf.len() {
+            assert!(matches!(
+                read(&buf[..len]),
+                LoadError::Truncated | LoadError::BadChecksum
+            ));
+        }
+        let mut corrupted = buf.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(read(&corrupted), LoadError::BadChecksum));
+        let mut corrupted = buf.clone();
+        corrupted[7] ^= 0x10;
+        assert!(matches!(read(&corrupted), LoadError::BadChecksum));
+
+        let mut newer = buf.clone();
+        newer[4] = 2;
+        assert!(matches!(read(&newer), LoadError::UnsupportedVersion(2)));
+        assert!(matches!(read(b"{\"start\": 0}"), LoadError::BadMagic));
+
+        // a consistent checksum over an inconsistent body
+        let mut forged = b"AUTD\x01\x00\x00\x00\x02\x00\x61\x01\x00\x61\x02\x00\x00".to_vec();
+        let sum = checksum(&forged);
+        forged.extend(&sum.to_le_bytes());
+        assert!(matches!(
+            read(&forged),
+            LoadError::Malformed("duplicated transition")
+        ));
+    }
+}
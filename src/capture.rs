@@ -0,0 +1,451 @@
+use crate::range::TransRange;
+use crate::re::{Re, RePriv};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Range;
+
+/// Spans of the groups of a pattern matching an input, keyed by group name.
+/// Groups which did not take part in the match are absent, and groups matched
+/// several times report the last span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    span_map: HashMap<String, Range<usize>>,
+}
+
+impl Captures {
+    pub fn get(&self, name: &str) -> Option<Range<usize>> {
+        self.span_map.get(name).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Range<usize>)> {
+        self.span_map
+            .iter()
+            .map(|(name, span)| (name.as_str(), span.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.span_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.span_map.is_empty()
+    }
+}
+
+/// The pattern given to `Re::captures` has an interleaving, for which group
+/// spans are not defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterleaveUnsupported;
+
+impl fmt::Display for InterleaveUnsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "captures are not supported for interleavings")
+    }
+}
+
+impl Error for InterleaveUnsupported {}
+
+// single transition matched by an instruction
+enum Matcher<'r, T> {
+    Plain(&'r T),
+    Wildcard,
+    AnyOf(&'r [T]),
+    NoneOf(&'r [T]),
+    Range(&'r TransRange<T>),
+}
+
+impl<'r, T> Matcher<'r, T>
+where
+    T: Eq,
+{
+    fn matches(&self, trans: &T) -> bool {
+        match self {
+            Matcher::Plain(plain) => *plain == trans,
+            Matcher::Wildcard => true,
+            Matcher::AnyOf(items) => items.contains(trans),
+            Matcher::NoneOf(excluded) => !excluded.contains(trans),
+            Matcher::Range(range) => range.contains(trans),
+        }
+    }
+}
+
+// instructions of a backtracking-free matching program, where a split prefers
+// its first target
+enum Inst<'r, T> {
+    Trans(Matcher<'r, T>),
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Fail,
+    Match,
+}
+
+struct Program<'r, T> {
+    inst_list: Vec<Inst<'r, T>>,
+    name_list: Vec<&'r str>,
+}
+
+// remaining work of `Program::compile`, kept on the heap so that deeply
+// nested patterns do not overflow the stack
+enum Step<'r, T> {
+    Compile(&'r RePriv<T>),
+    Emit(Inst<'r, T>),
+    // end of the first alternative of the split at the index
+    EitherSecond(usize, &'r RePriv<T>),
+    // point the jump at the index to the next instruction
+    PatchJump(usize),
+    // point the second target of the split at the index to the next
+    // instruction
+    PatchSplit(usize),
+    // end of the body of a star, whose split is at the index
+    LoopBack(usize),
+    // end of the body of a plus, which starts at the index
+    SplitBack(usize),
+    // optional copies still to compile, and the splits of the copies so far
+    OptionalCopies(&'r RePriv<T>, usize, Vec<usize>),
+}
+
+impl<'r, T> Program<'r, T> {
+    fn emit(&mut self, inst: Inst<'r, T>) -> usize {
+        self.inst_list.push(inst);
+        self.inst_list.len() - 1
+    }
+
+    // placeholder to be patched once the target is known
+    fn emit_hole(&mut self) -> usize {
+        self.emit(Inst::Fail)
+    }
+
+    fn next(&self) -> usize {
+        self.inst_list.len()
+    }
+
+    fn compile(&mut self, re: &'r RePriv<T>) -> Result<(), InterleaveUnsupported> {
+        let mut work_list = vec![Step::Compile(re)];
+        while let Some(step) = work_list.pop() {
+            match step {
+                Step::Compile(re) => self.compile_step(re, &mut work_list)?,
+                Step::Emit(inst) => {
+                    self.emit(inst);
+                }
+                Step::EitherSecond(split, second) => {
+                    let jump = self.emit_hole();
+                    self.inst_list[split] = Inst::Split(split + 1, self.next());
+                    work_list.push(Step::PatchJump(jump));
+                    work_list.push(Step::Compile(second));
+                }
+                Step::PatchJump(jump) => self.inst_list[jump] = Inst::Jump(self.next()),
+                Step::PatchSplit(split) => {
+                    self.inst_list[split] = Inst::Split(split + 1, self.next());
+                }
+                Step::LoopBack(split) => {
+                    self.emit(Inst::Jump(split));
+                    self.inst_list[split] = Inst::Split(split + 1, self.next());
+                }
+                Step::SplitBack(start) => {
+                    let split = self.next();
+                    self.emit(Inst::Split(start, split + 1));
+                }
+                Step::OptionalCopies(_, 0, split_list) => {
+                    let end = self.next();
+                    for split in split_list {
+                        self.inst_list[split] = Inst::Split(split + 1, end);
+                    }
+                }
+                Step::OptionalCopies(inner, count, mut split_list) => {
+                    // nested optional copies, so later copies need earlier ones
+                    split_list.push(self.emit_hole());
+                    work_list.push(Step::OptionalCopies(inner, count - 1, split_list));
+                    work_list.push(Step::Compile(inner));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // emit the instructions of the outermost pattern of `re`, pushing the
+    // remaining work onto `work_list` in reverse order
+    fn compile_step(
+        &mut self,
+        re: &'r RePriv<T>,
+        work_list: &mut Vec<Step<'r, T>>,
+    ) -> Result<(), InterleaveUnsupported> {
+        match re {
+            RePriv::Plain(trans) => {
+                self.emit(Inst::Trans(Matcher::Plain(trans)));
+            }
+            RePriv::Wildcard => {
+                self.emit(Inst::Trans(Matcher::Wildcard));
+            }
+            RePriv::AnyOf(items) => {
+                self.emit(Inst::Trans(Matcher::AnyOf(items)));
+            }
+            RePriv::NoneOf(excluded) => {
+                self.emit(Inst::Trans(Matcher::NoneOf(excluded)));
+            }
+            RePriv::Range(range) => {
+                self.emit(Inst::Trans(Matcher::Range(range)));
+            }
+            RePriv::Epsilon => {}
+            RePriv::Never => {
+                self.emit(Inst::Fail);
+            }
+            RePriv::Concat(first, second) => {
+                work_list.push(Step::Compile(second));
+                work_list.push(Step::Compile(first));
+            }
+            RePriv::Either(first, second) => {
+                let split = self.emit_hole();
+                work_list.push(Step::EitherSecond(split, second));
+                work_list.push(Step::Compile(first));
+            }
+            RePriv::ZeroOrMore(inner) => {
+                let split = self.emit_hole();
+                work_list.push(Step::LoopBack(split));
+                work_list.push(Step::Compile(inner));
+            }
+            RePriv::OneOrMore(inner) => {
+                work_list.push(Step::SplitBack(self.next()));
+                work_list.push(Step::Compile(inner));
+            }
+            RePriv::Optional(inner) => {
+                let split = self.emit_hole();
+                work_list.push(Step::PatchSplit(split));
+                work_list.push(Step::Compile(inner));
+            }
+            RePriv::Repeat(inner, min, max) => {
+                work_list.push(Step::OptionalCopies(inner, max - min, Vec::new()));
+                for _ in 0..*min {
+                    work_list.push(Step::Compile(inner));
+                }
+            }
+            RePriv::Group(inner, name) => {
+                let slot = self.name_list.len() * 2;
+                self.name_list.push(name);
+                self.emit(Inst::Save(slot));
+                work_list.push(Step::Emit(Inst::Save(slot + 1)));
+                work_list.push(Step::Compile(inner));
+            }
+            RePriv::Interleave(_, _) => return Err(InterleaveUnsupported),
+        }
+        Ok(())
+    }
+}
+
+struct Thread {
+    pc: usize,
+    slot_list: Vec<Option<usize>>,
+}
+
+// follow non-consuming instructions from `pc` in priority order, adding the
+// reached transition and match instructions to `thread_list`
+fn add_thread<T>(
+    program: &Program<'_, T>,
+    thread_list: &mut Vec<Thread>,
+    visited: &mut [bool],
+    pc: usize,
+    position: usize,
+    slot_list: Vec<Option<usize>>,
+) {
+    // pending instructions, the preferred one last
+    let mut pending_list = vec![(pc, slot_list)];
+    while let Some((pc, mut slot_list)) = pending_list.pop() {
+        if visited[pc] {
+            continue;
+        }
+        visited[pc] = true;
+        match &program.inst_list[pc] {
+            Inst::Jump(to) => pending_list.push((*to, slot_list)),
+            Inst::Split(first, second) => {
+                pending_list.push((*second, slot_list.clone()));
+                pending_list.push((*first, slot_list));
+            }
+            Inst::Save(slot) => {
+                slot_list[*slot] = Some(position);
+                pending_list.push((pc + 1, slot_list));
+            }
+            Inst::Fail => {}
+            Inst::Trans(_) | Inst::Match => thread_list.push(Thread { pc, slot_list }),
+        }
+    }
+}
+
+impl<T> Re<T>
+where
+    T: Hash + Eq,
+{
+    /// Match the whole `haystack` and report the spans of the groups, or
+    /// `None` if the pattern does not match. Among several ways to match,
+    /// alternatives are tried left first and repetitions are greedy.
+    /// Patterns containing an interleaving are rejected.
+    pub fn captures(&self, haystack: &[T]) -> Result<Option<Captures>, InterleaveUnsupported> {
+        let mut program = Program {
+            inst_list: Vec::new(),
+            name_list: Vec::new(),
+        };
+        program.compile(&self.0)?;
+        program.emit(Inst::Match);
+        let slot_count = program.name_list.len() * 2;
+
+        let mut thread_list = Vec::new();
+        let mut visited = vec![false; program.inst_list.len()];
+        add_thread(
+            &program,
+            &mut thread_list,
+            &mut visited,
+            0,
+            0,
+            vec![None; slot_count],
+        );
+        for (position, trans) in haystack.iter().enumerate() {
+            let mut next_list = Vec::new();
+            let mut visited = vec![false; program.inst_list.len()];
+            for thread in thread_list {
+                if let Inst::Trans(matcher) = &program.inst_list[thread.pc] {
+                    if matcher.matches(trans) {
+                        add_thread(
+                            &program,
+                            &mut next_list,
+                            &mut visited,
+                            thread.pc + 1,
+                            position + 1,
+                            thread.slot_list,
+                        );
+                    }
+                }
+            }
+            thread_list = next_list;
+        }
+        let thread = match thread_list
+            .into_iter()
+            .find(|thread| matches!(program.inst_list[thread.pc], Inst::Match))
+        {
+            Some(thread) => thread,
+            None => return Ok(None),
+        };
+        let span_map = program
+            .name_list
+            .iter()
+            .enumerate()
+            .filter_map(|(index, name)| {
+                match (thread.slot_list[index * 2], thread.slot_list[index * 2 + 1]) {
+                    (Some(start), Some(end)) => Some((name.to_string(), start..end)),
+                    _ => None,
+                }
+            })
+            .collect();
+        Ok(Some(Captures { span_map }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(re: &Re<char>, haystack: &str) -> Option<Vec<(String, Range<usize>)>> {
+        let haystack: Vec<_> = haystack.chars().collect();
+        re.captures(&haystack).unwrap().map(|captures| {
+            let mut span_list: Vec<_> = captures
+                .iter()
+                .map(|(name, span)| (name.to_string(), span))
+                .collect();
+            span_list.sort_by_key(|(name, _)| name.clone());
+            span_list
+        })
+    }
+
+    fn spans(span_list: &[(&str, Range<usize>)]) -> Option<Vec<(String, Range<usize>)>> {
+        Some(
+            span_list
+                .iter()
+                .map(|(name, span)| (name.to_string(), span.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn groups() {
+        // (?<key>[a-z]+)=(?<value>[0-9]*)
+        let re = Re::group(Re::range('a'..='z').plus(), "key")
+            + Re::plain('=')
+            + Re::group(Re::range('0'..='9').star(), "value");
+        assert_eq!(
+            captures(&re, "port=8080"),
+            spans(&[("key", 0..4), ("value", 5..9)])
+        );
+        assert_eq!(
+            captures(&re, "x="),
+            spans(&[("key", 0..1), ("value", 2..2)])
+        );
+        assert_eq!(captures(&re, "=1"), None);
+        assert_eq!(captures(&re, "port=80x"), None);
+    }
+
+    #[test]
+    fn nested_and_optional_groups() {
+        // (?<outer>a(?<inner>b)?c)d
+        let re = Re::group(
+            Re::plain('a') + Re::group(Re::plain('b'), "inner").opt() + Re::plain('c'),
+            "outer",
+        ) + Re::plain('d');
+        assert_eq!(
+            captures(&re, "abcd"),
+            spans(&[("inner", 1..2), ("outer", 0..3)])
+        );
+        assert_eq!(captures(&re, "acd"), spans(&[("outer", 0..2)]));
+    }
+
+    #[test]
+    fn repeated_and_greedy_groups() {
+        // ((?<item>[a-z])[0-9])*
+        let re = (Re::group(Re::range('a'..='z'), "item") + Re::range('0'..='9')).star();
+        assert_eq!(captures(&re, "a1b2c3"), spans(&[("item", 4..5)]));
+        assert_eq!(captures(&re, ""), spans(&[]));
+
+        // (?<first>a*)(?<second>a*)
+        let re =
+            Re::group(Re::plain('a').star(), "first") + Re::group(Re::plain('a').star(), "second");
+        assert_eq!(
+            captures(&re, "aaa"),
+            spans(&[("first", 0..3), ("second", 3..3)])
+        );
+
+        // (?<either>a|ab)(?<rest>b?)
+        let re = Re::group(Re::plain('a') | Re::literal("ab".chars()), "either")
+            + Re::group(Re::plain('b').opt(), "rest");
+        assert_eq!(
+            captures(&re, "ab"),
+            spans(&[("either", 0..1), ("rest", 1..2)])
+        );
+    }
+
+    #[test]
+    fn interleaving_unsupported() {
+        let re = Re::parse("a&b").unwrap();
+        assert_eq!(re.captures(&['a', 'b']), Err(InterleaveUnsupported));
+        let re = Re::group(Re::plain('a'), "a") + re;
+        assert_eq!(re.captures(&['a', 'a', 'b']), Err(InterleaveUnsupported));
+        assert_eq!(re.captures(&[]), Err(InterleaveUnsupported));
+    }
+
+    #[test]
+    fn deep_pattern() {
+        let haystack: Vec<_> = "ab".repeat(50_000).chars().collect();
+        let re = Re::group(Re::literal(haystack.iter().cloned()), "all");
+        assert_eq!(
+            re.captures(&haystack).unwrap().unwrap().get("all"),
+            Some(0..100_000)
+        );
+        let mut re = Re::group(Re::plain('a'), "inner");
+        for _ in 0..100_000 {
+            re = re.opt();
+        }
+        assert_eq!(
+            re.captures(&['a']).unwrap().unwrap().get("inner"),
+            Some(0..1)
+        );
+        assert_eq!(re.captures(&[]).unwrap().unwrap().get("inner"), None);
+    }
+}
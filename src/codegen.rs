@@ -0,0 +1,381 @@
+use crate::dfa::DFAutoBlueprint;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+
+/// Function name of `DFAutoBlueprint::to_rust_fn` that is not a Rust
+/// identifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFnName {
+    pub fn_name: String,
+}
+
+impl fmt::Display for InvalidFnName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid function name {:?}", self.fn_name)
+    }
+}
+
+impl Error for InvalidFnName {}
+
+// keywords of every edition, reserved ones included
+const KEYWORD_LIST: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// whether `name` is an ASCII identifier other than a keyword or `_`
+fn is_identifier(name: &str) -> bool {
+    let mut char_iter = name.chars();
+    let first_valid = char_iter
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    first_valid
+        && char_iter.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+        && !KEYWORD_LIST.contains(&name)
+}
+
+// a byte as a Rust literal, quoted if printable
+fn byte_literal(byte: u8) -> String {
+    if byte.is_ascii_graphic() && byte != b'\'' && byte != b'\\' {
+        format!("b'{}'", byte as char)
+    } else {
+        format!("0x{:02x}", byte)
+    }
+}
+
+impl DFAutoBlueprint<u32, u8> {
+    /// Generate the source of a standalone function `pub fn <fn_name>(input:
+    /// &[u8]) -> bool` testing `input` like `create().test(input)`, as a loop
+    /// matching on the state and then on the byte. Bytes leading to the same
+    /// state are merged into ranges, and a state with a fallback keeps it as
+    /// its `_` arm. The function returns early when a byte has no transition.
+    /// Only states reachable from the start state are written, in ascending
+    /// order. Fails if `fn_name` is not an ASCII identifier, or is a keyword.
+    pub fn to_rust_fn(&self, fn_name: &str) -> Result<String, InvalidFnName> {
+        if !is_identifier(fn_name) {
+            return Err(InvalidFnName {
+                fn_name: fn_name.to_string(),
+            });
+        }
+        let mut reached = HashSet::new();
+        reached.insert(*self.start_state());
+        let mut unresolved: VecDeque<_> = reached.iter().copied().collect();
+        let mut state_set = BTreeSet::new();
+        while let Some(state) = unresolved.pop_front() {
+            state_set.insert(state);
+            let connections = self.connections_from(&state);
+            for to in connections
+                .plain
+                .iter()
+                .map(|(_, to)| to)
+                .chain(connections.range.iter().map(|(_, to)| to))
+                .chain(connections.fallback)
+            {
+                if reached.insert(*to) {
+                    unresolved.push_back(*to);
+                }
+            }
+        }
+
+        let mut code = String::new();
+        writeln!(code, "pub fn {}(input: &[u8]) -> bool {{", fn_name).unwrap();
+        writeln!(code, "    let mut state: u32 = {};", self.start_state()).unwrap();
+        writeln!(code, "    for &byte in input {{").unwrap();
+        writeln!(code, "        state = match state {{").unwrap();
+        for state in state_set {
+            let fallback = self.connections_from(&state).fallback.copied();
+            // runs of bytes with the same target other than the fallback
+            let mut run_list: Vec<(u8, u8, u32)> = Vec::new();
+            let mut covered = 0;
+            for byte in 0..=u8::MAX {
+                let to = match self.next_state(&state, &byte) {
+                    Some(to) => *to,
+                    None => continue,
+                };
+                covered += 1;
+                if Some(to) == fallback {
+                    continue;
+                }
+                match run_list.last_mut() {
+                    Some((_, end, run_to)) if *end as u16 + 1 == byte as u16 && *run_to == to => {
+                        *end = byte
+                    }
+                    _ => run_list.push((byte, byte, to)),
+                }
+            }
+            if run_list.is_empty() && fallback.is_none() {
+                continue;
+            }
+            writeln!(code, "            {} => match byte {{", state).unwrap();
+            for (start, end, to) in &run_list {
+                let pattern = if start == end {
+                    byte_literal(*start)
+                } else {
+                    format!("{}..={}", byte_literal(*start), byte_literal(*end))
+                };
+                writeln!(code, "                {} => {},", pattern, to).unwrap();
+            }
+            let explicit: usize = run_list
+                .iter()
+                .map(|(start, end, _)| (end - start) as usize + 1)
+                .sum();
+            match fallback {
+                Some(to) if explicit < 256 => {
+                    writeln!(code, "                _ => {},", to).unwrap()
+                }
+                None if covered < 256 => {
+                    writeln!(code, "                _ => return false,").unwrap()
+                }
+                _ => {}
+            }
+            writeln!(code, "            }},").unwrap();
+        }
+        writeln!(code, "            _ => return false,").unwrap();
+        writeln!(code, "        }};").unwrap();
+        writeln!(code, "    }}").unwrap();
+        let mut accept_list: Vec<_> = self.accept_state_set().iter().collect();
+        accept_list.sort_unstable();
+        if accept_list.is_empty() {
+            writeln!(code, "    let _ = state;").unwrap();
+            writeln!(code, "    false").unwrap();
+        } else {
+            let accept_list: Vec<_> = accept_list.iter().map(|state| state.to_string()).collect();
+            writeln!(code, "    matches!(state, {})", accept_list.join(" | ")).unwrap();
+        }
+        code.push_str("}\n");
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::auto::Auto;
+    use crate::dfa::DFAutoBuilder;
+    use crate::re::Re;
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    // the transitions and accept states encoded by generated source, read
+    // back line by line
+    struct Generated {
+        start: u32,
+        arm_map: HashMap<u32, Vec<(u8, u8, Option<u32>)>>,
+        accept_set: HashSet<u32>,
+    }
+
+    fn parse_byte(text: &str) -> u8 {
+        match text.strip_prefix("b'") {
+            Some(quoted) => quoted.as_bytes()[0],
+            None => u8::from_str_radix(text.trim_start_matches("0x"), 16).unwrap(),
+        }
+    }
+
+    fn read_generated(code: &str) -> Generated {
+        let mut generated = Generated {
+            start: 0,
+            arm_map: HashMap::new(),
+            accept_set: HashSet::new(),
+        };
+        let mut state = None;
+        for line in code.lines().map(str::trim) {
+            if let Some(start) = line.strip_prefix("let mut state: u32 = ") {
+                generated.start = start.trim_end_matches(';').parse().unwrap();
+            } else if let Some(from) = line.strip_suffix(" => match byte {") {
+                state = Some(from.parse().unwrap());
+            } else if let Some(accept_list) = line.strip_prefix("matches!(state, ") {
+                generated.accept_set = accept_list
+                    .trim_end_matches(')')
+                    .split(" | ")
+                    .map(|state| state.parse().unwrap())
+                    .collect();
+            } else if let (Some(from), Some((pattern, to))) = (state, line.split_once(" => ")) {
+                let to = to.trim_end_matches(',');
+                let to = if to == "return false" {
+                    None
+                } else {
+                    Some(to.parse().unwrap())
+                };
+                let (start, end) = match pattern {
+                    "_" => (0, 255),
+                    _ => match pattern.split_once("..=") {
+                        Some((start, end)) => (parse_byte(start), parse_byte(end)),
+                        None => (parse_byte(pattern), parse_byte(pattern)),
+                    },
+                };
+                generated
+                    .arm_map
+                    .entry(from)
+                    .or_default()
+                    .push((start, end, to));
+            }
+            if line == "}," {
+                state = None;
+            }
+        }
+        generated
+    }
+
+    impl Generated {
+        // the first matching arm, as in a `match`
+        fn next_state(&self, state: u32, byte: u8) -> Option<u32> {
+            self.arm_map
+                .get(&state)?
+                .iter()
+                .find(|(start, end, _)| (*start..=*end).contains(&byte))?
+                .2
+        }
+    }
+
+    fn to_u32(dfa: &DFAutoBlueprint<u64, u8>) -> DFAutoBlueprint<u32, u8> {
+        let state = |state: &u64| u32::try_from(*state).unwrap();
+        let mut builder = DFAutoBuilder::start(state(dfa.start_state()));
+        for accept in dfa.accept_state_set() {
+            builder = builder.accept(state(accept));
+        }
+        for (from, trans, to) in dfa.iterate_connections() {
+            builder = builder.connect(state(from), *trans, state(to));
+        }
+        for (from, range, to) in dfa.iterate_range_connections() {
+            builder = builder.connect_range(state(from), *range.start()..=*range.end(), state(to));
+        }
+        builder.finalize()
+    }
+
+    #[test]
+    fn golden() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, b'a', 1)
+            .connect_range(0, b'0'..=b'9', 2)
+            .connect(1, b'\n', 1)
+            .connect_fallback(1, 0)
+            .connect_range(2, 0..=255, 2)
+            .accept(1)
+            .accept(2)
+            .finalize();
+        assert_eq!(
+            dfa.to_rust_fn("lex").unwrap(),
+            concat!(
+                "pub fn lex(input: &[u8]) -> bool {\n",
+                "    let mut state: u32 = 0;\n",
+                "    for &byte in input {\n",
+                "        state = match state {\n",
+                "            0 => match byte {\n",
+                "                b'0'..=b'9' => 2,\n",
+                "                b'a' => 1,\n",
+                "                _ => return false,\n",
+                "            },\n",
+                "            1 => match byte {\n",
+                "                0x0a => 1,\n",
+                "                _ => 0,\n",
+                "            },\n",
+                "            2 => match byte {\n",
+                "                0x00..=0xff => 2,\n",
+                "            },\n",
+                "            _ => return false,\n",
+                "        };\n",
+                "    }\n",
+                "    matches!(state, 1 | 2)\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn same_transitions() {
+        let re = (Re::range(b'a'..=b'z') + Re::plain(b'_').opt()).plus()
+            | (Re::plain(b'0') + Re::any_of(b"xX".iter().copied()) + Re::range(b'0'..=b'9').plus());
+        let mut dfa_list = vec![to_u32(&determinize(&re.compile()).unwrap().renumbered())];
+        dfa_list.push(
+            DFAutoBuilder::start(7)
+                .connect(7, b'q', 3)
+                .connect_fallback(7, 7)
+                .connect(3, b'q', 3)
+                .finalize(),
+        );
+        for dfa in dfa_list {
+            let generated = read_generated(&dfa.to_rust_fn("test").unwrap());
+            assert_eq!(generated.start, *dfa.start_state());
+            assert_eq!(&generated.accept_set, dfa.accept_state_set());
+            for state in generated.arm_map.keys().chain(Some(dfa.start_state())) {
+                for byte in 0..=u8::MAX {
+                    assert_eq!(
+                        generated.next_state(*state, byte),
+                        dfa.next_state(state, &byte).copied()
+                    );
+                }
+            }
+            let input = b"abc_d";
+            let mut state = Some(generated.start);
+            for byte in input {
+                state = state.and_then(|state| generated.next_state(state, *byte));
+            }
+            assert_eq!(
+                state.is_some_and(|state| generated.accept_set.contains(&state)),
+                dfa.create().test(input)
+            );
+        }
+    }
+
+    // generated by `to_rust_fn` in `compiled_fixtures`, included so that the
+    // generated source is compiled with the tests
+    mod compiled {
+        include!("../fixtures/codegen_lex.rs");
+        include!("../fixtures/codegen_ident.rs");
+    }
+
+    #[test]
+    fn compiled_fixtures() {
+        let lex = DFAutoBuilder::start(0)
+            .connect(0, b'a', 1)
+            .connect_range(0, b'0'..=b'9', 2)
+            .connect(1, b'\n', 1)
+            .connect_fallback(1, 0)
+            .connect_range(2, 0..=255, 2)
+            .accept(1)
+            .accept(2)
+            .finalize();
+        let re = (Re::range(b'a'..=b'z') + Re::plain(b'_').opt()).plus()
+            | (Re::plain(b'0') + Re::any_of(b"xX".iter().copied()) + Re::range(b'0'..=b'9').plus());
+        let ident = to_u32(&determinize(&re.compile()).unwrap().renumbered());
+        assert_eq!(
+            lex.to_rust_fn("lex").unwrap(),
+            include_str!("../fixtures/codegen_lex.rs")
+        );
+        assert_eq!(
+            ident.to_rust_fn("ident").unwrap(),
+            include_str!("../fixtures/codegen_ident.rs")
+        );
+
+        let input_list: [&[u8]; 10] = [
+            b"", b"a", b"a\n", b"ab", b"aba", b"7\xff", b"abc_d", b"a__", b"0x1f", b"0X",
+        ];
+        for input in input_list.iter() {
+            assert_eq!(compiled::lex(input), lex.create().test(*input));
+            assert_eq!(compiled::ident(input), ident.create().test(*input));
+        }
+    }
+
+    #[test]
+    fn invalid_fn_name() {
+        let dfa = DFAutoBuilder::start(0).accept(0).finalize();
+        for fn_name in ["1x", "fn", "", "_", "a-b", "match", "x y", "é"].iter() {
+            assert_eq!(
+                dfa.to_rust_fn(fn_name),
+                Err(InvalidFnName {
+                    fn_name: fn_name.to_string()
+                })
+            );
+        }
+        for fn_name in ["x1", "_x", "Lex_2", "union"].iter() {
+            assert!(dfa.to_rust_fn(fn_name).is_ok());
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+
+const STATE_COLUMN: &str = "state";
+const FALLBACK_COLUMN: &str = "__fallback__";
+const MARKER_COLUMN: &str = "marker";
+
+/// Error reading a transition matrix.
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    /// Invalid content at a row and a column, both counted from 1 with the
+    /// header as the first row.
+    Invalid {
+        row: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(error) => write!(f, "{}", error),
+            CsvError::Invalid {
+                row,
+                column,
+                message,
+            } => write!(f, "{} at row {}, column {}", message, row, column),
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(error: io::Error) -> Self {
+        CsvError::Io(error)
+    }
+}
+
+fn invalid(row: usize, column: usize, message: impl Into<String>) -> CsvError {
+    CsvError::Invalid {
+        row,
+        column,
+        message: message.into(),
+    }
+}
+
+// `field` quoted if it holds a separator, a quote or a line break
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_record(w: &mut impl Write, record: &[String]) -> io::Result<()> {
+    let record: Vec<_> = record.iter().map(|field| quote(field)).collect();
+    write!(w, "{}\r\n", record.join(","))
+}
+
+// the records of `text`, where quoted fields may hold separators, line
+// breaks and doubled quotes
+fn read_records(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut record_list = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    // whether the field was quoted, and whether the record has any content
+    let (mut quoted, mut started) = (false, false);
+    while let Some(c) = chars.next() {
+        let (row, column) = (record_list.len() + 1, record.len() + 1);
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => field.push(c),
+                        None => return Err(invalid(row, column, "unterminated quote")),
+                    }
+                }
+            }
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if started || !record.is_empty() || !field.is_empty() || quoted {
+                    record.push(std::mem::take(&mut field));
+                    record_list.push(std::mem::take(&mut record));
+                }
+                quoted = false;
+                started = false;
+                continue;
+            }
+            _ if quoted => return Err(invalid(row, column, "text after closing quote")),
+            c => field.push(c),
+        }
+        started = true;
+    }
+    if started {
+        record.push(field);
+        record_list.push(record);
+    }
+    Ok(record_list)
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord,
+    T: Hash + Eq + Ord,
+{
+    /// Write the transition matrix as CSV, with states and transitions
+    /// written by `Display`. See `to_csv_with`.
+    pub fn to_csv(&self, w: impl Write) -> io::Result<()>
+    where
+        S: Display,
+        T: Display,
+    {
+        self.to_csv_with(w, S::to_string, T::to_string)
+    }
+
+    /// Write the transition matrix as CSV, with a header row naming the
+    /// `state` column, a column per transition, the `__fallback__` column and
+    /// the `marker` column, then a row per state. Cells hold target states or
+    /// are empty, and markers are `>` for the start state and `*` for accept
+    /// states. Rows and columns are sorted. Fails on range connections, which
+    /// the matrix cannot hold.
+    pub fn to_csv_with(
+        &self,
+        mut w: impl Write,
+        state_label: impl Fn(&S) -> String,
+        trans_label: impl Fn(&T) -> String,
+    ) -> io::Result<()> {
+        if self.iterate_range_connections().next().is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "range connections cannot be written",
+            ));
+        }
+        let mut state_set: BTreeSet<_> = self.accept_state_set().iter().collect();
+        state_set.insert(self.start_state());
+        let mut trans_set = BTreeSet::new();
+        for (from, trans, to) in self.iterate_connections() {
+            state_set.extend(&[from, to]);
+            trans_set.insert(trans);
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            state_set.extend(&[from, to]);
+        }
+
+        let mut header = vec![STATE_COLUMN.to_string()];
+        header.extend(trans_set.iter().map(|trans| trans_label(trans)));
+        header.push(FALLBACK_COLUMN.to_string());
+        header.push(MARKER_COLUMN.to_string());
+        write_record(&mut w, &header)?;
+        for state in state_set {
+            let connections = self.connections_from(state);
+            let mut record = vec![state_label(state)];
+            record.extend(trans_set.iter().map(|trans| {
+                connections
+                    .plain
+                    .get(*trans)
+                    .map_or_else(String::new, &state_label)
+            }));
+            record.push(connections.fallback.map_or_else(String::new, &state_label));
+            let mut marker = String::new();
+            if state == self.start_state() {
+                marker.push('>');
+            }
+            if self.accept_state_set().contains(state) {
+                marker.push('*');
+            }
+            record.push(marker);
+            write_record(&mut w, &record)?;
+        }
+        Ok(())
+    }
+}
+
+impl DFAutoBuilder<String, String> {
+    /// Read a transition matrix written by `DFAutoBlueprint::to_csv`, keeping
+    /// states and transitions as strings. The `__fallback__` column may be
+    /// left out. Fails on malformed CSV, unknown markers, repeated states or
+    /// columns, and unless exactly one state is marked as start.
+    pub fn from_csv(mut r: impl Read) -> Result<Self, CsvError> {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+        let record_list = read_records(&text)?;
+        let header = record_list
+            .first()
+            .ok_or_else(|| invalid(1, 1, "missing header"))?;
+        if header.first().map(String::as_str) != Some(STATE_COLUMN) {
+            return Err(invalid(1, 1, format!("expected `{}`", STATE_COLUMN)));
+        }
+        if header.len() < 2 || header.last().map(String::as_str) != Some(MARKER_COLUMN) {
+            return Err(invalid(
+                1,
+                header.len(),
+                format!("expected `{}`", MARKER_COLUMN),
+            ));
+        }
+        let mut column_set = HashSet::new();
+        for (index, column) in header.iter().enumerate() {
+            if !column_set.insert(column) {
+                return Err(invalid(
+                    1,
+                    index + 1,
+                    format!("repeated column `{}`", column),
+                ));
+            }
+        }
+
+        let mut start = None;
+        let mut accept_list = Vec::new();
+        let mut connection_list = Vec::new();
+        let mut state_set = HashSet::new();
+        for (index, record) in record_list.iter().enumerate().skip(1) {
+            let row = index + 1;
+            if record.len() != header.len() {
+                return Err(invalid(
+                    row,
+                    record.len().min(header.len()) + 1,
+                    format!("expected {} fields", header.len()),
+                ));
+            }
+            let state = &record[0];
+            if state.is_empty() {
+                return Err(invalid(row, 1, "empty state"));
+            }
+            if !state_set.insert(state) {
+                return Err(invalid(row, 1, format!("repeated state `{}`", state)));
+            }
+            for (column, (trans, to)) in header.iter().zip(record).enumerate() {
+                if column == 0 || column == header.len() - 1 || to.is_empty() {
+                    continue;
+                }
+                let trans = Some(trans).filter(|trans| *trans != FALLBACK_COLUMN);
+                connection_list.push((state, trans, to));
+            }
+            match record[header.len() - 1].as_str() {
+                "" => {}
+                "*" => accept_list.push(state),
+                marker @ (">" | ">*") => {
+                    if start.replace(state).is_some() {
+                        return Err(invalid(row, header.len(), "second start state"));
+                    }
+                    if marker == ">*" {
+                        accept_list.push(state);
+                    }
+                }
+                marker => {
+                    return Err(invalid(
+                        row,
+                        header.len(),
+                        format!("unknown marker `{}`", marker),
+                    ))
+                }
+            }
+        }
+        let start = start.ok_or_else(|| invalid(1, header.len(), "no start state"))?;
+
+        let mut builder = DFAutoBuilder::start(start.clone());
+        for state in accept_list {
+            builder = builder.accept(state.clone());
+        }
+        for (from, trans, to) in connection_list {
+            builder = match trans {
+                Some(trans) => builder.connect(from.clone(), trans.clone(), to.clone()),
+                None => builder.connect_fallback(from.clone(), to.clone()),
+            };
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+
+    #[test]
+    fn rows_sorted_by_value() {
+        let dfa = DFAutoBuilder::start(1)
+            .connect(1, 'b', 2)
+            .connect(1, 'a', 1)
+            .connect(2, 'a', 10)
+            .connect_fallback(10, 1)
+            .accept(10)
+            .accept(1)
+            .finalize();
+        let mut buf = Vec::new();
+        dfa.to_csv(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        // `10` comes after `2`, as rows are sorted before being written
+        assert_eq!(
+            text,
+            concat!(
+                "state,a,b,__fallback__,marker\r\n",
+                "1,1,2,,>*\r\n",
+                "2,10,,,\r\n",
+                "10,,,1,*\r\n",
+            )
+        );
+        let restored = DFAutoBuilder::from_csv(text.as_bytes()).unwrap().finalize();
+        let input: Vec<_> = ["a", "b", "a"].iter().map(|s| s.to_string()).collect();
+        assert!(restored.create().test(&input));
+        assert!(!restored.create().test(&input[1..2]));
+        assert_eq!(
+            restored.connections_from(&"10".to_string()).fallback,
+            Some(&"1".to_string())
+        );
+    }
+
+    #[test]
+    fn lenient_reading() {
+        // line feeds only, blank lines, no fallback column nor final line break
+        let dfa = DFAutoBuilder::from_csv("state,x,marker\n\ns,t,>\n\nt,\"\",*".as_bytes())
+            .unwrap()
+            .finalize();
+        assert!(dfa.create().test(&["x".to_string()]));
+        assert!(!dfa.create().test(&["x".to_string(), "x".to_string()]));
+    }
+
+    #[test]
+    fn quoting() {
+        let dfa = DFAutoBuilder::start("s".to_string())
+            .connect("s".to_string(), "a,b".to_string(), "say \"t\"".to_string())
+            .connect("s".to_string(), "line\nbreak".to_string(), "s".to_string())
+            .accept("say \"t\"".to_string())
+            .finalize();
+        let mut buf = Vec::new();
+        dfa.to_csv(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                "state,\"a,b\",\"line\nbreak\",__fallback__,marker\r\n",
+                "s,\"say \"\"t\"\"\",s,,>\r\n",
+                "\"say \"\"t\"\"\",,,,*\r\n",
+            )
+        );
+        assert_eq!(
+            DFAutoBuilder::from_csv(text.as_bytes()).unwrap().finalize(),
+            dfa
+        );
+    }
+
+    #[test]
+    fn report_position() {
+        let position = |text: &str| match DFAutoBuilder::from_csv(text.as_bytes()) {
+            Err(CsvError::Invalid { row, column, .. }) => (row, column),
+            _ => panic!("expected an invalid matrix"),
+        };
+        assert_eq!(position("state,a,marker\ns,t,>\nt,s\n"), (3, 3));
+        assert_eq!(position("state,a,marker\ns,t,>\nt,s,+\n"), (3, 3));
+        assert_eq!(position("state,a,marker\ns,t,>\nt,s,>\n"), (3, 3));
+        assert_eq!(position("state,a,marker\ns,t,*\n"), (1, 3));
+        assert_eq!(position("state,a,a,marker\n"), (1, 3));
+        assert_eq!(position("from,a,marker\n"), (1, 1));
+        assert_eq!(position("state,a,marker\ns,\"t\"x,>\n"), (2, 2));
+        assert_eq!(position("state,a,marker\ns,\"t,>\n"), (2, 2));
+        assert_eq!(position("state,a,marker\ns,t,>\ns,t,\n"), (3, 1));
+        let plain = DFAutoBuilder::start(0).connect(0, 'a', 0).finalize();
+        assert!(plain.to_csv(io::sink()).is_ok());
+        let ranged = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 0)
+            .finalize();
+        assert!(ranged.to_csv(io::sink()).is_err());
+    }
+}
@@ -1,7 +1,11 @@
-use crate::auto::Auto;
-use std::collections::{HashMap, HashSet};
+use crate::auto::{Auto, AutoExt, Blueprint, BoxAuto, Checkpointable, Step};
+use crate::range::{insert_sorted, matching_disjoint, TransRange};
+use crate::view::{MapView, SetView};
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DFAutoBuilder<S, T>
@@ -10,9 +14,11 @@ where
     T: Eq + Hash,
 {
     graph: HashMap<S, HashMap<T, S>>,
+    range_graph: HashMap<S, Vec<(TransRange<T>, S)>>,
     fallback_graph: HashMap<S, S>,
     start_state: S,
     accept_state_set: HashSet<S>,
+    accept_tag_map: HashMap<S, HashSet<usize>>,
 }
 
 impl<S, T> DFAutoBuilder<S, T>
@@ -23,9 +29,11 @@ where
     pub fn start(start_state: S) -> Self {
         Self {
             graph: HashMap::new(),
+            range_graph: HashMap::new(),
             fallback_graph: HashMap::new(),
             start_state,
             accept_state_set: HashSet::new(),
+            accept_tag_map: HashMap::new(),
         }
     }
 }
@@ -46,6 +54,13 @@ where
     T: Eq + Hash,
 {
     pub fn connect(mut self, from: S, trans: T, to: S) -> Self {
+        let range_to_list = self.range_graph.get(&from).into_iter().flatten();
+        if range_to_list
+            .filter(|(range, _)| range.contains(&trans))
+            .any(|(_, range_to)| range_to != &to)
+        {
+            panic!("duplicated transition");
+        }
         if !self.graph.contains_key(&from) {
             self.graph.insert(from.clone(), HashMap::new());
         }
@@ -67,6 +82,47 @@ where
     }
 }
 
+impl<S, T> DFAutoBuilder<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash + Clone,
+{
+    pub(crate) fn connect_trans_range(mut self, from: S, range: TransRange<T>, to: S) -> Self {
+        let trans_to_list = self.graph.get(&from).into_iter().flatten();
+        if trans_to_list
+            .filter(|(trans, _)| range.contains(trans))
+            .any(|(_, trans_to)| trans_to != &to)
+        {
+            panic!("duplicated transition");
+        }
+        let range_to_list = self.range_graph.entry(from).or_default();
+        let mut merged = range;
+        for (other, other_to) in std::mem::take(range_to_list) {
+            if !merged.overlaps(&other) {
+                insert_sorted(range_to_list, other, other_to);
+            } else if other_to != to {
+                panic!("duplicated transition");
+            } else {
+                merged = merged.union(&other);
+            }
+        }
+        insert_sorted(range_to_list, merged, to);
+        self
+    }
+}
+
+impl<S, T> DFAutoBuilder<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash + Ord + Clone,
+{
+    /// Connect on every transition within `range`. Overlapping a range or a
+    /// plain transition leading elsewhere is a duplicated transition.
+    pub fn connect_range(self, from: S, range: RangeInclusive<T>, to: S) -> Self {
+        self.connect_trans_range(from, TransRange::new(range), to)
+    }
+}
+
 impl<S, T> DFAutoBuilder<S, T>
 where
     S: Eq + Hash,
@@ -78,6 +134,23 @@ where
     }
 }
 
+impl<S, T> DFAutoBuilder<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    /// Accept `state` and tag it, e.g. with the index of the pattern it
+    /// belongs to. A state may carry several tags; lower tags are meant to
+    /// take priority.
+    pub fn accept_with(mut self, state: S, tag: usize) -> Self {
+        self.accept_tag_map
+            .entry(state.clone())
+            .or_default()
+            .insert(tag);
+        self.accept(state)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DFAutoBlueprint<S, T>
 where
@@ -85,9 +158,11 @@ where
     T: Eq + Hash,
 {
     graph: HashMap<S, HashMap<T, S>>,
+    range_graph: HashMap<S, Vec<(TransRange<T>, S)>>,
     fallback_graph: HashMap<S, S>,
     start_state: S,
     accept_state_set: HashSet<S>,
+    accept_tag_map: HashMap<S, HashSet<usize>>,
 }
 
 impl<S, T> DFAutoBuilder<S, T>
@@ -98,9 +173,11 @@ where
     pub fn finalize(self) -> DFAutoBlueprint<S, T> {
         DFAutoBlueprint {
             graph: self.graph,
+            range_graph: self.range_graph,
             fallback_graph: self.fallback_graph,
             start_state: self.start_state,
             accept_state_set: self.accept_state_set,
+            accept_tag_map: self.accept_tag_map,
         }
     }
 }
@@ -118,11 +195,145 @@ where
         &self.accept_state_set
     }
 
+    /// Tags given to an accept state by `accept_with`.
+    pub fn accept_tags(&self, state: &S) -> SetView<'_, usize> {
+        SetView::new(self.accept_tag_map.get(state))
+    }
+
+    /// Same blueprint where every accept state keeps only its lowest tag, the
+    /// one taking priority, as wanted by lexers.
+    pub fn first_tag_only(mut self) -> Self {
+        for tag_set in self.accept_tag_map.values_mut() {
+            if let Some(first) = tag_set.iter().min().cloned() {
+                *tag_set = vec![first].into_iter().collect();
+            }
+        }
+        self
+    }
+
     pub fn iterate_connections(&self) -> impl Iterator<Item = (&S, &T, &S)> {
         self.graph
             .iter()
             .flat_map(|(from, trans_to)| trans_to.iter().map(move |(trans, to)| (from, trans, to)))
     }
+
+    pub fn iterate_range_connections(&self) -> impl Iterator<Item = (&S, &TransRange<T>, &S)> {
+        self.range_graph.iter().flat_map(|(from, range_to_list)| {
+            range_to_list
+                .iter()
+                .map(move |(range, to)| (from, range, to))
+        })
+    }
+
+    pub fn iterate_fallback_connections(&self) -> impl Iterator<Item = (&S, &S)> {
+        self.fallback_graph.iter()
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash + Ord,
+    T: Eq + Hash + Clone,
+{
+    /// Same blueprint with states numbered from 0 for the start state, keeping
+    /// only the states reachable from it. States are numbered in breadth-first
+    /// order, visiting the targets of plain transitions in state order, then
+    /// ranges, then the fallback, so the numbering is deterministic.
+    pub fn renumbered(&self) -> DFAutoBlueprint<u64, T> {
+        self.renumber_by(|state| state).0
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash,
+    T: Eq + Hash + Clone,
+{
+    // the renumbered blueprint with the original state of every number,
+    // visiting the targets of plain transitions in the order of `key`
+    pub(crate) fn renumber_by<'a, K, F>(&'a self, key: F) -> (DFAutoBlueprint<u64, T>, Vec<&'a S>)
+    where
+        K: Ord,
+        F: Fn(&'a S) -> K,
+    {
+        let mut number_map = HashMap::new();
+        number_map.insert(self.start_state(), 0);
+        let mut state_list = vec![self.start_state()];
+        let mut unresolved = VecDeque::new();
+        unresolved.push_back(self.start_state());
+        let mut builder = DFAutoBuilder::start(0);
+        while let Some(state) = unresolved.pop_front() {
+            let mut number = |state| {
+                let count = number_map.len() as u64;
+                *number_map.entry(state).or_insert_with(|| {
+                    unresolved.push_back(state);
+                    state_list.push(state);
+                    count
+                })
+            };
+            let from = number(state);
+            if self.accept_state_set.contains(state) {
+                builder = builder.accept(from);
+            }
+            for tag in self.accept_tags(state).iter() {
+                builder = builder.accept_with(from, *tag);
+            }
+            let connections = self.connections_from(state);
+            let mut plain_list: Vec<_> = connections.plain.iter().collect();
+            plain_list.sort_by_key(|(_, to)| key(to));
+            for (trans, to) in plain_list {
+                builder = builder.connect(from, trans.clone(), number(to));
+            }
+            for (range, to) in connections.range {
+                builder = builder.connect_trans_range(from, range.clone(), number(to));
+            }
+            if let Some(to) = connections.fallback {
+                builder = builder.connect_fallback(from, number(to));
+            }
+        }
+        (builder.finalize(), state_list)
+    }
+}
+
+/// Outgoing connections of a single state. States without any outgoing
+/// connection, including states only present as targets, yield an empty view.
+#[derive(Debug)]
+pub struct DfaConnections<'bp, S, T> {
+    /// Plain transitions, keyed by transition.
+    pub plain: MapView<'bp, T, S>,
+    /// Disjoint ranges of transitions sorted by start, taken when no plain
+    /// transition matches.
+    pub range: &'bp [(TransRange<T>, S)],
+    /// Target taken when no plain transition matches.
+    pub fallback: Option<&'bp S>,
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash,
+    T: Eq + Hash,
+{
+    pub fn connections_from(&self, state: &S) -> DfaConnections<'_, S, T> {
+        DfaConnections {
+            plain: MapView::new(self.graph.get(state)),
+            range: self
+                .range_graph
+                .get(state)
+                .map_or(&[], |range_to_list| range_to_list.as_slice()),
+            fallback: self.fallback_graph.get(state),
+        }
+    }
+
+    /// State reached from `state` on `trans`, taking the plain transition,
+    /// then a range, then the fallback. `None` if nothing matches.
+    pub fn next_state(&self, state: &S, trans: &T) -> Option<&S> {
+        let connections = self.connections_from(state);
+        connections
+            .plain
+            .get(trans)
+            .or_else(|| matching_disjoint(connections.range, trans))
+            .or(connections.fallback)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -140,7 +351,7 @@ where
     S: Eq + Hash + Clone,
     T: Eq + Hash,
 {
-    pub fn create(&self) -> DFAuto<S, T> {
+    pub fn create(&self) -> DFAuto<'_, S, T> {
         DFAuto {
             blueprint: self,
             current_state: self.start_state().clone(),
@@ -148,6 +359,41 @@ where
     }
 }
 
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    pub fn create_boxed(&self) -> BoxAuto<'_, T> {
+        Box::new(self.create())
+    }
+}
+
+impl<S, T> Blueprint for DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    type State = S;
+    type Trans = T;
+    type Runner<'a>
+        = DFAuto<'a, S, T>
+    where
+        Self: 'a;
+
+    fn create(&self) -> DFAuto<'_, S, T> {
+        DFAutoBlueprint::create(self)
+    }
+
+    fn start_state(&self) -> &S {
+        DFAutoBlueprint::start_state(self)
+    }
+
+    fn accept_state_set(&self) -> &HashSet<S> {
+        DFAutoBlueprint::accept_state_set(self)
+    }
+}
+
 impl<'b, S, T> DFAuto<'b, S, T>
 where
     S: Eq + Hash,
@@ -163,22 +409,29 @@ where
             .contains(self.current_state())
     }
 
+    /// Tags of the current state if it accepts, in priority order.
+    pub fn matched_tags(&self) -> BTreeSet<usize> {
+        self.blueprint
+            .accept_tags(self.current_state())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
     pub fn test_trigger(&self, trans: &T) -> bool {
-        let plain_test = if let Some(result) = self
-            .blueprint
-            .graph
-            .get(self.current_state())
-            .map(|from_state| from_state.contains_key(trans))
-        {
-            result
-        } else {
-            false
-        };
-        plain_test
-            || self
-                .blueprint
-                .fallback_graph
-                .contains_key(self.current_state())
+        self.blueprint
+            .next_state(self.current_state(), trans)
+            .is_some()
+    }
+
+    /// Whether the current state is a trap: not accepting, and without any
+    /// outgoing connection.
+    pub fn is_dead(&self) -> bool {
+        let connections = self.blueprint.connections_from(self.current_state());
+        !self.is_accepted()
+            && connections.plain.is_empty()
+            && connections.range.is_empty()
+            && connections.fallback.is_none()
     }
 }
 
@@ -190,19 +443,49 @@ where
     pub fn trigger(&mut self, trans: &T) {
         self.current_state = self
             .blueprint
-            .graph
-            .get(self.current_state())
-            .and_then(|from_state| from_state.get(trans))
-            .unwrap_or_else(|| {
-                self.blueprint
-                    .fallback_graph
-                    .get(self.current_state())
-                    .unwrap()
-            })
+            .next_state(self.current_state(), trans)
+            .unwrap()
             .clone()
     }
 }
 
+impl<'b, S, T> DFAuto<'b, S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    /// Same as `AutoExt::steps`, with the state after every step.
+    pub fn steps_with_state<I>(self, iter: I) -> impl Iterator<Item = (Step, S)> + 'b
+    where
+        I: IntoIterator,
+        I::IntoIter: 'b,
+        I::Item: Borrow<T>,
+    {
+        let mut steps = self.steps(iter);
+        std::iter::from_fn(move || {
+            let step = steps.next()?;
+            Some((step, steps.auto().current_state().clone()))
+        })
+    }
+}
+
+/// Checkpoints are the current state.
+impl<'b, S, T> Checkpointable for DFAuto<'b, S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    type Checkpoint = S;
+
+    fn checkpoint(&self) -> S {
+        self.current_state.clone()
+    }
+
+    fn restore(&mut self, checkpoint: &S) {
+        self.current_state = checkpoint.clone();
+    }
+}
+
 impl<'b, S, T> Auto for DFAuto<'b, S, T>
 where
     S: Eq + Hash + Clone,
@@ -214,6 +497,10 @@ where
         self.is_accepted()
     }
 
+    fn is_dead(&self) -> bool {
+        self.is_dead()
+    }
+
     fn test_trigger(&self, trans: &T) -> bool {
         self.test_trigger(trans)
     }
@@ -223,6 +510,121 @@ where
     }
 }
 
+/// Runner of a DFA blueprint made total: a transition without connection
+/// leads to a dead sink instead of panicking, see
+/// `DFAutoBlueprint::create_total`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotalDFAuto<'b, S, T>
+where
+    S: Eq + Hash,
+    T: Eq + Hash,
+{
+    blueprint: &'b DFAutoBlueprint<S, T>,
+    // `None` in the dead sink
+    current_state: Option<S>,
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    /// Create a runner where every transition can be triggered. One without
+    /// connection moves into a dead sink, which never accepts and which
+    /// further transitions do not leave.
+    pub fn create_total(&self) -> TotalDFAuto<'_, S, T> {
+        TotalDFAuto {
+            blueprint: self,
+            current_state: Some(self.start_state().clone()),
+        }
+    }
+}
+
+impl<'b, S, T> TotalDFAuto<'b, S, T>
+where
+    S: Eq + Hash,
+    T: Eq + Hash,
+{
+    /// Current state, or `None` in the dead sink.
+    pub fn current_state(&self) -> Option<&S> {
+        self.current_state.as_ref()
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.current_state()
+            .is_some_and(|state| self.blueprint.accept_state_set().contains(state))
+    }
+
+    /// Whether the runner is in the dead sink, or in a trap state as in
+    /// `DFAuto::is_dead`.
+    pub fn is_dead(&self) -> bool {
+        match self.current_state() {
+            Some(state) => {
+                let connections = self.blueprint.connections_from(state);
+                !self.is_accepted()
+                    && connections.plain.is_empty()
+                    && connections.range.is_empty()
+                    && connections.fallback.is_none()
+            }
+            None => true,
+        }
+    }
+}
+
+impl<'b, S, T> TotalDFAuto<'b, S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    pub fn trigger(&mut self, trans: &T) {
+        self.current_state = self
+            .current_state()
+            .and_then(|state| self.blueprint.next_state(state, trans))
+            .cloned();
+    }
+}
+
+/// Checkpoints are the current state, `None` in the dead sink.
+impl<'b, S, T> Checkpointable for TotalDFAuto<'b, S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    type Checkpoint = Option<S>;
+
+    fn checkpoint(&self) -> Option<S> {
+        self.current_state.clone()
+    }
+
+    fn restore(&mut self, checkpoint: &Option<S>) {
+        self.current_state = checkpoint.clone();
+    }
+}
+
+impl<'b, S, T> Auto for TotalDFAuto<'b, S, T>
+where
+    S: Eq + Hash + Clone,
+    T: Eq + Hash,
+{
+    type Trans = T;
+
+    fn is_accepted(&self) -> bool {
+        self.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_dead()
+    }
+
+    fn test_trigger(&self, _trans: &T) -> bool {
+        true
+    }
+
+    fn trigger(&mut self, trans: &T) {
+        self.trigger(trans);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,6 +682,51 @@ mod tests {
         assert!(auto.is_accepted());
     }
 
+    #[test]
+    fn test_trigger_on_final_state() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, "x", 1)
+            .accept(1)
+            .finalize();
+        let mut auto = dfa.create();
+        auto.trigger(&"x");
+        assert!(auto.is_accepted());
+        assert!(!auto.test_trigger(&"anything"));
+        assert!(!dfa.create().test(["x", "anything"].iter()));
+    }
+
+    #[test]
+    fn total_runner() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 0)
+            .accept(1)
+            .finalize();
+        let mut auto = dfa.create_total();
+        auto.trigger(&'a');
+        assert_eq!(auto.current_state(), Some(&1));
+        assert!(auto.is_accepted());
+        assert!(!auto.is_dead());
+        assert!(Auto::test_trigger(&auto, &'c'));
+        auto.trigger(&'c');
+        assert_eq!(auto.current_state(), None);
+        assert!(auto.is_dead());
+        assert!(!auto.is_accepted());
+        // no way back from the sink
+        auto.trigger(&'b');
+        auto.trigger(&'a');
+        assert_eq!(auto.current_state(), None);
+        auto.restore(&Some(0));
+        assert!(auto.test("aba".chars()));
+
+        assert!(!dfa.create_total().test("acb".chars()));
+        assert!(dfa.create_total().test("aba".chars()));
+        assert!(dfa.create_total().search("abacab".chars()));
+        assert!(!dfa.create_total().search("cab".chars()));
+        // the default runner still refuses the transition
+        assert!(!dfa.create().test_trigger(&'c'));
+    }
+
     #[test]
     fn trigger_fallback() {
         let dfa = DFAutoBuilder::start(0)
@@ -305,4 +752,121 @@ mod tests {
         assert!(auto.is_accepted());
         assert!(auto.test_trigger(&"error"));
     }
+
+    #[test]
+    fn steps_through_fallback() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, "0 -> 1", 1)
+            .connect(1, "1 -> 2", 2)
+            .connect(2, "2 -> 3", 3)
+            .accept(3)
+            .connect_fallback(0, 0)
+            .connect_fallback(1, 0)
+            .connect_fallback(2, 0)
+            .finalize();
+        let input = ["0 -> 1", "error", "0 -> 1", "1 -> 2", "2 -> 3", "0 -> 1"];
+        let step_list: Vec<_> = dfa.create().steps_with_state(input.iter()).collect();
+        let state_list: Vec<_> = step_list.iter().map(|(_, state)| *state).collect();
+        assert_eq!(state_list, vec![1, 0, 1, 2, 3, 3]);
+        let accepted_list: Vec<_> = step_list
+            .iter()
+            .map(|(step, _)| step.accepted_after)
+            .collect();
+        assert_eq!(accepted_list, vec![false, false, false, false, true, true]);
+        // the accept state has no fallback
+        assert_eq!(
+            step_list.last().unwrap().0,
+            Step {
+                index: 5,
+                accepted_after: true,
+                consumed: false,
+            }
+        );
+        assert!(step_list[..5].iter().all(|(step, _)| step.consumed));
+
+        let mut steps = dfa.create().steps(&["error", "0 -> 1"]);
+        assert!(steps.next().unwrap().consumed);
+        assert_eq!(steps.auto().current_state(), &0);
+        assert_eq!(steps.next().unwrap().index, 1);
+        assert_eq!(steps.next(), None);
+    }
+
+    #[test]
+    fn connections_from() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, "0 -> 1", 1)
+            .connect(0, "0 -> 0", 0)
+            .connect_fallback(0, 2)
+            .accept(1)
+            .finalize();
+        let connections = dfa.connections_from(&0);
+        assert_eq!(connections.plain.len(), 2);
+        assert_eq!(connections.plain.get(&"0 -> 1"), Some(&1));
+        assert_eq!(connections.fallback, Some(&2));
+
+        let connections = dfa.connections_from(&1);
+        assert!(connections.plain.is_empty());
+        assert_eq!(connections.plain.iter().count(), 0);
+        assert_eq!(connections.fallback, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_auto_with_overlapping_ranges() {
+        let _builder = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 1)
+            .connect_range(0, 'm'..='p', 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_auto_with_plain_trans_in_range() {
+        let _builder = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 1)
+            .connect(0, 'q', 2);
+    }
+
+    #[test]
+    fn trigger_range() {
+        // [0-9a-fA-F]+h
+        let dfa = DFAutoBuilder::start(0)
+            .connect_range(0, b'0'..=b'9', 1)
+            .connect_range(0, b'a'..=b'f', 1)
+            .connect_range(0, b'A'..=b'F', 1)
+            .connect_range(1, b'0'..=b'9', 1)
+            .connect_range(1, b'a'..=b'c', 1)
+            .connect_range(1, b'b'..=b'f', 1)
+            .connect_range(1, b'A'..=b'F', 1)
+            .connect(1, b'h', 2)
+            .accept(2)
+            .finalize();
+        assert_eq!(dfa.connections_from(&1).range.len(), 3);
+        for input in ["0h", "9h", "ah", "fh", "Ah", "Fh", "09afAFh"].iter() {
+            assert!(dfa.create().test(input.bytes()), "{}", input);
+        }
+        for input in ["/h", ":h", "`h", "gh", "@h", "Gh", "h"].iter() {
+            assert!(!dfa.create().test(input.bytes()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn renumbered() {
+        let bp = DFAutoBuilder::start("start")
+            .connect("start", 'a', "middle")
+            .connect_range("middle", '0'..='9', "end")
+            .connect_fallback("end", "start")
+            .connect("unreachable", 'a', "start")
+            .accept("end")
+            .finalize();
+        let renumbered = bp.renumbered();
+        assert_eq!(renumbered.start_state(), &0);
+        assert_eq!(renumbered.iterate_connections().count(), 1);
+        assert_eq!(renumbered.iterate_range_connections().count(), 1);
+        for s in ["a5", "a5xa0", "a", "ab", "5"].iter() {
+            assert_eq!(
+                bp.create().test(s.chars()),
+                renumbered.create().test(s.chars())
+            );
+        }
+    }
 }
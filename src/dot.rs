@@ -0,0 +1,209 @@
+use std::fmt::{Debug, Write};
+
+mod import;
+
+pub use import::{DotImportOptions, DotParseError};
+
+type LabelFn<'o, X> = dyn Fn(&X) -> String + 'o;
+
+/// Options of the Graphviz DOT export. States and transitions are labeled
+/// with their `Debug` format unless a label function is given.
+pub struct DotOptions<'o, S, T> {
+    name: String,
+    state_label: Option<Box<LabelFn<'o, S>>>,
+    trans_label: Option<Box<LabelFn<'o, T>>>,
+}
+
+impl<'o, S, T> Default for DotOptions<'o, S, T> {
+    fn default() -> Self {
+        Self {
+            name: "auto".to_string(),
+            state_label: None,
+            trans_label: None,
+        }
+    }
+}
+
+impl<'o, S, T> DotOptions<'o, S, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the graph, `auto` by default.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn state_label(mut self, state_label: impl Fn(&S) -> String + 'o) -> Self {
+        self.state_label = Some(Box::new(state_label));
+        self
+    }
+
+    pub fn trans_label(mut self, trans_label: impl Fn(&T) -> String + 'o) -> Self {
+        self.trans_label = Some(Box::new(trans_label));
+        self
+    }
+}
+
+impl<'o, S, T> DotOptions<'o, S, T>
+where
+    S: Debug,
+    T: Debug,
+{
+    pub(crate) fn graph_name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn label_state(&self, state: &S) -> String {
+        match &self.state_label {
+            Some(state_label) => state_label(state),
+            None => format!("{:?}", state),
+        }
+    }
+
+    pub(crate) fn label_trans(&self, trans: &T) -> String {
+        match &self.trans_label {
+            Some(trans_label) => trans_label(trans),
+            None => format!("{:?}", trans),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum EdgeStyle {
+    Solid,
+    Dotted,
+    Dashed,
+}
+
+// a blueprint as labeled nodes and edges between node indices, rendered the
+// same way for every kind of blueprint
+pub(crate) struct DotGraph {
+    // label, whether start, whether accepting
+    node_list: Vec<(String, bool, bool)>,
+    // from, style, label, to
+    edge_list: Vec<(usize, EdgeStyle, String, usize)>,
+}
+
+impl DotGraph {
+    pub(crate) fn new() -> Self {
+        Self {
+            node_list: Vec::new(),
+            edge_list: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_node(&mut self, label: String, start: bool, accept: bool) -> usize {
+        self.node_list.push((label, start, accept));
+        self.node_list.len() - 1
+    }
+
+    pub(crate) fn add_edge(&mut self, from: usize, style: EdgeStyle, label: String, to: usize) {
+        self.edge_list.push((from, style, label, to));
+    }
+
+    // nodes are ordered by label, shorter ones first so that numbered states
+    // come in numeric order, and edges by their ends, style and label
+    pub(crate) fn render(self, name: &str) -> String {
+        let mut order: Vec<_> = (0..self.node_list.len()).collect();
+        order.sort_by(|&first, &second| {
+            let (first, second) = (&self.node_list[first].0, &self.node_list[second].0);
+            (first.len(), first).cmp(&(second.len(), second))
+        });
+        let mut id_list = vec![0; order.len()];
+        for (id, &index) in order.iter().enumerate() {
+            id_list[index] = id;
+        }
+        let mut edge_list: Vec<_> = self
+            .edge_list
+            .into_iter()
+            .map(|(from, style, label, to)| (id_list[from], id_list[to], style, label))
+            .collect();
+        edge_list.sort();
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph \"{}\" {{", escape(name)).unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        for (id, &index) in order.iter().enumerate() {
+            let (label, _, accept) = &self.node_list[index];
+            let shape = if *accept { "doublecircle" } else { "circle" };
+            writeln!(
+                dot,
+                "    s{} [label=\"{}\", shape={}];",
+                id,
+                escape(label),
+                shape
+            )
+            .unwrap();
+        }
+        for (id, &index) in order.iter().enumerate() {
+            if self.node_list[index].1 {
+                writeln!(dot, "    start -> s{};", id).unwrap();
+            }
+        }
+        for (from, to, style, label) in edge_list {
+            write!(
+                dot,
+                "    s{} -> s{} [label=\"{}\"",
+                from,
+                to,
+                escape(&label)
+            )
+            .unwrap();
+            match style {
+                EdgeStyle::Solid => {}
+                EdgeStyle::Dotted => write!(dot, ", style=dotted").unwrap(),
+                EdgeStyle::Dashed => write!(dot, ", style=dashed").unwrap(),
+            }
+            writeln!(dot, "];").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// content of a quoted DOT string
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_graph() {
+        let mut graph = DotGraph::new();
+        let ten = graph.add_node("10".to_string(), false, true);
+        let two = graph.add_node("2".to_string(), true, false);
+        graph.add_edge(two, EdgeStyle::Dashed, "\"q\"\\".to_string(), ten);
+        graph.add_edge(two, EdgeStyle::Solid, "a\nb".to_string(), ten);
+        graph.add_edge(ten, EdgeStyle::Dotted, "ε".to_string(), two);
+        assert_eq!(
+            graph.render("g\""),
+            concat!(
+                "digraph \"g\\\"\" {\n",
+                "    rankdir=LR;\n",
+                "    start [shape=point];\n",
+                "    s0 [label=\"2\", shape=circle];\n",
+                "    s1 [label=\"10\", shape=doublecircle];\n",
+                "    start -> s0;\n",
+                "    s0 -> s1 [label=\"a\\nb\"];\n",
+                "    s0 -> s1 [label=\"\\\"q\\\"\\\\\", style=dashed];\n",
+                "    s1 -> s0 [label=\"ε\", style=dotted];\n",
+                "}\n",
+            )
+        );
+    }
+}
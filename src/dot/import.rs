@@ -0,0 +1,649 @@
+use crate::nfa::{NFAutoBlueprint, NFAutoBuilder};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// node pointing at start states, besides nodes shaped as points
+const START_NODE: &str = "__start";
+// labels and styles written by the export for void and wildcard connections
+const EXPORT_VOID: (&str, &str) = ("ε", "dotted");
+const EXPORT_WILDCARD: (&str, &str) = ("·", "dashed");
+const EXPORT_WILDCARD_EXCEPT: &str = "·∖{";
+
+/// Error parsing a DOT graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotParseError {
+    /// Line of the error, counted from 1.
+    pub line: usize,
+    /// Column of the error in characters, counted from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DotParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl Error for DotParseError {}
+
+/// Options of the Graphviz DOT import, telling which edge labels stand for
+/// void connections (`ε` by default) and wildcards (`*` by default).
+pub struct DotImportOptions {
+    epsilon_label: String,
+    wildcard_label: String,
+}
+
+impl Default for DotImportOptions {
+    fn default() -> Self {
+        Self {
+            epsilon_label: "ε".to_string(),
+            wildcard_label: "*".to_string(),
+        }
+    }
+}
+
+impl DotImportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn epsilon_label(mut self, label: impl Into<String>) -> Self {
+        self.epsilon_label = label.into();
+        self
+    }
+
+    pub fn wildcard_label(mut self, label: impl Into<String>) -> Self {
+        self.wildcard_label = label.into();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pos(usize, usize);
+
+impl Pos {
+    fn error(self, message: impl Into<String>) -> DotParseError {
+        DotParseError {
+            line: self.0,
+            column: self.1,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Id(String),
+    // quoted IDs are never keywords
+    Quoted(String),
+    Arrow,
+    Symbol(char),
+}
+
+struct Lexer<'s> {
+    chars: Peekable<Chars<'s>>,
+    pos: Pos,
+}
+
+impl<'s> Lexer<'s> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.pos = Pos(self.pos.0 + 1, 1);
+        } else {
+            self.pos.1 += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_line(&mut self) {
+        while self.chars.peek().is_some_and(|c| *c != '\n') {
+            self.bump();
+        }
+    }
+
+    // content of a quoted ID after its opening quote, with the escapes of
+    // the export undone and other backslashes kept
+    fn quoted(&mut self, start: Pos) -> Result<String, DotParseError> {
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.bump() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('n') => text.push('\n'),
+                    Some('\n') => {}
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => break,
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+        Err(start.error("unterminated string"))
+    }
+
+    fn tokens(mut self) -> Result<(Vec<(Token, Pos)>, Pos), DotParseError> {
+        let mut token_list = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            let start = self.pos;
+            if c.is_whitespace() {
+                self.bump();
+                continue;
+            }
+            self.bump();
+            let token = match c {
+                '#' => {
+                    self.skip_line();
+                    continue;
+                }
+                '/' if self.chars.peek() == Some(&'/') => {
+                    self.skip_line();
+                    continue;
+                }
+                '/' if self.chars.peek() == Some(&'*') => {
+                    self.bump();
+                    let mut last = None;
+                    loop {
+                        match self.bump() {
+                            Some('/') if last == Some('*') => break,
+                            Some(c) => last = Some(c),
+                            None => return Err(start.error("unterminated comment")),
+                        }
+                    }
+                    continue;
+                }
+                '"' => Token::Quoted(self.quoted(start)?),
+                '<' => return Err(start.error("HTML labels are not supported")),
+                '-' if self.chars.peek() == Some(&'>') => {
+                    self.bump();
+                    Token::Arrow
+                }
+                '-' if self.chars.peek() == Some(&'-') => {
+                    return Err(start.error("undirected edges are not supported"))
+                }
+                ':' => return Err(start.error("ports are not supported")),
+                '{' | '}' | '[' | ']' | '=' | ';' | ',' => Token::Symbol(c),
+                c if c == '-' || c == '.' || c == '_' || c.is_alphanumeric() => {
+                    let mut text = c.to_string();
+                    while let Some(&c) = self.chars.peek() {
+                        if c == '.' || c == '_' || c.is_alphanumeric() {
+                            text.push(c);
+                            self.bump();
+                        } else {
+                            break;
+                        }
+                    }
+                    Token::Id(text)
+                }
+                c => return Err(start.error(format!("unexpected character `{}`", c))),
+            };
+            token_list.push((token, start));
+        }
+        Ok((token_list, self.pos))
+    }
+}
+
+type Attrs = HashMap<String, String>;
+
+// the statements of a graph that matter to a blueprint
+#[derive(Default)]
+struct Graph {
+    // ID, position of first use and attributes, in order of first use
+    node_list: Vec<(String, Pos, Attrs)>,
+    node_index_map: HashMap<String, usize>,
+    // from, to, position and attributes
+    edge_list: Vec<(usize, usize, Pos, Attrs)>,
+}
+
+struct Parser {
+    token_list: Vec<(Token, Pos)>,
+    index: usize,
+    end: Pos,
+    graph: Graph,
+    node_default: Attrs,
+    edge_default: Attrs,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.token_list.get(self.index).map(|(token, _)| token)
+    }
+
+    fn pos(&self) -> Pos {
+        self.token_list
+            .get(self.index)
+            .map_or(self.end, |(_, pos)| *pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(id)) if id.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), DotParseError> {
+        let pos = self.pos();
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            _ => Err(pos.error(format!("expected `{}`", symbol))),
+        }
+    }
+
+    fn id(&mut self) -> Result<String, DotParseError> {
+        if self.is_keyword("subgraph") || self.peek() == Some(&Token::Symbol('{')) {
+            return Err(self.pos().error("subgraphs are not supported"));
+        }
+        let pos = self.pos();
+        match self.next() {
+            Some(Token::Id(id)) | Some(Token::Quoted(id)) => Ok(id),
+            _ => Err(pos.error("expected an ID")),
+        }
+    }
+
+    // any number of bracketed attribute lists, merged into `attrs`
+    fn attrs(&mut self, attrs: &mut Attrs) -> Result<(), DotParseError> {
+        while self.peek() == Some(&Token::Symbol('[')) {
+            self.next();
+            loop {
+                match self.peek() {
+                    Some(Token::Symbol(']')) => {
+                        self.next();
+                        break;
+                    }
+                    Some(Token::Symbol(',')) | Some(Token::Symbol(';')) => {
+                        self.next();
+                    }
+                    _ => {
+                        let name = self.id()?;
+                        self.expect('=')?;
+                        attrs.insert(name, self.id()?);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, id: String, pos: Pos) -> usize {
+        if let Some(index) = self.graph.node_index_map.get(&id) {
+            return *index;
+        }
+        let index = self.graph.node_list.len();
+        self.graph.node_index_map.insert(id.clone(), index);
+        self.graph
+            .node_list
+            .push((id, pos, self.node_default.clone()));
+        index
+    }
+
+    fn statement(&mut self) -> Result<(), DotParseError> {
+        if self.is_keyword("graph") {
+            self.next();
+            return self.attrs(&mut Attrs::new());
+        }
+        if self.is_keyword("node") || self.is_keyword("edge") {
+            let is_node = self.is_keyword("node");
+            self.next();
+            let mut attrs = Attrs::new();
+            self.attrs(&mut attrs)?;
+            let default = if is_node {
+                &mut self.node_default
+            } else {
+                &mut self.edge_default
+            };
+            default.extend(attrs);
+            return Ok(());
+        }
+        let pos = self.pos();
+        let id = self.id()?;
+        if self.peek() == Some(&Token::Symbol('=')) {
+            // graph attribute
+            self.next();
+            self.id()?;
+            return Ok(());
+        }
+        let mut chain = vec![(id, pos)];
+        while self.peek() == Some(&Token::Arrow) {
+            self.next();
+            let pos = self.pos();
+            chain.push((self.id()?, pos));
+        }
+        let mut attrs = Attrs::new();
+        self.attrs(&mut attrs)?;
+        let index_list: Vec<_> = chain
+            .into_iter()
+            .map(|(id, pos)| (self.node(id, pos), pos))
+            .collect();
+        if let [(index, _)] = index_list.as_slice() {
+            self.graph.node_list[*index].2.extend(attrs.clone());
+        }
+        for pair in index_list.windows(2) {
+            let mut edge_attrs = self.edge_default.clone();
+            edge_attrs.extend(attrs.clone());
+            self.graph
+                .edge_list
+                .push((pair[0].0, pair[1].0, pair[0].1, edge_attrs));
+        }
+        Ok(())
+    }
+
+    // the graph, with the position of its header
+    fn graph(mut self) -> Result<(Graph, Pos), DotParseError> {
+        if self.is_keyword("strict") {
+            self.next();
+        }
+        let header = self.pos();
+        if self.is_keyword("graph") {
+            return Err(header.error("undirected graphs are not supported"));
+        }
+        if !self.is_keyword("digraph") {
+            return Err(header.error("expected `digraph`"));
+        }
+        self.next();
+        if self.peek() != Some(&Token::Symbol('{')) {
+            self.id()?;
+        }
+        self.expect('{')?;
+        loop {
+            match self.peek() {
+                Some(Token::Symbol('}')) => {
+                    self.next();
+                    break;
+                }
+                Some(Token::Symbol(';')) | Some(Token::Symbol(',')) => {
+                    self.next();
+                }
+                Some(_) => self.statement()?,
+                None => return Err(self.end.error("expected `}`")),
+            }
+        }
+        if self.peek().is_some() {
+            return Err(self.pos().error("unexpected content after the graph"));
+        }
+        Ok((self.graph, header))
+    }
+}
+
+impl NFAutoBlueprint<String, String> {
+    /// Parse a Graphviz `digraph` with the default `DotImportOptions`. See
+    /// `from_dot_with`.
+    pub fn from_dot(src: &str) -> Result<Self, DotParseError> {
+        Self::from_dot_with(src, &DotImportOptions::new())
+    }
+
+    /// Parse a Graphviz `digraph`, such as the output of `to_dot`. States are
+    /// named by node labels, or by node IDs for nodes without one, and nodes
+    /// shaped `doublecircle` are accept states. Start states have the
+    /// attribute `start=true` or are pointed at by the node `__start` or by a
+    /// node shaped `point`, which are not states themselves. Edges labeled
+    /// with the epsilon or the wildcard label of `opts` make void and
+    /// wildcard connections, and so do the void and wildcard connections
+    /// written by `to_dot`, read back from their label together with their
+    /// style. Other edges make plain connections, including the range and
+    /// predicate labels of `to_dot`. Subgraphs, ports, HTML labels and
+    /// undirected graphs are rejected.
+    pub fn from_dot_with(src: &str, opts: &DotImportOptions) -> Result<Self, DotParseError> {
+        let lexer = Lexer {
+            chars: src.chars().peekable(),
+            pos: Pos(1, 1),
+        };
+        let (token_list, end) = lexer.tokens()?;
+        let parser = Parser {
+            token_list,
+            index: 0,
+            end,
+            graph: Graph::default(),
+            node_default: Attrs::new(),
+            edge_default: Attrs::new(),
+        };
+        let (graph, header) = parser.graph()?;
+
+        let attr = |attrs: &Attrs, name: &str, value: &str| {
+            attrs.get(name).is_some_and(|actual| actual == value)
+        };
+        let is_marker: Vec<_> = graph
+            .node_list
+            .iter()
+            .map(|(id, _, attrs)| id == START_NODE || attr(attrs, "shape", "point"))
+            .collect();
+        let mut name_list = Vec::new();
+        let mut name_map = HashMap::new();
+        for (index, (id, pos, attrs)) in graph.node_list.iter().enumerate() {
+            let name = attrs.get("label").unwrap_or(id);
+            if !is_marker[index] && name_map.insert(name, index).is_some() {
+                return Err(pos.error(format!("repeated state `{}`", name)));
+            }
+            name_list.push(name.clone());
+        }
+
+        let mut start_list: Vec<_> = (0..graph.node_list.len())
+            .filter(|index| !is_marker[*index] && attr(&graph.node_list[*index].2, "start", "true"))
+            .collect();
+        for (from, to, pos, _) in &graph.edge_list {
+            if is_marker[*to] {
+                return Err(pos.error("edge into a start marker"));
+            }
+            if is_marker[*from] && !start_list.contains(to) {
+                start_list.push(*to);
+            }
+        }
+        let (start, extra_start_list) = start_list
+            .split_first()
+            .ok_or_else(|| header.error("no start state"))?;
+        let mut builder = NFAutoBuilder::start(name_list[*start].clone());
+        for index in extra_start_list {
+            builder = builder.also_start(name_list[*index].clone());
+        }
+        for (index, (_, _, attrs)) in graph.node_list.iter().enumerate() {
+            if !is_marker[index] && attr(attrs, "shape", "doublecircle") {
+                builder = builder.accept(name_list[index].clone());
+            }
+        }
+        for (from, to, pos, attrs) in &graph.edge_list {
+            if is_marker[*from] {
+                continue;
+            }
+            let (from, to) = (name_list[*from].clone(), name_list[*to].clone());
+            let label = attrs
+                .get("label")
+                .ok_or_else(|| pos.error("edge without label"))?;
+            let exported = |(export_label, style): (&str, &str)| {
+                label == export_label && attr(attrs, "style", style)
+            };
+            builder = if *label == opts.epsilon_label || exported(EXPORT_VOID) {
+                builder.connect_void(from, to)
+            } else if *label == opts.wildcard_label || exported(EXPORT_WILDCARD) {
+                builder.connect_wildcard(from, to)
+            } else if let Some(excluded) = label
+                .strip_prefix(EXPORT_WILDCARD_EXCEPT)
+                .and_then(|rest| rest.strip_suffix('}'))
+                .filter(|_| attr(attrs, "style", EXPORT_WILDCARD.1))
+            {
+                let excluded = excluded
+                    .split(", ")
+                    .filter(|trans| !trans.is_empty())
+                    .map(str::to_string);
+                builder.connect_wildcard_except(from, excluded, to)
+            } else {
+                builder.connect(from, label.clone(), to)
+            };
+        }
+        Ok(builder.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Blueprint;
+    use crate::dot::DotOptions;
+    use crate::re::Re;
+
+    fn strings(input: &str) -> Vec<String> {
+        input.chars().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let nfa = NFAutoBuilder::start("in".to_string())
+            .also_start("again".to_string())
+            .connect(
+                "in".to_string(),
+                "say \"hi\"".to_string(),
+                "mid".to_string(),
+            )
+            .connect_void("mid".to_string(), "out".to_string())
+            .connect_wildcard("again".to_string(), "mid".to_string())
+            .connect_wildcard_except("mid".to_string(), strings("xy"), "in".to_string())
+            .connect_wildcard_except("out".to_string(), Vec::new(), "out".to_string())
+            .accept("out".to_string())
+            .finalize();
+        let opts = DotOptions::new()
+            .state_label(|state: &String| state.clone())
+            .trans_label(|trans: &String| trans.clone());
+        let dot = nfa.to_dot(&opts);
+        assert_eq!(NFAutoBlueprint::from_dot(&dot).unwrap(), nfa);
+
+        let nfa = Re::parse("(a|b)*c").unwrap().compile();
+        let opts = DotOptions::new().trans_label(|c: &char| c.to_string());
+        let restored = NFAutoBlueprint::from_dot(&nfa.to_dot(&opts)).unwrap();
+        for input in ["c", "abbac", "", "ab", "ca"].iter() {
+            assert_eq!(restored.accepts(strings(input)), nfa.accepts(input.chars()));
+        }
+    }
+
+    #[test]
+    fn legacy_graph() {
+        let dot = r#"
+            /* drawn by hand */
+            digraph legacy {
+                node [shape=circle]
+                __start -> idle
+                idle -> busy [label=go]; busy -> idle [label="eps"]
+                busy -> done -> done [label="*"]  // any event
+                done [shape=doublecircle]
+                # another entry point
+                "side entry" [start=true]
+                "side entry" -> done [label="eps"]
+            }
+        "#;
+        let opts = DotImportOptions::new().epsilon_label("eps");
+        let nfa = NFAutoBlueprint::from_dot_with(dot, &opts).unwrap();
+        let expected = NFAutoBuilder::start("side entry".to_string())
+            .also_start("idle".to_string())
+            .connect("idle".to_string(), "go".to_string(), "busy".to_string())
+            .connect_void("busy".to_string(), "idle".to_string())
+            .connect_wildcard("busy".to_string(), "done".to_string())
+            .connect_wildcard("done".to_string(), "done".to_string())
+            .connect_void("side entry".to_string(), "done".to_string())
+            .accept("done".to_string())
+            .finalize();
+        assert_eq!(nfa, expected);
+    }
+
+    #[test]
+    fn plain_symbol_labels() {
+        let dot = r#"
+            digraph {
+                __start -> a
+                a -> b [label="*"]
+                b -> c [label="ε"]
+                c -> d [label="·"]
+                d -> e [label="·∖{x}", style=dotted]
+                e [shape=doublecircle]
+            }
+        "#;
+        let nfa = NFAutoBlueprint::from_dot(dot).unwrap();
+        let symbols = |list: &[&str]| {
+            list.iter()
+                .map(|trans| trans.to_string())
+                .collect::<Vec<_>>()
+        };
+        // the default labels make void and wildcard connections, while the
+        // export labels need the export styles
+        assert!(nfa.accepts(symbols(&["x", "·", "·∖{x}"])));
+        assert!(!nfa.accepts(symbols(&["x", "ε", "·", "·∖{x}"])));
+        assert!(!nfa.accepts(symbols(&["x", "y", "·∖{x}"])));
+        let opts = DotImportOptions::new().wildcard_label("any");
+        let nfa = NFAutoBlueprint::from_dot_with(dot, &opts).unwrap();
+        assert!(nfa.accepts(symbols(&["*", "·", "·∖{x}"])));
+        assert!(!nfa.accepts(symbols(&["x", "·", "·∖{x}"])));
+
+        // the export keeps them apart from void and wildcard connections
+        // unless their labels are the ones of the import options
+        let nfa = NFAutoBuilder::start("0".to_string())
+            .connect("0".to_string(), "ε".to_string(), "1".to_string())
+            .connect("1".to_string(), "·".to_string(), "2".to_string())
+            .connect_void("0".to_string(), "2".to_string())
+            .connect_wildcard("2".to_string(), "3".to_string())
+            .accept("3".to_string())
+            .finalize();
+        let opts = DotOptions::new()
+            .state_label(|state: &String| state.clone())
+            .trans_label(|trans: &String| trans.clone());
+        let dot = nfa.to_dot(&opts);
+        let import_opts = DotImportOptions::new().epsilon_label("eps");
+        assert_eq!(
+            NFAutoBlueprint::from_dot_with(&dot, &import_opts).unwrap(),
+            nfa
+        );
+        assert_ne!(NFAutoBlueprint::from_dot(&dot).unwrap(), nfa);
+    }
+
+    #[test]
+    fn report_position() {
+        let error = |dot: &str| {
+            let error = NFAutoBlueprint::from_dot(dot).unwrap_err();
+            (error.line, error.column, error.message)
+        };
+        assert_eq!(
+            error("digraph {\n  a -> b;\n  subgraph x { c }\n}"),
+            (3, 3, "subgraphs are not supported".to_string())
+        );
+        assert_eq!(
+            error("digraph {\n  a [label=<<b>a</b>>];\n}"),
+            (2, 12, "HTML labels are not supported".to_string())
+        );
+        assert_eq!(
+            error("digraph {\n  a -> b [label=\"x];\n}"),
+            (2, 17, "unterminated string".to_string())
+        );
+        assert_eq!(
+            error("graph { a }"),
+            (1, 1, "undirected graphs are not supported".to_string())
+        );
+        assert_eq!(
+            error("digraph { a -- b }"),
+            (1, 13, "undirected edges are not supported".to_string())
+        );
+        assert_eq!(
+            error("digraph {\n  a -> b [label=x]\n}"),
+            (1, 1, "no start state".to_string())
+        );
+        assert_eq!(
+            error("digraph {\n  __start -> a\n  a -> b\n}"),
+            (3, 3, "edge without label".to_string())
+        );
+        assert_eq!(
+            error("digraph {\n  a -> b [label=x]"),
+            (2, 19, "expected `}`".to_string())
+        );
+    }
+}
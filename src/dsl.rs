@@ -0,0 +1,586 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use crate::nfa::{ConnType, NFAutoBlueprint, NFAutoBuilder};
+use crate::range::TransRange;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error in a textual description of a blueprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DslError {
+    /// Line of the error, counted from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl Error for DslError {}
+
+// what an arrow is labeled with
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Label {
+    Plain(char),
+    Range(char, char),
+    WildcardExcept(Vec<char>),
+    Fallback,
+    Void,
+    Wildcard,
+}
+
+impl Label {
+    // whether both labels may be taken on the same transition
+    fn overlaps(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Label::Plain(first), Label::Plain(second)) => first == second,
+            (Label::Plain(trans), Label::Range(start, end))
+            | (Label::Range(start, end), Label::Plain(trans)) => start <= trans && trans <= end,
+            (Label::Range(first_start, first_end), Label::Range(second_start, second_end)) => {
+                first_start <= second_end && second_start <= first_end
+            }
+            (Label::Fallback, Label::Fallback) => true,
+            _ => false,
+        }
+    }
+}
+
+enum Line {
+    Start(Vec<String>),
+    Accept(Vec<String>),
+    Edge(String, Label, String),
+}
+
+// every meaningful line with its number, where `special` gives the labels
+// standing for something else than a plain transition
+fn parse_lines(
+    s: &str,
+    special: fn(&str) -> Option<Label>,
+) -> Result<Vec<(usize, Line)>, DslError> {
+    let mut line_list = Vec::new();
+    for (index, text) in s.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: String| DslError {
+            line: number,
+            message,
+        };
+        let token_list: Vec<_> = text.split_whitespace().collect();
+        let state = |token: &str| {
+            parse_state(token).ok_or_else(|| error(format!("invalid state `{}`", token)))
+        };
+        let states = |token_list: &[&str]| -> Result<Vec<_>, DslError> {
+            token_list.iter().map(|token| state(token)).collect()
+        };
+        let line = match token_list.as_slice() {
+            [] => continue,
+            [first, ..] if first.starts_with('#') => continue,
+            [from, arrow, to] if is_arrow(arrow) => {
+                let text = &arrow[1..arrow.len() - 2];
+                let label = special(text)
+                    .or_else(|| parse_label(text))
+                    .ok_or_else(|| error(format!("invalid label `{}`", text)))?;
+                Line::Edge(state(from)?, label, state(to)?)
+            }
+            ["start"] | ["accept"] => {
+                return Err(error(format!("expected states after `{}`", token_list[0])))
+            }
+            ["start", state_list @ ..] => Line::Start(states(state_list)?),
+            ["accept", state_list @ ..] => Line::Accept(states(state_list)?),
+            [first, ..] => return Err(error(format!("unexpected `{}`", first))),
+        };
+        line_list.push((number, line));
+    }
+    Ok(line_list)
+}
+
+fn is_arrow(token: &str) -> bool {
+    token.len() >= 3 && token.starts_with('-') && token.ends_with("->")
+}
+
+fn parse_label(text: &str) -> Option<Label> {
+    if let Some(inner) = text.strip_prefix("[^").and_then(|t| t.strip_suffix(']')) {
+        let mut excluded = Vec::new();
+        let mut rest = inner;
+        while !rest.is_empty() {
+            let (trans, next) = parse_symbol(rest)?;
+            excluded.push(trans);
+            rest = next;
+        }
+        return Some(Label::WildcardExcept(excluded));
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let (start, rest) = parse_symbol(inner)?;
+        let (end, rest) = parse_symbol(rest.strip_prefix('-')?)?;
+        return if rest.is_empty() && start <= end {
+            Some(Label::Range(start, end))
+        } else {
+            None
+        };
+    }
+    match parse_symbol(text)? {
+        (trans, "") => Some(Label::Plain(trans)),
+        _ => None,
+    }
+}
+
+// a state written by `write_state`
+fn parse_state(token: &str) -> Option<String> {
+    if token == "\"\"" {
+        return Some(String::new());
+    }
+    let mut state = String::new();
+    let mut rest = token;
+    while !rest.is_empty() {
+        let (c, next) = parse_symbol(rest)?;
+        state.push(c);
+        rest = next;
+    }
+    Some(state)
+}
+
+// a single, possibly escaped, transition and the text after it
+fn parse_symbol(text: &str) -> Option<(char, &str)> {
+    let mut chars = text.chars();
+    let c = chars.next()?;
+    if c != '\\' {
+        return Some((c, chars.as_str()));
+    }
+    let c = match chars.next()? {
+        's' => ' ',
+        't' => '\t',
+        'n' => '\n',
+        'r' => '\r',
+        'u' => {
+            let rest = chars.as_str().strip_prefix('{')?;
+            let end = rest.find('}')?;
+            let c = char::from_u32(u32::from_str_radix(&rest[..end], 16).ok()?)?;
+            return Some((c, &rest[end + 1..]));
+        }
+        c => c,
+    };
+    Some((c, chars.as_str()))
+}
+
+// the textual form of a transition, escaping whitespace, control characters,
+// `\`, `[` and the labels in `special`
+fn write_symbol(trans: char, special: &str) -> String {
+    match trans {
+        ' ' => "\\s".to_string(),
+        '\t' => "\\t".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        c if c.is_whitespace() || c.is_control() => format!("\\u{{{:x}}}", c as u32),
+        c if c == '\\' || c == '[' || special.contains(c) => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+fn write_range(range: &TransRange<char>, special: &str) -> Option<String> {
+    // bounds made inclusive, skipping the surrogate gap
+    let mut start = *range.start();
+    if !range.contains(&start) {
+        start = match start {
+            '\u{d7ff}' => '\u{e000}',
+            c => char::from_u32(c as u32 + 1)?,
+        };
+    }
+    let mut end = *range.end();
+    if !range.contains(&end) {
+        end = match end {
+            '\u{e000}' => '\u{d7ff}',
+            c => char::from_u32((c as u32).checked_sub(1)?)?,
+        };
+    }
+    if start > end {
+        return None;
+    }
+    Some(format!(
+        "[{}-{}]",
+        write_symbol(start, special),
+        write_symbol(end, special)
+    ))
+}
+
+// the textual form of a state as a single token, escaping whitespace, control
+// characters, `\`, `"`, a leading `#` and a leading `-` of a state looking
+// like an arrow. The empty state is written `""`
+fn write_state(state: &str) -> String {
+    if state.is_empty() {
+        return "\"\"".to_string();
+    }
+    let mut text = String::new();
+    for (index, c) in state.chars().enumerate() {
+        match c {
+            '#' if index == 0 => text.push_str("\\#"),
+            '-' if index == 0 && is_arrow(state) => text.push_str("\\-"),
+            '\\' | '"' => {
+                text.push('\\');
+                text.push(c);
+            }
+            c if c.is_whitespace() || c.is_control() => text += &write_symbol(c, ""),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+// the lines of a description, with the start states in the given order
+fn write_lines<'s>(
+    start_list: Vec<&'s String>,
+    mut accept_list: Vec<&'s String>,
+    mut edge_list: Vec<(&'s String, String, &'s String)>,
+    mut comment_list: Vec<String>,
+) -> String {
+    accept_list.sort();
+    edge_list.sort();
+    comment_list.sort();
+    let mut text = format!(
+        "start {}\n",
+        start_list
+            .iter()
+            .map(|state| write_state(state))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    if !accept_list.is_empty() {
+        let accept_list: Vec<_> = accept_list.iter().map(|state| write_state(state)).collect();
+        text += &format!("accept {}\n", accept_list.join(" "));
+    }
+    for (from, label, to) in edge_list {
+        text += &format!("{} -{}-> {}\n", write_state(from), label, write_state(to));
+    }
+    for comment in comment_list {
+        text += &format!("# {}\n", comment);
+    }
+    text
+}
+
+const DFA_SPECIAL: &str = "*";
+const NFA_SPECIAL: &str = "e.";
+
+/// Parse a description made of lines like `start S0`, `accept S2 S3` and
+/// `S0 -a-> S1`, where a label `*` connects the fallback transition. Labels
+/// may also be ranges like `[a-z]`. Whitespace in labels is written `\s`,
+/// `\t`, `\n`, `\r` or `\u{hex}`, and `\` escapes any other character, such
+/// as `\*` for a plain `*`. States are escaped the same way, such as `\#`
+/// for a state starting with `#`, and `""` is the empty state. Lines
+/// starting with `#` are comments.
+impl FromStr for DFAutoBlueprint<String, char> {
+    type Err = DslError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line_list = parse_lines(s, |text| match text {
+            "*" => Some(Label::Fallback),
+            _ => None,
+        })?;
+        let mut start = None;
+        for (number, line) in &line_list {
+            match line {
+                Line::Start(state_list) if start.is_none() && state_list.len() == 1 => {
+                    start = Some(state_list[0].as_str())
+                }
+                Line::Start(_) => {
+                    return Err(DslError {
+                        line: *number,
+                        message: "more than one start state".to_string(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        let start = start.ok_or_else(|| DslError {
+            line: s.lines().count().max(1),
+            message: "missing `start`".to_string(),
+        })?;
+
+        let mut builder = DFAutoBuilder::start(start.to_string());
+        let mut label_map: HashMap<&str, Vec<(&Label, &str)>> = HashMap::new();
+        for (number, line) in &line_list {
+            match line {
+                Line::Start(_) => {}
+                Line::Accept(state_list) => {
+                    for state in state_list {
+                        builder = builder.accept(state.to_string());
+                    }
+                }
+                Line::Edge(from, label, to) => {
+                    let error = |message: &str| DslError {
+                        line: *number,
+                        message: message.to_string(),
+                    };
+                    let known_list = label_map.entry(from).or_default();
+                    if known_list
+                        .iter()
+                        .any(|(known, known_to)| known.overlaps(label) && known_to != to)
+                    {
+                        return Err(error("duplicated transition"));
+                    }
+                    known_list.push((label, to));
+                    let (from, to) = (from.to_string(), to.to_string());
+                    builder = match label {
+                        Label::Plain(trans) => builder.connect(from, *trans, to),
+                        Label::Range(start, end) => builder.connect_range(from, *start..=*end, to),
+                        Label::Fallback => builder.connect_fallback(from, to),
+                        _ => return Err(error("wildcard connection in a DFA")),
+                    };
+                }
+            }
+        }
+        Ok(builder.finalize())
+    }
+}
+
+impl DFAutoBlueprint<String, char> {
+    /// Describe the blueprint in the format read by `from_str`, with sorted
+    /// lines. Accept tags are left out.
+    pub fn to_dsl(&self) -> String {
+        let mut edge_list: Vec<_> = self
+            .iterate_connections()
+            .map(|(from, trans, to)| (from, write_symbol(*trans, DFA_SPECIAL), to))
+            .collect();
+        for (from, range, to) in self.iterate_range_connections() {
+            if let Some(label) = write_range(range, DFA_SPECIAL) {
+                edge_list.push((from, label, to));
+            }
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            edge_list.push((from, "*".to_string(), to));
+        }
+        write_lines(
+            vec![self.start_state()],
+            self.accept_state_set().iter().collect(),
+            edge_list,
+            Vec::new(),
+        )
+    }
+}
+
+/// Parse a description in the format of `DFAutoBlueprint`, except that
+/// `start` may list several states, the first one being the start state,
+/// and that `*` is a plain transition. A label `e` connects a void
+/// connection, `.` a wildcard and a class like `[^xy]` a wildcard except the
+/// listed transitions.
+impl FromStr for NFAutoBlueprint<String, char> {
+    type Err = DslError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line_list = parse_lines(s, |text| match text {
+            "e" => Some(Label::Void),
+            "." => Some(Label::Wildcard),
+            _ => None,
+        })?;
+        let start_list: Vec<_> = line_list
+            .iter()
+            .flat_map(|(_, line)| match line {
+                Line::Start(state_list) => state_list.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let (start, extra_start_list) = start_list.split_first().ok_or_else(|| DslError {
+            line: s.lines().count().max(1),
+            message: "missing `start`".to_string(),
+        })?;
+
+        let mut builder = NFAutoBuilder::start(start.to_string());
+        for state in extra_start_list {
+            builder = builder.also_start(state.to_string());
+        }
+        for (_, line) in line_list {
+            match line {
+                Line::Start(_) => {}
+                Line::Accept(state_list) => {
+                    for state in state_list {
+                        builder = builder.accept(state.to_string());
+                    }
+                }
+                Line::Edge(from, label, to) => {
+                    let (from, to) = (from.to_string(), to.to_string());
+                    builder = match label {
+                        Label::Plain(trans) => builder.connect(from, trans, to),
+                        Label::Range(start, end) => builder.connect_range(from, start..=end, to),
+                        Label::WildcardExcept(excluded) => {
+                            builder.connect_wildcard_except(from, excluded, to)
+                        }
+                        Label::Void => builder.connect_void(from, to),
+                        Label::Wildcard => builder.connect_wildcard(from, to),
+                        Label::Fallback => unreachable!(),
+                    };
+                }
+            }
+        }
+        Ok(builder.finalize())
+    }
+}
+
+impl NFAutoBlueprint<String, char> {
+    /// Describe the blueprint in the format read by `from_str`, with sorted
+    /// lines. Accept tags are left out, and predicate connections, which
+    /// cannot be described, are listed in comments.
+    pub fn to_dsl(&self) -> String {
+        let mut edge_list = Vec::new();
+        let mut comment_list = Vec::new();
+        for (from, conn, to) in self.iterate_connections() {
+            let label = match conn {
+                ConnType::Plain(trans) => write_symbol(*trans, NFA_SPECIAL),
+                ConnType::Void => "e".to_string(),
+                ConnType::Wildcard => ".".to_string(),
+                ConnType::WildcardExcept(excluded) => {
+                    let mut excluded: Vec<_> = excluded.iter().collect();
+                    excluded.sort();
+                    let excluded: String = excluded
+                        .into_iter()
+                        .map(|trans| write_symbol(*trans, NFA_SPECIAL))
+                        .collect();
+                    format!("[^{}]", excluded)
+                }
+                ConnType::Range(range) => match write_range(range, NFA_SPECIAL) {
+                    Some(label) => label,
+                    None => continue,
+                },
+                ConnType::Predicate(_) => {
+                    comment_list.push(format!("predicate connection {} -> {}", from, to));
+                    continue;
+                }
+            };
+            edge_list.push((from, label, to));
+        }
+        let mut start_list: Vec<_> = self.start_state_set().collect();
+        start_list[1..].sort();
+        write_lines(
+            start_list,
+            self.accept_state_set().iter().collect(),
+            edge_list,
+            comment_list,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+
+    #[test]
+    fn dfa_round_trip() {
+        let text = "
+            # even number of `a`, then anything but a space
+            start even
+            accept done
+            even -a-> odd
+            odd -a-> even
+            even -[0-9]-> even
+            even -\\s-> done
+            done -*-> done
+            odd -\\*-> odd
+        ";
+        let dfa: DFAutoBlueprint<String, char> = text.parse().unwrap();
+        assert!(dfa.create().test("aa1 x".chars()));
+        assert!(dfa.create().test("a*a ".chars()));
+        assert!(!dfa.create().test("a ".chars()));
+        assert!(!dfa.create().test("aa".chars()));
+
+        let dsl = dfa.to_dsl();
+        assert_eq!(
+            dsl,
+            concat!(
+                "start even\n",
+                "accept done\n",
+                "done -*-> done\n",
+                "even -[0-9]-> even\n",
+                "even -\\s-> done\n",
+                "even -a-> odd\n",
+                "odd -\\*-> odd\n",
+                "odd -a-> even\n",
+            )
+        );
+        assert_eq!(dsl.parse::<DFAutoBlueprint<_, _>>().unwrap(), dfa);
+    }
+
+    #[test]
+    fn escaped_states() {
+        let state_list = ["a b", "#x", "-a->", "", "\\", "\"\"", "x->", "start", "q\t"];
+        let mut builder = NFAutoBuilder::start("s".to_string());
+        for state in state_list.iter() {
+            builder = builder
+                .accept(state.to_string())
+                .connect("s".to_string(), 'a', state.to_string())
+                .connect_void(state.to_string(), "s".to_string());
+        }
+        let nfa = builder.finalize();
+        let dsl = nfa.to_dsl();
+        assert!(dsl.contains("accept \"\" \\\"\\\" \\#x \\-a-> \\\\ a\\sb q\\t start x->\n"));
+        assert_eq!(dsl.parse::<NFAutoBlueprint<_, _>>().unwrap(), nfa);
+
+        let dfa = DFAutoBuilder::start("#0".to_string())
+            .connect("#0".to_string(), 'a', "-->".to_string())
+            .connect_fallback("-->".to_string(), "".to_string())
+            .accept("".to_string())
+            .finalize();
+        let dsl = dfa.to_dsl();
+        assert_eq!(
+            dsl,
+            "start \\#0\naccept \"\"\n\\#0 -a-> \\-->\n\\--> -*-> \"\"\n"
+        );
+        assert_eq!(dsl.parse::<DFAutoBlueprint<_, _>>().unwrap(), dfa);
+        assert!("start \\".parse::<DFAutoBlueprint<String, char>>().is_err());
+    }
+
+    #[test]
+    fn nfa_round_trip() {
+        let text = "
+            start s0 t0
+            accept s2
+            accept t1
+            s0 -e-> s1
+            s1 -.-> s2
+            s2 -\\e-> s2
+            t0 -[^ab\\s]-> t1
+            t1 -[a-c]-> t1
+            t1 -\\u{3bb}-> s0
+        ";
+        let nfa: NFAutoBlueprint<String, char> = text.parse().unwrap();
+        assert!(nfa.create().test("xeee".chars()));
+        assert!(nfa.create().test("cab".chars()));
+        assert!(nfa.create().test("cλq".chars()));
+        assert!(!nfa.create().test("ab".chars()));
+        assert!(!nfa.create().test("".chars()));
+
+        let dsl = nfa.to_dsl();
+        assert!(dsl.starts_with("start s0 t0\naccept s2 t1\n"));
+        assert!(dsl.contains("t0 -[^\\sab]-> t1\n"));
+        assert_eq!(dsl.parse::<NFAutoBlueprint<_, _>>().unwrap(), nfa);
+
+        let nfa = NFAutoBuilder::start("0".to_string())
+            .connect_if("0".to_string(), |c: &char| c.is_numeric(), "1".to_string())
+            .finalize();
+        assert_eq!(nfa.to_dsl(), "start 0\n# predicate connection 0 -> 1\n");
+    }
+
+    #[test]
+    fn report_line() {
+        let error = |text: &str| text.parse::<DFAutoBlueprint<String, char>>().unwrap_err();
+        assert_eq!(
+            error("start a\n\na -x-> b\na -[w-z]-> c"),
+            DslError {
+                line: 4,
+                message: "duplicated transition".to_string()
+            }
+        );
+        assert_eq!(error("start a\nstart b").line, 2);
+        assert_eq!(error("accept a\n").line, 1);
+        assert_eq!(error("start a\na -ab-> b").line, 2);
+        assert_eq!(error("start a\naccept").line, 2);
+        assert_eq!(error("start a\na b c d").line, 2);
+        assert_eq!(
+            error("start a\na -[^x]-> b").message,
+            "wildcard connection in a DFA"
+        );
+        assert!("start a\na -[^x]-> b"
+            .parse::<NFAutoBlueprint<String, char>>()
+            .is_ok());
+    }
+}
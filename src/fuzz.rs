@@ -0,0 +1,168 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use crate::nfa::{NFAutoBlueprint, NFAutoBuilder};
+use crate::re::Re;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+// bounds keeping generated blueprints and patterns small enough to run
+const MAX_STATE_COUNT: u8 = 16;
+const MAX_CONN_COUNT: usize = 64;
+const MAX_RE_DEPTH: usize = 4;
+const MAX_REPEAT: usize = 3;
+
+fn arbitrary_state(u: &mut Unstructured<'_>, state_count: u8) -> Result<u8> {
+    u.int_in_range(0..=state_count - 1)
+}
+
+// arbitrary range of bytes, bounds in order
+fn arbitrary_range(u: &mut Unstructured<'_>) -> Result<(u8, u8)> {
+    let (first, second) = <(u8, u8)>::arbitrary(u)?;
+    Ok((first.min(second), first.max(second)))
+}
+
+/// States are `0..n` with 0 as start state. A connection is only added if it
+/// agrees with the earlier ones on every transition it covers, so the builder
+/// never sees a duplicated transition.
+impl<'a> Arbitrary<'a> for DFAutoBlueprint<u8, u8> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let state_count = u.int_in_range(1..=MAX_STATE_COUNT)?;
+        // target of every transition from every state, as connected so far
+        let mut table = vec![[None; 256]; state_count as usize];
+        let mut builder = DFAutoBuilder::start(0);
+        for _ in 0..u.int_in_range(0..=MAX_CONN_COUNT)? {
+            let from = arbitrary_state(u, state_count)?;
+            let to = arbitrary_state(u, state_count)?;
+            if bool::arbitrary(u)? {
+                let (start, end) = arbitrary_range(u)?;
+                let covered = &mut table[from as usize][start as usize..=end as usize];
+                if covered.iter().all(|target| target.is_none_or(|t| t == to)) {
+                    covered.iter_mut().for_each(|target| *target = Some(to));
+                    builder = builder.connect_range(from, start..=end, to);
+                }
+            } else {
+                let trans = u8::arbitrary(u)?;
+                let target = &mut table[from as usize][trans as usize];
+                if target.is_none_or(|t| t == to) {
+                    *target = Some(to);
+                    builder = builder.connect(from, trans, to);
+                }
+            }
+        }
+        for state in 0..state_count {
+            if bool::arbitrary(u)? {
+                builder = builder.connect_fallback(state, arbitrary_state(u, state_count)?);
+            }
+            if bool::arbitrary(u)? {
+                builder = builder.accept(state);
+            }
+        }
+        Ok(builder.finalize())
+    }
+}
+
+/// States are `0..n` with 0 as start state, and possibly more start states.
+/// Every kind of connection is generated except predicates.
+impl<'a> Arbitrary<'a> for NFAutoBlueprint<u8, u8> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let state_count = u.int_in_range(1..=MAX_STATE_COUNT)?;
+        let mut builder = NFAutoBuilder::start(0);
+        for _ in 0..u.int_in_range(0..=MAX_CONN_COUNT)? {
+            let from = arbitrary_state(u, state_count)?;
+            let to = arbitrary_state(u, state_count)?;
+            builder = match u.int_in_range(0..=4)? {
+                0 => builder.connect(from, u8::arbitrary(u)?, to),
+                1 => builder.connect_void(from, to),
+                2 => builder.connect_wildcard(from, to),
+                3 => {
+                    let excluded: Vec<u8> = u.arbitrary()?;
+                    builder.connect_wildcard_except(from, excluded, to)
+                }
+                _ => {
+                    let (start, end) = arbitrary_range(u)?;
+                    builder.connect_range(from, start..=end, to)
+                }
+            };
+        }
+        for state in 0..state_count {
+            if bool::arbitrary(u)? {
+                builder = builder.accept(state);
+            }
+            if state != 0 && u.ratio(1, 8)? {
+                builder = builder.also_start(state);
+            }
+        }
+        Ok(builder.finalize())
+    }
+}
+
+/// Patterns nested at most a few levels deep, with small repetition counts.
+impl<'a> Arbitrary<'a> for Re<u8> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_re(u, MAX_RE_DEPTH)
+    }
+}
+
+fn arbitrary_re(u: &mut Unstructured<'_>, depth: usize) -> Result<Re<u8>> {
+    // leaves only once the depth is used up
+    let kind_count = if depth == 0 { 7 } else { 15 };
+    Ok(match u.choose_index(kind_count)? {
+        0 => Re::plain(u8::arbitrary(u)?),
+        1 => Re::epsilon(),
+        2 => Re::never(),
+        3 => Re::wildcard(),
+        4 => Re::any_of(Vec::<u8>::arbitrary(u)?),
+        5 => Re::none_of(Vec::<u8>::arbitrary(u)?),
+        6 => {
+            let (start, end) = arbitrary_range(u)?;
+            Re::range(start..=end)
+        }
+        7 => arbitrary_re(u, depth - 1)?.star(),
+        8 => arbitrary_re(u, depth - 1)?.plus(),
+        9 => arbitrary_re(u, depth - 1)?.opt(),
+        10 => {
+            let min = u.int_in_range(0..=MAX_REPEAT)?;
+            let max = u.int_in_range(min..=MAX_REPEAT)?;
+            Re::repeat_between(arbitrary_re(u, depth - 1)?, min, max)
+        }
+        11 => arbitrary_re(u, depth - 1)? + arbitrary_re(u, depth - 1)?,
+        12 => arbitrary_re(u, depth - 1)? | arbitrary_re(u, depth - 1)?,
+        13 => Re::interleave(arbitrary_re(u, depth - 1)?, arbitrary_re(u, depth - 1)?),
+        _ => Re::group(arbitrary_re(u, depth - 1)?, "g"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+    use crate::lcg::Lcg;
+
+    // deterministic bytes standing in for fuzzer input
+    fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut rng = Lcg::new(seed);
+        (0..len).map(|_| rng.next(256) as u8).collect()
+    }
+
+    #[test]
+    fn generate_and_run() {
+        let input = b"\x00ab\xff";
+        for seed in 0..1000 {
+            let bytes = random_bytes(seed, 512);
+
+            let mut u = Unstructured::new(&bytes);
+            let dfa = DFAutoBlueprint::<u8, u8>::arbitrary(&mut u).unwrap();
+            assert_eq!(*dfa.start_state(), 0);
+            dfa.create().test(input);
+
+            let mut u = Unstructured::new(&bytes);
+            let nfa = NFAutoBlueprint::<u8, u8>::arbitrary(&mut u).unwrap();
+            assert!(nfa.start_state_set().all(|state| *state < MAX_STATE_COUNT));
+            let expected = nfa.create().test(input);
+            assert_eq!(nfa.create_lazy_dfa().test(input), expected);
+
+            let mut u = Unstructured::new(&bytes);
+            let re = Re::<u8>::arbitrary(&mut u).unwrap();
+            assert!(re.max_depth() <= MAX_RE_DEPTH + 1);
+            re.compile().create().test(input);
+        }
+    }
+}
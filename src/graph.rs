@@ -0,0 +1,369 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use crate::nfa::{ConnType, NFAutoBlueprint, NFAutoBuilder, Predicate};
+use crate::range::TransRange;
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+/// Kind of an edge in a graph converted from a blueprint. DFA blueprints
+/// only use plain, range and fallback edges, and NFA blueprints every kind
+/// but fallback edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeKind<T>
+where
+    T: Hash + Eq,
+{
+    Plain(T),
+    Range(TransRange<T>),
+    Fallback,
+    Void,
+    Wildcard,
+    WildcardExcept(HashSet<T>),
+    Predicate(Predicate<T>),
+}
+
+/// Error converting a graph into a blueprint, at the node where the graph
+/// cannot be read as one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub node: NodeIndex,
+    pub message: String,
+}
+
+impl Conflict {
+    fn new(node: NodeIndex, message: impl Into<String>) -> Self {
+        Self {
+            node,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at node {}", self.message, self.node.index())
+    }
+}
+
+impl Error for Conflict {}
+
+// a graph with a node for every state in `state_set`, in order
+fn graph_with<S, T>(state_set: BTreeSet<&S>) -> (DiGraph<S, EdgeKind<T>>, HashMap<&S, NodeIndex>)
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    let mut graph = DiGraph::new();
+    let index_map = state_set
+        .into_iter()
+        .map(|state| (state, graph.add_node(state.clone())))
+        .collect();
+    (graph, index_map)
+}
+
+// fail on a state given by two nodes, which a blueprint could not tell apart
+fn read_nodes<S, T>(graph: &DiGraph<S, EdgeKind<T>>) -> Result<(), Conflict>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    let mut state_set = HashSet::new();
+    for index in graph.node_indices() {
+        if !state_set.insert(&graph[index]) {
+            return Err(Conflict::new(index, "repeated state"));
+        }
+    }
+    Ok(())
+}
+
+fn check_node<S, T>(graph: &DiGraph<S, EdgeKind<T>>, index: NodeIndex) -> Result<(), Conflict>
+where
+    T: Hash + Eq,
+{
+    match graph.node_weight(index) {
+        Some(_) => Ok(()),
+        None => Err(Conflict::new(index, "unknown node")),
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Ord + Clone,
+{
+    /// Convert into a petgraph graph with a node per state, in ascending
+    /// order, and an edge per connection. Also returns the node of the start
+    /// state and the nodes of accept states. Accept tags are left out.
+    pub fn to_petgraph(&self) -> (DiGraph<S, EdgeKind<T>>, NodeIndex, Vec<NodeIndex>) {
+        let mut state_set: BTreeSet<_> = self.accept_state_set().iter().collect();
+        state_set.insert(self.start_state());
+        let mut edge_list = Vec::new();
+        for (from, trans, to) in self.iterate_connections() {
+            edge_list.push((from, EdgeKind::Plain(trans.clone()), to));
+        }
+        for (from, range, to) in self.iterate_range_connections() {
+            edge_list.push((from, EdgeKind::Range(range.clone()), to));
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            edge_list.push((from, EdgeKind::Fallback, to));
+        }
+        for (from, _, to) in &edge_list {
+            state_set.extend(&[*from, *to]);
+        }
+
+        let (mut graph, index_map) = graph_with(state_set);
+        edge_list.sort_by(|(from, _, to), (other_from, _, other_to)| {
+            (from, to).cmp(&(other_from, other_to))
+        });
+        for (from, kind, to) in edge_list {
+            graph.add_edge(index_map[from], index_map[to], kind);
+        }
+        let mut accept_list: Vec<_> = self
+            .accept_state_set()
+            .iter()
+            .map(|state| index_map[state])
+            .collect();
+        accept_list.sort();
+        (graph, index_map[self.start_state()], accept_list)
+    }
+
+    /// Convert a graph as returned by `to_petgraph` back into a blueprint.
+    /// Fails on nodes holding the same state, unknown start or accept nodes,
+    /// edge kinds of NFA blueprints, and on edges that would make the
+    /// blueprint nondeterministic.
+    pub fn from_petgraph(
+        graph: DiGraph<S, EdgeKind<T>>,
+        start: NodeIndex,
+        accepts: impl IntoIterator<Item = NodeIndex>,
+    ) -> Result<Self, Conflict> {
+        read_nodes(&graph)?;
+        check_node(&graph, start)?;
+        let mut builder = DFAutoBuilder::start(graph[start].clone());
+        for index in accepts {
+            check_node(&graph, index)?;
+            builder = builder.accept(graph[index].clone());
+        }
+
+        // targets already taken from every node, as the builder panics on
+        // conflicting ones
+        let mut plain_map: HashMap<_, HashMap<_, _>> = HashMap::new();
+        let mut range_map: HashMap<_, Vec<(&TransRange<T>, _)>> = HashMap::new();
+        let mut fallback_map = HashMap::new();
+        for edge in graph.edge_indices() {
+            let (from, to) = graph.edge_endpoints(edge).unwrap();
+            let conflicting = match &graph[edge] {
+                EdgeKind::Plain(trans) => {
+                    plain_map
+                        .entry(from)
+                        .or_default()
+                        .insert(trans, to)
+                        .is_some_and(|known| known != to)
+                        || range_map
+                            .get(&from)
+                            .into_iter()
+                            .flatten()
+                            .any(|(range, range_to)| range.contains(trans) && *range_to != to)
+                }
+                EdgeKind::Range(range) => {
+                    let conflicting = plain_map
+                        .get(&from)
+                        .into_iter()
+                        .flatten()
+                        .any(|(trans, trans_to)| range.contains(trans) && *trans_to != to)
+                        || range_map
+                            .get(&from)
+                            .into_iter()
+                            .flatten()
+                            .any(|(other, other_to)| other.overlaps(range) && *other_to != to);
+                    range_map.entry(from).or_default().push((range, to));
+                    conflicting
+                }
+                EdgeKind::Fallback => fallback_map
+                    .insert(from, to)
+                    .is_some_and(|known| known != to),
+                _ => return Err(Conflict::new(from, "edge kind of an NFA")),
+            };
+            if conflicting {
+                return Err(Conflict::new(from, "nondeterministic edges"));
+            }
+        }
+
+        for edge in graph.edge_indices() {
+            let (from, to) = graph.edge_endpoints(edge).unwrap();
+            let (from, to) = (graph[from].clone(), graph[to].clone());
+            builder = match &graph[edge] {
+                EdgeKind::Plain(trans) => builder.connect(from, trans.clone(), to),
+                EdgeKind::Range(range) => builder.connect_trans_range(from, range.clone(), to),
+                EdgeKind::Fallback => builder.connect_fallback(from, to),
+                _ => unreachable!(),
+            };
+        }
+        Ok(builder.finalize())
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    /// Convert into a petgraph graph as in `DFAutoBlueprint`, returning the
+    /// nodes of start states, the first one being the main start state.
+    pub fn to_petgraph(&self) -> (DiGraph<S, EdgeKind<T>>, Vec<NodeIndex>, Vec<NodeIndex>) {
+        let (mut graph, index_map) = graph_with(self.state_set().into_iter().collect());
+        let mut edge_list: Vec<_> = self.iterate_connections().collect();
+        edge_list.sort_by(|(from, _, to), (other_from, _, other_to)| {
+            (from, to).cmp(&(other_from, other_to))
+        });
+        for (from, conn, to) in edge_list {
+            let kind = match conn {
+                ConnType::Plain(trans) => EdgeKind::Plain(trans.clone()),
+                ConnType::Void => EdgeKind::Void,
+                ConnType::Wildcard => EdgeKind::Wildcard,
+                ConnType::WildcardExcept(excluded) => EdgeKind::WildcardExcept(excluded.clone()),
+                ConnType::Range(range) => EdgeKind::Range(range.clone()),
+                ConnType::Predicate(pred) => EdgeKind::Predicate(pred.clone()),
+            };
+            graph.add_edge(index_map[from], index_map[to], kind);
+        }
+        let mut start_list = vec![index_map[self.start_state()]];
+        let mut extra_start_list: Vec<_> = self
+            .start_state_set()
+            .skip(1)
+            .map(|state| index_map[state])
+            .collect();
+        extra_start_list.sort();
+        start_list.extend(extra_start_list);
+        let mut accept_list: Vec<_> = self
+            .accept_state_set()
+            .iter()
+            .map(|state| index_map[state])
+            .collect();
+        accept_list.sort();
+        (graph, start_list, accept_list)
+    }
+
+    /// Convert a graph as returned by `to_petgraph` back into a blueprint,
+    /// the first start node giving the main start state. Fails on nodes
+    /// holding the same state, unknown or missing start nodes, unknown accept
+    /// nodes, and fallback edges.
+    pub fn from_petgraph(
+        graph: DiGraph<S, EdgeKind<T>>,
+        starts: impl IntoIterator<Item = NodeIndex>,
+        accepts: impl IntoIterator<Item = NodeIndex>,
+    ) -> Result<Self, Conflict> {
+        read_nodes(&graph)?;
+        let mut starts = starts.into_iter();
+        let start = starts
+            .next()
+            .ok_or_else(|| Conflict::new(NodeIndex::end(), "no start node"))?;
+        check_node(&graph, start)?;
+        let mut builder = NFAutoBuilder::start(graph[start].clone());
+        for index in starts {
+            check_node(&graph, index)?;
+            builder = builder.also_start(graph[index].clone());
+        }
+        for index in accepts {
+            check_node(&graph, index)?;
+            builder = builder.accept(graph[index].clone());
+        }
+        for edge in graph.edge_indices() {
+            let (from, to) = graph.edge_endpoints(edge).unwrap();
+            let conn = match &graph[edge] {
+                EdgeKind::Plain(trans) => ConnType::Plain(trans),
+                EdgeKind::Range(range) => ConnType::Range(range),
+                EdgeKind::Void => ConnType::Void,
+                EdgeKind::Wildcard => ConnType::Wildcard,
+                EdgeKind::WildcardExcept(excluded) => ConnType::WildcardExcept(excluded),
+                EdgeKind::Predicate(pred) => ConnType::Predicate(pred),
+                EdgeKind::Fallback => return Err(Conflict::new(from, "fallback edge")),
+            };
+            builder = builder.connect_as(graph[from].clone(), conn, graph[to].clone());
+        }
+        Ok(builder.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+    use petgraph::algo::has_path_connecting;
+
+    #[test]
+    fn dfa_round_trip() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 1)
+            .connect_range(1, '0'..='9', 2)
+            .connect_fallback(2, 0)
+            .accept(2)
+            .finalize();
+        let (graph, start, accepts) = dfa.to_petgraph();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(graph[start], 0);
+        assert_eq!(
+            accepts
+                .iter()
+                .map(|index| graph[*index])
+                .collect::<Vec<_>>(),
+            [2]
+        );
+        assert!(has_path_connecting(&graph, start, accepts[0], None));
+        let restored = DFAutoBlueprint::from_petgraph(graph, start, accepts).unwrap();
+        assert_eq!(restored, dfa);
+        assert!(restored.create().test("abb7".chars()));
+    }
+
+    #[test]
+    fn nfa_round_trip() {
+        let nfa = NFAutoBuilder::start('p')
+            .also_start('q')
+            .connect('p', 0, 'p')
+            .connect('p', 0, 'q')
+            .connect_void('q', 'r')
+            .connect_wildcard('r', 'p')
+            .connect_wildcard_except('r', vec![1, 2], 'q')
+            .connect_range('q', 5..=9, 'r')
+            .connect_if('r', |trans| trans % 2 == 0, 's')
+            .accept('s')
+            .finalize();
+        let (graph, starts, accepts) = nfa.to_petgraph();
+        assert_eq!(
+            starts.iter().map(|index| graph[*index]).collect::<Vec<_>>(),
+            ['p', 'q']
+        );
+        let restored = NFAutoBlueprint::from_petgraph(graph, starts, accepts).unwrap();
+        assert_eq!(restored, nfa);
+    }
+
+    #[test]
+    fn reject_nondeterministic() {
+        let mut graph = DiGraph::new();
+        let (a, b, c) = (graph.add_node(0), graph.add_node(1), graph.add_node(2));
+        graph.add_edge(a, b, EdgeKind::Plain('x'));
+        graph.add_edge(a, b, EdgeKind::Range(TransRange::new('a'..='z')));
+        assert!(DFAutoBlueprint::from_petgraph(graph.clone(), a, vec![b]).is_ok());
+
+        let mut conflicting = graph.clone();
+        conflicting.add_edge(a, c, EdgeKind::Plain('x'));
+        let conflict = DFAutoBlueprint::from_petgraph(conflicting, a, vec![]).unwrap_err();
+        assert_eq!(conflict, Conflict::new(a, "nondeterministic edges"));
+        let mut conflicting = graph.clone();
+        conflicting.add_edge(a, c, EdgeKind::Range(TransRange::new('y'..='z')));
+        assert!(DFAutoBlueprint::from_petgraph(conflicting, a, vec![]).is_err());
+        let mut conflicting = graph.clone();
+        conflicting.add_edge(b, c, EdgeKind::Wildcard);
+        let conflict = DFAutoBlueprint::from_petgraph(conflicting, a, vec![]).unwrap_err();
+        assert_eq!(conflict.node, b);
+        assert!(NFAutoBlueprint::from_petgraph(graph.clone(), vec![a], vec![b]).is_ok());
+
+        let mut repeated = graph;
+        let d = repeated.add_node(1);
+        let conflict = DFAutoBlueprint::from_petgraph(repeated, a, vec![]).unwrap_err();
+        assert_eq!(conflict, Conflict::new(d, "repeated state"));
+    }
+}
@@ -0,0 +1,417 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// Error converting a blueprint from or to a transition table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub message: String,
+}
+
+impl JsonError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for JsonError {}
+
+// the table of a blueprint with its states and transitions written by
+// `state` and `trans`, leaving out range connections
+fn write_table<S, T>(
+    dfa: &DFAutoBlueprint<S, T>,
+    state: impl Fn(&S) -> String,
+    trans: impl Fn(&T) -> String,
+) -> Value
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    let mut accept_list: Vec<_> = dfa.accept_state_set().iter().map(&state).collect();
+    accept_list.sort();
+    let mut edge_list: Vec<_> = dfa
+        .iterate_connections()
+        .map(|(from, on, to)| (state(from), trans(on), state(to)))
+        .collect();
+    edge_list.sort();
+    let mut fallback_list: Vec<_> = dfa
+        .iterate_fallback_connections()
+        .map(|(from, to)| (state(from), state(to)))
+        .collect();
+    fallback_list.sort();
+    json!({
+        "start": state(dfa.start_state()),
+        "accepts": accept_list,
+        "edges": edge_list
+            .into_iter()
+            .map(|(from, on, to)| json!({ "from": from, "on": on, "to": to }))
+            .collect::<Vec<_>>(),
+        "fallbacks": fallback_list
+            .into_iter()
+            .map(|(from, to)| json!({ "from": from, "to": to }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// `value` written by `Display`, checking that it is parsed back unchanged
+fn write_lossless<X>(value: &X, kind: &str) -> Result<String, JsonError>
+where
+    X: fmt::Display + FromStr + PartialEq,
+{
+    let text = value.to_string();
+    match text.parse::<X>() {
+        Ok(parsed) if parsed == *value => Ok(text),
+        _ => Err(JsonError::new(format!(
+            "{} `{}` is not parsed back from its string form",
+            kind, text
+        ))),
+    }
+}
+
+/// Tables follow the schema
+/// `{ "start": "s", "accepts": ["s", ...], "edges": [{ "from": "s", "on": "t",
+/// "to": "s" }, ...], "fallbacks": [{ "from": "s", "to": "s" }, ...] }`, where
+/// every state and transition is a string. Lists are sorted.
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// The transition table with states and transitions written by
+    /// `Display`. Fails if one of them is not parsed back unchanged by
+    /// `FromStr`, if the blueprint has a range connection, or if it has accept
+    /// tags, none of which the table can hold.
+    pub fn to_json_value(&self) -> Result<Value, JsonError>
+    where
+        S: fmt::Display + FromStr,
+        T: fmt::Display + FromStr,
+    {
+        if self.iterate_range_connections().next().is_some() {
+            return Err(JsonError::new("range connections cannot be written"));
+        }
+        if self
+            .accept_state_set()
+            .iter()
+            .any(|state| !self.accept_tags(state).is_empty())
+        {
+            return Err(JsonError::new("accept tags cannot be written"));
+        }
+        let mut state_list = vec![self.start_state()];
+        state_list.extend(self.accept_state_set());
+        for (from, _, to) in self.iterate_connections() {
+            state_list.extend(&[from, to]);
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            state_list.extend(&[from, to]);
+        }
+        for state in state_list {
+            write_lossless(state, "state")?;
+        }
+        for (_, trans, _) in self.iterate_connections() {
+            write_lossless(trans, "transition")?;
+        }
+        Ok(write_table(self, S::to_string, T::to_string))
+    }
+
+    /// The transition table with states and transitions written by `Debug`,
+    /// for reading only. Ranges are written as edges on `start..=end`, and
+    /// accept tags are left out.
+    pub fn to_json_value_lossy(&self) -> Value
+    where
+        S: fmt::Debug,
+        T: fmt::Debug,
+    {
+        let mut table = write_table(
+            self,
+            |state| format!("{:?}", state),
+            |trans| format!("{:?}", trans),
+        );
+        let mut range_list: Vec<_> = self
+            .iterate_range_connections()
+            .map(|(from, range, to)| {
+                json!({
+                    "from": format!("{:?}", from),
+                    "on": format!("{:?}..={:?}", range.start(), range.end()),
+                    "to": format!("{:?}", to),
+                })
+            })
+            .collect();
+        if let Value::Array(edge_list) = &mut table["edges"] {
+            edge_list.append(&mut range_list);
+        }
+        table
+    }
+
+    /// Read a transition table, parsing states and transitions by `FromStr`.
+    /// `accepts`, `edges` and `fallbacks` may be left out. Fails on other
+    /// fields, on values which cannot be parsed, and on two edges or two
+    /// fallbacks leaving the same state on the same transition for
+    /// different targets.
+    pub fn from_json_value(value: &Value) -> Result<Self, JsonError>
+    where
+        S: FromStr + Clone,
+        T: FromStr,
+    {
+        let table = value
+            .as_object()
+            .ok_or_else(|| JsonError::new("table is not an object"))?;
+        if let Some(key) = table
+            .keys()
+            .find(|key| !["start", "accepts", "edges", "fallbacks"].contains(&key.as_str()))
+        {
+            return Err(JsonError::new(format!("unknown field `{}`", key)));
+        }
+        let start = table
+            .get("start")
+            .ok_or_else(|| JsonError::new("missing field `start`"))?;
+        let mut builder = DFAutoBuilder::start(read_value::<S>(start, "state")?);
+        for state in read_list(table, "accepts")? {
+            builder = builder.accept(read_value(state, "state")?);
+        }
+
+        let mut edge_list = Vec::new();
+        for edge in read_list(table, "edges")? {
+            let edge = read_object(edge, &["from", "on", "to"])?;
+            edge_list.push((
+                read_value::<S>(edge[0], "state")?,
+                read_value::<T>(edge[1], "transition")?,
+                read_value::<S>(edge[2], "state")?,
+            ));
+        }
+        let mut fallback_list = Vec::new();
+        for fallback in read_list(table, "fallbacks")? {
+            let fallback = read_object(fallback, &["from", "to"])?;
+            fallback_list.push((
+                read_value::<S>(fallback[0], "state")?,
+                read_value::<S>(fallback[1], "state")?,
+            ));
+        }
+        // checked before connecting, which panics on duplicates
+        let mut edge_map = HashMap::new();
+        for (from, on, to) in &edge_list {
+            if edge_map
+                .insert((from, on), to)
+                .is_some_and(|known| known != to)
+            {
+                return Err(JsonError::new("duplicated edge"));
+            }
+        }
+        let mut fallback_map = HashMap::new();
+        for (from, to) in &fallback_list {
+            if fallback_map
+                .insert(from, to)
+                .is_some_and(|known| known != to)
+            {
+                return Err(JsonError::new("duplicated fallback"));
+            }
+        }
+
+        for (from, on, to) in edge_list {
+            builder = builder.connect(from, on, to);
+        }
+        for (from, to) in fallback_list {
+            builder = builder.connect_fallback(from, to);
+        }
+        Ok(builder.finalize())
+    }
+}
+
+// the list under `key`, empty if absent
+fn read_list<'v>(table: &'v Map<String, Value>, key: &str) -> Result<&'v [Value], JsonError> {
+    match table.get(key) {
+        None => Ok(&[]),
+        Some(Value::Array(list)) => Ok(list),
+        Some(_) => Err(JsonError::new(format!("`{}` is not a list", key))),
+    }
+}
+
+// the values of exactly the fields in `key_list`
+fn read_object<'v>(value: &'v Value, key_list: &[&str]) -> Result<Vec<&'v Value>, JsonError> {
+    let object = value
+        .as_object()
+        .filter(|object| object.len() == key_list.len())
+        .ok_or_else(|| {
+            JsonError::new(format!(
+                "expected an object with `{}`",
+                key_list.join("`, `")
+            ))
+        })?;
+    key_list
+        .iter()
+        .map(|key| {
+            object
+                .get(*key)
+                .ok_or_else(|| JsonError::new(format!("missing field `{}`", key)))
+        })
+        .collect()
+}
+
+fn read_value<X>(value: &Value, kind: &str) -> Result<X, JsonError>
+where
+    X: FromStr,
+{
+    let text = value
+        .as_str()
+        .ok_or_else(|| JsonError::new(format!("{} {} is not a string", kind, value)))?;
+    text.parse()
+        .map_err(|_| JsonError::new(format!("invalid {} `{}`", kind, text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+
+    #[test]
+    fn sorted_by_string_form() {
+        let dfa = DFAutoBuilder::start(2)
+            .connect(2, 'b', 10)
+            .connect(2, 'a', 2)
+            .connect(10, 'a', 9)
+            .connect_fallback(9, 2)
+            .accept(9)
+            .accept(10)
+            .finalize();
+        let table = dfa.to_json_value().unwrap();
+        // "10" is written before "2" and "9", as lists are sorted as text
+        assert_eq!(
+            table,
+            json!({
+                "start": "2",
+                "accepts": ["10", "9"],
+                "edges": [
+                    { "from": "10", "on": "a", "to": "9" },
+                    { "from": "2", "on": "a", "to": "2" },
+                    { "from": "2", "on": "b", "to": "10" },
+                ],
+                "fallbacks": [{ "from": "9", "to": "2" }],
+            })
+        );
+        let restored = DFAutoBlueprint::<u32, char>::from_json_value(&table).unwrap();
+        assert_eq!(restored, dfa);
+
+        let restored =
+            DFAutoBlueprint::<String, String>::from_json_value(&json!({ "start": "idle" }))
+                .unwrap();
+        assert!(!restored.create().test(Vec::<String>::new()));
+        assert_eq!(
+            restored.to_json_value().unwrap(),
+            json!({ "start": "idle", "accepts": [], "edges": [], "fallbacks": [] })
+        );
+    }
+
+    #[test]
+    fn escaped_text() {
+        let dfa = DFAutoBuilder::start("say \"hi\"".to_string())
+            .connect("say \"hi\"".to_string(), String::new(), "a\\b".to_string())
+            .connect(
+                "a\\b".to_string(),
+                "line\nbreak".to_string(),
+                "λ".to_string(),
+            )
+            .accept("λ".to_string())
+            .finalize();
+        let text = serde_json::to_string(&dfa.to_json_value().unwrap()).unwrap();
+        assert!(text.contains(r#""on":"line\nbreak""#));
+        assert!(text.contains(r#""from":"say \"hi\"""#));
+        let table: Value = serde_json::from_str(&text).unwrap();
+        let restored = DFAutoBlueprint::<String, String>::from_json_value(&table).unwrap();
+        assert_eq!(restored, dfa);
+        let input = vec![String::new(), "line\nbreak".to_string()];
+        assert!(restored.create().test(&input));
+    }
+
+    // state written rounded down to tens
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Rounded(u32);
+
+    impl fmt::Display for Rounded {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0 / 10 * 10)
+        }
+    }
+
+    impl FromStr for Rounded {
+        type Err = std::num::ParseIntError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse().map(Rounded)
+        }
+    }
+
+    #[test]
+    fn lossless_or_lossy() {
+        let dfa = DFAutoBuilder::start(Rounded(20))
+            .connect(Rounded(20), 'a', Rounded(30))
+            .finalize();
+        assert!(dfa.to_json_value().is_ok());
+        let dfa = DFAutoBuilder::start(Rounded(20))
+            .connect(Rounded(20), 'a', Rounded(35))
+            .finalize();
+        assert_eq!(
+            dfa.to_json_value().unwrap_err(),
+            JsonError::new("state `30` is not parsed back from its string form")
+        );
+        assert_eq!(
+            dfa.to_json_value_lossy()["edges"],
+            json!([{ "from": "Rounded(20)", "on": "'a'", "to": "Rounded(35)" }])
+        );
+
+        let dfa = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 1)
+            .finalize();
+        assert!(dfa.to_json_value().is_err());
+        assert_eq!(
+            dfa.to_json_value_lossy()["edges"],
+            json!([{ "from": "0", "on": "'a'..='z'", "to": "1" }])
+        );
+        let dfa = DFAutoBuilder::<u32, char>::start(0)
+            .accept_with(0, 1)
+            .finalize();
+        assert!(dfa.to_json_value().is_err());
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let read = |value: Value| DFAutoBlueprint::<u32, char>::from_json_value(&value);
+        assert!(read(json!({ "start": "0", "accepts": ["1"] })).is_ok());
+        assert!(read(json!({ "accepts": ["1"] })).is_err());
+        assert!(read(json!({ "start": 0 })).is_err());
+        assert!(read(json!({ "start": "zero" })).is_err());
+        assert!(read(json!({ "start": "0", "initial": "0" })).is_err());
+        assert!(
+            read(json!({ "start": "0", "edges": [{ "from": "0", "on": "ab", "to": "1" }] }))
+                .is_err()
+        );
+        assert!(read(json!({ "start": "0", "edges": [{ "from": "0", "to": "1" }] })).is_err());
+        assert_eq!(
+            read(json!({
+                "start": "0",
+                "edges": [
+                    { "from": "0", "on": "a", "to": "1" },
+                    { "from": "0", "on": "a", "to": "1" },
+                    { "from": "00", "on": "a", "to": "2" },
+                ],
+            }))
+            .unwrap_err(),
+            JsonError::new("duplicated edge")
+        );
+        assert!(read(json!({
+            "start": "0",
+            "fallbacks": [{ "from": "0", "to": "1" }, { "from": "0", "to": "0" }],
+        }))
+        .is_err());
+    }
+}
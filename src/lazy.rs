@@ -0,0 +1,215 @@
+use crate::algo::extend_state_set;
+use crate::auto::Auto;
+use crate::nfa::{next_state_set, NFAutoBlueprint};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+/// Default maximum number of transitions cached by a `LazyDfa`.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1 << 16;
+
+/// DFA built from an NFA blueprint while running: every transition between
+/// state sets is computed on first use and cached for the following ones.
+/// The cache is kept across inputs, see `reset`.
+#[derive(Debug)]
+pub struct LazyDfa<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    blueprint: &'b NFAutoBlueprint<S, T>,
+    // cached state sets, identified by their index
+    state_set_list: Vec<BTreeSet<S>>,
+    state_id_map: HashMap<BTreeSet<S>, usize>,
+    trans_map: HashMap<(usize, T), usize>,
+    cache_capacity: usize,
+    start_state_id: usize,
+    current_state_id: usize,
+    miss_count: usize,
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    pub fn create_lazy_dfa(&self) -> LazyDfa<'_, S, T> {
+        let mut lazy = LazyDfa {
+            blueprint: self,
+            state_set_list: Vec::new(),
+            state_id_map: HashMap::new(),
+            trans_map: HashMap::new(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            start_state_id: 0,
+            current_state_id: 0,
+            miss_count: 0,
+        };
+        let start_state_set = extend_state_set(self, self.start_state_set());
+        lazy.start_state_id = lazy.state_id(start_state_set.into_iter().collect());
+        lazy.current_state_id = lazy.start_state_id;
+        lazy
+    }
+}
+
+impl<'b, S, T> LazyDfa<'b, S, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    /// Bound the number of cached transitions. The whole cache is cleared
+    /// when it would grow larger.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity;
+        if self.trans_map.len() > capacity {
+            self.clear_cache();
+        }
+    }
+
+    /// Go back to the start states to run on another input, keeping the
+    /// cache.
+    pub fn reset(&mut self) {
+        self.current_state_id = self.start_state_id;
+    }
+
+    pub fn current_state_set(&self) -> &BTreeSet<S> {
+        &self.state_set_list[self.current_state_id]
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.current_state_set()
+            .iter()
+            .any(|state| self.blueprint.accept_state_set().contains(state))
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current_state_set().is_empty()
+    }
+
+    /// Number of transitions which were not cached and had to be computed
+    /// from the blueprint.
+    pub fn miss_count(&self) -> usize {
+        self.miss_count
+    }
+
+    pub fn trigger(&mut self, trans: &T) {
+        if let Some(next_id) = self.trans_map.get(&(self.current_state_id, trans.clone())) {
+            self.current_state_id = *next_id;
+            return;
+        }
+        self.miss_count += 1;
+        let state_set = next_state_set(self.blueprint, self.current_state_set(), trans);
+        if self.trans_map.len() >= self.cache_capacity {
+            self.clear_cache();
+        }
+        let next_id = self.state_id(state_set.into_iter().collect());
+        self.trans_map
+            .insert((self.current_state_id, trans.clone()), next_id);
+        self.current_state_id = next_id;
+    }
+
+    fn state_id(&mut self, state_set: BTreeSet<S>) -> usize {
+        if let Some(id) = self.state_id_map.get(&state_set) {
+            return *id;
+        }
+        self.state_set_list.push(state_set.clone());
+        self.state_id_map
+            .insert(state_set, self.state_set_list.len() - 1);
+        self.state_set_list.len() - 1
+    }
+
+    // forget every cached state set and transition except the start and
+    // current state sets
+    fn clear_cache(&mut self) {
+        let start_state_set = self.state_set_list[self.start_state_id].clone();
+        let current_state_set = self.current_state_set().clone();
+        self.state_set_list.clear();
+        self.state_id_map.clear();
+        self.trans_map.clear();
+        self.start_state_id = self.state_id(start_state_set);
+        self.current_state_id = self.state_id(current_state_set);
+    }
+}
+
+impl<'b, S, T> Auto for LazyDfa<'b, S, T>
+where
+    S: Hash + Eq + Ord + Clone,
+    T: Hash + Eq + Clone,
+{
+    type Trans = T;
+
+    fn test_trigger(&self, _trans: &Self::Trans) -> bool {
+        !self.is_dead()
+    }
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        self.trigger(trans)
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_dead()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcg::Lcg;
+    use crate::re::Re;
+
+    fn random_input_list(alphabet: &str, count: usize) -> Vec<String> {
+        let alphabet: Vec<_> = alphabet.chars().collect();
+        let mut rng = Lcg::new(7);
+        (0..count)
+            .map(|_| {
+                let len = rng.next(10);
+                (0..len)
+                    .map(|_| alphabet[rng.next(alphabet.len())])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_as_nfa() {
+        let pattern_list = [
+            "(a|b)*a(a|b)(a|b)",
+            "a(ab|.c)*",
+            "[^a]*(b|[c-d])+",
+            "(ab&cd)e?",
+        ];
+        for pattern in pattern_list.iter() {
+            let nfa = Re::parse(pattern).unwrap().compile();
+            let mut lazy = nfa.create_lazy_dfa();
+            let mut small = nfa.create_lazy_dfa();
+            small.set_cache_capacity(3);
+            for input in random_input_list("abcde", 300) {
+                let expected = nfa.create().test(input.chars());
+                lazy.reset();
+                assert_eq!(lazy.test(input.chars()), expected, "{} {}", pattern, input);
+                small.reset();
+                assert_eq!(small.test(input.chars()), expected, "{} {}", pattern, input);
+            }
+        }
+    }
+
+    #[test]
+    fn cached_second_pass() {
+        let nfa = Re::parse("(a|b)*a(a|b)(a|b)").unwrap().compile();
+        let mut lazy = nfa.create_lazy_dfa();
+        let input_list = random_input_list("ab", 50);
+        for input in input_list.iter() {
+            lazy.reset();
+            lazy.test(input.chars());
+        }
+        let miss_count = lazy.miss_count();
+        assert!(miss_count > 0);
+        for input in input_list.iter() {
+            lazy.reset();
+            lazy.test(input.chars());
+        }
+        assert_eq!(lazy.miss_count(), miss_count);
+    }
+}
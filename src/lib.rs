@@ -1,5 +1,34 @@
 pub mod algo;
 pub mod auto;
+pub mod binary;
+pub mod capture;
+pub mod codegen;
+pub mod csv;
 pub mod dfa;
+pub mod dot;
+pub mod dsl;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "petgraph")]
+pub mod graph;
+#[cfg(feature = "serde_json")]
+pub mod json;
+pub mod lazy;
+mod lcg;
+mod mermaid;
 pub mod nfa;
+pub mod parse;
+pub mod range;
 pub mod re;
+#[cfg(feature = "rand")]
+mod sample;
+mod scc;
+#[cfg(feature = "serde")]
+mod serial;
+#[cfg(feature = "serde")]
+pub mod spec;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+mod table;
+pub mod view;
+pub mod witness;
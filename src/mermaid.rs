@@ -0,0 +1,168 @@
+use crate::dfa::DFAutoBlueprint;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+// `name` reduced to the characters Mermaid accepts in state ids, starting
+// with a letter
+fn sanitize(name: &str) -> String {
+    let id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id
+    } else {
+        format!("s{}", id)
+    }
+}
+
+// `label` with the characters ending a Mermaid statement escaped
+fn escape(label: &str) -> String {
+    label
+        .replace('#', "#35;")
+        .replace(';', "#59;")
+        .replace('\n', "#10;")
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Debug,
+    T: Hash + Eq + Debug,
+{
+    /// Render as a Mermaid `stateDiagram-v2`, with states and transitions
+    /// written by `Debug`. Ranges are labeled like `'a'..='z'`, fallbacks
+    /// `otherwise`, and accept states get the `accept` class. State names are
+    /// turned into Mermaid ids by replacing other characters than ASCII
+    /// letters and digits with `_`, prefixing `s` unless they start with a
+    /// letter, and suffixing `_2`, `_3`, ... on collisions. Comments at the top
+    /// map every id back to its name.
+    pub fn to_mermaid(&self) -> String {
+        let mut state_set: HashSet<_> = self.accept_state_set().iter().collect();
+        state_set.insert(self.start_state());
+        for (from, _, to) in self.iterate_connections() {
+            state_set.extend(&[from, to]);
+        }
+        for (from, _, to) in self.iterate_range_connections() {
+            state_set.extend(&[from, to]);
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            state_set.extend(&[from, to]);
+        }
+        // ordered as in the DOT export, shorter names first
+        let mut state_list: Vec<_> = state_set
+            .into_iter()
+            .map(|state| (format!("{:?}", state), state))
+            .collect();
+        state_list
+            .sort_by(|(first, _), (second, _)| (first.len(), first).cmp(&(second.len(), second)));
+
+        let mut used = HashSet::new();
+        let mut id_map = HashMap::new();
+        let mut legend = String::new();
+        for (name, state) in &state_list {
+            let base = sanitize(name);
+            let mut id = base.clone();
+            let mut suffix = 2;
+            while !used.insert(id.clone()) {
+                id = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            writeln!(legend, "    %% {}: {}", id, name.replace('\n', " ")).unwrap();
+            id_map.insert(*state, id);
+        }
+
+        let mut edge_list: Vec<_> = self
+            .iterate_connections()
+            .map(|(from, trans, to)| (from, to, format!("{:?}", trans)))
+            .chain(self.iterate_range_connections().map(|(from, range, to)| {
+                let label = format!("{:?}..={:?}", range.start(), range.end());
+                (from, to, label)
+            }))
+            .map(|(from, to, label)| (&id_map[from], &id_map[to], escape(&label)))
+            .collect();
+        edge_list.sort();
+        let mut fallback_list: Vec<_> = self
+            .iterate_fallback_connections()
+            .map(|(from, to)| (&id_map[from], &id_map[to]))
+            .collect();
+        fallback_list.sort();
+        let mut accept_list: Vec<_> = self
+            .accept_state_set()
+            .iter()
+            .map(|state| id_map[state].as_str())
+            .collect();
+        accept_list.sort();
+
+        let mut text = "stateDiagram-v2\n".to_string();
+        text += &legend;
+        writeln!(text, "    [*] --> {}", id_map[self.start_state()]).unwrap();
+        for (from, to, label) in edge_list {
+            writeln!(text, "    {} --> {}: {}", from, to, label).unwrap();
+        }
+        for (from, to) in fallback_list {
+            writeln!(text, "    {} --> {}: otherwise", from, to).unwrap();
+        }
+        if !accept_list.is_empty() {
+            text += "    classDef accept stroke-width:3px\n";
+            writeln!(text, "    class {} accept", accept_list.join(",")).unwrap();
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dfa::DFAutoBuilder;
+
+    #[test]
+    fn golden() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, ';', 1)
+            .connect_range(1, '0'..='9', 2)
+            .connect_fallback(2, 0)
+            .accept(1)
+            .accept(2)
+            .finalize();
+        assert_eq!(
+            dfa.to_mermaid(),
+            concat!(
+                "stateDiagram-v2\n",
+                "    %% s0: 0\n",
+                "    %% s1: 1\n",
+                "    %% s2: 2\n",
+                "    [*] --> s0\n",
+                "    s0 --> s1: 'a'\n",
+                "    s1 --> s1: '#59;'\n",
+                "    s1 --> s2: '0'..='9'\n",
+                "    s2 --> s0: otherwise\n",
+                "    classDef accept stroke-width:3px\n",
+                "    class s1,s2 accept\n",
+            )
+        );
+    }
+
+    #[test]
+    fn colliding_names() {
+        let dfa = DFAutoBuilder::start("a b")
+            .connect("a b", 'x', "a-b")
+            .connect("a-b", 'y', "a_b")
+            .connect("a_b", 'z', "a_b_2")
+            .finalize();
+        assert_eq!(
+            dfa.to_mermaid(),
+            concat!(
+                "stateDiagram-v2\n",
+                "    %% s_a_b_: \"a b\"\n",
+                "    %% s_a_b__2: \"a-b\"\n",
+                "    %% s_a_b__3: \"a_b\"\n",
+                "    %% s_a_b_2_: \"a_b_2\"\n",
+                "    [*] --> s_a_b_\n",
+                "    s_a_b_ --> s_a_b__2: 'x'\n",
+                "    s_a_b__2 --> s_a_b__3: 'y'\n",
+                "    s_a_b__3 --> s_a_b_2_: 'z'\n",
+            )
+        );
+    }
+}
@@ -1,8 +1,132 @@
-use crate::algo::extend_state_set;
-use crate::auto::Auto;
-use std::collections::{HashMap, HashSet};
+use crate::algo::{extend_state_set, try_dfa_to_nfa, FallbackWithRange};
+use crate::auto::{Auto, AutoExt, Blueprint, BoxAuto, Checkpointable, Step};
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use crate::dot::{DotGraph, DotOptions, EdgeStyle};
+use crate::range::{insert_sorted, matching, TransRange};
+use crate::view::{MapView, SetView};
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
 use std::hash::Hash;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+/// Opaque transition test used by predicate connections.
+pub struct Predicate<T>(PredicateKind<T>);
+
+type PredicateFn<T> = dyn Fn(&T) -> bool;
+
+enum PredicateKind<T> {
+    Test(Rc<PredicateFn<T>>),
+    Range(Rc<TransRange<T>>),
+    Both(Rc<(Predicate<T>, Predicate<T>)>),
+    Except(Rc<ExcludedSet<T>>),
+}
+
+// excluded transitions and ranges with the lookup of transitions, captured
+// where `T: Hash + Eq` holds
+type ExcludedSet<T> = (HashSet<T>, Vec<TransRange<T>>, fn(&HashSet<T>, &T) -> bool);
+
+impl<T> Predicate<T> {
+    pub fn new<F>(pred: F) -> Self
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        Self(PredicateKind::Test(Rc::new(pred)))
+    }
+
+    pub(crate) fn in_range(range: TransRange<T>) -> Self {
+        Self(PredicateKind::Range(Rc::new(range)))
+    }
+
+    pub(crate) fn both(first: Self, second: Self) -> Self {
+        Self(PredicateKind::Both(Rc::new((first, second))))
+    }
+
+    pub(crate) fn except(excluded: HashSet<T>) -> Self
+    where
+        T: Hash + Eq,
+    {
+        Self::except_ranges(excluded, Vec::new())
+    }
+
+    // passing transitions neither in `excluded` nor in a range of
+    // `range_list`, as a fallback next to ranges
+    pub(crate) fn except_ranges(excluded: HashSet<T>, range_list: Vec<TransRange<T>>) -> Self
+    where
+        T: Hash + Eq,
+    {
+        Self(PredicateKind::Except(Rc::new((
+            excluded,
+            range_list,
+            |excluded, trans| excluded.contains(trans),
+        ))))
+    }
+
+    // the excluded transitions and ranges of a predicate passing every other
+    // transition
+    pub(crate) fn as_except(&self) -> Option<(&HashSet<T>, &[TransRange<T>])> {
+        match &self.0 {
+            PredicateKind::Except(excluded) => Some((&excluded.0, &excluded.1)),
+            _ => None,
+        }
+    }
+
+    pub fn test(&self, trans: &T) -> bool {
+        match &self.0 {
+            PredicateKind::Test(pred) => pred(trans),
+            PredicateKind::Range(range) => range.contains(trans),
+            PredicateKind::Both(both) => both.0.test(trans) && both.1.test(trans),
+            PredicateKind::Except(excluded) => {
+                !(excluded.2)(&excluded.0, trans)
+                    && !excluded.1.iter().any(|range| range.contains(trans))
+            }
+        }
+    }
+}
+
+impl<T> Clone for Predicate<T> {
+    fn clone(&self) -> Self {
+        Self(match &self.0 {
+            PredicateKind::Test(pred) => PredicateKind::Test(pred.clone()),
+            PredicateKind::Range(range) => PredicateKind::Range(range.clone()),
+            PredicateKind::Both(both) => PredicateKind::Both(both.clone()),
+            PredicateKind::Except(excluded) => PredicateKind::Except(excluded.clone()),
+        })
+    }
+}
+
+impl<T> fmt::Debug for Predicate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Predicate")
+    }
+}
+
+// predicates are compared by identity
+impl<T> PartialEq for Predicate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (PredicateKind::Test(pred), PredicateKind::Test(other_pred)) => {
+                Rc::ptr_eq(pred, other_pred)
+            }
+            (PredicateKind::Range(range), PredicateKind::Range(other_range)) => {
+                Rc::ptr_eq(range, other_range)
+            }
+            (PredicateKind::Both(both), PredicateKind::Both(other_both)) => {
+                Rc::ptr_eq(both, other_both)
+            }
+            (PredicateKind::Except(excluded), PredicateKind::Except(other_excluded)) => {
+                Rc::ptr_eq(excluded, other_excluded)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<T> Eq for Predicate<T> {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NFAutoBuilder<S, T>
@@ -13,8 +137,13 @@ where
     graph: HashMap<S, HashMap<T, HashSet<S>>>,
     void_graph: HashMap<S, HashSet<S>>,
     wildcard_graph: HashMap<S, HashSet<S>>,
+    wildcard_except_graph: HashMap<S, HashMap<S, HashSet<T>>>,
+    range_graph: HashMap<S, Vec<(TransRange<T>, S)>>,
+    predicate_graph: HashMap<S, Vec<(Predicate<T>, S)>>,
     start_state: S,
+    extra_start_state_set: HashSet<S>,
     accept_state_set: HashSet<S>,
+    accept_tag_map: HashMap<S, HashSet<usize>>,
 }
 
 impl<S, T> NFAutoBuilder<S, T>
@@ -27,9 +156,23 @@ where
             graph: HashMap::new(),
             void_graph: HashMap::new(),
             wildcard_graph: HashMap::new(),
+            wildcard_except_graph: HashMap::new(),
+            range_graph: HashMap::new(),
+            predicate_graph: HashMap::new(),
             start_state,
+            extra_start_state_set: HashSet::new(),
             accept_state_set: HashSet::new(),
+            accept_tag_map: HashMap::new(),
+        }
+    }
+
+    /// Mark another state as a start state, in addition to the one passed to
+    /// `start`. An automaton starts from all start states simultaneously.
+    pub fn also_start(mut self, state: S) -> Self {
+        if state != self.start_state {
+            self.extra_start_state_set.insert(state);
         }
+        self
     }
 
     pub fn accept(mut self, state: S) -> Self {
@@ -38,6 +181,33 @@ where
     }
 }
 
+impl<S, T> NFAutoBuilder<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    /// Accept `state` and tag it, e.g. with the index of the pattern it
+    /// belongs to. A state may carry several tags; lower tags are meant to
+    /// take priority.
+    pub fn accept_with(mut self, state: S, tag: usize) -> Self {
+        self.accept_tag_map
+            .entry(state.clone())
+            .or_default()
+            .insert(tag);
+        self.accept(state)
+    }
+
+    // accept `state` with the tags it has in `blueprint`
+    pub(crate) fn accept_as(self, state: S, blueprint: &NFAutoBlueprint<S, T>) -> Self {
+        let tag_list: Vec<_> = blueprint.accept_tags(&state).iter().cloned().collect();
+        tag_list
+            .into_iter()
+            .fold(self.accept(state.clone()), |b, tag| {
+                b.accept_with(state.clone(), tag)
+            })
+    }
+}
+
 impl<S, T> Default for NFAutoBuilder<S, T>
 where
     S: Hash + Eq + Default,
@@ -54,32 +224,123 @@ where
     T: Hash + Eq + Clone,
 {
     pub fn connect(mut self, from: S, trans: T, to: S) -> Self {
-        if !self.graph.contains_key(&from) {
-            self.graph.insert(from.clone(), HashMap::new());
-        }
-        let trans_to = self.graph.get_mut(&from).unwrap();
-        if !trans_to.contains_key(&trans) {
-            trans_to.insert(trans.clone(), HashSet::new());
-        }
-        trans_to.get_mut(&trans).unwrap().insert(to);
+        self.connect_mut(from, trans, to);
         self
     }
 
     pub fn connect_void(mut self, from: S, to: S) -> Self {
-        if !self.void_graph.contains_key(&from) {
-            self.void_graph.insert(from.clone(), HashSet::new());
-        }
-        self.void_graph.get_mut(&from).unwrap().insert(to);
+        self.connect_void_mut(from, to);
         self
     }
 
     pub fn connect_wildcard(mut self, from: S, to: S) -> Self {
-        if !self.wildcard_graph.contains_key(&from) {
-            self.wildcard_graph.insert(from.clone(), HashSet::new());
-        }
-        self.wildcard_graph.get_mut(&from).unwrap().insert(to);
+        self.connect_wildcard_mut(from, to);
+        self
+    }
+
+    /// Connect with a wildcard taken on every transition except the excluded
+    /// ones. Connecting the same states again only excludes the transitions
+    /// excluded by both connections.
+    pub fn connect_wildcard_except<I>(mut self, from: S, excluded: I, to: S) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.connect_wildcard_except_mut(from, excluded, to);
+        self
+    }
+
+    /// Connect with a transition test: the connection is taken on every
+    /// transition for which `pred` returns true.
+    pub fn connect_if<F>(self, from: S, pred: F, to: S) -> Self
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.connect_predicate(from, Predicate::new(pred), to)
+    }
+
+    pub(crate) fn connect_trans_range(mut self, from: S, range: TransRange<T>, to: S) -> Self {
+        self.connect_trans_range_mut(from, range, to);
+        self
+    }
+
+    pub(crate) fn connect_predicate(mut self, from: S, pred: Predicate<T>, to: S) -> Self {
+        self.connect_predicate_mut(from, pred, to);
+        self
+    }
+
+    pub(crate) fn connect_as(mut self, from: S, conn: ConnType<'_, T>, to: S) -> Self {
+        self.connect_as_mut(from, conn, to);
         self
     }
+
+    /// Same as `connect`, in place.
+    pub fn connect_mut(&mut self, from: S, trans: T, to: S) {
+        self.graph
+            .entry(from)
+            .or_default()
+            .entry(trans)
+            .or_default()
+            .insert(to);
+    }
+
+    /// Same as `connect_void`, in place.
+    pub fn connect_void_mut(&mut self, from: S, to: S) {
+        self.void_graph.entry(from).or_default().insert(to);
+    }
+
+    /// Same as `connect_wildcard`, in place.
+    pub fn connect_wildcard_mut(&mut self, from: S, to: S) {
+        self.wildcard_graph.entry(from).or_default().insert(to);
+    }
+
+    /// Same as `connect_wildcard_except`, in place.
+    pub fn connect_wildcard_except_mut<I>(&mut self, from: S, excluded: I, to: S)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let excluded: HashSet<_> = excluded.into_iter().collect();
+        let to_excluded = self.wildcard_except_graph.entry(from).or_default();
+        if let Some(known) = to_excluded.get_mut(&to) {
+            known.retain(|trans| excluded.contains(trans));
+        } else {
+            to_excluded.insert(to, excluded);
+        }
+    }
+
+    pub(crate) fn connect_trans_range_mut(&mut self, from: S, range: TransRange<T>, to: S) {
+        insert_sorted(self.range_graph.entry(from).or_default(), range, to);
+    }
+
+    pub(crate) fn connect_predicate_mut(&mut self, from: S, pred: Predicate<T>, to: S) {
+        self.predicate_graph
+            .entry(from)
+            .or_default()
+            .push((pred, to));
+    }
+
+    pub(crate) fn connect_as_mut(&mut self, from: S, conn: ConnType<'_, T>, to: S) {
+        match conn {
+            ConnType::Plain(trans) => self.connect_mut(from, trans.clone(), to),
+            ConnType::Void => self.connect_void_mut(from, to),
+            ConnType::Wildcard => self.connect_wildcard_mut(from, to),
+            ConnType::WildcardExcept(excluded) => {
+                self.connect_wildcard_except_mut(from, excluded.iter().cloned(), to)
+            }
+            ConnType::Range(range) => self.connect_trans_range_mut(from, range.clone(), to),
+            ConnType::Predicate(pred) => self.connect_predicate_mut(from, pred.clone(), to),
+        }
+    }
+}
+
+impl<S, T> NFAutoBuilder<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Ord + Clone,
+{
+    /// Connect on every transition within `range`.
+    pub fn connect_range(self, from: S, range: RangeInclusive<T>, to: S) -> Self {
+        self.connect_trans_range(from, TransRange::new(range), to)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -91,8 +352,13 @@ where
     graph: HashMap<S, HashMap<T, HashSet<S>>>,
     void_graph: HashMap<S, HashSet<S>>,
     wildcard_graph: HashMap<S, HashSet<S>>,
+    wildcard_except_graph: HashMap<S, HashMap<S, HashSet<T>>>,
+    range_graph: HashMap<S, Vec<(TransRange<T>, S)>>,
+    predicate_graph: HashMap<S, Vec<(Predicate<T>, S)>>,
     start_state: S,
+    extra_start_state_set: HashSet<S>,
     accept_state_set: HashSet<S>,
+    accept_tag_map: HashMap<S, HashSet<usize>>,
 }
 
 impl<S, T> NFAutoBuilder<S, T>
@@ -105,9 +371,141 @@ where
             graph: self.graph,
             void_graph: self.void_graph,
             wildcard_graph: self.wildcard_graph,
+            wildcard_except_graph: self.wildcard_except_graph,
+            range_graph: self.range_graph,
+            predicate_graph: self.predicate_graph,
             start_state: self.start_state,
+            extra_start_state_set: self.extra_start_state_set,
             accept_state_set: self.accept_state_set,
+            accept_tag_map: self.accept_tag_map,
+        }
+    }
+}
+
+/// Defect found in a blueprint by `finalize_checked` or `warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding<S> {
+    /// Accept state not reachable from any start state.
+    UnreachableAccept(S),
+    /// Void connection from a state to itself. Harmless, only a warning.
+    VoidSelfLoop(S),
+    /// Non-accepting target of a void connection without outgoing connection.
+    VoidDeadEnd(S),
+    NoAcceptState,
+}
+
+impl<S> Finding<S> {
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Finding::VoidSelfLoop(_))
+    }
+}
+
+impl<S> fmt::Display for Finding<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::UnreachableAccept(state) => {
+                write!(f, "accept state {:?} is unreachable", state)
+            }
+            Finding::VoidSelfLoop(state) => write!(f, "void connection from {:?} to itself", state),
+            Finding::VoidDeadEnd(state) => write!(
+                f,
+                "state {:?} is reached by void connection but leads nowhere",
+                state
+            ),
+            Finding::NoAcceptState => write!(f, "no accept state"),
+        }
+    }
+}
+
+/// All defects found in a blueprint by `finalize_checked`, warnings
+/// included, when at least one of them is not a warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError<S> {
+    pub finding_list: Vec<Finding<S>>,
+}
+
+impl<S> fmt::Display for ValidationError<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid automaton")?;
+        for (i, finding) in self.finding_list.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ':' } else { ';' }, finding)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> std::error::Error for ValidationError<S> where S: fmt::Debug {}
+
+impl<S, T> NFAutoBuilder<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    /// Same as `finalize`, but report suspicious structure of the blueprint.
+    /// Blueprints with only warnings are returned, see `warnings` to get
+    /// them.
+    pub fn finalize_checked(self) -> Result<NFAutoBlueprint<S, T>, ValidationError<S>> {
+        let blueprint = self.finalize();
+        let finding_list = blueprint.findings();
+        if finding_list.iter().all(Finding::is_warning) {
+            Ok(blueprint)
+        } else {
+            Err(ValidationError { finding_list })
+        }
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    /// Findings of `finalize_checked` which are only warnings.
+    pub fn warnings(&self) -> Vec<Finding<S>> {
+        let mut finding_list = self.findings();
+        finding_list.retain(Finding::is_warning);
+        finding_list
+    }
+
+    fn findings(&self) -> Vec<Finding<S>> {
+        let mut finding_list = Vec::new();
+        if self.accept_state_set.is_empty() {
+            finding_list.push(Finding::NoAcceptState);
         }
+        let reachable = self.reachable_state_set();
+        finding_list.extend(
+            self.accept_state_set
+                .iter()
+                .filter(|state| !reachable.contains(state))
+                .map(|state| Finding::UnreachableAccept(state.clone())),
+        );
+        let mut dead_end_set = HashSet::new();
+        for (from, to_set) in &self.void_graph {
+            if to_set.contains(from) {
+                finding_list.push(Finding::VoidSelfLoop(from.clone()));
+            }
+            for to in to_set {
+                let connections = self.connections_from(to);
+                if !self.accept_state_set.contains(to)
+                    && connections.plain.is_empty()
+                    && connections.void.is_empty()
+                    && connections.wildcard.is_empty()
+                    && connections.wildcard_except.is_empty()
+                    && connections.range.is_empty()
+                    && connections.predicate.is_empty()
+                    && dead_end_set.insert(to)
+                {
+                    finding_list.push(Finding::VoidDeadEnd(to.clone()));
+                }
+            }
+        }
+        finding_list
     }
 }
 
@@ -120,23 +518,44 @@ where
         &self.start_state
     }
 
+    /// All start states, the one returned by `start_state` first.
+    pub fn start_state_set(&self) -> impl Iterator<Item = &S> {
+        std::iter::once(&self.start_state).chain(self.extra_start_state_set.iter())
+    }
+
     pub fn accept_state_set(&self) -> &HashSet<S> {
         &self.accept_state_set
     }
+
+    /// Tags given to an accept state by `accept_with`.
+    pub fn accept_tags(&self, state: &S) -> SetView<'_, usize> {
+        SetView::new(self.accept_tag_map.get(state))
+    }
 }
 
 pub enum ConnType<'t, T> {
     Plain(&'t T),
     Void,
     Wildcard,
+    WildcardExcept(&'t HashSet<T>),
+    Range(&'t TransRange<T>),
+    Predicate(&'t Predicate<T>),
+}
+
+impl<'t, T> Clone for ConnType<'t, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<'t, T> Copy for ConnType<'t, T> {}
+
 impl<S, T> NFAutoBlueprint<S, T>
 where
     S: Hash + Eq,
     T: Hash + Eq,
 {
-    pub fn iterate_connections(&self) -> impl Iterator<Item = (&S, ConnType<T>, &S)> {
+    pub fn iterate_connections(&self) -> impl Iterator<Item = (&S, ConnType<'_, T>, &S)> {
         self.graph
             .iter()
             .flat_map(|(from, trans_to)| {
@@ -154,140 +573,1121 @@ where
             .chain(self.wildcard_graph.iter().flat_map(|(from, to_set)| {
                 to_set.iter().map(move |to| (from, ConnType::Wildcard, to))
             }))
+            .chain(
+                self.wildcard_except_graph
+                    .iter()
+                    .flat_map(|(from, to_excluded)| {
+                        to_excluded.iter().map(move |(to, excluded)| {
+                            (from, ConnType::WildcardExcept(excluded), to)
+                        })
+                    }),
+            )
+            .chain(self.range_graph.iter().flat_map(|(from, range_to_list)| {
+                range_to_list
+                    .iter()
+                    .map(move |(range, to)| (from, ConnType::Range(range), to))
+            }))
+            .chain(
+                self.predicate_graph
+                    .iter()
+                    .flat_map(|(from, pred_to_list)| {
+                        pred_to_list
+                            .iter()
+                            .map(move |(pred, to)| (from, ConnType::Predicate(pred), to))
+                    }),
+            )
+    }
+
+    /// Whether the empty string is accepted, i.e. an accept state is reached
+    /// from a start state through void connections only.
+    pub fn accepts_empty(&self) -> bool {
+        self.min_accepted_length() == Some(0)
+    }
+
+    /// Length of the shortest accepted string, or `None` if nothing is
+    /// accepted. Void connections do not count, other connections count as
+    /// one transition each.
+    pub fn min_accepted_length(&self) -> Option<usize> {
+        let mut length_map: HashMap<&S, usize> = HashMap::new();
+        let mut unresolved: VecDeque<_> = self.start_state_set().map(|state| (state, 0)).collect();
+        while let Some((state, length)) = unresolved.pop_front() {
+            if length_map.get(state).is_some_and(|known| *known <= length) {
+                continue;
+            }
+            length_map.insert(state, length);
+            if self.accept_state_set.contains(state) {
+                return Some(length);
+            }
+            let connections = self.connections_from(state);
+            for to in connections.void.iter() {
+                unresolved.push_front((to, length));
+            }
+            for to in connections
+                .plain
+                .iter()
+                .flat_map(|(_, to_set)| to_set)
+                .chain(connections.wildcard.iter())
+                .chain(connections.wildcard_except.iter().map(|(to, _)| to))
+                .chain(connections.range.iter().map(|(_, to)| to))
+                .chain(connections.predicate.iter().map(|(_, to)| to))
+            {
+                unresolved.push_back((to, length + 1));
+            }
+        }
+        None
+    }
+
+    /// Whether some connection is a wildcard, excluding transitions or not.
+    pub fn has_wildcard(&self) -> bool {
+        self.wildcard_graph
+            .values()
+            .any(|to_set| !to_set.is_empty())
+            || self
+                .wildcard_except_graph
+                .values()
+                .any(|to_map| !to_map.is_empty())
     }
-}
 
-pub struct ConnectionsFrom<'bp, S, T> {
-    pub plain: Option<&'bp HashMap<T, HashSet<S>>>,
-    pub wildcard: Option<&'bp HashSet<S>>,
-    pub void: Option<&'bp HashSet<S>>,
+    /// Every state mentioned by the blueprint, as a start, accept, source or
+    /// target of a connection.
+    pub fn state_set(&self) -> HashSet<&S> {
+        self.start_state_set()
+            .chain(self.accept_state_set.iter())
+            .chain(
+                self.iterate_connections()
+                    .flat_map(|(from, _, to)| vec![from, to]),
+            )
+            .collect()
+    }
 }
 
 impl<S, T> NFAutoBlueprint<S, T>
 where
-    S: Hash + Eq,
-    T: Hash + Eq,
+    S: Hash + Eq + fmt::Debug,
+    T: Hash + Eq + fmt::Debug,
 {
-    pub fn connections_from(&self, from: &S) -> ConnectionsFrom<S, T> {
-        ConnectionsFrom {
-            plain: self.graph.get(from),
-            wildcard: self.wildcard_graph.get(from),
-            void: self.void_graph.get(from),
+    /// Render as a Graphviz DOT graph. Plain and range connections are solid,
+    /// void ones dotted and labeled `ε`, wildcards dashed and labeled `·`
+    /// (followed by `∖` and the excluded transitions, if any), and predicates
+    /// solid and labeled `?`. Accept states are double circles and start
+    /// states are pointed at by an arrow. States are ordered by label, so
+    /// the output only depends on the blueprint when labels are distinct.
+    pub fn to_dot(&self, opts: &DotOptions<'_, S, T>) -> String {
+        let mut graph = DotGraph::new();
+        let start_set: HashSet<_> = self.start_state_set().collect();
+        let index_map: HashMap<_, _> = self
+            .state_set()
+            .into_iter()
+            .map(|state| {
+                let index = graph.add_node(
+                    opts.label_state(state),
+                    start_set.contains(state),
+                    self.accept_state_set.contains(state),
+                );
+                (state, index)
+            })
+            .collect();
+        for (from, conn, to) in self.iterate_connections() {
+            let (style, label) = match conn {
+                ConnType::Plain(trans) => (EdgeStyle::Solid, opts.label_trans(trans)),
+                ConnType::Void => (EdgeStyle::Dotted, "ε".to_string()),
+                ConnType::Wildcard => (EdgeStyle::Dashed, "·".to_string()),
+                ConnType::WildcardExcept(excluded) => {
+                    let mut label_list: Vec<_> = excluded
+                        .iter()
+                        .map(|trans| opts.label_trans(trans))
+                        .collect();
+                    label_list.sort();
+                    (
+                        EdgeStyle::Dashed,
+                        format!("·∖{{{}}}", label_list.join(", ")),
+                    )
+                }
+                ConnType::Range(range) => {
+                    let open = if range.contains(range.start()) {
+                        '['
+                    } else {
+                        '('
+                    };
+                    let close = if range.contains(range.end()) {
+                        ']'
+                    } else {
+                        ')'
+                    };
+                    let label = format!(
+                        "{}{}, {}{}",
+                        open,
+                        opts.label_trans(range.start()),
+                        opts.label_trans(range.end()),
+                        close
+                    );
+                    (EdgeStyle::Solid, label)
+                }
+                ConnType::Predicate(_) => (EdgeStyle::Solid, "?".to_string()),
+            };
+            graph.add_edge(index_map[from], style, label, index_map[to]);
         }
+        graph.render(opts.graph_name())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct NFAuto<'b, S, T>
+impl<S, T> NFAutoBlueprint<S, T>
 where
-    S: Eq + Hash,
-    T: Eq + Hash,
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
 {
-    blueprint: &'b NFAutoBlueprint<S, T>,
-    current_state_set: HashSet<S>,
+    /// Build the automaton accepting the reversed strings of this one. Every
+    /// connection is flipped in place, keeping its kind, accept states become
+    /// start states and start states become accept states.
+    pub fn reverse(&self) -> Self {
+        let mut accept_iter = self.accept_state_set.iter();
+        let mut builder = if let Some(first) = accept_iter.next() {
+            let builder = accept_iter.fold(NFAutoBuilder::start(first.clone()), |b, state| {
+                b.also_start(state.clone())
+            });
+            self.start_state_set()
+                .fold(builder, |b, state| b.accept(state.clone()))
+        } else {
+            // nothing is accepted either way, keep the start state as a placeholder
+            NFAutoBuilder::start(self.start_state.clone())
+        };
+        for (from, conn, to) in self.iterate_connections() {
+            builder = builder.connect_as(to.clone(), conn, from.clone());
+        }
+        builder.finalize()
+    }
+
+    /// Build an equivalent automaton without void connections. Every state
+    /// takes over the plain and wildcard connections of the states in its void
+    /// closure, and accepts if any of them accepts.
+    pub fn remove_void(&self) -> Self {
+        let mut builder = self.extra_start_state_set.iter().fold(
+            NFAutoBuilder::start(self.start_state.clone()),
+            |b, state| b.also_start(state.clone()),
+        );
+        let mut member_map: HashMap<_, Vec<_>> = HashMap::new();
+        for (from, conn, to) in self.iterate_connections() {
+            if !matches!(conn, ConnType::Void) {
+                member_map.entry(from).or_default().push((conn, to));
+            }
+        }
+        for state in self.state_set() {
+            let closure = extend_state_set(self, vec![state]);
+            for member in closure.iter() {
+                for (conn, to) in member_map.get(member).into_iter().flatten() {
+                    builder = builder.connect_as(state.clone(), *conn, (*to).clone());
+                }
+            }
+            for member in closure.intersection(&self.accept_state_set) {
+                builder = builder.accept(state.clone());
+                for tag in self.accept_tags(member).iter() {
+                    builder = builder.accept_with(state.clone(), *tag);
+                }
+            }
+        }
+        builder.finalize()
+    }
 }
 
 impl<S, T> NFAutoBlueprint<S, T>
 where
-    S: Hash + Eq + Clone,
+    S: Hash + Eq,
     T: Hash + Eq,
 {
-    pub fn create(&self) -> NFAuto<S, T> {
-        let mut auto = NFAuto {
-            blueprint: self,
-            current_state_set: vec![self.start_state().clone()].into_iter().collect(),
-        };
-        auto.extend_current_state_set();
-        auto
+    /// Whether the blueprint already behaves deterministically: a single start
+    /// state, no void, wildcard-except, range or predicate connections, at
+    /// most one target per transition and per wildcard, and plain connections
+    /// of a state with a wildcard connection all leading to the wildcard
+    /// target.
+    pub fn is_deterministic(&self) -> bool {
+        self.extra_start_state_set.is_empty()
+            && self
+                .predicate_graph
+                .values()
+                .all(|pred_to_list| pred_to_list.is_empty())
+            && self
+                .range_graph
+                .values()
+                .all(|range_to_list| range_to_list.is_empty())
+            && self
+                .wildcard_except_graph
+                .values()
+                .all(|to_excluded| to_excluded.is_empty())
+            && self.void_graph.values().all(|to_set| to_set.is_empty())
+            && self.wildcard_graph.values().all(|to_set| to_set.len() <= 1)
+            && self.graph.iter().all(|(from, trans_to)| {
+                let wildcard_to = self
+                    .wildcard_graph
+                    .get(from)
+                    .and_then(|to_set| to_set.iter().next());
+                trans_to.values().all(|to_set| {
+                    to_set.len() <= 1 && wildcard_to.is_none_or(|to| to_set.contains(to))
+                })
+            })
     }
 }
 
-impl<'b, S, T> NFAuto<'b, S, T>
+impl<S, T> NFAutoBlueprint<S, T>
 where
     S: Hash + Eq + Clone,
-    T: Hash + Eq,
+    T: Hash + Eq + Clone,
 {
-    fn extend_current_state_set(&mut self) {
-        self.current_state_set = extend_state_set(self.blueprint, self.current_state_set());
-    }
-
-    pub fn is_accepted(&self) -> bool {
-        !(&self.current_state_set & self.blueprint.accept_state_set()).is_empty()
-    }
-
-    pub fn is_dead(&self) -> bool {
-        self.current_state_set().is_empty()
+    /// Convert structurally into a DFA blueprint with the same states, turning
+    /// wildcard connections into fallback connections. Returns `None` if the
+    /// blueprint is not deterministic.
+    pub fn try_into_dfa(&self) -> Option<DFAutoBlueprint<S, T>> {
+        if !self.is_deterministic() {
+            return None;
+        }
+        let mut builder = DFAutoBuilder::start(self.start_state.clone());
+        for (from, conn, to) in self.iterate_connections() {
+            builder = match conn {
+                ConnType::Plain(trans) => builder.connect(from.clone(), trans.clone(), to.clone()),
+                ConnType::Wildcard => builder.connect_fallback(from.clone(), to.clone()),
+                ConnType::Void
+                | ConnType::WildcardExcept(_)
+                | ConnType::Range(_)
+                | ConnType::Predicate(_) => unreachable!(),
+            };
+        }
+        Some(
+            self.accept_state_set
+                .iter()
+                .fold(builder, |b, state| b.accept(state.clone()))
+                .finalize(),
+        )
     }
+}
 
-    pub fn current_state_set(&self) -> &HashSet<S> {
-        &self.current_state_set
-    }
+/// Same as `algo::try_dfa_to_nfa`.
+impl<S, T> TryFrom<&DFAutoBlueprint<S, T>> for NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    type Error = FallbackWithRange<S>;
 
-    pub fn trigger(&mut self, trans: &T) {
-        let placeholder_state = HashMap::new();
-        let placeholder_trans = HashSet::new();
-        self.current_state_set = self
-            .current_state_set()
-            .iter()
-            .flat_map(|state| {
-                self.blueprint
-                    .graph
-                    .get(state)
-                    .unwrap_or(&placeholder_state)
-                    .get(trans)
-                    .unwrap_or(&placeholder_trans)
-                    .union(
-                        self.blueprint
-                            .wildcard_graph
-                            .get(state)
-                            .unwrap_or(&placeholder_trans),
-                    )
-            })
-            .cloned()
-            .collect();
-        self.extend_current_state_set();
+    fn try_from(dfa: &DFAutoBlueprint<S, T>) -> Result<Self, Self::Error> {
+        try_dfa_to_nfa(dfa)
     }
 }
 
-impl<'b, S, T> Auto for NFAuto<'b, S, T>
+/// Conversion keeping every state and connection of the DFA, unreachable
+/// ones included, as well as accept tags. A fallback connection becomes a
+/// wildcard connection except on the plain transitions of its state, which
+/// fails on a state that also has range connections.
+impl<S, T> TryFrom<DFAutoBlueprint<S, T>> for NFAutoBlueprint<S, T>
 where
     S: Hash + Eq + Clone,
-    T: Hash + Eq + 'static,
+    T: Hash + Eq + Clone,
 {
-    type Trans = T;
+    type Error = FallbackWithRange<S>;
 
-    fn test_trigger(&self, _trans: &Self::Trans) -> bool {
-        !self.is_dead()
+    fn try_from(dfa: DFAutoBlueprint<S, T>) -> Result<Self, Self::Error> {
+        let mut builder = NFAutoBuilder::start(dfa.start_state().clone());
+        for state in dfa.accept_state_set() {
+            builder = builder.accept(state.clone());
+            for tag in dfa.accept_tags(state).iter() {
+                builder = builder.accept_with(state.clone(), *tag);
+            }
+        }
+        for (from, trans, to) in dfa.iterate_connections() {
+            builder = builder.connect(from.clone(), trans.clone(), to.clone());
+        }
+        for (from, range, to) in dfa.iterate_range_connections() {
+            builder = builder.connect_trans_range(from.clone(), range.clone(), to.clone());
+        }
+        for (from, to) in dfa.iterate_fallback_connections() {
+            let connections = dfa.connections_from(from);
+            if !connections.range.is_empty() {
+                return Err(FallbackWithRange {
+                    state: from.clone(),
+                });
+            }
+            let excluded = connections.plain.iter().map(|(trans, _)| trans.clone());
+            builder = builder.connect_wildcard_except(from.clone(), excluded, to.clone());
+        }
+        Ok(builder.finalize())
     }
+}
 
-    fn trigger(&mut self, trans: &Self::Trans) {
-        self.trigger(trans)
-    }
+/// A state of an NFA blueprint with connections a DFA blueprint cannot
+/// express: a second start state, a void or predicate connection, several
+/// targets on a transition, or wildcards that do not agree with the other
+/// connections of the state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotDeterministic<S> {
+    pub state: S,
+}
 
-    fn is_accepted(&self) -> bool {
-        self.is_accepted()
+impl<S> fmt::Display for NotDeterministic<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nondeterministic connections on {:?}", self.state)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<S> Error for NotDeterministic<S> where S: fmt::Debug {}
 
-    #[test]
-    fn trigger_nfa() {
-        // ab*a
-        let bp = NFAutoBuilder::start(0)
-            .connect(0, 'a', 1)
-            .connect_void(1, 2)
-            .connect(2, 'b', 3)
-            .connect_void(3, 4)
-            .connect_void(3, 2)
-            .connect_void(1, 4)
-            .connect(4, 'a', 5)
-            .accept(5)
-            .finalize();
-        let mut auto = bp.create();
-        assert_eq!(auto.current_state_set(), &vec![0].into_iter().collect());
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    // the fallback target of `from` in a DFA with the same behavior, `Ok(None)`
+    // if it needs none, or an error if no DFA has the same behavior
+    fn deterministic_fallback(&self, from: &S) -> Result<Option<&S>, ()> {
+        let trans_to = self.graph.get(from);
+        let range_to_list = self.range_graph.get(from).map_or(&[][..], Vec::as_slice);
+        if self
+            .void_graph
+            .get(from)
+            .is_some_and(|to_set| !to_set.is_empty())
+            || self
+                .predicate_graph
+                .get(from)
+                .is_some_and(|pred_to_list| !pred_to_list.is_empty())
+            || trans_to
+                .into_iter()
+                .flatten()
+                .any(|(_, to_set)| to_set.len() > 1)
+        {
+            return Err(());
+        }
+        let trans_to = || {
+            trans_to
+                .into_iter()
+                .flatten()
+                .filter_map(|(trans, to_set)| Some((trans, to_set.iter().next()?)))
+        };
+        for (index, (range, to)) in range_to_list.iter().enumerate() {
+            if trans_to().any(|(trans, trans_to)| range.contains(trans) && trans_to != to)
+                || range_to_list[..index]
+                    .iter()
+                    .any(|(other, other_to)| other.overlaps(range) && other_to != to)
+            {
+                return Err(());
+            }
+        }
+
+        let wildcard_to_set = self.wildcard_graph.get(from);
+        let to_excluded = self.wildcard_except_graph.get(from);
+        let (fallback, excluded) = match (
+            wildcard_to_set.map_or(0, HashSet::len),
+            to_excluded.map_or(0, HashMap::len),
+        ) {
+            (0, 0) => return Ok(None),
+            (1, 0) => (wildcard_to_set.unwrap().iter().next().unwrap(), None),
+            (0, 1) => {
+                let (to, excluded) = to_excluded.unwrap().iter().next().unwrap();
+                (to, Some(excluded))
+            }
+            _ => return Err(()),
+        };
+        // the fallback is taken on excluded transitions unless they are
+        // plain ones, and the wildcard on the others alongside them
+        let is_excluded = |trans| excluded.is_some_and(|excluded| excluded.contains(trans));
+        let plain = |trans| trans_to().any(|(other, _)| other == trans);
+        if excluded.is_some_and(|excluded| !excluded.iter().all(plain))
+            || trans_to().any(|(trans, to)| !is_excluded(trans) && to != fallback)
+            || range_to_list.iter().any(|(_, to)| to != fallback)
+        {
+            return Err(());
+        }
+        Ok(Some(fallback))
+    }
+}
+
+/// Conversion succeeding when the NFA behaves deterministically, see
+/// `NotDeterministic`. A wildcard connection, or a wildcard connection except
+/// on plain transitions of its state, becomes a fallback connection, so this
+/// reverses the conversion from `DFAutoBlueprint`. Unlike `try_into_dfa`,
+/// ranges and accept tags are kept.
+impl<S, T> TryFrom<NFAutoBlueprint<S, T>> for DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    type Error = NotDeterministic<S>;
+
+    fn try_from(nfa: NFAutoBlueprint<S, T>) -> Result<Self, Self::Error> {
+        if let Some(state) = nfa.extra_start_state_set.iter().next() {
+            return Err(NotDeterministic {
+                state: state.clone(),
+            });
+        }
+        let mut builder = DFAutoBuilder::start(nfa.start_state.clone());
+        for state in nfa.state_set() {
+            let fallback = nfa
+                .deterministic_fallback(state)
+                .map_err(|_| NotDeterministic {
+                    state: state.clone(),
+                })?;
+            if let Some(to) = fallback {
+                builder = builder.connect_fallback(state.clone(), to.clone());
+            }
+        }
+        for state in &nfa.accept_state_set {
+            builder = builder.accept(state.clone());
+            for tag in nfa.accept_tags(state).iter() {
+                builder = builder.accept_with(state.clone(), *tag);
+            }
+        }
+        for (from, conn, to) in nfa.iterate_connections() {
+            builder = match conn {
+                ConnType::Plain(trans) => builder.connect(from.clone(), trans.clone(), to.clone()),
+                ConnType::Range(range) => {
+                    builder.connect_trans_range(from.clone(), range.clone(), to.clone())
+                }
+                ConnType::Wildcard | ConnType::WildcardExcept(_) => builder,
+                ConnType::Void | ConnType::Predicate(_) => unreachable!(),
+            };
+        }
+        Ok(builder.finalize())
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    fn reachable_state_set(&self) -> HashSet<&S> {
+        let mut reachable: HashSet<_> = self.start_state_set().collect();
+        let mut unresolved: Vec<_> = reachable.iter().cloned().collect();
+        while let Some(state) = unresolved.pop() {
+            let connections = self.connections_from(state);
+            for to in connections
+                .plain
+                .iter()
+                .flat_map(|(_, to_set)| to_set)
+                .chain(connections.void.iter())
+                .chain(connections.wildcard.iter())
+                .chain(connections.wildcard_except.iter().map(|(to, _)| to))
+                .chain(connections.range.iter().map(|(_, to)| to))
+                .chain(connections.predicate.iter().map(|(_, to)| to))
+            {
+                if reachable.insert(to) {
+                    unresolved.push(to);
+                }
+            }
+        }
+        reachable
+    }
+
+    fn productive_state_set(&self) -> HashSet<&S> {
+        self.productive_state_set_by(|_| true)
+    }
+
+    // states from which an accept state can be reached through connections
+    // passing `keep`
+    fn productive_state_set_by<F>(&self, keep: F) -> HashSet<&S>
+    where
+        F: Fn(ConnType<'_, T>) -> bool,
+    {
+        let mut reversed_graph: HashMap<_, Vec<_>> = HashMap::new();
+        for (from, conn, to) in self.iterate_connections() {
+            if keep(conn) {
+                reversed_graph.entry(to).or_default().push(from);
+            }
+        }
+        let mut productive: HashSet<_> = self.accept_state_set.iter().collect();
+        let mut unresolved: Vec<_> = productive.iter().cloned().collect();
+        while let Some(state) = unresolved.pop() {
+            for from in reversed_graph.get(state).into_iter().flatten() {
+                if productive.insert(from) {
+                    unresolved.push(from);
+                }
+            }
+        }
+        productive
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    /// Drop states that cannot be reached from any start state.
+    pub fn prune_unreachable(&self) -> Self {
+        self.retain_state_set(&self.reachable_state_set())
+    }
+
+    /// Drop states from which no accept state can be reached. The start state
+    /// is always kept.
+    pub fn prune_unproductive(&self) -> Self {
+        self.retain_state_set(&self.productive_state_set())
+    }
+
+    /// Drop states that are unreachable or unproductive.
+    pub fn trim(&self) -> Self {
+        self.retain_state_set(&(&self.reachable_state_set() & &self.productive_state_set()))
+    }
+
+    fn retain_state_set(&self, state_set: &HashSet<&S>) -> Self {
+        let mut builder = self
+            .extra_start_state_set
+            .iter()
+            .filter(|state| state_set.contains(state))
+            .fold(
+                NFAutoBuilder::start(self.start_state.clone()),
+                |b, state| b.also_start(state.clone()),
+            );
+        for (from, conn, to) in self.iterate_connections() {
+            if !state_set.contains(from) || !state_set.contains(to) {
+                continue;
+            }
+            builder = builder.connect_as(from.clone(), conn, to.clone());
+        }
+        self.accept_state_set
+            .iter()
+            .filter(|state| state_set.contains(state))
+            .fold(builder, |b, state| b.accept_as(state.clone(), self))
+            .finalize()
+    }
+}
+
+/// Outgoing connections of a single state. States without any outgoing
+/// connection, including states only present as targets, yield empty views.
+#[derive(Debug)]
+pub struct NfaConnections<'bp, S, T> {
+    /// Plain transitions, keyed by transition.
+    pub plain: MapView<'bp, T, HashSet<S>>,
+    /// Targets of void connections, taken without consuming a transition.
+    pub void: SetView<'bp, S>,
+    /// Targets of wildcard connections, taken on any transition.
+    pub wildcard: SetView<'bp, S>,
+    /// Wildcard-except connections, keyed by target, with the excluded
+    /// transitions.
+    pub wildcard_except: MapView<'bp, S, HashSet<T>>,
+    /// Range connections sorted by range start, possibly overlapping.
+    pub range: &'bp [(TransRange<T>, S)],
+    /// Predicate connections, taken on transitions passing the predicate.
+    pub predicate: &'bp [(Predicate<T>, S)],
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    pub fn connections_from(&self, state: &S) -> NfaConnections<'_, S, T> {
+        NfaConnections {
+            plain: MapView::new(self.graph.get(state)),
+            void: SetView::new(self.void_graph.get(state)),
+            wildcard: SetView::new(self.wildcard_graph.get(state)),
+            wildcard_except: MapView::new(self.wildcard_except_graph.get(state)),
+            range: self
+                .range_graph
+                .get(state)
+                .map_or(&[], |range_to_list| range_to_list.as_slice()),
+            predicate: self
+                .predicate_graph
+                .get(state)
+                .map_or(&[], |pred_to_list| pred_to_list.as_slice()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NFAuto<'b, S, T>
+where
+    S: Eq + Hash,
+    T: Eq + Hash,
+{
+    blueprint: &'b NFAutoBlueprint<S, T>,
+    current_state_set: HashSet<S>,
+    state_limit: Option<usize>,
+    trace: Option<Trace<S, T>>,
+}
+
+// transitions with the state sets they led to, and a way to copy transitions
+// without requiring `T: Clone` on every run
+struct Trace<S, T> {
+    clone: fn(&T) -> T,
+    step_list: Vec<(T, HashSet<S>)>,
+}
+
+impl<S, T> Trace<S, T> {
+    fn record(&mut self, trans: &T, state_set: HashSet<S>) {
+        self.step_list.push(((self.clone)(trans), state_set));
+    }
+}
+
+impl<S, T> Clone for Trace<S, T>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            clone: self.clone,
+            step_list: self
+                .step_list
+                .iter()
+                .map(|(trans, state_set)| ((self.clone)(trans), state_set.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<S, T> fmt::Debug for Trace<S, T>
+where
+    S: fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.step_list.fmt(f)
+    }
+}
+
+impl<S, T> PartialEq for Trace<S, T>
+where
+    S: Hash + Eq,
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.step_list == other.step_list
+    }
+}
+
+impl<S, T> Eq for Trace<S, T>
+where
+    S: Hash + Eq,
+    T: Eq,
+{
+}
+
+/// The state set after a transition would grow beyond the configured limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateLimitExceeded {
+    /// Size of the rejected state set, or number of states found by
+    /// `algo::determinize_bounded` when giving up.
+    pub size: usize,
+}
+
+impl fmt::Display for StateLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state limit exceeded by a set of {} states", self.size)
+    }
+}
+
+impl std::error::Error for StateLimitExceeded {}
+
+/// Reason of a failed `try_trigger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerError {
+    /// No state is left after the transition.
+    Dead,
+    StateLimitExceeded(StateLimitExceeded),
+}
+
+impl From<StateLimitExceeded> for TriggerError {
+    fn from(err: StateLimitExceeded) -> Self {
+        TriggerError::StateLimitExceeded(err)
+    }
+}
+
+impl fmt::Display for TriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerError::Dead => write!(f, "automaton is dead"),
+            TriggerError::StateLimitExceeded(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TriggerError {}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    pub fn create(&self) -> NFAuto<'_, S, T> {
+        let mut auto = NFAuto {
+            blueprint: self,
+            current_state_set: self.start_state_set().cloned().collect(),
+            state_limit: None,
+            trace: None,
+        };
+        auto.extend_current_state_set();
+        auto
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    pub fn create_boxed(&self) -> BoxAuto<'_, T> {
+        Box::new(self.create())
+    }
+}
+
+impl<S, T> Blueprint for NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    type State = S;
+    type Trans = T;
+    type Runner<'a>
+        = NFAuto<'a, S, T>
+    where
+        Self: 'a;
+
+    fn create(&self) -> NFAuto<'_, S, T> {
+        NFAutoBlueprint::create(self)
+    }
+
+    fn start_state(&self) -> &S {
+        NFAutoBlueprint::start_state(self)
+    }
+
+    fn accept_state_set(&self) -> &HashSet<S> {
+        NFAutoBlueprint::accept_state_set(self)
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    /// Every input over `alphabet` accepted by the blueprint, shortest first
+    /// and in the order of `alphabet` among inputs of the same length. The
+    /// iterator only ends for finite languages.
+    pub fn enumerate_language<'a>(
+        &'a self,
+        alphabet: &'a [T],
+    ) -> impl Iterator<Item = Vec<T>> + 'a {
+        // states which can still reach an accept state on `alphabet`
+        let productive = self.productive_state_set_by(|conn| match conn {
+            ConnType::Plain(trans) => alphabet.contains(trans),
+            ConnType::Void => true,
+            ConnType::Wildcard => !alphabet.is_empty(),
+            ConnType::WildcardExcept(excluded) => {
+                alphabet.iter().any(|trans| !excluded.contains(trans))
+            }
+            ConnType::Range(range) => alphabet.iter().any(|trans| range.contains(trans)),
+            ConnType::Predicate(pred) => alphabet.iter().any(|trans| pred.test(trans)),
+        });
+        let live = move |state_set: HashSet<S>| -> HashSet<S> {
+            state_set
+                .into_iter()
+                .filter(|state| productive.contains(state))
+                .collect()
+        };
+        let mut unresolved = VecDeque::new();
+        let start_state_set = live(extend_state_set(self, self.start_state_set()));
+        if !start_state_set.is_empty() {
+            unresolved.push_back((Vec::new(), start_state_set));
+        }
+        std::iter::from_fn(move || {
+            while let Some((prefix, state_set)) = unresolved.pop_front() {
+                for trans in alphabet {
+                    let next_state_set = live(next_state_set(self, &state_set, trans));
+                    if !next_state_set.is_empty() {
+                        let mut next_prefix = prefix.clone();
+                        next_prefix.push(trans.clone());
+                        unresolved.push_back((next_prefix, next_state_set));
+                    }
+                }
+                if state_set
+                    .iter()
+                    .any(|state| self.accept_state_set.contains(state))
+                {
+                    return Some(prefix);
+                }
+            }
+            None
+        })
+    }
+}
+
+// states reached from `state_set` on `trans`, through void connections
+// included
+pub(crate) fn next_state_set<'s, S, T>(
+    blueprint: &NFAutoBlueprint<S, T>,
+    state_set: impl IntoIterator<Item = &'s S>,
+    trans: &T,
+) -> HashSet<S>
+where
+    S: Hash + Eq + Clone + 's,
+    T: Hash + Eq,
+{
+    let state_set: HashSet<_> = state_set
+        .into_iter()
+        .flat_map(|state| {
+            let connections = blueprint.connections_from(state);
+            connections
+                .plain
+                .get(trans)
+                .into_iter()
+                .flatten()
+                .chain(connections.wildcard.iter())
+                .chain(
+                    connections
+                        .wildcard_except
+                        .iter()
+                        .filter(|(_, excluded)| !excluded.contains(trans))
+                        .map(|(to, _)| to),
+                )
+                .chain(matching(connections.range, trans))
+                .chain(
+                    connections
+                        .predicate
+                        .iter()
+                        .filter(|(pred, _)| pred.test(trans))
+                        .map(|(_, to)| to),
+                )
+        })
+        .cloned()
+        .collect();
+    extend_state_set(blueprint, &state_set)
+}
+
+impl<'b, S, T> NFAuto<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    fn extend_current_state_set(&mut self) {
+        self.current_state_set = extend_state_set(self.blueprint, self.current_state_set());
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        !(&self.current_state_set & self.blueprint.accept_state_set()).is_empty()
+    }
+
+    /// Accept states among the current states, reached through void
+    /// connections included.
+    pub fn accepting_states(&self) -> impl Iterator<Item = &S> {
+        self.current_state_set
+            .intersection(self.blueprint.accept_state_set())
+    }
+
+    /// Tags of the accepting states among the current states, in priority
+    /// order.
+    pub fn matched_tags(&self) -> BTreeSet<usize> {
+        self.accepting_states()
+            .flat_map(|state| self.blueprint.accept_tags(state).iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current_state_set().is_empty()
+    }
+
+    pub fn current_state_set(&self) -> &HashSet<S> {
+        &self.current_state_set
+    }
+
+    /// Bound the size of the state set. A transition leading to a larger set
+    /// fails in `try_trigger` and kills the automaton in `trigger`.
+    pub fn set_state_limit(&mut self, limit: usize) {
+        self.state_limit = Some(limit);
+    }
+
+    /// Whether `trans` leads to some state, within the state limit if any.
+    pub fn test_trigger(&self, trans: &T) -> bool {
+        let state_set = next_state_set(self.blueprint, self.current_state_set(), trans);
+        !state_set.is_empty()
+            && self
+                .state_limit
+                .is_none_or(|limit| state_set.len() <= limit)
+    }
+
+    pub fn trigger(&mut self, trans: &T) {
+        if let Err(TriggerError::StateLimitExceeded(_)) = self.try_trigger(trans) {
+            self.current_state_set.clear();
+            if let Some(trace) = &mut self.trace {
+                trace.record(trans, HashSet::new());
+            }
+        }
+    }
+
+    /// Transitions triggered so far, each with the state set it led to. Empty
+    /// unless the automaton was created `with_trace`.
+    pub fn trace(&self) -> &[(T, HashSet<S>)] {
+        self.trace
+            .as_ref()
+            .map_or(&[], |trace| trace.step_list.as_slice())
+    }
+
+    /// Same as `trigger`, but report the death of the automaton, and keep the
+    /// current state set with an error if the state limit would be exceeded.
+    pub fn try_trigger(&mut self, trans: &T) -> Result<(), TriggerError> {
+        let state_set = next_state_set(self.blueprint, self.current_state_set(), trans);
+        if let Some(limit) = self.state_limit {
+            if state_set.len() > limit {
+                return Err(StateLimitExceeded {
+                    size: state_set.len(),
+                }
+                .into());
+            }
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.record(trans, state_set.clone());
+        }
+        self.current_state_set = state_set;
+        if self.is_dead() {
+            return Err(TriggerError::Dead);
+        }
+        Ok(())
+    }
+
+    /// Same as `try_trigger`, but restore the previous state set (and trace)
+    /// if the automaton dies.
+    pub fn trigger_or_rollback(&mut self, trans: &T) -> Result<(), TriggerError> {
+        let state_set = self.current_state_set.clone();
+        let result = self.try_trigger(trans);
+        if result == Err(TriggerError::Dead) {
+            self.current_state_set = state_set;
+            if let Some(trace) = &mut self.trace {
+                trace.step_list.pop();
+            }
+        }
+        result
+    }
+}
+
+impl<'b, S, T> NFAuto<'b, S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    /// Record every following transition and the state set it leads to.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Trace {
+            clone: T::clone,
+            step_list: Vec::new(),
+        });
+        self
+    }
+}
+
+impl<'b, S, T> NFAuto<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    /// Same as `AutoExt::steps`, with the state set after every step.
+    pub fn steps_with_state<I>(self, iter: I) -> impl Iterator<Item = (Step, HashSet<S>)> + 'b
+    where
+        I: IntoIterator,
+        I::IntoIter: 'b,
+        I::Item: Borrow<T>,
+    {
+        let mut steps = self.steps(iter);
+        std::iter::from_fn(move || {
+            let step = steps.next()?;
+            Some((step, steps.auto().current_state_set().clone()))
+        })
+    }
+}
+
+/// Progress of an `NFAuto`, taken by `Checkpointable::checkpoint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NfaCheckpoint<S>
+where
+    S: Hash + Eq,
+{
+    state_set: HashSet<S>,
+    trace_len: usize,
+}
+
+/// Restoring also drops the trace recorded after the checkpoint.
+impl<'b, S, T> Checkpointable for NFAuto<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq,
+{
+    type Checkpoint = NfaCheckpoint<S>;
+
+    fn checkpoint(&self) -> NfaCheckpoint<S> {
+        NfaCheckpoint {
+            state_set: self.current_state_set.clone(),
+            trace_len: self.trace().len(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: &NfaCheckpoint<S>) {
+        self.current_state_set = checkpoint.state_set.clone();
+        if let Some(trace) = &mut self.trace {
+            trace.step_list.truncate(checkpoint.trace_len);
+        }
+    }
+}
+
+impl<'b, S, T> Auto for NFAuto<'b, S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + 'static,
+{
+    type Trans = T;
+
+    fn test_trigger(&self, trans: &Self::Trans) -> bool {
+        self.test_trigger(trans)
+    }
+
+    fn trigger(&mut self, trans: &Self::Trans) {
+        self.trigger(trans)
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.is_accepted()
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_dead()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::re::Re;
+
+    fn corpus() -> Vec<Vec<char>> {
+        let mut corpus = vec![vec![]];
+        let mut last = vec![vec![]];
+        for _ in 0..4 {
+            last = last
+                .iter()
+                .flat_map(|prefix: &Vec<char>| {
+                    "abc".chars().map(move |c| {
+                        let mut s = prefix.clone();
+                        s.push(c);
+                        s
+                    })
+                })
+                .collect();
+            corpus.extend(last.iter().cloned());
+        }
+        corpus
+    }
+
+    #[test]
+    fn trigger_nfa() {
+        // ab*a
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .accept(5)
+            .finalize();
+        let mut auto = bp.create();
+        assert_eq!(auto.current_state_set(), &vec![0].into_iter().collect());
         assert!(!auto.is_dead());
         assert!(!auto.is_accepted());
         auto.trigger(&'a');
@@ -334,4 +1734,610 @@ mod tests {
         }
         assert!(auto.is_accepted());
     }
+
+    #[test]
+    fn connections_from() {
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(0, 'a', 2)
+            .connect_void(0, 3)
+            .connect_wildcard(0, 4)
+            .accept(4)
+            .finalize();
+        let connections = bp.connections_from(&0);
+        assert_eq!(connections.plain.len(), 1);
+        assert_eq!(
+            connections.plain.get(&'a'),
+            Some(&vec![1, 2].into_iter().collect())
+        );
+        assert!(connections.void.contains(&3));
+        assert_eq!(connections.wildcard.iter().collect::<Vec<_>>(), vec![&4]);
+
+        let connections = bp.connections_from(&4);
+        assert!(connections.plain.is_empty());
+        assert!(connections.plain.get(&'a').is_none());
+        assert!(connections.void.is_empty());
+        assert_eq!(connections.wildcard.iter().count(), 0);
+    }
+
+    #[test]
+    fn reverse() {
+        // ab*c
+        let abc = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 2)
+            .connect_void(3, 4)
+            .connect_void(1, 4)
+            .connect(4, 'c', 5)
+            .accept(5)
+            .finalize();
+        // a|bc.
+        let multi_accept = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(0, 'b', 2)
+            .connect(2, 'c', 3)
+            .connect_wildcard(3, 4)
+            .accept(1)
+            .accept(4)
+            .finalize();
+        // [a-b][^a]
+        let symbolic = NFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='b', 1)
+            .connect_wildcard_except(1, vec!['a'], 2)
+            .accept(2)
+            .finalize();
+        let never = NFAutoBuilder::start(0).connect(0, 'a', 1).finalize();
+        for bp in [abc, multi_accept, symbolic, never].iter() {
+            let reversed = bp.reverse();
+            for s in corpus() {
+                assert_eq!(
+                    bp.create().test(s.iter()),
+                    reversed.create().test(s.iter().rev()),
+                    "{:?}",
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn remove_void() {
+        // (a|b)*c
+        let bp = Re::concat(
+            Re::zero_or_more(Re::either(Re::plain('a'), Re::plain('b'))),
+            Re::plain('c'),
+        )
+        .compile();
+        let removed = bp.remove_void();
+        assert!(removed
+            .iterate_connections()
+            .all(|(_, conn, _)| !matches!(conn, ConnType::Void)));
+        for s in corpus() {
+            assert_eq!(
+                bp.create().test(s.iter()),
+                removed.create().test(s.iter()),
+                "{:?}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn convert_by_value() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_range(1, 'a'..='b', 2)
+            .connect(2, 'c', 0)
+            .connect_fallback(2, 2)
+            .connect(7, 'a', 0)
+            .accept_with(2, 3)
+            .finalize();
+        let nfa = NFAutoBlueprint::try_from(dfa.clone()).unwrap();
+        assert!(nfa.state_set().contains(&7));
+        assert_eq!(nfa.accept_tags(&2).iter().collect::<Vec<_>>(), [&3]);
+        for s in corpus() {
+            assert_eq!(nfa.create().test(s.iter()), dfa.create().test(s.iter()));
+        }
+        assert_eq!(DFAutoBlueprint::try_from(nfa), Ok(dfa));
+
+        let ranged_fallback = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='b', 1)
+            .connect_fallback(0, 0)
+            .finalize();
+        assert_eq!(
+            NFAutoBlueprint::try_from(ranged_fallback),
+            Err(FallbackWithRange { state: 0 })
+        );
+
+        // wildcards agreeing with the plain and range connections
+        let nfa = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_wildcard(0, 1)
+            .connect_range(1, 'a'..='b', 2)
+            .connect(1, 'a', 2)
+            .connect_wildcard_except(2, vec!['a'], 0)
+            .connect(2, 'a', 2)
+            .connect(2, 'b', 0)
+            .accept(2)
+            .finalize();
+        let dfa = DFAutoBlueprint::try_from(nfa.clone()).unwrap();
+        assert_eq!(dfa.iterate_fallback_connections().count(), 2);
+        for s in corpus() {
+            assert_eq!(nfa.create().test(s.iter()), dfa.create().test(s.iter()));
+        }
+
+        let rejected = |builder: NFAutoBuilder<u8, char>, state| {
+            assert_eq!(
+                DFAutoBlueprint::try_from(builder.finalize()),
+                Err(NotDeterministic { state })
+            );
+        };
+        rejected(NFAutoBuilder::start(0).also_start(1), 1);
+        rejected(NFAutoBuilder::start(0).connect_void(0, 1), 0);
+        rejected(NFAutoBuilder::start(0).connect_if(1, |_| true, 1), 1);
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect(0, 'a', 1)
+                .connect(0, 'a', 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect_range(0, 'a'..='c', 1)
+                .connect_range(0, 'c'..='d', 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect_range(0, 'a'..='c', 1)
+                .connect(0, 'b', 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect(0, 'a', 1)
+                .connect_wildcard(0, 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect_wildcard(0, 1)
+                .connect_wildcard(0, 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect_wildcard(0, 1)
+                .connect_wildcard_except(0, vec!['a'], 1),
+            0,
+        );
+        // `b` would take the fallback instead of no connection
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect(0, 'a', 1)
+                .connect_wildcard_except(0, vec!['a', 'b'], 2),
+            0,
+        );
+        rejected(
+            NFAutoBuilder::start(0)
+                .connect_range(0, 'a'..='b', 1)
+                .connect_wildcard(0, 2),
+            0,
+        );
+    }
+
+    #[test]
+    fn try_into_dfa() {
+        // ab.*
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 2)
+            .connect_wildcard(2, 2)
+            .connect(2, 'a', 2)
+            .accept(2)
+            .finalize();
+        assert!(bp.is_deterministic());
+        let dfa = bp.try_into_dfa().unwrap();
+        for s in corpus() {
+            assert_eq!(
+                bp.create().test(s.iter()),
+                dfa.create().test(s.iter()),
+                "{:?}",
+                s
+            );
+        }
+
+        let two_target = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(0, 'a', 2)
+            .finalize();
+        assert!(!two_target.is_deterministic());
+        assert!(two_target.try_into_dfa().is_none());
+
+        let conflicting_wildcard = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_wildcard(0, 2)
+            .finalize();
+        assert!(conflicting_wildcard.try_into_dfa().is_none());
+
+        let void = NFAutoBuilder::<_, char>::start(0)
+            .connect_void(0, 1)
+            .finalize();
+        assert!(void.try_into_dfa().is_none());
+    }
+
+    #[test]
+    fn trim() {
+        // ab*a, with orphan state 6 and dead end 7
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .connect(6, 'a', 5)
+            .connect_wildcard(4, 7)
+            .accept(5)
+            .finalize();
+        let unreachable = bp.prune_unreachable();
+        assert!(unreachable
+            .iterate_connections()
+            .all(|(from, _, _)| from != &6));
+        assert!(unreachable.state_set().contains(&7));
+        let unproductive = bp.prune_unproductive();
+        assert!(unproductive.state_set().contains(&6));
+        assert!(!unproductive.state_set().contains(&7));
+        let trimmed = bp.trim();
+        assert_eq!(trimmed.state_set(), [0, 1, 2, 3, 4, 5].iter().collect());
+        for s in corpus() {
+            assert_eq!(
+                bp.create().test(s.iter()),
+                trimmed.create().test(s.iter()),
+                "{:?}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn accepting_states() {
+        // ab|ac, the second accept state reached through a void connection
+        let bp = NFAutoBuilder::start(0)
+            .connect_void(0, 1)
+            .connect_void(0, 4)
+            .connect(1, 'a', 2)
+            .connect(2, 'b', 3)
+            .connect(4, 'a', 5)
+            .connect(5, 'c', 6)
+            .connect_void(6, 7)
+            .accept(3)
+            .accept(7)
+            .finalize();
+        let mut auto = bp.create();
+        auto.trigger(&'a');
+        assert_eq!(auto.accepting_states().count(), 0);
+        auto.trigger(&'b');
+        assert_eq!(auto.accepting_states().collect::<Vec<_>>(), vec![&3]);
+        let mut auto = bp.create();
+        auto.trigger(&'a');
+        auto.trigger(&'c');
+        assert_eq!(auto.accepting_states().collect::<Vec<_>>(), vec![&7]);
+    }
+
+    #[test]
+    fn state_limit() {
+        let bp = (1..=50)
+            .fold(NFAutoBuilder::start(0), |b, state| {
+                b.connect(0, 'a', state).connect(state, 'b', 51)
+            })
+            .accept(51)
+            .finalize();
+        let mut auto = bp.create();
+        auto.set_state_limit(10);
+        assert_eq!(
+            auto.try_trigger(&'a'),
+            Err(TriggerError::StateLimitExceeded(StateLimitExceeded {
+                size: 50
+            }))
+        );
+        assert_eq!(auto.current_state_set(), &vec![0].into_iter().collect());
+        auto.trigger(&'a');
+        assert!(auto.is_dead());
+
+        let mut auto = bp.create();
+        auto.set_state_limit(10);
+        assert!(!auto.test("ab".chars()));
+        let mut auto = bp.create();
+        auto.set_state_limit(100);
+        assert!(auto.test("ab".chars()));
+    }
+
+    #[test]
+    fn trace() {
+        // ab*a
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .accept(5)
+            .finalize();
+        let mut auto = bp.create().with_trace();
+        for c in "abbac".chars() {
+            auto.trigger(&c);
+        }
+        let expected: Vec<(char, HashSet<_>)> = vec![
+            ('a', vec![1, 2, 4].into_iter().collect()),
+            ('b', vec![2, 3, 4].into_iter().collect()),
+            ('b', vec![2, 3, 4].into_iter().collect()),
+            ('a', vec![5].into_iter().collect()),
+            ('c', HashSet::new()),
+        ];
+        assert_eq!(auto.trace(), expected.as_slice());
+        assert!(bp.create().trace().is_empty());
+    }
+
+    #[test]
+    fn try_trigger() {
+        // ab*a
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .accept(5)
+            .finalize();
+        let mut auto = bp.create();
+        assert_eq!(auto.try_trigger(&'a'), Ok(()));
+        assert_eq!(auto.try_trigger(&'b'), Ok(()));
+        assert_eq!(auto.try_trigger(&'c'), Err(TriggerError::Dead));
+        assert!(auto.is_dead());
+
+        let mut auto = bp.create().with_trace();
+        assert_eq!(auto.trigger_or_rollback(&'a'), Ok(()));
+        assert_eq!(auto.trigger_or_rollback(&'b'), Ok(()));
+        assert_eq!(auto.trigger_or_rollback(&'c'), Err(TriggerError::Dead));
+        assert_eq!(
+            auto.current_state_set(),
+            &vec![2, 3, 4].into_iter().collect()
+        );
+        assert_eq!(auto.trace().len(), 2);
+        assert_eq!(auto.trigger_or_rollback(&'a'), Ok(()));
+        assert!(auto.is_accepted());
+    }
+
+    #[test]
+    fn finalize_checked() {
+        let valid = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .accept(5)
+            .finalize_checked();
+        assert!(valid.unwrap().warnings().is_empty());
+
+        let findings = |builder: NFAutoBuilder<i32, char>| builder.finalize_checked().unwrap_err();
+        assert_eq!(
+            findings(NFAutoBuilder::start(0).connect(0, 'a', 1)).finding_list,
+            vec![Finding::NoAcceptState]
+        );
+        assert_eq!(
+            findings(
+                NFAutoBuilder::start(0)
+                    .connect(0, 'a', 1)
+                    .accept(1)
+                    .accept(2)
+            )
+            .finding_list,
+            vec![Finding::UnreachableAccept(2)]
+        );
+        let bp = NFAutoBuilder::<_, char>::start(0)
+            .connect_void(0, 0)
+            .accept(0)
+            .finalize_checked()
+            .unwrap();
+        assert!(bp.accepts_empty());
+        assert_eq!(bp.warnings(), vec![Finding::VoidSelfLoop(0)]);
+        assert!(bp.warnings()[0].is_warning());
+        // warnings are reported along with errors
+        assert_eq!(
+            findings(NFAutoBuilder::start(0).connect_void(0, 0)).finding_list,
+            vec![Finding::NoAcceptState, Finding::VoidSelfLoop(0)]
+        );
+        assert_eq!(
+            findings(
+                NFAutoBuilder::start(0)
+                    .connect_void(0, 2)
+                    .connect(0, 'a', 1)
+                    .accept(1)
+            )
+            .finding_list,
+            vec![Finding::VoidDeadEnd(2)]
+        );
+    }
+
+    #[test]
+    fn language_queries() {
+        let a_star = Re::zero_or_more(Re::plain('a')).compile();
+        assert!(a_star.accepts_empty());
+        assert_eq!(a_star.min_accepted_length(), Some(0));
+        assert!(!a_star.has_wildcard());
+
+        // ab*a
+        let bp = NFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect(2, 'b', 3)
+            .connect_void(3, 4)
+            .connect_void(3, 2)
+            .connect_void(1, 4)
+            .connect(4, 'a', 5)
+            .accept(5)
+            .finalize();
+        assert!(!bp.accepts_empty());
+        assert_eq!(bp.min_accepted_length(), Some(2));
+
+        let never = NFAutoBuilder::start(0)
+            .connect_wildcard(0, 1)
+            .connect(2, 'a', 3)
+            .accept(3)
+            .finalize();
+        assert!(!never.accepts_empty());
+        assert_eq!(never.min_accepted_length(), None);
+        assert!(never.has_wildcard());
+        assert!(Re::none_of("a".chars()).compile().has_wildcard());
+    }
+
+    #[test]
+    fn wildcard_except() {
+        // "[^"]*"
+        let bp = Re::concat(
+            Re::plain('"'),
+            Re::concat(Re::zero_or_more(Re::none_of(vec!['"'])), Re::plain('"')),
+        )
+        .compile();
+        let mut auto = bp.create();
+        for c in "\"abc\"".chars() {
+            assert!(!auto.is_accepted());
+            auto.trigger(&c);
+        }
+        assert!(auto.is_accepted());
+        auto.trigger(&'"');
+        assert!(auto.is_dead());
+        assert!(!bp.create().test("\"ab\"c\"".chars()));
+        assert!(bp.create().test("\"\"".chars()));
+
+        let bp = NFAutoBuilder::start(0)
+            .connect_wildcard_except(0, vec!['a', 'b'], 1)
+            .connect_wildcard_except(0, vec!['b', 'c'], 1)
+            .accept(1)
+            .finalize();
+        assert_eq!(
+            bp.connections_from(&0).wildcard_except.get(&1),
+            Some(&vec!['b'].into_iter().collect())
+        );
+        assert!(bp.create().test("a".chars()));
+        assert!(!bp.create().test("b".chars()));
+    }
+
+    #[test]
+    fn enumerate_language() {
+        let alphabet = ['a', 'b', 'c'];
+        let enumerate = |pattern: &str, count: usize| {
+            Re::parse(pattern)
+                .unwrap()
+                .compile()
+                .enumerate_language(&alphabet)
+                .take(count)
+                .map(|input| input.into_iter().collect::<String>())
+                .collect::<Vec<_>>()
+        };
+        // every string over `ab` followed by `c`, shortest first
+        let mut expected = vec![String::new()];
+        let mut last = vec![String::new()];
+        while expected.len() < 20 {
+            last = last
+                .iter()
+                .flat_map(|prefix| vec![format!("{}a", prefix), format!("{}b", prefix)])
+                .collect();
+            expected.extend(last.iter().cloned());
+        }
+        let expected: Vec<_> = expected
+            .into_iter()
+            .take(20)
+            .map(|prefix| format!("{}c", prefix))
+            .collect();
+        assert_eq!(enumerate("(a|b)*c", 20), expected);
+
+        assert_eq!(
+            enumerate("b|a.|ca?", 100),
+            vec!["b", "c", "aa", "ab", "ac", "ca"]
+        );
+        // no input over the alphabet reaches the `x`
+        assert_eq!(enumerate("a*x", 100), Vec::<String>::new());
+    }
+
+    #[test]
+    fn steps_with_state() {
+        let nfa = NFAutoBuilder::start(0)
+            .connect(0, 'a', 0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 2)
+            .accept(2)
+            .finalize();
+        let step_list: Vec<_> = nfa.create().steps_with_state("aabx".chars()).collect();
+        let state_set = |state_list: &[u32]| state_list.iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(step_list.len(), 4);
+        assert_eq!(step_list[0].1, state_set(&[0, 1]));
+        assert_eq!(step_list[1].1, state_set(&[0, 1]));
+        assert_eq!(step_list[2].1, state_set(&[2]));
+        assert!(step_list[2].0.accepted_after);
+        assert!(!step_list[3].0.consumed);
+        assert_eq!(step_list[3].1, state_set(&[2]));
+    }
+
+    #[test]
+    fn to_dot() {
+        let nfa = Re::parse("(a|b)*c").unwrap().compile();
+        let opts = DotOptions::new().trans_label(|c: &char| c.to_string());
+        assert_eq!(
+            nfa.to_dot(&opts),
+            concat!(
+                "digraph \"auto\" {\n",
+                "    rankdir=LR;\n",
+                "    start [shape=point];\n",
+                "    s0 [label=\"0\", shape=circle];\n",
+                "    s1 [label=\"1\", shape=doublecircle];\n",
+                "    s2 [label=\"2\", shape=circle];\n",
+                "    s3 [label=\"3\", shape=circle];\n",
+                "    s4 [label=\"4\", shape=circle];\n",
+                "    s5 [label=\"5\", shape=circle];\n",
+                "    s6 [label=\"6\", shape=circle];\n",
+                "    s7 [label=\"7\", shape=circle];\n",
+                "    s8 [label=\"8\", shape=circle];\n",
+                "    start -> s0;\n",
+                "    s0 -> s2 [label=\"ε\", style=dotted];\n",
+                "    s0 -> s3 [label=\"ε\", style=dotted];\n",
+                "    s2 -> s1 [label=\"c\"];\n",
+                "    s3 -> s5 [label=\"ε\", style=dotted];\n",
+                "    s3 -> s7 [label=\"ε\", style=dotted];\n",
+                "    s4 -> s2 [label=\"ε\", style=dotted];\n",
+                "    s4 -> s3 [label=\"ε\", style=dotted];\n",
+                "    s5 -> s6 [label=\"a\"];\n",
+                "    s6 -> s4 [label=\"ε\", style=dotted];\n",
+                "    s7 -> s8 [label=\"b\"];\n",
+                "    s8 -> s4 [label=\"ε\", style=dotted];\n",
+                "}\n",
+            )
+        );
+
+        let nfa = NFAutoBuilder::start("in")
+            .also_start("out")
+            .connect_wildcard("in", "mid")
+            .connect_wildcard_except("mid", vec!['y', 'x'], "out")
+            .connect_range("out", 'a'..='z', "out")
+            .accept("out")
+            .finalize();
+        let dot = nfa.to_dot(&DotOptions::new().name("words"));
+        assert!(dot.starts_with("digraph \"words\" {\n"));
+        assert!(dot.contains("    s0 [label=\"\\\"in\\\"\", shape=circle];\n"));
+        assert!(dot.contains("    start -> s0;\n    start -> s2;\n"));
+        assert!(dot.contains("    s0 -> s1 [label=\"·\", style=dashed];\n"));
+        assert!(dot.contains("    s1 -> s2 [label=\"·∖{'x', 'y'}\", style=dashed];\n"));
+        assert!(dot.contains("    s2 -> s2 [label=\"['a', 'z']\"];\n"));
+    }
 }
@@ -0,0 +1,353 @@
+use crate::re::Re;
+use std::error::Error;
+use std::fmt;
+
+/// Syntax error in a textual pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset of the error in the pattern.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl Error for ParseError {}
+
+// characters with a meaning in patterns, written escaped
+const SPECIAL: &str = "\\.*+?|&()[]^-";
+
+pub(crate) fn escape(c: char) -> String {
+    if SPECIAL.contains(c) {
+        format!("\\{}", c)
+    } else {
+        c.to_string()
+    }
+}
+
+// parentheses nested deeper are rejected, as parsing recurses on them
+const NESTING_LIMIT: usize = 256;
+
+// negated classes are expanded into excluded symbols, so their ranges must
+// stay small
+const NEGATED_RANGE_LIMIT: u32 = 256;
+
+impl Re<char> {
+    /// Parse a pattern made of literal characters, `.`, `*`, `+`, `?`, `|`,
+    /// `&` for interleaving (binding tighter than `|`), parentheses, named
+    /// groups like `(?<key>...)`, character classes like `[abc]`, `[a-z]` or
+    /// `[^"]`, and `\` escaping the next character. `()` matches the empty
+    /// input and `[]` matches nothing. Parentheses may nest up to 256
+    /// levels.
+    pub fn parse(pattern: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            pattern,
+            char_list: pattern.char_indices().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let re = parser.parse_either()?;
+        if let Some(c) = parser.peek() {
+            return Err(parser.error(if c == ')' {
+                "unmatched `)`".to_string()
+            } else {
+                format!("unexpected `{}`", c)
+            }));
+        }
+        Ok(re)
+    }
+}
+
+struct Parser<'p> {
+    pattern: &'p str,
+    char_list: Vec<(usize, char)>,
+    position: usize,
+    // number of parentheses around the position
+    depth: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn peek(&self) -> Option<char> {
+        self.char_list.get(self.position).map(|(_, c)| *c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
+    }
+
+    fn offset(&self) -> usize {
+        self.char_list
+            .get(self.position)
+            .map_or(self.pattern.len(), |(offset, _)| *offset)
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        self.error_at(self.offset(), message)
+    }
+
+    fn error_at(&self, offset: usize, message: String) -> ParseError {
+        ParseError { offset, message }
+    }
+
+    fn parse_either(&mut self) -> Result<Re<char>, ParseError> {
+        let mut re = self.parse_interleave()?;
+        while self.peek() == Some('|') {
+            self.next();
+            re = Re::either(re, self.parse_interleave()?);
+        }
+        Ok(re)
+    }
+
+    fn parse_interleave(&mut self) -> Result<Re<char>, ParseError> {
+        let mut re = self.parse_concat()?;
+        while self.peek() == Some('&') {
+            self.next();
+            re = Re::interleave(re, self.parse_concat()?);
+        }
+        Ok(re)
+    }
+
+    fn parse_concat(&mut self) -> Result<Re<char>, ParseError> {
+        let mut re: Option<Re<char>> = None;
+        while let Some(c) = self.peek() {
+            if c == '|' || c == '&' || c == ')' {
+                break;
+            }
+            let item = self.parse_repeat()?;
+            re = Some(match re {
+                Some(re) => Re::concat(re, item),
+                None => item,
+            });
+        }
+        Ok(re.unwrap_or_else(Re::epsilon))
+    }
+
+    fn parse_repeat(&mut self) -> Result<Re<char>, ParseError> {
+        let mut re = self.parse_atom()?;
+        while let Some(c) = self.peek() {
+            re = match c {
+                '*' => Re::zero_or_more(re),
+                '+' => Re::one_or_more(re),
+                '?' => Re::optional(re),
+                _ => break,
+            };
+            self.next();
+        }
+        Ok(re)
+    }
+
+    fn parse_atom(&mut self) -> Result<Re<char>, ParseError> {
+        let offset = self.offset();
+        match self.next() {
+            Some('.') => Ok(Re::wildcard()),
+            Some('(') => {
+                if self.depth == NESTING_LIMIT {
+                    return Err(self.error_at(
+                        offset,
+                        format!("parentheses nested deeper than {}", NESTING_LIMIT),
+                    ));
+                }
+                let name = if self.peek() == Some('?') {
+                    self.next();
+                    Some(self.parse_group_name()?)
+                } else {
+                    None
+                };
+                self.depth += 1;
+                let re = self.parse_either()?;
+                self.depth -= 1;
+                if self.next() != Some(')') {
+                    return Err(self.error_at(offset, "unclosed `(`".to_string()));
+                }
+                Ok(match name {
+                    Some(name) => Re::group(re, name),
+                    None => re,
+                })
+            }
+            Some('[') => self.parse_class(offset),
+            Some('\\') => self.parse_escaped().map(Re::plain),
+            Some(c @ ('*' | '+' | '?')) => {
+                Err(self.error_at(offset, format!("nothing to repeat before `{}`", c)))
+            }
+            Some(c) => Ok(Re::plain(c)),
+            None => Err(self.error("unexpected end of pattern".to_string())),
+        }
+    }
+
+    // the `(?` opening the group is already consumed
+    fn parse_group_name(&mut self) -> Result<String, ParseError> {
+        if self.peek() != Some('<') {
+            return Err(self.error("expected `<` after `(?`".to_string()));
+        }
+        self.next();
+        let offset = self.offset();
+        let mut name = String::new();
+        loop {
+            match self.next() {
+                Some('>') => break,
+                Some(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                _ => return Err(self.error_at(offset, "invalid group name".to_string())),
+            }
+        }
+        if name.is_empty() {
+            return Err(self.error_at(offset, "invalid group name".to_string()));
+        }
+        Ok(name)
+    }
+
+    fn parse_escaped(&mut self) -> Result<char, ParseError> {
+        self.next()
+            .ok_or_else(|| self.error("dangling `\\`".to_string()))
+    }
+
+    // the opening `[` at `offset` is already consumed
+    fn parse_class(&mut self, offset: usize) -> Result<Re<char>, ParseError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.next();
+        }
+        let mut item_list = Vec::new();
+        let mut range_list = Vec::new();
+        loop {
+            let item_offset = self.offset();
+            let start = match self.next() {
+                Some(']') => break,
+                Some('\\') => self.parse_escaped()?,
+                Some(c) => c,
+                None => return Err(self.error_at(offset, "unclosed `[`".to_string())),
+            };
+            let is_range = self.peek() == Some('-')
+                && self
+                    .char_list
+                    .get(self.position + 1)
+                    .is_some_and(|(_, c)| *c != ']');
+            if !is_range {
+                item_list.push(start);
+                continue;
+            }
+            self.next();
+            let end = match self.next() {
+                Some('\\') => self.parse_escaped()?,
+                Some(c) => c,
+                None => return Err(self.error_at(offset, "unclosed `[`".to_string())),
+            };
+            if start > end {
+                return Err(
+                    self.error_at(item_offset, format!("invalid range `{}-{}`", start, end))
+                );
+            }
+            if negated && end as u32 - start as u32 >= NEGATED_RANGE_LIMIT {
+                return Err(self.error_at(
+                    item_offset,
+                    format!("range `{}-{}` too large in negated class", start, end),
+                ));
+            }
+            range_list.push(start..=end);
+        }
+        if negated {
+            if item_list.is_empty() && range_list.is_empty() {
+                return Err(self.error_at(offset, "empty negated class".to_string()));
+            }
+            return Ok(Re::none_of(
+                item_list
+                    .into_iter()
+                    .chain(range_list.into_iter().flatten()),
+            ));
+        }
+        let re = range_list.into_iter().map(Re::range).fold(
+            if item_list.is_empty() {
+                Re::never()
+            } else {
+                Re::any_of(item_list)
+            },
+            Re::either,
+        );
+        Ok(re)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::auto::Auto;
+
+    #[test]
+    fn parse_and_match() {
+        let table: &[(&str, &[&str], &[&str])] = &[
+            ("abc", &["abc"], &["", "ab", "abcd"]),
+            ("a.c", &["abc", "a-c"], &["ac", "abbc"]),
+            ("ab*c", &["ac", "abbbc"], &["abbb", "bc"]),
+            ("ab+c", &["abc", "abbc"], &["ac"]),
+            ("ab?c", &["ac", "abc"], &["abbc"]),
+            ("a|bc|", &["a", "bc", ""], &["b", "abc"]),
+            ("(ab)*", &["", "ab", "abab"], &["aba"]),
+            ("[abc]+", &["a", "cab"], &["", "abd"]),
+            ("[0-9a-f]+", &["0", "9f", "dead"], &["g", "0x"]),
+            (
+                "\"[^\"\\\\]*\"",
+                &["\"\"", "\"abc\""],
+                &["\"a\"b\"", "\"\\\""],
+            ),
+            ("a\\*\\.", &["a*."], &["aa.", "a*b"]),
+            ("[-a]x", &["-x", "ax"], &["bx"]),
+            ("(?<key>a+)=(?<value>b*)", &["a=", "aa=bb"], &["=b", "a=ba"]),
+        ];
+        for (pattern, accepted, rejected) in table {
+            let dfa = determinize(&Re::parse(pattern).unwrap().compile()).unwrap();
+            for input in accepted.iter() {
+                assert!(dfa.create().test(input.chars()), "{} {}", pattern, input);
+            }
+            for input in rejected.iter() {
+                assert!(!dfa.create().test(input.chars()), "{} {}", pattern, input);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_error() {
+        let table = [
+            ("ab(c", 2, "unclosed `(`"),
+            ("abc)", 3, "unmatched `)`"),
+            ("a|*", 2, "nothing to repeat before `*`"),
+            ("[ab", 0, "unclosed `[`"),
+            ("a[^]", 1, "empty negated class"),
+            ("[z-a]", 1, "invalid range `z-a`"),
+            ("ab\\", 3, "dangling `\\`"),
+            ("é(", 2, "unclosed `(`"),
+            ("(?a)", 2, "expected `<` after `(?`"),
+            ("(?<a-b>x)", 3, "invalid group name"),
+            ("(?<>x)", 3, "invalid group name"),
+        ];
+        for (pattern, offset, message) in table.iter() {
+            let err = Re::parse(pattern).err().unwrap();
+            assert_eq!(
+                (err.offset, err.message.as_str()),
+                (*offset, *message),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| "(".repeat(depth) + "a" + &")".repeat(depth);
+        assert!(Re::parse(&nested(NESTING_LIMIT)).is_ok());
+        let err = Re::parse(&nested(NESTING_LIMIT + 1)).unwrap_err();
+        assert_eq!(err.offset, NESTING_LIMIT);
+        assert_eq!(err.message, "parentheses nested deeper than 256");
+        // far too deep to recurse into
+        assert!(Re::parse(&nested(1_000_000)).is_err());
+        // siblings do not add up
+        assert!(Re::parse(&nested(200).repeat(10)).is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+
+/// Interval of transitions of an ordered type. The comparison is captured on
+/// construction, so matching a transition against the range needs no `Ord`
+/// bound at the call site.
+pub struct TransRange<T> {
+    start: T,
+    end: T,
+    start_inclusive: bool,
+    end_inclusive: bool,
+    cmp: fn(&T, &T) -> Ordering,
+}
+
+impl<T> TransRange<T>
+where
+    T: Ord,
+{
+    pub fn new(range: RangeInclusive<T>) -> Self {
+        let (start, end) = range.into_inner();
+        Self::with_cmp(start, true, end, true, T::cmp)
+    }
+}
+
+impl<T> TransRange<T> {
+    pub(crate) fn with_cmp(
+        start: T,
+        start_inclusive: bool,
+        end: T,
+        end_inclusive: bool,
+        cmp: fn(&T, &T) -> Ordering,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            start_inclusive,
+            end_inclusive,
+            cmp,
+        }
+    }
+
+    pub(crate) fn cmp_fn(&self) -> fn(&T, &T) -> Ordering {
+        self.cmp
+    }
+
+    pub fn start(&self) -> &T {
+        &self.start
+    }
+
+    pub fn end(&self) -> &T {
+        &self.end
+    }
+
+    pub fn contains(&self, trans: &T) -> bool {
+        self.start_admits(trans) && self.end_admits(trans)
+    }
+
+    // whether `trans` is not below the start
+    fn start_admits(&self, trans: &T) -> bool {
+        match (self.cmp)(&self.start, trans) {
+            Ordering::Less => true,
+            Ordering::Equal => self.start_inclusive,
+            Ordering::Greater => false,
+        }
+    }
+
+    // whether `trans` is not above the end
+    fn end_admits(&self, trans: &T) -> bool {
+        match (self.cmp)(trans, &self.end) {
+            Ordering::Less => true,
+            Ordering::Equal => self.end_inclusive,
+            Ordering::Greater => false,
+        }
+    }
+
+    pub(crate) fn start_cut(&self) -> Cut<'_, T> {
+        Cut(&self.start, !self.start_inclusive)
+    }
+
+    pub(crate) fn end_cut(&self) -> Cut<'_, T> {
+        Cut(&self.end, self.end_inclusive)
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.cmp_cut(self.start_cut(), other.end_cut()) == Ordering::Less
+            && self.cmp_cut(other.start_cut(), self.end_cut()) == Ordering::Less
+    }
+
+    pub(crate) fn cmp_cut(&self, first: Cut<'_, T>, second: Cut<'_, T>) -> Ordering {
+        cmp_cut(self.cmp, first, second)
+    }
+}
+
+/// A position between two transitions: just before the transition, or just
+/// after it when the flag is set.
+#[derive(Debug)]
+pub(crate) struct Cut<'t, T>(pub &'t T, pub bool);
+
+impl<'t, T> Clone for Cut<'t, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'t, T> Copy for Cut<'t, T> {}
+
+pub(crate) fn cmp_cut<T>(
+    cmp: fn(&T, &T) -> Ordering,
+    first: Cut<'_, T>,
+    second: Cut<'_, T>,
+) -> Ordering {
+    cmp(first.0, second.0).then(first.1.cmp(&second.1))
+}
+
+impl<T> TransRange<T>
+where
+    T: Clone,
+{
+    // the range between two cuts
+    pub(crate) fn between(start: Cut<'_, T>, end: Cut<'_, T>, cmp: fn(&T, &T) -> Ordering) -> Self {
+        Self::with_cmp(start.0.clone(), !start.1, end.0.clone(), end.1, cmp)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        let start = if self.cmp_cut(self.start_cut(), other.start_cut()) == Ordering::Less {
+            other.start_cut()
+        } else {
+            self.start_cut()
+        };
+        let end = if self.cmp_cut(self.end_cut(), other.end_cut()) == Ordering::Less {
+            self.end_cut()
+        } else {
+            other.end_cut()
+        };
+        Some(Self::between(start, end, self.cmp))
+    }
+
+    // the disjoint ranges left after removing `excluded` transitions
+    pub(crate) fn without<'t>(&self, excluded: impl Iterator<Item = &'t T>) -> Vec<Self>
+    where
+        T: 't,
+    {
+        let mut point_list: Vec<_> = excluded.filter(|trans| self.contains(trans)).collect();
+        point_list.sort_by(|first, second| (self.cmp)(first, second));
+        let mut cut_list = vec![self.start_cut()];
+        for point in point_list {
+            cut_list.push(Cut(point, false));
+            cut_list.push(Cut(point, true));
+        }
+        cut_list.push(self.end_cut());
+        cut_list
+            .chunks(2)
+            .filter(|pair| self.cmp_cut(pair[0], pair[1]) == Ordering::Less)
+            .map(|pair| Self::between(pair[0], pair[1], self.cmp))
+            .collect()
+    }
+
+    pub(crate) fn union(&self, other: &Self) -> Self {
+        let start = if self.cmp_cut(self.start_cut(), other.start_cut()) == Ordering::Less {
+            self.start_cut()
+        } else {
+            other.start_cut()
+        };
+        let end = if self.cmp_cut(self.end_cut(), other.end_cut()) == Ordering::Less {
+            other.end_cut()
+        } else {
+            self.end_cut()
+        };
+        Self::between(start, end, self.cmp)
+    }
+}
+
+impl<T> RangeBounds<T> for TransRange<T> {
+    fn start_bound(&self) -> Bound<&T> {
+        if self.start_inclusive {
+            Bound::Included(&self.start)
+        } else {
+            Bound::Excluded(&self.start)
+        }
+    }
+
+    fn end_bound(&self) -> Bound<&T> {
+        if self.end_inclusive {
+            Bound::Included(&self.end)
+        } else {
+            Bound::Excluded(&self.end)
+        }
+    }
+}
+
+impl<T> Clone for TransRange<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::with_cmp(
+            self.start.clone(),
+            self.start_inclusive,
+            self.end.clone(),
+            self.end_inclusive,
+            self.cmp,
+        )
+    }
+}
+
+impl<T> fmt::Debug for TransRange<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{:?}, {:?}{}",
+            if self.start_inclusive { '[' } else { '(' },
+            self.start,
+            self.end,
+            if self.end_inclusive { ']' } else { ')' }
+        )
+    }
+}
+
+impl<T> PartialEq for TransRange<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+            && self.end == other.end
+            && self.start_inclusive == other.start_inclusive
+            && self.end_inclusive == other.end_inclusive
+    }
+}
+
+impl<T> Eq for TransRange<T> where T: Eq {}
+
+impl<T> Hash for TransRange<T>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.start.hash(state);
+        self.end.hash(state);
+        self.start_inclusive.hash(state);
+        self.end_inclusive.hash(state);
+    }
+}
+
+// insert keeping the list sorted by range start
+pub(crate) fn insert_sorted<T, S>(list: &mut Vec<(TransRange<T>, S)>, range: TransRange<T>, to: S) {
+    let index = list.partition_point(|(other, _)| {
+        range.cmp_cut(other.start_cut(), range.start_cut()) != Ordering::Greater
+    });
+    list.insert(index, (range, to));
+}
+
+// ranges of a list sorted by start which contain `trans`
+pub(crate) fn matching<'l, T, S>(
+    list: &'l [(TransRange<T>, S)],
+    trans: &'l T,
+) -> impl Iterator<Item = &'l S> {
+    let count = list.partition_point(|(range, _)| range.start_admits(trans));
+    list[..count]
+        .iter()
+        .filter(move |(range, _)| range.end_admits(trans))
+        .map(|(_, to)| to)
+}
+
+// the range of a list of disjoint ranges sorted by start which contains `trans`
+pub(crate) fn matching_disjoint<'l, T, S>(
+    list: &'l [(TransRange<T>, S)],
+    trans: &T,
+) -> Option<&'l S> {
+    let count = list.partition_point(|(range, _)| range.start_admits(trans));
+    list[..count]
+        .last()
+        .filter(|(range, _)| range.end_admits(trans))
+        .map(|(_, to)| to)
+}
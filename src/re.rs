@@ -1,86 +1,431 @@
-use crate::nfa::{NFAutoBlueprint, NFAutoBuilder};
+use crate::algo::{
+    complement, determinize, determinize_over, dfa_difference_witness, dfa_inclusion_witness,
+    nfa_intersect,
+};
+use crate::dfa::DFAutoBlueprint;
+use crate::nfa::{ConnType, NFAutoBlueprint, NFAutoBuilder};
+use crate::parse::escape;
+use crate::range::TransRange;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::Hash;
 use std::mem;
+use std::ops::{Add, BitOr, RangeInclusive};
 
-enum RePriv<T> {
+#[derive(Debug)]
+pub(crate) enum RePriv<T> {
     Plain(T),
     ZeroOrMore(Box<RePriv<T>>),
+    OneOrMore(Box<RePriv<T>>),
+    Optional(Box<RePriv<T>>),
     Concat(Box<RePriv<T>>, Box<RePriv<T>>),
     Either(Box<RePriv<T>>, Box<RePriv<T>>),
     Wildcard,
+    NoneOf(Vec<T>),
+    Epsilon,
+    Never,
+    Repeat(Box<RePriv<T>>, usize, usize),
+    AnyOf(Vec<T>),
+    Range(TransRange<T>),
+    Interleave(Box<RePriv<T>>, Box<RePriv<T>>),
+    Group(Box<RePriv<T>>, String),
 }
 
-pub struct Re<T>(RePriv<T>);
+// dropping detaches inner patterns onto a list instead of recursing, so that
+// deeply nested patterns do not overflow the stack
+impl<T> Drop for RePriv<T> {
+    fn drop(&mut self) {
+        let mut inner_list = Vec::new();
+        self.detach_inner(&mut inner_list);
+        while let Some(mut inner) = inner_list.pop() {
+            inner.detach_inner(&mut inner_list);
+        }
+    }
+}
+
+impl<T> RePriv<T> {
+    fn detach_inner(&mut self, inner_list: &mut Vec<Box<Self>>) {
+        match self {
+            RePriv::ZeroOrMore(inner)
+            | RePriv::OneOrMore(inner)
+            | RePriv::Optional(inner)
+            | RePriv::Repeat(inner, _, _)
+            | RePriv::Group(inner, _) => inner_list.push(take(inner)),
+            RePriv::Concat(first, second)
+            | RePriv::Either(first, second)
+            | RePriv::Interleave(first, second) => {
+                inner_list.push(take(first));
+                inner_list.push(take(second));
+            }
+            _ => {}
+        }
+    }
+
+    // put back the inner patterns detached by `detach_inner`, popping the
+    // first one first from `inner_list`
+    fn attach_inner(&mut self, inner_list: &mut Vec<Self>) {
+        let mut attach = |inner: &mut Box<Self>| **inner = inner_list.pop().unwrap();
+        match self {
+            RePriv::ZeroOrMore(inner)
+            | RePriv::OneOrMore(inner)
+            | RePriv::Optional(inner)
+            | RePriv::Repeat(inner, _, _)
+            | RePriv::Group(inner, _) => attach(inner),
+            RePriv::Concat(first, second)
+            | RePriv::Either(first, second)
+            | RePriv::Interleave(first, second) => {
+                attach(first);
+                attach(second);
+            }
+            _ => {}
+        }
+    }
+}
+
+// move an inner pattern out, leaving `epsilon` in place
+fn take<T>(inner: &mut Box<RePriv<T>>) -> Box<RePriv<T>> {
+    mem::replace(inner, Box::new(RePriv::Epsilon))
+}
+
+impl<T> Clone for RePriv<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        self.fold(&mut Cloned)
+    }
+}
+
+// compares pairs of patterns from a list instead of recursing, so that deeply
+// nested patterns do not overflow the stack
+impl<T> PartialEq for RePriv<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        use RePriv::*;
+        let mut pair_list = vec![(self, other)];
+        while let Some(pair) = pair_list.pop() {
+            match pair {
+                (Plain(trans), Plain(other_trans)) if trans == other_trans => {}
+                (ZeroOrMore(inner), ZeroOrMore(other_inner))
+                | (OneOrMore(inner), OneOrMore(other_inner))
+                | (Optional(inner), Optional(other_inner)) => pair_list.push((inner, other_inner)),
+                (Repeat(inner, min, max), Repeat(other_inner, other_min, other_max))
+                    if (min, max) == (other_min, other_max) =>
+                {
+                    pair_list.push((inner, other_inner))
+                }
+                (Group(inner, name), Group(other_inner, other_name)) if name == other_name => {
+                    pair_list.push((inner, other_inner))
+                }
+                (Concat(first, second), Concat(other_first, other_second))
+                | (Either(first, second), Either(other_first, other_second))
+                | (Interleave(first, second), Interleave(other_first, other_second)) => {
+                    pair_list.push((second, other_second));
+                    pair_list.push((first, other_first));
+                }
+                (Wildcard, Wildcard) | (Epsilon, Epsilon) | (Never, Never) => {}
+                (NoneOf(trans_list), NoneOf(other_list))
+                | (AnyOf(trans_list), AnyOf(other_list))
+                    if trans_list == other_list => {}
+                (Range(range), Range(other_range)) if range == other_range => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<T> Eq for RePriv<T> where T: Eq {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Re<T>(pub(crate) RePriv<T>);
 
 impl<T> RePriv<T>
 where
     T: Eq + Hash + Clone,
 {
-    pub fn compile(self) -> NFAutoBlueprint<u64, T> {
+    pub fn compile(&self) -> NFAutoBlueprint<u64, T> {
         let mut builder = NFAutoBuilder::start(0).accept(1);
         let mut counter = 2;
-        self.recursive_compile(&mut builder, &mut counter, 0, 1);
+        // patterns still to compile between their two states, kept on the heap
+        // so that deeply nested patterns do not overflow the stack
+        let mut work_list = vec![(self, 0, 1)];
+        while let Some((re, left, right)) = work_list.pop() {
+            re.compile_between(&mut builder, &mut counter, left, right, &mut work_list);
+            debug_assert!(counter < u64::MAX / 2, "state counter overflow");
+        }
         builder.finalize()
     }
 
-    fn recursive_compile(
-        self,
+    // connect `left` to `right` as the outermost pattern, pushing the inner
+    // patterns onto `work_list`
+    fn compile_between<'r>(
+        &'r self,
         builder: &mut NFAutoBuilder<u64, T>,
         counter: &mut u64,
         left: u64,
         right: u64,
+        work_list: &mut Vec<(&'r Self, u64, u64)>,
     ) {
         match self {
             RePriv::Plain(trans) => {
-                update_builder(builder, |b| b.connect(left, trans, right));
+                builder.connect_mut(left, trans.clone(), right);
             }
             RePriv::ZeroOrMore(inner) => {
                 let (inner_left, inner_right) = (*counter, *counter + 1);
                 *counter += 2;
-                update_builder(builder, |b| {
-                    b.connect_void(left, inner_left)
-                        .connect_void(inner_right, right)
-                        .connect_void(inner_right, inner_left)
-                        .connect_void(left, right)
-                });
-                inner.recursive_compile(builder, counter, inner_left, inner_right);
+                builder.connect_void_mut(left, inner_left);
+                builder.connect_void_mut(inner_right, right);
+                builder.connect_void_mut(inner_right, inner_left);
+                builder.connect_void_mut(left, right);
+                work_list.push((inner, inner_left, inner_right));
+            }
+            RePriv::OneOrMore(inner) => {
+                let (inner_left, inner_right) = (*counter, *counter + 1);
+                *counter += 2;
+                builder.connect_void_mut(left, inner_left);
+                builder.connect_void_mut(inner_right, right);
+                builder.connect_void_mut(inner_right, inner_left);
+                work_list.push((inner, inner_left, inner_right));
+            }
+            RePriv::Optional(inner) => {
+                builder.connect_void_mut(left, right);
+                work_list.push((inner, left, right));
             }
             RePriv::Concat(first, second) => {
                 let middle = *counter;
                 *counter += 1;
-                first.recursive_compile(builder, counter, left, middle);
-                second.recursive_compile(builder, counter, middle, right);
+                work_list.push((first, left, middle));
+                work_list.push((second, middle, right));
             }
             RePriv::Either(first, second) => {
                 let (first_left, first_right, second_left, second_right) =
                     (*counter, *counter + 1, *counter + 2, *counter + 3);
                 *counter += 4;
-                update_builder(builder, |b| {
-                    b.connect_void(left, first_left)
-                        .connect_void(left, second_left)
-                        .connect_void(first_right, right)
-                        .connect_void(second_right, right)
-                });
-                first.recursive_compile(builder, counter, first_left, first_right);
-                second.recursive_compile(builder, counter, second_left, second_right);
+                builder.connect_void_mut(left, first_left);
+                builder.connect_void_mut(left, second_left);
+                builder.connect_void_mut(first_right, right);
+                builder.connect_void_mut(second_right, right);
+                work_list.push((first, first_left, first_right));
+                work_list.push((second, second_left, second_right));
             }
             RePriv::Wildcard => {
-                update_builder(builder, |b| b.connect_wildcard(left, right));
+                builder.connect_wildcard_mut(left, right);
+            }
+            RePriv::Epsilon => {
+                builder.connect_void_mut(left, right);
+            }
+            RePriv::Never => {}
+            RePriv::Repeat(inner, min, max) => {
+                let (min, max) = (*min, *max);
+                if max == 0 {
+                    builder.connect_void_mut(left, right);
+                    return;
+                }
+                // compile a copy between every two consecutive points of a
+                // chain from left to right
+                let mut point = left;
+                for i in 0..max {
+                    let next = if i + 1 == max {
+                        right
+                    } else {
+                        *counter += 1;
+                        *counter - 1
+                    };
+                    if i >= min {
+                        builder.connect_void_mut(point, right);
+                    }
+                    work_list.push((inner, point, next));
+                    point = next;
+                }
             }
+            RePriv::AnyOf(trans_list) => {
+                for trans in trans_list {
+                    builder.connect_mut(left, trans.clone(), right);
+                }
+            }
+            RePriv::Range(range) => {
+                builder.connect_trans_range_mut(left, range.clone(), right);
+            }
+            RePriv::Interleave(first, second) => {
+                // product of both automata where each step advances one side
+                let (first, second) = (first.compile(), second.compile());
+                let (outgoing1, outgoing2) = (outgoing_map(&first), outgoing_map(&second));
+                let mut number_map = HashMap::new();
+                let mut unresolved = Vec::new();
+                let mut number = |pair, unresolved: &mut Vec<_>| {
+                    *number_map.entry(pair).or_insert_with(|| {
+                        unresolved.push(pair);
+                        *counter += 1;
+                        *counter - 1
+                    })
+                };
+                for s1 in first.start_state_set() {
+                    for s2 in second.start_state_set() {
+                        let start = number((s1, s2), &mut unresolved);
+                        builder.connect_void_mut(left, start);
+                    }
+                }
+                while let Some((s1, s2)) = unresolved.pop() {
+                    let from = number((s1, s2), &mut unresolved);
+                    if first.accept_state_set().contains(s1)
+                        && second.accept_state_set().contains(s2)
+                    {
+                        builder.connect_void_mut(from, right);
+                    }
+                    for (conn, to1) in outgoing1.get(s1).into_iter().flatten() {
+                        let to = number((to1, s2), &mut unresolved);
+                        builder.connect_as_mut(from, *conn, to);
+                    }
+                    for (conn, to2) in outgoing2.get(s2).into_iter().flatten() {
+                        let to = number((s1, to2), &mut unresolved);
+                        builder.connect_as_mut(from, *conn, to);
+                    }
+                }
+            }
+            RePriv::NoneOf(excluded) => {
+                builder.connect_wildcard_except_mut(left, excluded.iter().cloned(), right);
+            }
+            RePriv::Group(inner, _) => work_list.push((inner, left, right)),
+        }
+    }
+}
+
+// binding strength of pattern syntax, from loosest to tightest
+const EITHER: u8 = 0;
+const INTERLEAVE: u8 = 1;
+const CONCAT: u8 = 2;
+const REPEAT: u8 = 3;
+const ATOM: u8 = 4;
+
+impl<T> RePriv<T> {
+    // how tightly the pattern binds when written out
+    fn precedence(&self) -> u8 {
+        let mut re = self;
+        loop {
+            return match re {
+                RePriv::Either(_, _) => EITHER,
+                RePriv::Interleave(_, _) => INTERLEAVE,
+                RePriv::Concat(_, _) => CONCAT,
+                RePriv::ZeroOrMore(_) | RePriv::OneOrMore(_) | RePriv::Optional(_) => REPEAT,
+                RePriv::Repeat(_, _, 0) => ATOM,
+                RePriv::Repeat(inner, 1, 1) => {
+                    re = inner;
+                    continue;
+                }
+                RePriv::Repeat(_, _, 1) => REPEAT,
+                RePriv::Repeat(_, _, _) => CONCAT,
+                _ => ATOM,
+            };
+        }
+    }
+
+    fn to_pattern(&self, fmt_symbol: &dyn Fn(&T) -> String) -> String {
+        enum Piece<'r, T> {
+            // a pattern, parenthesized if it binds looser than the precedence
+            Operand(&'r RePriv<T>, u8),
+            Text(String),
         }
+        use Piece::*;
+        let class = |trans_list: &[T]| trans_list.iter().map(fmt_symbol).collect::<String>();
+        let mut pattern = String::new();
+        // pieces still to write out, last one first, kept on the heap so that
+        // deeply nested patterns do not overflow the stack
+        let mut piece_list = vec![Operand(self, EITHER)];
+        while let Some(piece) = piece_list.pop() {
+            let (re, prec) = match piece {
+                Text(text) => {
+                    pattern.push_str(&text);
+                    continue;
+                }
+                Operand(re, prec) if re.precedence() < prec => {
+                    pattern.push('(');
+                    piece_list.push(Text(")".to_string()));
+                    piece_list.push(Operand(re, EITHER));
+                    continue;
+                }
+                Operand(re, prec) => (re, prec),
+            };
+            let piece_seq = match re {
+                RePriv::Plain(trans) => vec![Text(fmt_symbol(trans))],
+                RePriv::ZeroOrMore(inner) => vec![Operand(inner, REPEAT), Text("*".to_string())],
+                RePriv::OneOrMore(inner) => vec![Operand(inner, REPEAT), Text("+".to_string())],
+                RePriv::Optional(inner) => vec![Operand(inner, REPEAT), Text("?".to_string())],
+                RePriv::Concat(first, second) => {
+                    vec![Operand(first, CONCAT), Operand(second, CONCAT)]
+                }
+                RePriv::Either(first, second) => vec![
+                    Operand(first, EITHER),
+                    Text("|".to_string()),
+                    Operand(second, EITHER),
+                ],
+                RePriv::Interleave(first, second) => vec![
+                    Operand(first, INTERLEAVE),
+                    Text("&".to_string()),
+                    Operand(second, INTERLEAVE),
+                ],
+                RePriv::Group(inner, name) => vec![
+                    Text(format!("(?<{}>", name)),
+                    Operand(inner, EITHER),
+                    Text(")".to_string()),
+                ],
+                RePriv::Wildcard => vec![Text(".".to_string())],
+                RePriv::NoneOf(excluded) if excluded.is_empty() => vec![Text(".".to_string())],
+                RePriv::NoneOf(excluded) => vec![Text(format!("[^{}]", class(excluded)))],
+                RePriv::Epsilon => vec![Text("()".to_string())],
+                RePriv::Never => vec![Text("[]".to_string())],
+                RePriv::Repeat(_, _, 0) => vec![Text("()".to_string())],
+                RePriv::Repeat(inner, 1, 1) => vec![Operand(inner, prec)],
+                RePriv::Repeat(inner, min, max) => {
+                    // written out as required copies followed by optional ones
+                    let mut piece_seq = Vec::new();
+                    for _ in 0..*min {
+                        piece_seq.push(Operand(inner, CONCAT));
+                    }
+                    for _ in *min..*max {
+                        piece_seq.push(Operand(inner, REPEAT));
+                        piece_seq.push(Text("?".to_string()));
+                    }
+                    piece_seq
+                }
+                RePriv::AnyOf(trans_list) => vec![Text(format!("[{}]", class(trans_list)))],
+                RePriv::Range(range) => vec![Text(format!(
+                    "[{}-{}]",
+                    fmt_symbol(range.start()),
+                    fmt_symbol(range.end())
+                ))],
+            };
+            piece_list.extend(piece_seq.into_iter().rev());
+        }
+        pattern
     }
 }
 
-fn update_builder<T, F>(builder_mut: &mut NFAutoBuilder<u64, T>, updater: F)
+// connections of a blueprint grouped by source
+fn outgoing_map<T>(bp: &NFAutoBlueprint<u64, T>) -> HashMap<&u64, Vec<(ConnType<'_, T>, &u64)>>
 where
     T: Hash + Eq,
-    F: FnOnce(NFAutoBuilder<u64, T>) -> NFAutoBuilder<u64, T>,
 {
-    let mut updated = updater(mem::replace(builder_mut, Default::default()));
-    mem::swap(builder_mut, &mut updated);
+    let mut outgoing_map: HashMap<_, Vec<_>> = HashMap::new();
+    for (from, conn, to) in bp.iterate_connections() {
+        outgoing_map.entry(from).or_default().push((conn, to));
+    }
+    outgoing_map
 }
 
 impl<T> Re<T> {
+    /// Matches the empty input only.
+    pub fn epsilon() -> Self {
+        Self(RePriv::Epsilon)
+    }
+
+    /// Matches nothing.
+    pub fn never() -> Self {
+        Self(RePriv::Never)
+    }
+
     pub fn plain(trans: T) -> Self {
         Self(RePriv::Plain(trans))
     }
@@ -89,6 +434,27 @@ impl<T> Re<T> {
         Self(RePriv::ZeroOrMore(Box::new(inner.0)))
     }
 
+    pub fn one_or_more(inner: Self) -> Self {
+        Self(RePriv::OneOrMore(Box::new(inner.0)))
+    }
+
+    pub fn optional(inner: Self) -> Self {
+        Self(RePriv::Optional(Box::new(inner.0)))
+    }
+
+    /// Exactly `n` repetitions of `inner`.
+    pub fn repeat(inner: Self, n: usize) -> Self {
+        Self::repeat_between(inner, n, n)
+    }
+
+    /// From `min` to `max` repetitions of `inner`, both inclusive.
+    pub fn repeat_between(inner: Self, min: usize, max: usize) -> Self {
+        if min > max {
+            panic!("minimum repetition greater than maximum");
+        }
+        Self(RePriv::Repeat(Box::new(inner.0), min, max))
+    }
+
     pub fn concat(first: Self, second: Self) -> Self {
         Self(RePriv::Concat(Box::new(first.0), Box::new(second.0)))
     }
@@ -97,9 +463,580 @@ impl<T> Re<T> {
         Self(RePriv::Either(Box::new(first.0), Box::new(second.0)))
     }
 
+    /// Matches the same as `inner`, recording the matched span under `name`
+    /// for `Re::captures`.
+    pub fn group(inner: Self, name: impl Into<String>) -> Self {
+        Self(RePriv::Group(Box::new(inner.0), name.into()))
+    }
+
+    /// Every interleaving of a string matched by `first` with a string matched
+    /// by `second`.
+    pub fn interleave(first: Self, second: Self) -> Self {
+        Self(RePriv::Interleave(Box::new(first.0), Box::new(second.0)))
+    }
+
     pub fn wildcard() -> Self {
         Self(RePriv::Wildcard)
     }
+
+    /// Any single transition among `items`. Matches nothing if empty.
+    pub fn any_of(items: impl IntoIterator<Item = T>) -> Self {
+        Self(RePriv::AnyOf(items.into_iter().collect()))
+    }
+
+    /// The sequence of `items`. Matches the empty input only if empty.
+    pub fn literal(items: impl IntoIterator<Item = T>) -> Self {
+        items
+            .into_iter()
+            .map(Self::plain)
+            .reduce(Self::concat)
+            .unwrap_or_else(Self::epsilon)
+    }
+
+    /// Any single transition except the excluded ones.
+    pub fn none_of(excluded: impl IntoIterator<Item = T>) -> Self {
+        Self(RePriv::NoneOf(excluded.into_iter().collect()))
+    }
+}
+
+impl<T> Re<T> {
+    /// Same as `Re::zero_or_more(self)`.
+    pub fn star(self) -> Self {
+        Self::zero_or_more(self)
+    }
+
+    /// Same as `Re::one_or_more(self)`.
+    pub fn plus(self) -> Self {
+        Self::one_or_more(self)
+    }
+
+    /// Same as `Re::optional(self)`.
+    pub fn opt(self) -> Self {
+        Self::optional(self)
+    }
+
+    /// Textual pattern in the syntax of `Re::parse`, writing every transition
+    /// with `fmt_symbol`.
+    pub fn to_pattern(&self, fmt_symbol: impl Fn(&T) -> String) -> String {
+        self.0.to_pattern(&fmt_symbol)
+    }
+}
+
+/// Computation over the structure of a pattern, driven by `Re::fold`. Every
+/// method receives the results already computed for the inner patterns.
+pub trait ReVisitor<'r, T, R> {
+    fn plain(&mut self, trans: &'r T) -> R;
+    fn zero_or_more(&mut self, inner: R) -> R;
+    fn one_or_more(&mut self, inner: R) -> R;
+    fn optional(&mut self, inner: R) -> R;
+    fn concat(&mut self, first: R, second: R) -> R;
+    fn either(&mut self, first: R, second: R) -> R;
+    fn wildcard(&mut self) -> R;
+    fn none_of(&mut self, excluded: &'r [T]) -> R;
+    fn epsilon(&mut self) -> R;
+    fn never(&mut self) -> R;
+    fn repeat_between(&mut self, inner: R, min: usize, max: usize) -> R;
+    fn any_of(&mut self, items: &'r [T]) -> R;
+    fn range(&mut self, range: &'r TransRange<T>) -> R;
+    fn interleave(&mut self, first: R, second: R) -> R;
+    fn group(&mut self, inner: R, name: &'r str) -> R;
+}
+
+impl<T> RePriv<T> {
+    fn fold<'r, R>(&'r self, f: &mut impl ReVisitor<'r, T, R>) -> R {
+        // patterns to visit, and patterns whose inner results are ready, kept
+        // on the heap so that deeply nested patterns do not overflow the stack
+        let mut step_list = vec![(self, false)];
+        let mut result_list = Vec::new();
+        while let Some((re, inner_done)) = step_list.pop() {
+            if !inner_done {
+                step_list.push((re, true));
+                match re {
+                    RePriv::ZeroOrMore(inner)
+                    | RePriv::OneOrMore(inner)
+                    | RePriv::Optional(inner)
+                    | RePriv::Repeat(inner, _, _)
+                    | RePriv::Group(inner, _) => step_list.push((inner, false)),
+                    RePriv::Concat(first, second)
+                    | RePriv::Either(first, second)
+                    | RePriv::Interleave(first, second) => {
+                        step_list.push((second, false));
+                        step_list.push((first, false));
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            let mut pop = || result_list.pop().unwrap();
+            let result = match re {
+                RePriv::Plain(trans) => f.plain(trans),
+                RePriv::ZeroOrMore(_) => f.zero_or_more(pop()),
+                RePriv::OneOrMore(_) => f.one_or_more(pop()),
+                RePriv::Optional(_) => f.optional(pop()),
+                RePriv::Concat(_, _) => {
+                    let second = pop();
+                    f.concat(pop(), second)
+                }
+                RePriv::Either(_, _) => {
+                    let second = pop();
+                    f.either(pop(), second)
+                }
+                RePriv::Wildcard => f.wildcard(),
+                RePriv::NoneOf(excluded) => f.none_of(excluded),
+                RePriv::Epsilon => f.epsilon(),
+                RePriv::Never => f.never(),
+                RePriv::Repeat(_, min, max) => f.repeat_between(pop(), *min, *max),
+                RePriv::AnyOf(items) => f.any_of(items),
+                RePriv::Range(range) => f.range(range),
+                RePriv::Interleave(_, _) => {
+                    let second = pop();
+                    f.interleave(pop(), second)
+                }
+                RePriv::Group(_, name) => f.group(pop(), name),
+            };
+            result_list.push(result);
+        }
+        result_list.pop().unwrap()
+    }
+}
+
+impl<T> Re<T> {
+    /// Compute over the pattern bottom-up with `f`.
+    pub fn fold<'r, R>(&'r self, f: &mut impl ReVisitor<'r, T, R>) -> R {
+        self.0.fold(f)
+    }
+
+    /// Nesting depth of the pattern, 1 for a pattern without inner pattern.
+    pub fn max_depth(&self) -> usize {
+        self.fold(&mut MaxDepth)
+    }
+}
+
+impl<T> Re<T>
+where
+    T: Hash + Eq,
+{
+    /// Every transition mentioned by the pattern, including excluded ones and
+    /// range bounds.
+    pub fn symbols(&self) -> HashSet<&T> {
+        self.fold(&mut Symbols)
+    }
+}
+
+struct MaxDepth;
+
+impl<'r, T> ReVisitor<'r, T, usize> for MaxDepth {
+    fn plain(&mut self, _trans: &'r T) -> usize {
+        1
+    }
+
+    fn zero_or_more(&mut self, inner: usize) -> usize {
+        inner + 1
+    }
+
+    fn one_or_more(&mut self, inner: usize) -> usize {
+        inner + 1
+    }
+
+    fn optional(&mut self, inner: usize) -> usize {
+        inner + 1
+    }
+
+    fn concat(&mut self, first: usize, second: usize) -> usize {
+        first.max(second) + 1
+    }
+
+    fn either(&mut self, first: usize, second: usize) -> usize {
+        first.max(second) + 1
+    }
+
+    fn wildcard(&mut self) -> usize {
+        1
+    }
+
+    fn none_of(&mut self, _excluded: &'r [T]) -> usize {
+        1
+    }
+
+    fn epsilon(&mut self) -> usize {
+        1
+    }
+
+    fn never(&mut self) -> usize {
+        1
+    }
+
+    fn repeat_between(&mut self, inner: usize, _min: usize, _max: usize) -> usize {
+        inner + 1
+    }
+
+    fn any_of(&mut self, _items: &'r [T]) -> usize {
+        1
+    }
+
+    fn range(&mut self, _range: &'r TransRange<T>) -> usize {
+        1
+    }
+
+    fn interleave(&mut self, first: usize, second: usize) -> usize {
+        first.max(second) + 1
+    }
+
+    fn group(&mut self, inner: usize, _name: &'r str) -> usize {
+        inner + 1
+    }
+}
+
+struct Symbols;
+
+impl<'r, T> ReVisitor<'r, T, HashSet<&'r T>> for Symbols
+where
+    T: Hash + Eq,
+{
+    fn plain(&mut self, trans: &'r T) -> HashSet<&'r T> {
+        vec![trans].into_iter().collect()
+    }
+
+    fn zero_or_more(&mut self, inner: HashSet<&'r T>) -> HashSet<&'r T> {
+        inner
+    }
+
+    fn one_or_more(&mut self, inner: HashSet<&'r T>) -> HashSet<&'r T> {
+        inner
+    }
+
+    fn optional(&mut self, inner: HashSet<&'r T>) -> HashSet<&'r T> {
+        inner
+    }
+
+    fn concat(&mut self, first: HashSet<&'r T>, second: HashSet<&'r T>) -> HashSet<&'r T> {
+        &first | &second
+    }
+
+    fn either(&mut self, first: HashSet<&'r T>, second: HashSet<&'r T>) -> HashSet<&'r T> {
+        &first | &second
+    }
+
+    fn wildcard(&mut self) -> HashSet<&'r T> {
+        HashSet::new()
+    }
+
+    fn none_of(&mut self, excluded: &'r [T]) -> HashSet<&'r T> {
+        excluded.iter().collect()
+    }
+
+    fn epsilon(&mut self) -> HashSet<&'r T> {
+        HashSet::new()
+    }
+
+    fn never(&mut self) -> HashSet<&'r T> {
+        HashSet::new()
+    }
+
+    fn repeat_between(
+        &mut self,
+        inner: HashSet<&'r T>,
+        _min: usize,
+        _max: usize,
+    ) -> HashSet<&'r T> {
+        inner
+    }
+
+    fn any_of(&mut self, items: &'r [T]) -> HashSet<&'r T> {
+        items.iter().collect()
+    }
+
+    fn range(&mut self, range: &'r TransRange<T>) -> HashSet<&'r T> {
+        vec![range.start(), range.end()].into_iter().collect()
+    }
+
+    fn interleave(&mut self, first: HashSet<&'r T>, second: HashSet<&'r T>) -> HashSet<&'r T> {
+        &first | &second
+    }
+
+    fn group(&mut self, inner: HashSet<&'r T>, _name: &'r str) -> HashSet<&'r T> {
+        inner
+    }
+}
+
+struct Cloned;
+
+impl<'r, T> ReVisitor<'r, T, RePriv<T>> for Cloned
+where
+    T: Clone,
+{
+    fn plain(&mut self, trans: &'r T) -> RePriv<T> {
+        RePriv::Plain(trans.clone())
+    }
+
+    fn zero_or_more(&mut self, inner: RePriv<T>) -> RePriv<T> {
+        RePriv::ZeroOrMore(Box::new(inner))
+    }
+
+    fn one_or_more(&mut self, inner: RePriv<T>) -> RePriv<T> {
+        RePriv::OneOrMore(Box::new(inner))
+    }
+
+    fn optional(&mut self, inner: RePriv<T>) -> RePriv<T> {
+        RePriv::Optional(Box::new(inner))
+    }
+
+    fn concat(&mut self, first: RePriv<T>, second: RePriv<T>) -> RePriv<T> {
+        RePriv::Concat(Box::new(first), Box::new(second))
+    }
+
+    fn either(&mut self, first: RePriv<T>, second: RePriv<T>) -> RePriv<T> {
+        RePriv::Either(Box::new(first), Box::new(second))
+    }
+
+    fn wildcard(&mut self) -> RePriv<T> {
+        RePriv::Wildcard
+    }
+
+    fn none_of(&mut self, excluded: &'r [T]) -> RePriv<T> {
+        RePriv::NoneOf(excluded.to_vec())
+    }
+
+    fn epsilon(&mut self) -> RePriv<T> {
+        RePriv::Epsilon
+    }
+
+    fn never(&mut self) -> RePriv<T> {
+        RePriv::Never
+    }
+
+    fn repeat_between(&mut self, inner: RePriv<T>, min: usize, max: usize) -> RePriv<T> {
+        RePriv::Repeat(Box::new(inner), min, max)
+    }
+
+    fn any_of(&mut self, items: &'r [T]) -> RePriv<T> {
+        RePriv::AnyOf(items.to_vec())
+    }
+
+    fn range(&mut self, range: &'r TransRange<T>) -> RePriv<T> {
+        RePriv::Range(range.clone())
+    }
+
+    fn interleave(&mut self, first: RePriv<T>, second: RePriv<T>) -> RePriv<T> {
+        RePriv::Interleave(Box::new(first), Box::new(second))
+    }
+
+    fn group(&mut self, inner: RePriv<T>, name: &'r str) -> RePriv<T> {
+        RePriv::Group(Box::new(inner), name.to_string())
+    }
+}
+
+/// `a | b` is `Re::either(a, b)`.
+impl<T> BitOr for Re<T> {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self::either(self, other)
+    }
+}
+
+/// `a + b` is `Re::concat(a, b)`.
+impl<T> Add for Re<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::concat(self, other)
+    }
+}
+
+impl<T> RePriv<T>
+where
+    T: PartialEq,
+{
+    fn simplify(self) -> Self {
+        // patterns to simplify, and patterns whose inner patterns are
+        // simplified, kept on the heap so that deeply nested patterns do not
+        // overflow the stack
+        let mut step_list = vec![(self, false)];
+        let mut simplified_list = Vec::new();
+        while let Some((mut re, inner_done)) = step_list.pop() {
+            if inner_done {
+                re.attach_inner(&mut simplified_list);
+                simplified_list.push(re.rewrite());
+            } else {
+                let mut inner_list = Vec::new();
+                re.detach_inner(&mut inner_list);
+                step_list.push((re, true));
+                step_list.extend(inner_list.into_iter().map(|inner| (*inner, false)));
+            }
+        }
+        simplified_list.pop().unwrap()
+    }
+
+    // rewrite the outermost pattern, whose inner patterns are simplified
+    fn rewrite(mut self) -> Self {
+        use RePriv::*;
+        match &mut self {
+            Concat(first, second) | Interleave(first, second) => match (&**first, &**second) {
+                (Never, _) | (_, Never) => Never,
+                (Epsilon, _) => *take(second),
+                (_, Epsilon) => *take(first),
+                _ => self,
+            },
+            Either(first, second) => match (&**first, &**second) {
+                (Never, _) => *take(second),
+                (_, Never) => *take(first),
+                (first_re, second_re) if first_re == second_re => *take(first),
+                _ => self,
+            },
+            ZeroOrMore(inner) => match &mut **inner {
+                Epsilon | Never => Epsilon,
+                ZeroOrMore(inner) | OneOrMore(inner) | Optional(inner) => ZeroOrMore(take(inner)),
+                _ => self,
+            },
+            OneOrMore(inner) => match &mut **inner {
+                Epsilon | Never | ZeroOrMore(_) | OneOrMore(_) => *take(inner),
+                Optional(inner) => ZeroOrMore(take(inner)),
+                _ => self,
+            },
+            Optional(inner) => match &mut **inner {
+                Epsilon | Never => Epsilon,
+                ZeroOrMore(_) | Optional(_) => *take(inner),
+                OneOrMore(inner) => ZeroOrMore(take(inner)),
+                _ => self,
+            },
+            Repeat(inner, min, max) => match (&**inner, *min, *max) {
+                (_, _, 0) | (Epsilon, _, _) | (Never, 0, _) => Epsilon,
+                (Never, _, _) => Never,
+                (_, 1, 1) => *take(inner),
+                _ => self,
+            },
+            AnyOf(trans_list) => {
+                let mut item_list: Vec<T> = Vec::new();
+                for trans in mem::take(trans_list) {
+                    if !item_list.contains(&trans) {
+                        item_list.push(trans);
+                    }
+                }
+                match item_list.len() {
+                    0 => Never,
+                    1 => Plain(item_list.pop().unwrap()),
+                    _ => AnyOf(item_list),
+                }
+            }
+            NoneOf(trans_list) if trans_list.is_empty() => Wildcard,
+            _ => self,
+        }
+    }
+}
+
+impl<T> Re<T>
+where
+    T: PartialEq,
+{
+    /// Equivalent pattern with redundancies removed, applying bottom-up:
+    ///
+    /// * `never` absorbs concatenations and interleavings, and disappears
+    ///   from alternatives, as does `epsilon` from concatenations and
+    ///   interleavings
+    /// * alternatives between equal patterns are merged
+    /// * nested `zero_or_more`, `one_or_more` and `optional` collapse into
+    ///   one, and vanish around `epsilon` and `never`
+    /// * repetitions of `epsilon` or `never`, zero times or exactly once are
+    ///   unfolded
+    /// * `any_of` drops duplicates and becomes `plain` or `never` with one
+    ///   or no item, and `none_of` without exclusion becomes `wildcard`
+    ///
+    /// Groups are kept, so captures are unchanged. Simplifying twice gives
+    /// the same pattern as simplifying once.
+    pub fn simplify(self) -> Self {
+        Self(self.0.simplify())
+    }
+}
+
+// the character with its simple case mappings; mappings to several
+// characters, like 'ß' to "SS", are left out
+fn case_variants(c: char) -> Vec<char> {
+    let mut variant_list = vec![c];
+    let mapped_list: [Vec<_>; 2] = [c.to_lowercase().collect(), c.to_uppercase().collect()];
+    for mapped in mapped_list.iter() {
+        if let [variant] = mapped.as_slice() {
+            if !variant_list.contains(variant) {
+                variant_list.push(*variant);
+            }
+        }
+    }
+    variant_list
+}
+
+impl RePriv<char> {
+    fn case_insensitive(mut self) -> Self {
+        self.make_case_insensitive();
+        self
+    }
+
+    fn make_case_insensitive(&mut self) {
+        let variants = |trans_list: &mut Vec<char>| {
+            let mut variant_list = Vec::new();
+            for variant in trans_list.drain(..).flat_map(case_variants) {
+                if !variant_list.contains(&variant) {
+                    variant_list.push(variant);
+                }
+            }
+            *trans_list = variant_list;
+        };
+        let mut work_list = vec![self];
+        while let Some(re) = work_list.pop() {
+            match re {
+                RePriv::Plain(c) => {
+                    let variant_list = case_variants(*c);
+                    if variant_list.len() > 1 {
+                        *re = RePriv::AnyOf(variant_list);
+                    }
+                }
+                RePriv::ZeroOrMore(inner)
+                | RePriv::OneOrMore(inner)
+                | RePriv::Optional(inner)
+                | RePriv::Repeat(inner, _, _)
+                | RePriv::Group(inner, _) => work_list.push(inner),
+                RePriv::Concat(first, second)
+                | RePriv::Either(first, second)
+                | RePriv::Interleave(first, second) => {
+                    work_list.push(first);
+                    work_list.push(second);
+                }
+                RePriv::NoneOf(trans_list) | RePriv::AnyOf(trans_list) => variants(trans_list),
+                RePriv::Wildcard | RePriv::Epsilon | RePriv::Never | RePriv::Range(_) => {}
+            }
+        }
+    }
+}
+
+impl Re<char> {
+    /// Matches `c` in any case. Only the simple case mappings of `c` are
+    /// considered, so e.g. 'ß' does not match "SS", and no case folding of
+    /// the Unicode standard beyond `char::to_lowercase` and
+    /// `char::to_uppercase` is applied.
+    pub fn plain_ci(c: char) -> Self {
+        Self(RePriv::Plain(c).case_insensitive())
+    }
+
+    /// Make every character of the pattern match in any case, with the
+    /// caveats of `plain_ci`. Ranges are left untouched.
+    pub fn case_insensitive(self) -> Self {
+        Self(self.0.case_insensitive())
+    }
+}
+
+impl fmt::Display for Re<char> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_pattern(|c| escape(*c)))
+    }
+}
+
+impl<T> Re<T>
+where
+    T: Ord,
+{
+    /// Any single transition in `range`.
+    pub fn range(range: RangeInclusive<T>) -> Self {
+        Self(RePriv::Range(TransRange::new(range)))
+    }
 }
 
 impl<T> Re<T>
@@ -107,6 +1044,66 @@ where
     T: Eq + Hash + Clone,
 {
     pub fn compile(self) -> NFAutoBlueprint<u64, T> {
+        self.compile_ref()
+    }
+
+    /// Build the DFA accepting every string over `alphabet` not matched by
+    /// the pattern. The result is a blueprint rather than a pattern, since
+    /// complements cannot be written with the pattern constructors.
+    pub fn not(self, alphabet: impl IntoIterator<Item = T>) -> DFAutoBlueprint<u64, T> {
+        let alphabet: Vec<_> = alphabet.into_iter().collect();
+        complement(&determinize_over(&self.compile(), &alphabet), &alphabet)
+    }
+
+    /// Build the DFA accepting the strings matched by both patterns.
+    pub fn and(self, other: Self) -> DFAutoBlueprint<u64, T> {
+        determinize(&nfa_intersect(&self.compile(), &other.compile()))
+            .unwrap_or_else(|_| unreachable!())
+            .renumbered()
+    }
+
+    /// Shortest input over `alphabet` matched by exactly one of the patterns,
+    /// or `None` if they match the same inputs over `alphabet`.
+    pub fn difference_witness(
+        &self,
+        other: &Self,
+        alphabet: impl IntoIterator<Item = T>,
+    ) -> Option<Vec<T>> {
+        let alphabet: Vec<_> = alphabet.into_iter().collect();
+        dfa_difference_witness(
+            &determinize_over(&self.compile_ref(), &alphabet),
+            &determinize_over(&other.compile_ref(), &alphabet),
+            &alphabet,
+        )
+    }
+
+    /// Whether both patterns match the same inputs over `alphabet`.
+    pub fn equivalent(&self, other: &Self, alphabet: impl IntoIterator<Item = T>) -> bool {
+        self.difference_witness(other, alphabet).is_none()
+    }
+
+    /// Whether every input over `alphabet` matched by `other` is also matched
+    /// by the pattern.
+    pub fn contains(&self, other: &Self, alphabet: impl IntoIterator<Item = T>) -> bool {
+        let alphabet: Vec<_> = alphabet.into_iter().collect();
+        dfa_inclusion_witness(
+            &determinize_over(&self.compile_ref(), &alphabet),
+            &determinize_over(&other.compile_ref(), &alphabet),
+            &alphabet,
+        )
+        .is_none()
+    }
+
+    /// Compile the pattern straight to a DFA, with states numbered from 0
+    /// for the start state as in `DFAutoBlueprint::renumbered`.
+    pub fn compile_dfa(self) -> DFAutoBlueprint<u64, T> {
+        determinize(&self.compile())
+            .unwrap_or_else(|_| unreachable!())
+            .renumbered()
+    }
+
+    /// Same as `compile`, keeping the pattern for later use.
+    pub fn compile_ref(&self) -> NFAutoBlueprint<u64, T> {
         self.0.compile()
     }
 }
@@ -146,4 +1143,529 @@ mod tests {
         assert!(bp.create().test("ababbabc".chars()));
         assert!(!bp.create().test("ababbabd".chars()));
     }
+
+    #[test]
+    fn optional_and_one_or_more() {
+        use crate::auto::Auto;
+
+        // ab?c
+        let bp = Re::concat(
+            Re::plain('a'),
+            Re::concat(Re::optional(Re::plain('b')), Re::plain('c')),
+        )
+        .compile();
+        assert!(bp.create().test("ac".chars()));
+        assert!(bp.create().test("abc".chars()));
+        assert!(!bp.create().test("abbc".chars()));
+
+        // a+b+
+        let bp = Re::concat(
+            Re::one_or_more(Re::plain('a')),
+            Re::one_or_more(Re::plain('b')),
+        )
+        .compile();
+        assert!(!bp.create().test("".chars()));
+        assert!(!bp.create().test("a".chars()));
+        assert!(bp.create().test("aabbb".chars()));
+        assert!(!bp.create().test("abab".chars()));
+    }
+
+    #[test]
+    fn epsilon_and_never() {
+        use crate::auto::Auto;
+
+        let corpus = ["", "a", "b", "ab", "ba", "aab"];
+        let same = |first: Re<char>, second: Re<char>| {
+            let (first, second) = (first.compile(), second.compile());
+            corpus
+                .iter()
+                .all(|s| first.create().test(s.chars()) == second.create().test(s.chars()))
+        };
+        let ab = || Re::concat(Re::plain('a'), Re::plain('b'));
+        assert!(Re::<char>::epsilon().compile().create().test("".chars()));
+        assert!(!Re::epsilon().compile().create().test("a".chars()));
+        assert!(corpus
+            .iter()
+            .all(|s| !Re::<char>::never().compile().create().test(s.chars())));
+        assert!(same(Re::concat(Re::epsilon(), ab()), ab()));
+        assert!(same(Re::concat(ab(), Re::epsilon()), ab()));
+        assert!(same(Re::either(Re::never(), ab()), ab()));
+        assert!(same(Re::concat(Re::never(), ab()), Re::never()));
+        assert!(same(Re::zero_or_more(Re::never()), Re::epsilon()));
+    }
+
+    #[test]
+    fn repeat() {
+        use crate::auto::Auto;
+
+        let count = |bp: &NFAutoBlueprint<u64, char>| {
+            (0..7)
+                .filter(|n| bp.create().test("a".repeat(*n).chars()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(count(&Re::repeat(Re::plain('a'), 3).compile()), vec![3]);
+        assert_eq!(
+            count(&Re::repeat_between(Re::plain('a'), 2, 4).compile()),
+            vec![2, 3, 4]
+        );
+        assert_eq!(
+            count(&Re::repeat_between(Re::plain('a'), 0, 2).compile()),
+            vec![0, 1, 2]
+        );
+        assert_eq!(count(&Re::repeat(Re::plain('a'), 0).compile()), vec![0]);
+        assert_eq!(
+            count(&Re::repeat_between(Re::plain('a'), 0, 0).compile()),
+            vec![0]
+        );
+        // (ab?){2}
+        let bp = Re::repeat(Re::concat(Re::plain('a'), Re::optional(Re::plain('b'))), 2).compile();
+        assert!(bp.create().test("aab".chars()));
+        assert!(bp.create().test("abab".chars()));
+        assert!(bp.create().test("aba".chars()));
+        assert!(!bp.create().test("abb".chars()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn repeat_min_greater_than_max() {
+        Re::repeat_between(Re::plain('a'), 3, 2);
+    }
+
+    #[test]
+    fn any_of_and_literal() {
+        use crate::auto::Auto;
+
+        let class = Re::any_of("abcdef".chars()).compile();
+        let nested = "abcdef"
+            .chars()
+            .map(Re::plain)
+            .reduce(Re::either)
+            .unwrap()
+            .compile();
+        for s in ["", "a", "c", "f", "g", "ab"].iter() {
+            assert_eq!(
+                class.create().test(s.chars()),
+                nested.create().test(s.chars())
+            );
+        }
+        assert_eq!(class.state_set().len(), 2);
+        assert!(nested.state_set().len() > 2);
+        assert!(!Re::any_of(vec![]).compile().create().test("".chars()));
+
+        let bp = Re::concat(Re::literal("abc".chars()), Re::any_of("xy".chars())).compile();
+        assert!(bp.create().test("abcx".chars()));
+        assert!(bp.create().test("abcy".chars()));
+        assert!(!bp.create().test("abx".chars()));
+        assert!(Re::literal("".chars()).compile().create().test("".chars()));
+    }
+
+    #[test]
+    fn range() {
+        use crate::auto::Auto;
+
+        // [0-9]+
+        let bp = Re::one_or_more(Re::range('0'..='9')).compile();
+        assert!(bp.create().test("42".chars()));
+        assert!(bp.create().test("09".chars()));
+        assert!(!bp.create().test("4a2".chars()));
+        assert!(!bp.create().test("".chars()));
+        assert!(!bp.create().test("/".chars()));
+        assert!(!bp.create().test(":".chars()));
+    }
+
+    #[test]
+    fn display() {
+        use crate::algo::determinize;
+        use crate::auto::Auto;
+
+        let re_list = vec![
+            Re::concat(
+                Re::either(Re::plain('a'), Re::plain('b')),
+                Re::zero_or_more(Re::concat(Re::plain('c'), Re::plain('*'))),
+            ),
+            Re::one_or_more(Re::optional(Re::either(
+                Re::literal("ab".chars()),
+                Re::epsilon(),
+            ))),
+            Re::concat(Re::any_of("a]".chars()), Re::none_of("b-".chars())),
+            Re::repeat_between(Re::either(Re::plain('a'), Re::range('b'..='c')), 1, 3),
+            Re::concat(Re::repeat(Re::plain('a'), 1), Re::plain('b')),
+            Re::either(Re::never(), Re::concat(Re::plain('|'), Re::plain('\\'))),
+            Re::concat(
+                Re::group(Re::plain('a') | Re::plain('b'), "x"),
+                Re::plain('c'),
+            )
+            .star(),
+        ];
+        assert_eq!(
+            re_list.iter().map(|re| re.to_string()).collect::<Vec<_>>(),
+            vec![
+                "(a|b)(c\\*)*",
+                "(ab|())?+",
+                "[a\\]][^b\\-]",
+                "(a|[b-c])(a|[b-c])?(a|[b-c])?",
+                "ab",
+                "[]|\\|\\\\",
+                "((?<x>a|b)c)*",
+            ]
+        );
+        let mut corpus = vec![String::new()];
+        for _ in 0..4 {
+            corpus = corpus
+                .iter()
+                .flat_map(|s| "abc*]-|\\".chars().map(move |c| format!("{}{}", s, c)))
+                .chain(corpus.iter().cloned())
+                .collect();
+        }
+        for re in re_list {
+            let parsed = determinize(&Re::parse(&re.to_string()).unwrap().compile()).unwrap();
+            let bp = re.compile();
+            for s in corpus.iter() {
+                assert_eq!(
+                    parsed.create().test(s.chars()),
+                    bp.create().test(s.chars()),
+                    "{}",
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn operators() {
+        use crate::algo::determinize;
+        use crate::auto::Auto;
+
+        let (a, b, c) = (|| Re::plain('a'), || Re::plain('b'), || Re::plain('c'));
+        let pairs = vec![
+            (
+                (a() | b()).star() + c(),
+                Re::concat(Re::zero_or_more(Re::either(a(), b())), c()),
+            ),
+            (
+                a().plus() + b().opt() + (c() | a()),
+                Re::concat(
+                    Re::concat(Re::one_or_more(a()), Re::optional(b())),
+                    Re::either(c(), a()),
+                ),
+            ),
+        ];
+        for (operated, constructed) in pairs {
+            assert_eq!(operated.to_string(), constructed.to_string());
+            let (operated, constructed) = (
+                determinize(&operated.compile()).unwrap(),
+                determinize(&constructed.compile()).unwrap(),
+            );
+            for s in ["", "c", "abc", "aac", "aaba", "ab", "bbc"].iter() {
+                assert_eq!(
+                    operated.create().test(s.chars()),
+                    constructed.create().test(s.chars()),
+                    "{}",
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let re = (Re::plain('a') | Re::any_of("bc".chars())).star()
+            + Re::repeat(Re::range('x'..='z'), 2)
+            + Re::none_of(vec!['a', 'd']);
+        assert_eq!(
+            re.symbols(),
+            vec![&'a', &'b', &'c', &'d', &'x', &'z']
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(re.max_depth(), 5);
+        assert_eq!(Re::<char>::wildcard().max_depth(), 1);
+        assert!(Re::<char>::epsilon().symbols().is_empty());
+    }
+
+    #[test]
+    fn compile_ref() {
+        use crate::auto::Auto;
+
+        let re = (Re::plain('a') | Re::range('b'..='c')).plus() + Re::none_of(vec!['a']);
+        let (first, second) = (re.compile_ref(), re.clone().compile());
+        for s in ["", "a", "ab", "abd", "cba", "bca"].iter() {
+            assert_eq!(
+                first.create().test(s.chars()),
+                second.create().test(s.chars())
+            );
+        }
+        assert_eq!(
+            re,
+            (Re::plain('a') | Re::range('b'..='c')).plus() + Re::none_of(vec!['a'])
+        );
+        assert_ne!(re, Re::plain('a'));
+        assert!(format!("{:?}", Re::plain('a')).contains("Plain"));
+    }
+
+    #[test]
+    fn compile_dfa() {
+        use crate::auto::Auto;
+
+        // (a|b)*.(c|d)
+        let re = (Re::plain('a') | Re::plain('b')).star()
+            + Re::wildcard()
+            + (Re::plain('c') | Re::plain('d'));
+        let dfa = re.clone().compile_dfa();
+        assert!(dfa.create().test("abababb&c".chars()));
+        assert!(dfa.create().test("ababbba?d".chars()));
+        assert!(!dfa.create().test("ababbbe-d".chars()));
+        assert_eq!(dfa, re.compile_dfa());
+
+        let mut state_set = HashSet::new();
+        let mut unresolved = vec![*dfa.start_state()];
+        while let Some(state) = unresolved.pop() {
+            if !state_set.insert(state) {
+                continue;
+            }
+            let connections = dfa.connections_from(&state);
+            unresolved.extend(connections.plain.iter().map(|(_, to)| *to));
+            unresolved.extend(connections.fallback.copied());
+        }
+        let mut state_list: Vec<_> = state_set.into_iter().collect();
+        state_list.sort_unstable();
+        assert_eq!(dfa.start_state(), &0);
+        assert_eq!(state_list, (0..state_list.len() as u64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn simplify() {
+        use crate::algo::determinize;
+        use crate::auto::Auto;
+
+        let a = || Re::plain('a');
+        let table = vec![
+            (Re::either(a(), a()), a()),
+            (Re::concat(Re::epsilon(), a()), a()),
+            (Re::either(Re::never(), a()), a()),
+            (Re::concat(a(), Re::never()), Re::never()),
+            (a().star().star(), a().star()),
+            (a().opt().plus(), a().star()),
+            (Re::repeat_between(a(), 1, 1), a()),
+            (Re::any_of("aa".chars()), a()),
+            (Re::group(Re::epsilon() + a(), "x"), Re::group(a(), "x")),
+        ];
+        for (re, simplified) in table {
+            assert_eq!(re.simplify(), simplified);
+        }
+
+        let re_list = vec![
+            a().star().star(),
+            Re::parse("((a|a)*|b+?)(()|[])c?+").unwrap(),
+            Re::parse("(a?b|a?b)*|([]&a)|.").unwrap(),
+            Re::repeat_between(Re::parse("a*|b").unwrap(), 0, 2) + Re::any_of("bcb".chars()),
+        ];
+        let corpus = [
+            "", "a", "b", "c", "aa", "ab", "bc", "abab", "aac", "bbc", "abc",
+        ];
+        for re in re_list {
+            let simplified = re.clone().simplify();
+            assert_eq!(simplified.clone().simplify(), simplified);
+            assert!(
+                simplified.compile_ref().state_set().len() <= re.compile_ref().state_set().len()
+            );
+            let (original, simplified) = (
+                determinize(&re.compile()).unwrap(),
+                determinize(&simplified.compile()).unwrap(),
+            );
+            for s in corpus.iter() {
+                assert_eq!(
+                    original.create().test(s.chars()),
+                    simplified.create().test(s.chars()),
+                    "{}",
+                    s
+                );
+            }
+        }
+        assert!(
+            a().star().star().simplify().compile().state_set().len()
+                < a().star().star().compile().state_set().len()
+        );
+    }
+
+    #[test]
+    fn equivalent_and_contains() {
+        use crate::auto::Auto;
+
+        let re = |pattern| Re::parse(pattern).unwrap();
+        let equivalent_list = [
+            ("a(a|b)*", "(a+b*)+"),
+            ("(a|b)*", "(a*b*)*"),
+            ("(ab)*a", "a(ba)*"),
+            ("a?a?", "()|a|aa"),
+        ];
+        for (first, second) in equivalent_list.iter() {
+            assert!(re(first).equivalent(&re(second), "ab".chars()), "{}", first);
+        }
+
+        let (first, second) = (re("(a|b)*b"), re("(a|b)*"));
+        assert!(!first.equivalent(&second, "ab".chars()));
+        assert!(second.contains(&first, "ab".chars()));
+        assert!(!first.contains(&second, "ab".chars()));
+        assert_eq!(
+            first.difference_witness(&second, "ab".chars()),
+            Some(vec![])
+        );
+
+        let (first, second) = (re("a(ba)*"), re("(ab)*"));
+        let witness = first.difference_witness(&second, "ab".chars()).unwrap();
+        assert_ne!(
+            first.compile_ref().create().test(witness.iter().copied()),
+            second.compile_ref().create().test(witness.iter().copied())
+        );
+        assert_eq!(witness, vec![]);
+        let witness = first
+            .difference_witness(&re("a(ba)*|()"), "ab".chars())
+            .unwrap();
+        assert_eq!(witness, vec![]);
+        assert_eq!(
+            re("a.").difference_witness(&re("ab"), "ab".chars()),
+            Some(vec!['a', 'a'])
+        );
+    }
+
+    #[test]
+    fn deep_pattern() {
+        use crate::auto::Auto;
+
+        // a small stack makes any recursion over the pattern depth overflow
+        std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                let literal: Vec<_> = (0..100_000).map(|i| i % 7).collect();
+                let re = Re::literal(literal.iter().copied());
+                let bp = re.compile_ref();
+                assert!(bp.create().test(literal.iter().copied()));
+                assert!(!bp.create().test(literal[1..].iter().copied()));
+
+                let cloned = re.clone();
+                assert_eq!(cloned, re);
+                assert_ne!(cloned, Re::literal(literal[1..].iter().copied()));
+                assert_eq!(re.max_depth(), 100_000);
+                let pattern: String = literal.iter().map(|i| i.to_string()).collect();
+                assert_eq!(re.to_pattern(|i| i.to_string()), pattern);
+                assert_eq!(re.clone().simplify(), re);
+                drop(re);
+
+                // nested through the second operand and unary operators
+                let re = (0..100_000).fold(Re::epsilon(), |re, _| Re::plain('a') + re.opt());
+                assert_eq!(re.clone(), re);
+                assert!(re.to_pattern(|c| c.to_string()).starts_with("a(a(a"));
+                let simplified = re.simplify();
+                assert_eq!(simplified.max_depth(), 199_999);
+                let re = simplified.case_insensitive();
+                assert!(re.to_pattern(|c| c.to_string()).starts_with("[aA]([aA]"));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn compile_structure() {
+        let re = Re::parse("([a-f0-9]+|\\.|[^x]?)*(ab|cd)+&(x.y|z)").unwrap();
+        let re = Re::repeat_between(re, 1, 3) + Re::literal("end".chars());
+        let bp = re.compile();
+        let mut count_map = HashMap::new();
+        for (_, conn, _) in bp.iterate_connections() {
+            let kind = match conn {
+                ConnType::Plain(_) => "plain",
+                ConnType::Void => "void",
+                ConnType::Wildcard => "wildcard",
+                ConnType::WildcardExcept(_) => "wildcard_except",
+                ConnType::Range(_) => "range",
+                ConnType::Predicate(_) => "predicate",
+            };
+            *count_map.entry(kind).or_insert(0) += 1;
+        }
+        assert_eq!(bp.state_set().len(), 727);
+        let expected = [
+            ("plain", 393),
+            ("void", 1088),
+            ("wildcard", 90),
+            ("wildcard_except", 24),
+            ("range", 48),
+        ];
+        assert_eq!(count_map, expected.iter().cloned().collect());
+    }
+
+    #[test]
+    fn not() {
+        use crate::auto::Auto;
+
+        let not = Re::parse("(a|b)*c").unwrap().not("abc".chars());
+        assert!(not.create().test("ab".chars()));
+        assert!(not.create().test("".chars()));
+        assert!(not.create().test("abcc".chars()));
+        assert!(!not.create().test("abc".chars()));
+        assert!(!not.create().test("c".chars()));
+        assert!(!not.create().test("d".chars()));
+    }
+
+    #[test]
+    fn and() {
+        use crate::auto::Auto;
+
+        let and = Re::parse("a(a|b)*")
+            .unwrap()
+            .and(Re::parse("(a|b)*b").unwrap());
+        assert!(and.create().test("ab".chars()));
+        assert!(and.create().test("aab".chars()));
+        assert!(!and.create().test("a".chars()));
+        assert!(!and.create().test("ba".chars()));
+
+        let and = Re::parse("[a-z]+")
+            .unwrap()
+            .and(Re::parse("[^x]*").unwrap());
+        assert!(and.create().test("abc".chars()));
+        assert!(!and.create().test("axc".chars()));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        use crate::auto::Auto;
+
+        let bp = Re::parse("select_[^x]")
+            .unwrap()
+            .case_insensitive()
+            .compile();
+        for s in ["select_a", "SELECT_a", "Select_b", "sElEcT_c"].iter() {
+            assert!(bp.create().test(s.chars()), "{}", s);
+        }
+        for s in ["select-a", "selec_a", "select_x", "select_X"].iter() {
+            assert!(!bp.create().test(s.chars()), "{}", s);
+        }
+        assert_eq!(Re::plain_ci('1'), Re::plain('1'));
+        assert_eq!(Re::plain_ci('a'), Re::any_of(vec!['a', 'A']));
+        assert_eq!(Re::plain_ci('ß'), Re::plain('ß'));
+    }
+
+    #[test]
+    fn interleave() {
+        use crate::auto::Auto;
+
+        let re = Re::interleave(Re::literal("ab".chars()), Re::literal("cd".chars()));
+        assert_eq!(re.to_string(), "ab&cd");
+        let bp = re.compile();
+        for s in ["acbd", "cabd", "abcd", "cdab", "acdb"].iter() {
+            assert!(bp.create().test(s.chars()), "{}", s);
+        }
+        for s in ["adbc", "bacd", "abc", "abcdd", "abce"].iter() {
+            assert!(!bp.create().test(s.chars()), "{}", s);
+        }
+
+        // (ab)*&c
+        let bp = Re::parse("(ab)*&c").unwrap().compile();
+        for s in ["c", "abc", "acb", "cabab", "abacb"].iter() {
+            assert!(bp.create().test(s.chars()), "{}", s);
+        }
+        for s in ["", "ab", "acc", "bac"].iter() {
+            assert!(!bp.create().test(s.chars()), "{}", s);
+        }
+    }
 }
@@ -0,0 +1,435 @@
+use crate::dfa::DFAutoBlueprint;
+use crate::range::TransRange;
+use rand::distributions::uniform::SampleUniform;
+use rand::distributions::{Distribution, Standard};
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// number of random transitions drawn when looking for one taking a
+// fallback, first to find out whether the fallback can be taken at all and
+// then for every step taking it
+const FALLBACK_SEARCH_COUNT: usize = 4096;
+const FALLBACK_TRY_COUNT: usize = 64;
+
+// targets of the ranges with a bound to take when no draw falls within them,
+// leaving out ranges excluding both of their bounds
+fn sampled_ranges<S, T>(range_list: &[(TransRange<T>, S)]) -> impl Iterator<Item = &S> {
+    range_list
+        .iter()
+        .filter(|(range, _)| range.contains(range.start()) || range.contains(range.end()))
+        .map(|(_, to)| to)
+}
+
+// a transition within `range` among the ones drawn by `draw`, if any within
+// `try_count` draws
+fn draw_in_range<T, R>(
+    range: &TransRange<T>,
+    rng: &mut R,
+    mut draw: impl FnMut(&mut R) -> Option<T>,
+    try_count: usize,
+) -> Option<T> {
+    (0..try_count)
+        .filter_map(|_| draw(rng))
+        .find(|trans| range.contains(trans))
+}
+
+// draws a transition uniformly within the range if any, or from `Standard`
+// for fallbacks
+fn draw_uniform<T, R>(rng: &mut R, range: Option<&TransRange<T>>) -> Option<T>
+where
+    T: SampleUniform + PartialOrd + Clone,
+    Standard: Distribution<T>,
+    R: Rng,
+{
+    match range {
+        None => Some(rng.gen()),
+        // excluded bounds are drawn again
+        Some(range) => draw_in_range(
+            range,
+            rng,
+            |rng| Some(rng.gen_range(range.start().clone()..=range.end().clone())),
+            FALLBACK_TRY_COUNT,
+        ),
+    }
+}
+
+// draws with `draw_fallback` for fallbacks, and keeps the draws within the
+// range if any
+fn draw_within<T, R>(
+    mut draw_fallback: impl FnMut(&mut R) -> Option<T>,
+) -> impl FnMut(&mut R, Option<&TransRange<T>>) -> Option<T> {
+    move |rng, range| match range {
+        None => draw_fallback(rng),
+        Some(range) => draw_in_range(range, rng, &mut draw_fallback, FALLBACK_TRY_COUNT),
+    }
+}
+
+// least number of transitions to an accept state for the states reaching one
+// from the start state, and a transition taking the fallback of every state
+// whose fallback can be taken
+struct Walk<'b, S, T> {
+    distance_map: HashMap<&'b S, usize>,
+    fallback_map: HashMap<&'b S, T>,
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq + Clone + SampleUniform + PartialOrd,
+    Standard: Distribution<T>,
+{
+    /// Random input accepted by the DFA and not longer than `max_len`, or
+    /// `None` if there is none. The walk only takes transitions from which an
+    /// accept state can still be reached in time. Range transitions take a
+    /// transition drawn uniformly within the range, and fallbacks take a
+    /// transition drawn from `Standard` matching no other connection, so
+    /// `T` needs both distributions; see `sample_accepted_with` otherwise.
+    pub fn sample_accepted(&self, rng: &mut impl Rng, max_len: usize) -> Option<Vec<T>> {
+        let walk = self.walk(rng, &mut draw_uniform);
+        self.sample_with(&walk, rng, max_len, &mut draw_uniform)
+    }
+
+    /// Endless samples of `sample_accepted`, stopping only when it gives
+    /// `None`.
+    pub fn sample_iter<'a, R>(&'a self, rng: R, max_len: usize) -> impl Iterator<Item = Vec<T>> + 'a
+    where
+        R: Rng + 'a,
+    {
+        self.sample_iter_by(rng, max_len, draw_uniform)
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq + Clone,
+{
+    /// Same as `sample_accepted`, with fallbacks and range transitions taking
+    /// the transitions drawn by `draw_fallback` instead. A fallback is never
+    /// taken if no transition drawn matches it, and a range transition takes
+    /// one of the bounds of the range if no transition drawn is within it, so
+    /// `draw_fallback` may always return `None` for blueprints without
+    /// fallback.
+    pub fn sample_accepted_with<R>(
+        &self,
+        rng: &mut R,
+        max_len: usize,
+        draw_fallback: impl FnMut(&mut R) -> Option<T>,
+    ) -> Option<Vec<T>>
+    where
+        R: Rng,
+    {
+        let mut draw = draw_within(draw_fallback);
+        let walk = self.walk(rng, &mut draw);
+        self.sample_with(&walk, rng, max_len, &mut draw)
+    }
+
+    /// Same as `sample_iter`, with fallbacks taking the transitions drawn by
+    /// `draw_fallback`, see `sample_accepted_with`.
+    pub fn sample_iter_with<'a, R>(
+        &'a self,
+        rng: R,
+        max_len: usize,
+        draw_fallback: impl FnMut(&mut R) -> Option<T> + 'a,
+    ) -> impl Iterator<Item = Vec<T>> + 'a
+    where
+        R: Rng + 'a,
+    {
+        self.sample_iter_by(rng, max_len, draw_within(draw_fallback))
+    }
+
+    // `draw` gives a transition within the range it is passed, or any
+    // transition for fallbacks when passed none
+    fn sample_iter_by<'a, R>(
+        &'a self,
+        mut rng: R,
+        max_len: usize,
+        mut draw: impl FnMut(&mut R, Option<&TransRange<T>>) -> Option<T> + 'a,
+    ) -> impl Iterator<Item = Vec<T>> + 'a
+    where
+        R: Rng + 'a,
+    {
+        let walk = self.walk(&mut rng, &mut draw);
+        std::iter::from_fn(move || self.sample_with(&walk, &mut rng, max_len, &mut draw))
+    }
+
+    // a transition drawn by `draw` taking the fallback of `state` to `to`, if
+    // any within `try_count` draws
+    fn draw_fallback_trans<R>(
+        &self,
+        state: &S,
+        to: &S,
+        rng: &mut R,
+        draw: &mut impl FnMut(&mut R, Option<&TransRange<T>>) -> Option<T>,
+        try_count: usize,
+    ) -> Option<T> {
+        (0..try_count)
+            .filter_map(|_| draw(rng, None))
+            .find(|trans| self.next_state(state, trans) == Some(to))
+    }
+
+    fn walk<R>(
+        &self,
+        rng: &mut R,
+        draw: &mut impl FnMut(&mut R, Option<&TransRange<T>>) -> Option<T>,
+    ) -> Walk<'_, S, T> {
+        let mut reverse_graph: HashMap<_, Vec<_>> = HashMap::new();
+        let mut fallback_map = HashMap::new();
+        let mut unresolved = vec![self.start_state()];
+        let mut seen_list = Vec::new();
+        while let Some(state) = unresolved.pop() {
+            if reverse_graph.contains_key(state) {
+                continue;
+            }
+            reverse_graph.insert(state, Vec::new());
+            seen_list.push(state);
+            let connections = self.connections_from(state);
+            unresolved.extend(connections.plain.iter().map(|(_, to)| to));
+            unresolved.extend(sampled_ranges(connections.range));
+            if let Some(to) = connections.fallback {
+                let trans = self.draw_fallback_trans(state, to, rng, draw, FALLBACK_SEARCH_COUNT);
+                if let Some(trans) = trans {
+                    fallback_map.insert(state, trans);
+                    unresolved.push(to);
+                }
+            }
+        }
+        for from in seen_list {
+            let connections = self.connections_from(from);
+            let to_list = connections
+                .plain
+                .iter()
+                .map(|(_, to)| to)
+                .chain(sampled_ranges(connections.range))
+                .chain(
+                    connections
+                        .fallback
+                        .filter(|_| fallback_map.contains_key(from)),
+                );
+            for to in to_list {
+                reverse_graph.get_mut(to).unwrap().push(from);
+            }
+        }
+
+        let mut distance_map = HashMap::new();
+        let mut layer: Vec<_> = reverse_graph
+            .keys()
+            .copied()
+            .filter(|state| self.accept_state_set().contains(state))
+            .collect();
+        let mut distance = 0;
+        while !layer.is_empty() {
+            let mut next_layer = Vec::new();
+            for state in layer {
+                if distance_map.contains_key(state) {
+                    continue;
+                }
+                distance_map.insert(state, distance);
+                next_layer.extend(reverse_graph[state].iter().copied());
+            }
+            layer = next_layer;
+            distance += 1;
+        }
+        Walk {
+            distance_map,
+            fallback_map,
+        }
+    }
+
+    fn sample_with<R>(
+        &self,
+        walk: &Walk<'_, S, T>,
+        rng: &mut R,
+        max_len: usize,
+        draw: &mut impl FnMut(&mut R, Option<&TransRange<T>>) -> Option<T>,
+    ) -> Option<Vec<T>>
+    where
+        R: Rng,
+    {
+        let distance_map = &walk.distance_map;
+        let mut state = self.start_state();
+        if distance_map
+            .get(state)
+            .is_none_or(|distance| *distance > max_len)
+        {
+            return None;
+        }
+        let mut input = Vec::new();
+        loop {
+            let remaining = max_len - input.len();
+            // `None` stands for stopping at an accept state
+            let mut choice_list = Vec::new();
+            if self.accept_state_set().contains(state) {
+                choice_list.push(None);
+            }
+            let in_time = |to: &S| {
+                distance_map
+                    .get(to)
+                    .is_some_and(|distance| *distance < remaining)
+            };
+            let connections = self.connections_from(state);
+            for (trans, to) in connections.plain.iter() {
+                if in_time(to) {
+                    choice_list.push(Some((trans.clone(), to)));
+                }
+            }
+            for (range, to) in connections.range.iter() {
+                if !in_time(to) {
+                    continue;
+                }
+                let trans = draw(rng, Some(range)).or_else(|| {
+                    // one of the bounds when no draw is within the range
+                    let bound_list: Vec<_> = vec![range.start(), range.end()]
+                        .into_iter()
+                        .filter(|bound| range.contains(bound))
+                        .collect();
+                    if bound_list.is_empty() {
+                        None
+                    } else {
+                        Some(bound_list[rng.gen_range(0..bound_list.len())].clone())
+                    }
+                });
+                if let Some(trans) = trans {
+                    choice_list.push(Some((trans, to)));
+                }
+            }
+            if let Some(known) = walk.fallback_map.get(state) {
+                let to = connections.fallback.unwrap();
+                if in_time(to) {
+                    // the transition found by `walk` when fresh draws miss
+                    let trans = self
+                        .draw_fallback_trans(state, to, rng, draw, FALLBACK_TRY_COUNT)
+                        .unwrap_or_else(|| known.clone());
+                    choice_list.push(Some((trans, to)));
+                }
+            }
+            // never empty, as `state` is an accept state or in time for one
+            let index = rng.gen_range(0..choice_list.len());
+            match choice_list.swap_remove(index) {
+                None => return Some(input),
+                Some((trans, to)) => {
+                    input.push(trans);
+                    state = to;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algo::determinize;
+    use crate::auto::Auto;
+    use crate::dfa::DFAutoBuilder;
+    use crate::re::Re;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn accepted_samples() {
+        let pattern_list = ["(a|b)*c", "x[0-9]+y?", ".(c|d)", "(ab|cd)*"];
+        let mut rng = StdRng::seed_from_u64(7);
+        for pattern in pattern_list.iter() {
+            let dfa = determinize(&Re::parse(pattern).unwrap().compile()).unwrap();
+            for _ in 0..100 {
+                let input = dfa.sample_accepted(&mut rng, 8).unwrap();
+                assert!(input.len() <= 8, "{} {:?}", pattern, input);
+                assert!(
+                    dfa.create().test(input.iter().cloned()),
+                    "{} {:?}",
+                    pattern,
+                    input
+                );
+            }
+        }
+
+        let dfa = determinize(&Re::parse("abcd").unwrap().compile()).unwrap();
+        assert_eq!(dfa.sample_accepted(&mut rng, 3), None);
+        let dfa = determinize(&(Re::plain('a') + Re::never()).compile()).unwrap();
+        assert_eq!(dfa.sample_accepted(&mut rng, 8), None);
+    }
+
+    #[test]
+    fn sample_both_branches() {
+        let dfa = determinize(&Re::parse("ab*|c").unwrap().compile()).unwrap();
+        let sample_list: Vec<_> = dfa
+            .sample_iter(StdRng::seed_from_u64(7), 5)
+            .take(100)
+            .collect();
+        assert_eq!(sample_list.len(), 100);
+        assert!(sample_list.iter().any(|input| input[0] == 'a'));
+        assert!(sample_list.iter().any(|input| input[0] == 'c'));
+        assert!(sample_list.iter().any(|input| input.len() > 2));
+    }
+
+    #[test]
+    fn range_samples() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect_range(0, 'a'..='z', 1)
+            .accept(1)
+            .finalize();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut sampled_set = std::collections::HashSet::new();
+        for _ in 0..200 {
+            let input = dfa.sample_accepted(&mut rng, 1).unwrap();
+            assert!(input[0].is_ascii_lowercase());
+            sampled_set.insert(input[0]);
+        }
+        assert!(sampled_set.len() > 20);
+
+        // draws of `draw_fallback` within the range are taken
+        let sample_list: Vec<_> = dfa
+            .sample_iter_with(StdRng::seed_from_u64(7), 1, |rng| Some(rng.gen::<char>()))
+            .take(20)
+            .collect();
+        assert!(sample_list
+            .iter()
+            .all(|input| input[0].is_ascii_lowercase()));
+        let sample_list: Vec<_> = dfa
+            .sample_iter_with(StdRng::seed_from_u64(7), 1, |rng| {
+                Some(*['m', '0'].choose(rng).unwrap())
+            })
+            .take(20)
+            .collect();
+        assert!(sample_list.iter().all(|input| input == &['m']));
+        // bounds otherwise
+        let sample_list: Vec<_> = dfa
+            .sample_iter_with(StdRng::seed_from_u64(7), 1, |_| None)
+            .take(20)
+            .collect();
+        assert!(sample_list
+            .iter()
+            .all(|input| input == &['a'] || input == &['z']));
+    }
+
+    #[test]
+    fn narrow_fallback() {
+        // fallback taken by 251 to 255 only
+        let dfa = DFAutoBuilder::start(0u8)
+            .connect_range(0, 0..=250u8, 2)
+            .connect_fallback(0, 1)
+            .accept(1)
+            .finalize();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let input = dfa.sample_accepted(&mut rng, 3).unwrap();
+            assert_eq!(input.len(), 1);
+            assert!(input[0] > 250);
+        }
+
+        // fallbacks are not taken without transitions to draw
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, "go", 1)
+            .connect_fallback(0, 2)
+            .accept(1)
+            .accept(2)
+            .finalize();
+        let sample_list: Vec<_> = dfa
+            .sample_iter_with(StdRng::seed_from_u64(7), 3, |_| None)
+            .take(20)
+            .collect();
+        assert!(sample_list.iter().all(|input| input == &["go"]));
+    }
+}
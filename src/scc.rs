@@ -0,0 +1,306 @@
+use crate::dfa::DFAutoBlueprint;
+use crate::nfa::NFAutoBlueprint;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Strongly connected components of the states reachable from the start
+    /// state, through plain, range and fallback connections. Components are
+    /// listed after every component they lead to.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<&S>> {
+        tarjan(vec![self.start_state()], |state| self.successors(state))
+    }
+
+    /// Reachable states lying on a cycle.
+    pub fn cyclic_states(&self) -> HashSet<&S> {
+        cyclic_states(&self.strongly_connected_components(), |state| {
+            self.successors(state)
+        })
+    }
+
+    /// Whether a cycle lies on the way to an accept state, that is whether
+    /// infinitely many inputs are accepted.
+    pub fn has_accepting_cycle(&self) -> bool {
+        has_accepting_cycle(
+            &self.strongly_connected_components(),
+            |state| self.successors(state),
+            |state| self.successors(state),
+            |state| self.accept_state_set().contains(state),
+        )
+    }
+
+    fn successors(&self, state: &S) -> Vec<&S> {
+        let connections = self.connections_from(state);
+        connections
+            .plain
+            .iter()
+            .map(|(_, to)| to)
+            .chain(connections.range.iter().map(|(_, to)| to))
+            .chain(connections.fallback)
+            .collect()
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq,
+    T: Hash + Eq,
+{
+    /// Strongly connected components of the states reachable from the start
+    /// states, through connections of every kind. Components are listed after
+    /// every component they lead to.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<&S>> {
+        tarjan(self.start_state_set(), |state| self.successors(state, true))
+    }
+
+    /// Reachable states lying on a cycle, void connections included.
+    pub fn cyclic_states(&self) -> HashSet<&S> {
+        cyclic_states(&self.strongly_connected_components(), |state| {
+            self.successors(state, true)
+        })
+    }
+
+    /// Whether a cycle taking at least one transition lies on the way to an
+    /// accept state, that is whether infinitely many inputs are accepted.
+    /// Cycles of void connections only do not count.
+    pub fn has_accepting_cycle(&self) -> bool {
+        has_accepting_cycle(
+            &self.strongly_connected_components(),
+            |state| self.successors(state, true),
+            |state| self.successors(state, false),
+            |state| self.accept_state_set().contains(state),
+        )
+    }
+
+    // targets of the connections of `state`, through void connections only if
+    // `void` is set
+    fn successors(&self, state: &S, void: bool) -> Vec<&S> {
+        let connections = self.connections_from(state);
+        let mut successor_list: Vec<_> = connections
+            .plain
+            .iter()
+            .flat_map(|(_, to_set)| to_set)
+            .chain(connections.wildcard.iter())
+            .chain(connections.wildcard_except.iter().map(|(to, _)| to))
+            .chain(connections.range.iter().map(|(_, to)| to))
+            .chain(connections.predicate.iter().map(|(_, to)| to))
+            .collect();
+        if void {
+            successor_list.extend(connections.void.iter());
+        }
+        successor_list
+    }
+}
+
+// strongly connected components of the graph reachable from `root_list`,
+// listed after the components they lead to, found by Tarjan's algorithm
+// without recursion
+fn tarjan<'a, N, F>(root_list: impl IntoIterator<Item = &'a N>, successors: F) -> Vec<Vec<&'a N>>
+where
+    N: Hash + Eq,
+    F: Fn(&'a N) -> Vec<&'a N>,
+{
+    let mut search = Tarjan {
+        successors,
+        index_map: HashMap::new(),
+        low_map: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        call_stack: Vec::new(),
+        component_list: Vec::new(),
+    };
+    for root in root_list {
+        if !search.index_map.contains_key(root) {
+            search.run(root);
+        }
+    }
+    search.component_list
+}
+
+struct Tarjan<'a, N, F> {
+    successors: F,
+    index_map: HashMap<&'a N, usize>,
+    low_map: HashMap<&'a N, usize>,
+    stack: Vec<&'a N>,
+    on_stack: HashSet<&'a N>,
+    // nodes being visited, with their successors and how many of them were
+    // visited
+    call_stack: Vec<(&'a N, Vec<&'a N>, usize)>,
+    component_list: Vec<Vec<&'a N>>,
+}
+
+impl<'a, N, F> Tarjan<'a, N, F>
+where
+    N: Hash + Eq,
+    F: Fn(&'a N) -> Vec<&'a N>,
+{
+    fn visit(&mut self, node: &'a N) {
+        let index = self.index_map.len();
+        self.index_map.insert(node, index);
+        self.low_map.insert(node, index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+        self.call_stack.push((node, (self.successors)(node), 0));
+    }
+
+    fn lower(&mut self, node: &'a N, low: usize) {
+        let node_low = self.low_map.get_mut(node).unwrap();
+        *node_low = (*node_low).min(low);
+    }
+
+    fn run(&mut self, root: &'a N) {
+        self.visit(root);
+        while let Some((node, successor_list, visited_count)) = self.call_stack.last_mut() {
+            let node = *node;
+            if let Some(next) = successor_list.get(*visited_count).copied() {
+                *visited_count += 1;
+                if !self.index_map.contains_key(next) {
+                    self.visit(next);
+                } else if self.on_stack.contains(next) {
+                    self.lower(node, self.index_map[next]);
+                }
+                continue;
+            }
+            self.call_stack.pop();
+            if let Some((parent, _, _)) = self.call_stack.last() {
+                self.lower(parent, self.low_map[node]);
+            }
+            if self.low_map[node] == self.index_map[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.component_list.push(component);
+            }
+        }
+    }
+}
+
+fn cyclic_states<'a, N, F>(component_list: &[Vec<&'a N>], successors: F) -> HashSet<&'a N>
+where
+    N: Hash + Eq,
+    F: Fn(&'a N) -> Vec<&'a N>,
+{
+    component_list
+        .iter()
+        .filter(|component| component.len() > 1 || successors(component[0]).contains(&component[0]))
+        .flatten()
+        .copied()
+        .collect()
+}
+
+// whether a component with a `consuming` connection inside it leads to an
+// accept state, relying on components being listed after those they lead to
+fn has_accepting_cycle<'a, N, F, G, A>(
+    component_list: &[Vec<&'a N>],
+    successors: F,
+    consuming_successors: G,
+    accepting: A,
+) -> bool
+where
+    N: Hash + Eq,
+    F: Fn(&'a N) -> Vec<&'a N>,
+    G: Fn(&'a N) -> Vec<&'a N>,
+    A: Fn(&N) -> bool,
+{
+    let mut productive_set = HashSet::new();
+    for component in component_list {
+        let productive = component.iter().any(|state| accepting(state))
+            || component.iter().any(|state| {
+                successors(state)
+                    .iter()
+                    .any(|to| productive_set.contains(to))
+            });
+        if !productive {
+            continue;
+        }
+        let member_set: HashSet<_> = component.iter().copied().collect();
+        if component.iter().any(|state| {
+            consuming_successors(state)
+                .iter()
+                .any(|to| member_set.contains(to))
+        }) {
+            return true;
+        }
+        productive_set.extend(component.iter().copied());
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algo::{determinize, minimize_brzozowski, strongly_connected_components};
+    use crate::dfa::DFAutoBuilder;
+    use crate::re::Re;
+
+    #[test]
+    fn trie_components() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'b', 2)
+            .connect(1, 'c', 3)
+            .connect(0, 'b', 4)
+            .accept(2)
+            .accept(3)
+            .accept(4)
+            .finalize();
+        let component_list = strongly_connected_components(&dfa);
+        assert_eq!(component_list.len(), 5);
+        assert!(component_list.iter().all(|component| component.len() == 1));
+        // the start state leads to every other state
+        assert_eq!(component_list.last().unwrap(), &vec![&0]);
+        assert!(dfa.cyclic_states().is_empty());
+        assert!(!dfa.has_accepting_cycle());
+    }
+
+    #[test]
+    fn loop_components() {
+        let nfa = Re::parse("(ab)*").unwrap().compile();
+        let dfa = determinize(&nfa).unwrap();
+        assert!(dfa
+            .strongly_connected_components()
+            .iter()
+            .any(|component| component.len() >= 2));
+        assert!(dfa.has_accepting_cycle());
+        assert!(nfa.has_accepting_cycle());
+        assert!(!nfa.cyclic_states().is_empty());
+
+        let dfa = minimize_brzozowski(&dfa);
+        let start_component = dfa
+            .strongly_connected_components()
+            .into_iter()
+            .find(|component| component.contains(&dfa.start_state()))
+            .unwrap();
+        assert_eq!(start_component.len(), 2);
+        assert!(dfa.cyclic_states().contains(dfa.start_state()));
+        assert!(dfa.has_accepting_cycle());
+
+        // the loop cannot reach the accept state
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'a', 1)
+            .connect(1, 'a', 1)
+            .connect(0, 'b', 2)
+            .accept(2)
+            .finalize();
+        assert_eq!(
+            dfa.cyclic_states().into_iter().collect::<Vec<_>>(),
+            vec![&1]
+        );
+        assert!(!dfa.has_accepting_cycle());
+
+        // a cycle of void connections only
+        let nfa = Re::parse("(a?)*").unwrap().compile();
+        assert!(nfa.has_accepting_cycle());
+        let nfa = Re::parse("(()*)*b").unwrap().compile();
+        assert!(!nfa.cyclic_states().is_empty());
+        assert!(!nfa.has_accepting_cycle());
+    }
+}
@@ -0,0 +1,905 @@
+use crate::nfa::{ConnType, NFAutoBlueprint, NFAutoBuilder};
+use crate::range::TransRange;
+use crate::re::{Re, RePriv};
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+// version written in serialized blueprints, and the latest one read
+const FORMAT_VERSION: u32 = 1;
+
+// serialized shape of an NFA blueprint, with every list sorted so that the
+// output is stable; each kind of connection has its own optional section
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(
+    deny_unknown_fields,
+    bound(deserialize = "S: Deserialize<'de>, T: Deserialize<'de>")
+)]
+struct NfaRepr<S, T> {
+    version: u32,
+    states: Vec<S>,
+    start: S,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra_start: Vec<S>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    accept: Vec<S>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    accept_tags: Vec<TagRepr<S>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    plain: Vec<PlainRepr<S, T>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    void: Vec<EdgeRepr<S>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    wildcard: Vec<EdgeRepr<S>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    wildcard_except: Vec<ExceptRepr<S, T>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    range: Vec<RangeRepr<S, T>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+struct TagRepr<S> {
+    state: S,
+    tags: Vec<usize>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+struct EdgeRepr<S> {
+    from: S,
+    to: S,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+struct PlainRepr<S, T> {
+    from: S,
+    trans: T,
+    to: S,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+struct ExceptRepr<S, T> {
+    from: S,
+    excluded: Vec<T>,
+    to: S,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+struct RangeRepr<S, T> {
+    from: S,
+    start: T,
+    end: T,
+    #[serde(default = "inclusive", skip_serializing_if = "is_inclusive")]
+    start_inclusive: bool,
+    #[serde(default = "inclusive", skip_serializing_if = "is_inclusive")]
+    end_inclusive: bool,
+    to: S,
+}
+
+fn inclusive() -> bool {
+    true
+}
+
+fn is_inclusive(inclusive: &bool) -> bool {
+    *inclusive
+}
+
+/// Serialized as a map with a `version` (currently 1), every state in
+/// `states`, the `start` state, and the optional sections `extra_start`,
+/// `accept`, `accept_tags` (`{ "state", "tags" }`), then one section per kind
+/// of connection: `plain` (`{ "from", "trans", "to" }`), `void` and
+/// `wildcard` (`{ "from", "to" }`), `wildcard_except`
+/// (`{ "from", "excluded", "to" }`) and `range`
+/// (`{ "from", "start", "end", "to" }`, with `start_inclusive` and
+/// `end_inclusive` only if false). Empty sections are left out, and every
+/// list is sorted. Predicate connections cannot be serialized.
+impl<S, T> Serialize for NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Clone + Serialize,
+    T: Hash + Eq + Ord + Clone + Serialize,
+{
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let mut repr = NfaRepr {
+            version: FORMAT_VERSION,
+            states: self.state_set().into_iter().cloned().collect(),
+            start: self.start_state().clone(),
+            extra_start: self.start_state_set().skip(1).cloned().collect(),
+            accept: self.accept_state_set().iter().cloned().collect(),
+            accept_tags: Vec::new(),
+            plain: Vec::new(),
+            void: Vec::new(),
+            wildcard: Vec::new(),
+            wildcard_except: Vec::new(),
+            range: Vec::new(),
+        };
+        for state in self.accept_state_set() {
+            let mut tags: Vec<_> = self.accept_tags(state).iter().cloned().collect();
+            if !tags.is_empty() {
+                tags.sort_unstable();
+                repr.accept_tags.push(TagRepr {
+                    state: state.clone(),
+                    tags,
+                });
+            }
+        }
+        for (from, conn, to) in self.iterate_connections() {
+            let (from, to) = (from.clone(), to.clone());
+            match conn {
+                ConnType::Plain(trans) => repr.plain.push(PlainRepr {
+                    from,
+                    trans: trans.clone(),
+                    to,
+                }),
+                ConnType::Void => repr.void.push(EdgeRepr { from, to }),
+                ConnType::Wildcard => repr.wildcard.push(EdgeRepr { from, to }),
+                ConnType::WildcardExcept(excluded) => {
+                    let mut excluded: Vec<_> = excluded.iter().cloned().collect();
+                    excluded.sort_unstable();
+                    repr.wildcard_except.push(ExceptRepr { from, excluded, to })
+                }
+                ConnType::Range(range) => repr.range.push(RangeRepr {
+                    from,
+                    start: range.start().clone(),
+                    end: range.end().clone(),
+                    start_inclusive: range.contains(range.start()),
+                    end_inclusive: range.contains(range.end()),
+                    to,
+                }),
+                ConnType::Predicate(_) => {
+                    return Err(ser::Error::custom(
+                        "predicate connections cannot be serialized",
+                    ))
+                }
+            }
+        }
+        repr.states.sort_unstable();
+        repr.extra_start.sort_unstable();
+        repr.accept.sort_unstable();
+        repr.accept_tags.sort_unstable();
+        repr.plain.sort_unstable();
+        repr.void.sort_unstable();
+        repr.wildcard.sort_unstable();
+        repr.wildcard_except.sort_unstable();
+        repr.range.sort_unstable();
+        repr.serialize(serializer)
+    }
+}
+
+/// Reads the shape written by `Serialize`, failing on a newer version, an
+/// unknown section, or a state missing from `states`.
+impl<'de, S, T> Deserialize<'de> for NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone + Deserialize<'de>,
+    T: Hash + Eq + Ord + Clone + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = NfaRepr::<S, T>::deserialize(deserializer)?;
+        if repr.version > FORMAT_VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported version {}",
+                repr.version
+            )));
+        }
+        let state_set: HashSet<_> = repr.states.iter().collect();
+        let referenced = std::iter::once(&repr.start)
+            .chain(&repr.extra_start)
+            .chain(&repr.accept)
+            .chain(repr.accept_tags.iter().map(|tag| &tag.state))
+            .chain(
+                repr.plain
+                    .iter()
+                    .flat_map(|edge| vec![&edge.from, &edge.to]),
+            )
+            .chain(repr.void.iter().flat_map(|edge| vec![&edge.from, &edge.to]))
+            .chain(
+                repr.wildcard
+                    .iter()
+                    .flat_map(|edge| vec![&edge.from, &edge.to]),
+            )
+            .chain(
+                repr.wildcard_except
+                    .iter()
+                    .flat_map(|edge| vec![&edge.from, &edge.to]),
+            )
+            .chain(
+                repr.range
+                    .iter()
+                    .flat_map(|edge| vec![&edge.from, &edge.to]),
+            );
+        for state in referenced {
+            if !state_set.contains(state) {
+                return Err(de::Error::custom("state missing from `states`"));
+            }
+        }
+
+        let mut builder = NFAutoBuilder::start(repr.start);
+        for state in repr.extra_start {
+            builder = builder.also_start(state);
+        }
+        for state in repr.accept {
+            builder = builder.accept(state);
+        }
+        for tag in repr.accept_tags {
+            for index in tag.tags {
+                builder = builder.accept_with(tag.state.clone(), index);
+            }
+        }
+        for edge in repr.plain {
+            builder = builder.connect(edge.from, edge.trans, edge.to);
+        }
+        for edge in repr.void {
+            builder = builder.connect_void(edge.from, edge.to);
+        }
+        for edge in repr.wildcard {
+            builder = builder.connect_wildcard(edge.from, edge.to);
+        }
+        for edge in repr.wildcard_except {
+            builder = builder.connect_wildcard_except(edge.from, edge.excluded, edge.to);
+        }
+        for edge in repr.range {
+            let range = TransRange::with_cmp(
+                edge.start,
+                edge.start_inclusive,
+                edge.end,
+                edge.end_inclusive,
+                T::cmp,
+            );
+            builder = builder.connect_trans_range(edge.from, range, edge.to);
+        }
+        Ok(builder.finalize())
+    }
+}
+
+// default nesting depth allowed when deserializing a pattern, as deep as the
+// parentheses allowed by `Re::parse`, keeping the stack used by the
+// deserializer within the stack of a thread even in debug builds
+const DEFAULT_MAX_DEPTH: usize = 256;
+
+// borrowed shape of a pattern, externally tagged by its variant
+#[derive(serde::Serialize)]
+#[serde(rename = "Re", rename_all = "snake_case")]
+enum ReRef<'r, T> {
+    Plain(&'r T),
+    ZeroOrMore(&'r RePriv<T>),
+    OneOrMore(&'r RePriv<T>),
+    Optional(&'r RePriv<T>),
+    Concat(Vec<&'r RePriv<T>>),
+    Either(Vec<&'r RePriv<T>>),
+    Wildcard,
+    NoneOf(&'r [T]),
+    Epsilon,
+    Never,
+    Repeat(&'r RePriv<T>, usize, usize),
+    AnyOf(&'r [T]),
+    Range(ReRangeRef<'r, T>),
+    Interleave(Vec<&'r RePriv<T>>),
+    Group(&'r RePriv<T>, &'r str),
+}
+
+// operands of the binary pattern `re` and of the same patterns nested in
+// its first operand, in order, so that the left-nested chains built by
+// `Re::parse` or `+` take a single level
+fn operand_list<'r, T>(
+    re: &'r RePriv<T>,
+    split: impl Fn(&'r RePriv<T>) -> Option<(&'r RePriv<T>, &'r RePriv<T>)>,
+) -> Vec<&'r RePriv<T>> {
+    let mut operand_list = Vec::new();
+    let mut re = re;
+    while let Some((first, second)) = split(re) {
+        operand_list.push(second);
+        re = first;
+    }
+    operand_list.push(re);
+    operand_list.reverse();
+    operand_list
+}
+
+fn split_concat<T>(re: &RePriv<T>) -> Option<(&RePriv<T>, &RePriv<T>)> {
+    match re {
+        RePriv::Concat(first, second) => Some((first, second)),
+        _ => None,
+    }
+}
+
+fn split_either<T>(re: &RePriv<T>) -> Option<(&RePriv<T>, &RePriv<T>)> {
+    match re {
+        RePriv::Either(first, second) => Some((first, second)),
+        _ => None,
+    }
+}
+
+fn split_interleave<T>(re: &RePriv<T>) -> Option<(&RePriv<T>, &RePriv<T>)> {
+    match re {
+        RePriv::Interleave(first, second) => Some((first, second)),
+        _ => None,
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ReRangeRef<'r, T> {
+    start: &'r T,
+    end: &'r T,
+    #[serde(skip_serializing_if = "is_inclusive")]
+    start_inclusive: bool,
+    #[serde(skip_serializing_if = "is_inclusive")]
+    end_inclusive: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReRangeRepr<T> {
+    start: T,
+    end: T,
+    #[serde(default = "inclusive")]
+    start_inclusive: bool,
+    #[serde(default = "inclusive")]
+    end_inclusive: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ReTag {
+    Plain,
+    ZeroOrMore,
+    OneOrMore,
+    Optional,
+    Concat,
+    Either,
+    Wildcard,
+    NoneOf,
+    Epsilon,
+    Never,
+    Repeat,
+    AnyOf,
+    Range,
+    Interleave,
+    Group,
+}
+
+const RE_VARIANTS: &[&str] = &[
+    "plain",
+    "zero_or_more",
+    "one_or_more",
+    "optional",
+    "concat",
+    "either",
+    "wildcard",
+    "none_of",
+    "epsilon",
+    "never",
+    "repeat",
+    "any_of",
+    "range",
+    "interleave",
+    "group",
+];
+
+impl<T> Serialize for RePriv<T>
+where
+    T: Serialize,
+{
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let re_ref = match self {
+            RePriv::Plain(trans) => ReRef::Plain(trans),
+            RePriv::ZeroOrMore(inner) => ReRef::ZeroOrMore(inner),
+            RePriv::OneOrMore(inner) => ReRef::OneOrMore(inner),
+            RePriv::Optional(inner) => ReRef::Optional(inner),
+            RePriv::Concat(_, _) => ReRef::Concat(operand_list(self, split_concat)),
+            RePriv::Either(_, _) => ReRef::Either(operand_list(self, split_either)),
+            RePriv::Wildcard => ReRef::Wildcard,
+            RePriv::NoneOf(excluded) => ReRef::NoneOf(excluded),
+            RePriv::Epsilon => ReRef::Epsilon,
+            RePriv::Never => ReRef::Never,
+            RePriv::Repeat(inner, min, max) => ReRef::Repeat(inner, *min, *max),
+            RePriv::AnyOf(items) => ReRef::AnyOf(items),
+            RePriv::Range(range) => ReRef::Range(ReRangeRef {
+                start: range.start(),
+                end: range.end(),
+                start_inclusive: range.contains(range.start()),
+                end_inclusive: range.contains(range.end()),
+            }),
+            RePriv::Interleave(_, _) => ReRef::Interleave(operand_list(self, split_interleave)),
+            RePriv::Group(inner, name) => ReRef::Group(inner, name),
+        };
+        re_ref.serialize(serializer)
+    }
+}
+
+/// Externally tagged by the name of the constructor: `"wildcard"`,
+/// `"epsilon"` and `"never"` as plain strings, and otherwise a single entry
+/// map such as `{ "plain": "a" }`, `{ "zero_or_more": <pattern> }`,
+/// `{ "concat": [<pattern>, <pattern>, ...] }`, `{ "any_of": ["a", "b"] }`,
+/// `{ "repeat": [<pattern>, min, max] }`, `{ "group": [<pattern>, "name"] }`
+/// or `{ "range": { "start": "a", "end": "z" } }`. Concatenations,
+/// alternatives and interleavings nested in their first operand are listed
+/// as one, so that long literals stay flat.
+impl<T> Serialize for Re<T>
+where
+    T: Serialize,
+{
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Fails on patterns nested deeper than 256 levels, see
+/// `deserialize_with_max_depth`. Formats may have a lower limit of their
+/// own, such as the recursion limit of serde_json.
+impl<'de, T> Deserialize<'de> for Re<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_with_max_depth(deserializer, DEFAULT_MAX_DEPTH)
+    }
+}
+
+impl<T> Re<T>
+where
+    T: Ord,
+{
+    /// Deserialize a pattern, failing before nesting deeper than `max_depth`
+    /// levels as counted by `max_depth`, so that untrusted documents cannot
+    /// overflow the stack.
+    pub fn deserialize_with_max_depth<'de, D>(
+        deserializer: D,
+        max_depth: usize,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        ReSeed::new(max_depth).deserialize(deserializer).map(Re)
+    }
+}
+
+// deserializes a pattern allowed to nest `depth_left` more levels
+struct ReSeed<T> {
+    depth_left: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T> ReSeed<T> {
+    fn new(depth_left: usize) -> Self {
+        Self {
+            depth_left,
+            marker: PhantomData,
+        }
+    }
+
+    fn inner<E>(&self) -> Result<Self, E>
+    where
+        E: de::Error,
+    {
+        match self.depth_left {
+            0 | 1 => Err(E::custom("pattern nested too deeply")),
+            depth_left => Ok(Self::new(depth_left - 1)),
+        }
+    }
+}
+
+impl<'de, T> DeserializeSeed<'de> for ReSeed<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = RePriv<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if self.depth_left == 0 {
+            return Err(de::Error::custom("pattern nested too deeply"));
+        }
+        deserializer.deserialize_enum("Re", RE_VARIANTS, self)
+    }
+}
+
+impl<'de, T> Visitor<'de> for ReSeed<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = RePriv<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a pattern")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (tag, variant) = data.variant()?;
+        Ok(match tag {
+            ReTag::Plain => RePriv::Plain(variant.newtype_variant()?),
+            ReTag::ZeroOrMore => {
+                RePriv::ZeroOrMore(Box::new(variant.newtype_variant_seed(self.inner()?)?))
+            }
+            ReTag::OneOrMore => {
+                RePriv::OneOrMore(Box::new(variant.newtype_variant_seed(self.inner()?)?))
+            }
+            ReTag::Optional => {
+                RePriv::Optional(Box::new(variant.newtype_variant_seed(self.inner()?)?))
+            }
+            ReTag::Concat => variant
+                .newtype_variant_seed(OperandSeed(self.inner()?))?
+                .into_iter()
+                .reduce(|first, second| RePriv::Concat(Box::new(first), Box::new(second)))
+                .unwrap(),
+            ReTag::Either => variant
+                .newtype_variant_seed(OperandSeed(self.inner()?))?
+                .into_iter()
+                .reduce(|first, second| RePriv::Either(Box::new(first), Box::new(second)))
+                .unwrap(),
+            ReTag::Interleave => variant
+                .newtype_variant_seed(OperandSeed(self.inner()?))?
+                .into_iter()
+                .reduce(|first, second| RePriv::Interleave(Box::new(first), Box::new(second)))
+                .unwrap(),
+            ReTag::Wildcard => {
+                variant.unit_variant()?;
+                RePriv::Wildcard
+            }
+            ReTag::Epsilon => {
+                variant.unit_variant()?;
+                RePriv::Epsilon
+            }
+            ReTag::Never => {
+                variant.unit_variant()?;
+                RePriv::Never
+            }
+            ReTag::NoneOf => RePriv::NoneOf(variant.newtype_variant()?),
+            ReTag::AnyOf => RePriv::AnyOf(variant.newtype_variant()?),
+            ReTag::Range => {
+                let range: ReRangeRepr<T> = variant.newtype_variant()?;
+                RePriv::Range(TransRange::with_cmp(
+                    range.start,
+                    range.start_inclusive,
+                    range.end,
+                    range.end_inclusive,
+                    T::cmp,
+                ))
+            }
+            ReTag::Repeat => {
+                let (inner, min, max) = variant.tuple_variant(3, RepeatVisitor(self.inner()?))?;
+                if min > max {
+                    return Err(de::Error::custom("repetition with min above max"));
+                }
+                RePriv::Repeat(inner, min, max)
+            }
+            ReTag::Group => {
+                let (inner, name) = variant.tuple_variant(2, GroupVisitor(self.inner()?))?;
+                RePriv::Group(inner, name)
+            }
+        })
+    }
+}
+
+// operands of a binary pattern, at least two
+struct OperandSeed<T>(ReSeed<T>);
+
+impl<'de, T> DeserializeSeed<'de> for OperandSeed<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = Vec<RePriv<T>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T> Visitor<'de> for OperandSeed<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = Vec<RePriv<T>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of at least two patterns")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut operand_list = Vec::new();
+        while let Some(operand) = seq.next_element_seed(ReSeed::new(self.0.depth_left))? {
+            operand_list.push(operand);
+        }
+        if operand_list.len() < 2 {
+            return Err(de::Error::invalid_length(operand_list.len(), &self));
+        }
+        Ok(operand_list)
+    }
+}
+
+// an inner pattern with its bounds
+struct RepeatVisitor<T>(ReSeed<T>);
+
+impl<'de, T> Visitor<'de> for RepeatVisitor<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = (Box<RePriv<T>>, usize, usize);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a pattern followed by its bounds")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let expected = "a pattern followed by its bounds";
+        let inner = seq
+            .next_element_seed(self.0)?
+            .ok_or_else(|| de::Error::invalid_length(0, &expected))?;
+        let min = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &expected))?;
+        let max = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &expected))?;
+        Ok((Box::new(inner), min, max))
+    }
+}
+
+// an inner pattern with its name
+struct GroupVisitor<T>(ReSeed<T>);
+
+impl<'de, T> Visitor<'de> for GroupVisitor<T>
+where
+    T: Ord + Deserialize<'de>,
+{
+    type Value = (Box<RePriv<T>>, String);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a pattern followed by its name")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let expected = "a pattern followed by its name";
+        let inner = seq
+            .next_element_seed(self.0)?
+            .ok_or_else(|| de::Error::invalid_length(0, &expected))?;
+        let name = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &expected))?;
+        Ok((Box::new(inner), name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auto::Auto;
+    use crate::nfa::{NFAutoBlueprint, NFAutoBuilder};
+    use crate::re::Re;
+    use serde::Deserialize;
+
+    #[test]
+    fn sorted_output() {
+        let nfa = NFAutoBuilder::start(0)
+            .connect(0, 'b', 2)
+            .connect(0, 'a', 1)
+            .connect_wildcard_except(1, vec!['y', 'x'], 2)
+            .accept_with(2, 1)
+            .accept_with(2, 0)
+            .finalize();
+        let reversed = NFAutoBuilder::start(0)
+            .accept_with(2, 0)
+            .accept_with(2, 1)
+            .connect_wildcard_except(1, vec!['x', 'y'], 2)
+            .connect(0, 'a', 1)
+            .connect(0, 'b', 2)
+            .finalize();
+        let json = serde_json::to_value(&nfa).unwrap();
+        assert_eq!(serde_json::to_value(&reversed).unwrap(), json);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "states": [0, 1, 2],
+                "start": 0,
+                "accept": [2],
+                "accept_tags": [{ "state": 2, "tags": [0, 1] }],
+                "plain": [
+                    { "from": 0, "trans": "a", "to": 1 },
+                    { "from": 0, "trans": "b", "to": 2 },
+                ],
+                "wildcard_except": [{ "from": 1, "excluded": ["x", "y"], "to": 2 }],
+            })
+        );
+        let restored: NFAutoBlueprint<u32, char> = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, nfa);
+
+        // empty sections are left out
+        let json = serde_json::to_value(NFAutoBuilder::<_, char>::start(0).finalize()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "version": 1, "states": [0], "start": 0 })
+        );
+    }
+
+    #[test]
+    fn compiled_round_trip() {
+        let nfa = Re::parse("(ab|[c-e]x?)*[^q]").unwrap().compile();
+        let json = serde_json::to_string(&nfa).unwrap();
+        let restored: NFAutoBlueprint<u64, char> = serde_json::from_str(&json).unwrap();
+        for input in ["abz", "cxda", "q", "abq", ""].iter() {
+            assert_eq!(
+                restored.create().test(input.chars()),
+                nfa.create().test(input.chars())
+            );
+        }
+    }
+
+    #[test]
+    fn read_fixture() {
+        let nfa: NFAutoBlueprint<u32, char> =
+            serde_json::from_str(include_str!("../fixtures/nfa_v1.json")).unwrap();
+        let expected = NFAutoBuilder::start(0)
+            .also_start(5)
+            .connect(0, 'a', 1)
+            .connect_void(1, 2)
+            .connect_wildcard(2, 3)
+            .connect_wildcard_except(3, vec!['x', 'y'], 4)
+            .connect_range(4, '0'..='9', 4)
+            .connect(5, 'z', 4)
+            .accept_with(4, 1)
+            .accept_with(4, 0)
+            .finalize();
+        assert_eq!(nfa, expected);
+        assert!(nfa.create().test("a!7".chars()));
+        assert!(nfa.create().test("z".chars()));
+        assert!(!nfa.create().test("ax".chars()));
+    }
+
+    #[test]
+    fn reject_inconsistent() {
+        let read = |json: &str| serde_json::from_str::<NFAutoBlueprint<u32, char>>(json);
+        assert!(read(r#"{ "version": 1, "states": [0], "start": 0, "accept": [1] }"#).is_err());
+        assert!(read(
+            r#"{ "version": 1, "states": [0], "start": 0,
+                "void": [{ "from": 0, "to": 2 }] }"#
+        )
+        .is_err());
+        assert!(read(r#"{ "version": 2, "states": [0], "start": 0 }"#).is_err());
+        assert!(read(r#"{ "version": 1, "states": [0], "start": 0, "lambda": [] }"#).is_err());
+        assert!(read(r#"{ "version": 1, "states": [0, 1], "start": 0, "accept": [1] }"#).is_ok());
+
+        let nfa = NFAutoBuilder::start(0)
+            .connect_if(0, |c: &char| c.is_alphabetic(), 1)
+            .finalize();
+        assert!(serde_json::to_string(&nfa).is_err());
+    }
+
+    #[test]
+    fn re_round_trip() {
+        let re = (Re::plain('a') + Re::range('0'..='9').plus()) | Re::wildcard();
+        let json = serde_json::to_value(&re).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "either": [
+                { "concat": [
+                    { "plain": "a" },
+                    { "one_or_more": { "range": { "start": "0", "end": "9" } } },
+                ] },
+                "wildcard",
+            ] })
+        );
+        assert_eq!(serde_json::from_value::<Re<char>>(json).unwrap(), re);
+
+        let alphabet: Vec<_> = "abcxyz019_".chars().collect();
+        for pattern in [
+            "(ab|c)*x?",
+            "[^xy]+z",
+            "(?<word>[a-c]+)_[0-9]",
+            "ab&xy",
+            "()|[]",
+        ]
+        .iter()
+        {
+            let re = Re::parse(pattern).unwrap();
+            let json = serde_json::to_string(&re).unwrap();
+            let restored: Re<char> = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, re);
+            assert!(restored.equivalent(&re, alphabet.clone()));
+        }
+    }
+
+    #[test]
+    fn re_flat_operands() {
+        let json = serde_json::to_value(Re::parse("abc|d|ef").unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "either": [
+                { "concat": [{ "plain": "a" }, { "plain": "b" }, { "plain": "c" }] },
+                { "plain": "d" },
+                { "concat": [{ "plain": "e" }, { "plain": "f" }] },
+            ] })
+        );
+        // right-nested operands stay nested
+        let re = Re::plain('a') + (Re::plain('b') + Re::plain('c'));
+        let json = serde_json::to_string(&re).unwrap();
+        assert_eq!(serde_json::from_str::<Re<char>>(&json).unwrap(), re);
+        assert!(serde_json::from_str::<Re<char>>(r#"{ "concat": [{ "plain": "a" }] }"#).is_err());
+
+        let literal: String = "abcdefghij".repeat(100);
+        let re = Re::parse(&literal).unwrap();
+        let json = serde_json::to_string(&re).unwrap();
+        let restored: Re<char> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, re);
+        assert!(restored.compile().create().test(literal.chars()));
+    }
+
+    #[test]
+    fn re_depth_limit() {
+        let nested = |depth: usize| {
+            "{\"optional\":".repeat(depth - 1) + "\"epsilon\"" + &"}".repeat(depth - 1)
+        };
+        let read = |json: &str, max_depth: usize| {
+            let mut deserializer = serde_json::Deserializer::from_str(json);
+            deserializer.disable_recursion_limit();
+            Re::<char>::deserialize_with_max_depth(&mut deserializer, max_depth)
+        };
+        let re = read(&nested(10), 10).unwrap();
+        assert_eq!(re.max_depth(), 10);
+        assert!(read(&nested(11), 10).is_err());
+
+        // pairs count the same
+        let json = r#"{ "concat": [{ "plain": "a" }, { "optional": { "plain": "b" } }] }"#;
+        assert!(read(json, 3).is_ok());
+        assert!(read(json, 2).is_err());
+        assert!(read(r#"{ "repeat": [{ "plain": "a" }, 3, 1] }"#, 10).is_err());
+
+        // the default limit, past the recursion limit of serde_json
+        let read_default = |json: &str| {
+            let mut deserializer = serde_json::Deserializer::from_str(json);
+            deserializer.disable_recursion_limit();
+            Re::<char>::deserialize(&mut deserializer)
+        };
+        assert_eq!(read_default(&nested(256)).unwrap().max_depth(), 256);
+        assert!(read_default(&nested(257)).is_err());
+        assert!(read_default(&nested(1_000_000)).is_err());
+        assert!(serde_json::from_str::<Re<char>>(&nested(1_000_000)).is_err());
+
+        // patterns are serialized whatever their depth
+        let mut re = Re::any_of("ab".chars());
+        for _ in 1..120 {
+            re = Re::plain('x') + re.opt();
+        }
+        let json = serde_json::to_string(&re).unwrap();
+        assert_eq!(read_default(&json).unwrap(), re);
+    }
+}
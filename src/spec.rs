@@ -0,0 +1,261 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// Definition of a machine as found in configuration files, to be
+/// deserialized from any format and turned into a blueprint by
+/// `DFAutoBlueprint::from_spec`. Every state and event has to be declared.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MachineSpec {
+    pub states: Vec<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub transitions: Vec<TransitionSpec>,
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackSpec>,
+    #[serde(default)]
+    pub accepts: Vec<String>,
+    pub start: Option<String>,
+}
+
+/// A transition from `from` to `to` on `event`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionSpec {
+    pub from: String,
+    pub event: String,
+    pub to: String,
+}
+
+/// A transition from `from` to `to` on any event without a transition.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FallbackSpec {
+    pub from: String,
+    pub to: String,
+}
+
+/// Error validating a `MachineSpec`, naming the offending entry like
+/// `transitions[2]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    pub entry: String,
+    pub message: String,
+}
+
+impl SpecError {
+    fn new(entry: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            entry: entry.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.entry, self.message)
+    }
+}
+
+impl Error for SpecError {}
+
+// the set of `name_list`, failing on the first repeated name
+fn declared<'s>(
+    name_list: &'s [String],
+    section: &str,
+    kind: &str,
+) -> Result<HashSet<&'s str>, SpecError> {
+    let mut name_set = HashSet::new();
+    for (index, name) in name_list.iter().enumerate() {
+        if !name_set.insert(name.as_str()) {
+            return Err(SpecError::new(
+                format!("{}[{}]", section, index),
+                format!("repeated {} `{}`", kind, name),
+            ));
+        }
+    }
+    Ok(name_set)
+}
+
+impl DFAutoBlueprint<String, String> {
+    /// Build a blueprint from `spec`. Fails on a repeated state or event, on
+    /// a reference to an undeclared one, on a missing start state, and on a
+    /// second transition or fallback from the same state on the same event.
+    pub fn from_spec(spec: MachineSpec) -> Result<Self, SpecError> {
+        let state_set = declared(&spec.states, "states", "state")?;
+        let event_set = declared(&spec.events, "events", "event")?;
+        let check_state = |entry: &dyn Fn() -> String, state: &str| {
+            if state_set.contains(state) {
+                Ok(())
+            } else {
+                Err(SpecError::new(
+                    entry(),
+                    format!("unknown state `{}`", state),
+                ))
+            }
+        };
+
+        let start = spec
+            .start
+            .as_ref()
+            .ok_or_else(|| SpecError::new("start", "missing start state"))?;
+        check_state(&|| "start".to_string(), start)?;
+        for (index, state) in spec.accepts.iter().enumerate() {
+            check_state(&|| format!("accepts[{}]", index), state)?;
+        }
+        // index of the transition taken from every state on every event
+        let mut edge_map = HashMap::new();
+        for (index, transition) in spec.transitions.iter().enumerate() {
+            let entry = || format!("transitions[{}]", index);
+            check_state(&entry, &transition.from)?;
+            check_state(&entry, &transition.to)?;
+            if !event_set.contains(transition.event.as_str()) {
+                return Err(SpecError::new(
+                    entry(),
+                    format!("unknown event `{}`", transition.event),
+                ));
+            }
+            if let Some(known) = edge_map.insert((&transition.from, &transition.event), index) {
+                return Err(SpecError::new(
+                    entry(),
+                    format!(
+                        "transition from `{}` on `{}` already given by transitions[{}]",
+                        transition.from, transition.event, known
+                    ),
+                ));
+            }
+        }
+        let mut fallback_map = HashMap::new();
+        for (index, fallback) in spec.fallbacks.iter().enumerate() {
+            let entry = || format!("fallbacks[{}]", index);
+            check_state(&entry, &fallback.from)?;
+            check_state(&entry, &fallback.to)?;
+            if let Some(known) = fallback_map.insert(&fallback.from, index) {
+                return Err(SpecError::new(
+                    entry(),
+                    format!(
+                        "fallback from `{}` already given by fallbacks[{}]",
+                        fallback.from, known
+                    ),
+                ));
+            }
+        }
+
+        let mut builder = DFAutoBuilder::start(start.clone());
+        for state in spec.accepts {
+            builder = builder.accept(state);
+        }
+        for transition in spec.transitions {
+            builder = builder.connect(transition.from, transition.event, transition.to);
+        }
+        for fallback in spec.fallbacks {
+            builder = builder.connect_fallback(fallback.from, fallback.to);
+        }
+        Ok(builder.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::Auto;
+
+    const TOML_SPEC: &str = r#"
+        states = ["idle", "running", "done"]
+        events = ["go", "stop"]
+        accepts = ["done"]
+        start = "idle"
+
+        [[transitions]]
+        from = "idle"
+        event = "go"
+        to = "running"
+
+        [[transitions]]
+        from = "running"
+        event = "stop"
+        to = "done"
+
+        [[fallbacks]]
+        from = "running"
+        to = "running"
+    "#;
+
+    const YAML_SPEC: &str = "
+states: [idle, running, done]
+events: [go, stop]
+accepts: [done]
+start: idle
+transitions:
+  - { from: idle, event: go, to: running }
+  - { from: running, event: stop, to: done }
+fallbacks:
+  - { from: running, to: running }
+";
+
+    fn run(dfa: &DFAutoBlueprint<String, String>, input: &[&str]) -> bool {
+        let input: Vec<_> = input.iter().map(|event| event.to_string()).collect();
+        dfa.create().test(&input)
+    }
+
+    #[test]
+    fn toml_and_yaml() {
+        let spec: MachineSpec = toml::from_str(TOML_SPEC).unwrap();
+        assert_eq!(spec, serde_yaml::from_str(YAML_SPEC).unwrap());
+        let dfa = DFAutoBlueprint::from_spec(spec).unwrap();
+        assert!(run(&dfa, &["go", "stop"]));
+        assert!(run(&dfa, &["go", "go", "stop"]));
+        assert!(!run(&dfa, &["go"]));
+        assert!(!run(&dfa, &["stop"]));
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let error = |yaml: &str| {
+            let spec: MachineSpec = serde_yaml::from_str(yaml).unwrap();
+            DFAutoBlueprint::from_spec(spec).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error("states: [a]\nevents: [x]"),
+            "start: missing start state"
+        );
+        assert_eq!(
+            error("states: [a, b, a]\nstart: a"),
+            "states[2]: repeated state `a`"
+        );
+        assert_eq!(
+            error("states: [a]\nstart: a\naccepts: [b]"),
+            "accepts[0]: unknown state `b`"
+        );
+        assert_eq!(
+            error("states: [a]\nstart: a\ntransitions: [{ from: a, event: x, to: a }]"),
+            "transitions[0]: unknown event `x`"
+        );
+        assert_eq!(
+            error(concat!(
+                "states: [a, b]\nevents: [x]\nstart: a\ntransitions:\n",
+                "  - { from: a, event: x, to: a }\n",
+                "  - { from: b, event: x, to: c }\n",
+            )),
+            "transitions[1]: unknown state `c`"
+        );
+        assert_eq!(
+            error(concat!(
+                "states: [a, b]\nevents: [x]\nstart: a\ntransitions:\n",
+                "  - { from: a, event: x, to: a }\n",
+                "  - { from: a, event: x, to: b }\n",
+            )),
+            "transitions[1]: transition from `a` on `x` already given by transitions[0]"
+        );
+        assert_eq!(
+            error("states: [a]\nstart: a\nfallbacks: [{ from: a, to: a }, { from: a, to: a }]"),
+            "fallbacks[1]: fallback from `a` already given by fallbacks[0]"
+        );
+        assert!(serde_yaml::from_str::<MachineSpec>("states: [a]\nstart: a\nfinal: [a]").is_err());
+    }
+}
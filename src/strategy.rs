@@ -0,0 +1,197 @@
+use crate::dfa::{DFAutoBlueprint, DFAutoBuilder};
+use crate::nfa::{NFAutoBlueprint, NFAutoBuilder};
+use crate::re::Re;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Range;
+
+// most connections generated per state, on average
+const CONN_PER_STATE: usize = 3;
+
+/// DFAs over `alphabet` with a number of states in `states`, numbered from 0
+/// which is the start state. Shrinking removes states, connections and accept
+/// states. Connections touching a removed state are removed with it, and only
+/// the first connection on a transition is kept, so every value stays
+/// deterministic.
+pub fn arb_dfa<T>(
+    states: Range<usize>,
+    alphabet: Vec<T>,
+) -> impl Strategy<Value = DFAutoBlueprint<u32, T>>
+where
+    T: Hash + Eq + Clone + Debug,
+{
+    let alphabet = dedup(alphabet);
+    let max_count = max_state_count(&states);
+    (
+        states,
+        vec(
+            (0..max_count, 0..alphabet.len(), 0..max_count),
+            0..=max_count as usize * CONN_PER_STATE,
+        ),
+        vec(0..max_count, 0..=max_count as usize),
+    )
+        .prop_map(move |(count, conn_list, accept_list)| {
+            let count = count as u32;
+            let mut builder = DFAutoBuilder::start(0);
+            let mut connected = HashSet::new();
+            for (from, index, to) in conn_list {
+                if from < count && to < count && connected.insert((from, index)) {
+                    builder = builder.connect(from, alphabet[index].clone(), to);
+                }
+            }
+            for state in accept_list {
+                if state < count {
+                    builder = builder.accept(state);
+                }
+            }
+            builder.finalize()
+        })
+}
+
+/// NFAs over `alphabet` with plain and void connections, shaped and shrunk
+/// as in `arb_dfa` except that several connections may share a transition.
+pub fn arb_nfa<T>(
+    states: Range<usize>,
+    alphabet: Vec<T>,
+) -> impl Strategy<Value = NFAutoBlueprint<u32, T>>
+where
+    T: Hash + Eq + Clone + Debug,
+{
+    let alphabet = dedup(alphabet);
+    let max_count = max_state_count(&states);
+    (
+        states,
+        // `None` stands for a void connection
+        vec(
+            (0..max_count, option::of(0..alphabet.len()), 0..max_count),
+            0..=max_count as usize * CONN_PER_STATE,
+        ),
+        vec(0..max_count, 0..=max_count as usize),
+    )
+        .prop_map(move |(count, conn_list, accept_list)| {
+            let count = count as u32;
+            let mut builder = NFAutoBuilder::start(0);
+            for (from, index, to) in conn_list {
+                if from < count && to < count {
+                    builder = match index {
+                        Some(index) => builder.connect(from, alphabet[index].clone(), to),
+                        None => builder.connect_void(from, to),
+                    };
+                }
+            }
+            for state in accept_list {
+                if state < count {
+                    builder = builder.accept(state);
+                }
+            }
+            builder.finalize()
+        })
+}
+
+/// Patterns over `alphabet` nested at most `depth` levels deep, shrinking
+/// towards their inner patterns.
+pub fn arb_re<T>(alphabet: Vec<T>, depth: u32) -> impl Strategy<Value = Re<T>>
+where
+    T: Clone + Debug + 'static,
+{
+    assert!(!alphabet.is_empty(), "empty alphabet");
+    let leaf = prop_oneof![
+        4 => select(alphabet).prop_map(Re::plain),
+        1 => Just(Re::epsilon()),
+        1 => Just(Re::never()),
+        1 => Just(Re::wildcard()),
+    ];
+    leaf.prop_recursive(depth, 1 << depth.min(6), 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(Re::star),
+            inner.clone().prop_map(Re::opt),
+            (inner.clone(), inner.clone()).prop_map(|(first, second)| first + second),
+            (inner.clone(), inner).prop_map(|(first, second)| first | second),
+        ]
+    })
+}
+
+// alphabet without repeated items, which would connect the same transition
+// twice
+fn dedup<T>(alphabet: Vec<T>) -> Vec<T>
+where
+    T: Hash + Eq + Clone,
+{
+    let mut seen = HashSet::new();
+    let alphabet: Vec<_> = alphabet
+        .into_iter()
+        .filter(|trans| seen.insert(trans.clone()))
+        .collect();
+    assert!(!alphabet.is_empty(), "empty alphabet");
+    alphabet
+}
+
+fn max_state_count(states: &Range<usize>) -> u32 {
+    assert!(
+        states.start >= 1 && states.start < states.end,
+        "state count range without a positive count"
+    );
+    (states.end - 1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::auto::Auto;
+    use proptest::test_runner::{TestError, TestRunner};
+
+    fn arb_input() -> impl Strategy<Value = Vec<Vec<char>>> {
+        vec(vec(select(vec!['a', 'b', 'c']), 0..8), 16)
+    }
+
+    proptest! {
+        #[test]
+        fn determinize_preserves_language(
+            nfa in arb_nfa(1..6, vec!['a', 'b']),
+            input_list in arb_input(),
+        ) {
+            let dfa = determinize(&nfa).unwrap();
+            for input in &input_list {
+                prop_assert_eq!(nfa.create().test(input), dfa.create().test(input));
+            }
+        }
+
+        #[test]
+        fn compiled_pattern_agrees(
+            re in arb_re(vec!['a', 'b'], 4),
+            input_list in arb_input(),
+        ) {
+            let nfa = re.compile();
+            let dfa = determinize(&nfa).unwrap();
+            for input in &input_list {
+                prop_assert_eq!(nfa.create().test(input), dfa.create().test(input));
+            }
+        }
+
+        #[test]
+        fn dfa_stays_deterministic(dfa in arb_dfa(1..6, vec!['a', 'b', 'a'])) {
+            prop_assert_eq!(dfa.start_state(), &0);
+            dfa.create().test(&['a', 'b', 'a']);
+        }
+    }
+
+    #[test]
+    fn shrink_to_single_state() {
+        let result = TestRunner::default().run(&arb_dfa(1..8, vec!['a', 'b']), |dfa| {
+            prop_assert!(dfa.accept_state_set().is_empty());
+            Ok(())
+        });
+        match result {
+            Err(TestError::Fail(_, dfa)) => {
+                assert_eq!(dfa, DFAutoBuilder::start(0).accept(0).finalize())
+            }
+            _ => panic!("property not falsified"),
+        }
+    }
+}
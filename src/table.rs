@@ -0,0 +1,252 @@
+use crate::dfa::DFAutoBlueprint;
+use crate::nfa::{ConnType, NFAutoBlueprint};
+use crate::range::TransRange;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+// what a column of the table is taken on, in column order
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Column<'b, T> {
+    Plain(&'b T),
+    // bounds with whether they are included
+    Range(&'b T, bool, &'b T, bool),
+    WildcardExcept(Vec<&'b T>),
+    Wildcard,
+    Void,
+    Predicate,
+    Fallback,
+}
+
+impl<'b, T> Column<'b, T>
+where
+    T: Debug,
+{
+    fn range(range: &'b TransRange<T>) -> Self {
+        Column::Range(
+            range.start(),
+            range.contains(range.start()),
+            range.end(),
+            range.contains(range.end()),
+        )
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Column::Plain(trans) => format!("{:?}", trans),
+            Column::Range(start, start_inclusive, end, end_inclusive) => format!(
+                "{}{:?}, {:?}{}",
+                if *start_inclusive { '[' } else { '(' },
+                start,
+                end,
+                if *end_inclusive { ']' } else { ')' }
+            ),
+            Column::WildcardExcept(excluded) => format!("·∖{:?}", excluded),
+            Column::Wildcard => "·".to_string(),
+            Column::Void => "ε".to_string(),
+            Column::Predicate => "?".to_string(),
+            Column::Fallback => "otherwise".to_string(),
+        }
+    }
+}
+
+// targets of every state under every column, with a row for every state
+// given or connected
+struct Table<'b, S, T> {
+    row_set: BTreeSet<&'b S>,
+    cell_map: BTreeMap<Column<'b, T>, BTreeMap<&'b S, BTreeSet<&'b S>>>,
+}
+
+impl<'b, S, T> Table<'b, S, T>
+where
+    S: Ord + Debug,
+    T: Ord + Debug,
+{
+    fn new(row_set: BTreeSet<&'b S>) -> Self {
+        Self {
+            row_set,
+            cell_map: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, from: &'b S, column: Column<'b, T>, to: &'b S) {
+        self.row_set.extend(&[from, to]);
+        self.cell_map
+            .entry(column)
+            .or_default()
+            .entry(from)
+            .or_default()
+            .insert(to);
+    }
+
+    // rows start with `>` for start states and `*` for accept states, and
+    // cells hold the target, or the set of targets if `set_valued`
+    fn render(
+        self,
+        is_start: impl Fn(&S) -> bool,
+        is_accept: impl Fn(&S) -> bool,
+        set_valued: bool,
+    ) -> String {
+        let mut line_list = vec![vec![String::new(), String::new()]];
+        line_list[0].extend(self.cell_map.keys().map(Column::label));
+        for state in &self.row_set {
+            let mark = format!(
+                "{}{}",
+                if is_start(state) { ">" } else { " " },
+                if is_accept(state) { "*" } else { " " }
+            );
+            let mut line = vec![mark, format!("{:?}", state)];
+            for cell in self.cell_map.values() {
+                line.push(match cell.get(state) {
+                    None => "-".to_string(),
+                    Some(to_set) if set_valued => format!("{:?}", to_set),
+                    Some(to_set) => to_set
+                        .iter()
+                        .map(|to| format!("{:?}", to))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            }
+            line_list.push(line);
+        }
+
+        let width_list: Vec<_> = (0..line_list[0].len())
+            .map(|column| {
+                line_list
+                    .iter()
+                    .map(|line| line[column].chars().count())
+                    .max()
+                    .unwrap()
+            })
+            .collect();
+        let mut text = String::new();
+        for line in line_list {
+            let mut row = String::new();
+            for (column, (cell, width)) in line.iter().zip(&width_list).enumerate() {
+                let separator = match column {
+                    0 => "",
+                    1 => " ",
+                    _ => " | ",
+                };
+                write!(row, "{}{:width$}", separator, cell, width = width).unwrap();
+            }
+            writeln!(text, "{}", row.trim_end()).unwrap();
+        }
+        text
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Debug,
+    T: Hash + Eq + Ord + Debug,
+{
+    /// Render as a fixed-width transition table with a row per state and a
+    /// column per transition, followed by columns for ranges and, if any
+    /// state has one, for fallbacks. States and transitions are written by
+    /// `Debug` and sorted. The start state is marked `>` and accept states
+    /// `*`, and missing transitions are written `-`.
+    pub fn to_table_string(&self) -> String {
+        let mut row_set: BTreeSet<_> = self.accept_state_set().iter().collect();
+        row_set.insert(self.start_state());
+        let mut table = Table::new(row_set);
+        for (from, trans, to) in self.iterate_connections() {
+            table.add(from, Column::Plain(trans), to);
+        }
+        for (from, range, to) in self.iterate_range_connections() {
+            table.add(from, Column::range(range), to);
+        }
+        for (from, to) in self.iterate_fallback_connections() {
+            table.add(from, Column::Fallback, to);
+        }
+        table.render(
+            |state| state == self.start_state(),
+            |state| self.accept_state_set().contains(state),
+            false,
+        )
+    }
+}
+
+impl<S, T> NFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Ord + Debug,
+    T: Hash + Eq + Ord + Debug,
+{
+    /// Render as a transition table as in `DFAutoBlueprint`, with cells
+    /// holding sets of targets. After the plain transitions and ranges come
+    /// columns for wildcard-except connections, wildcards (`·`), void
+    /// connections (`ε`) and predicates (`?`), if any.
+    pub fn to_table_string(&self) -> String {
+        let mut table = Table::new(self.state_set().into_iter().collect());
+        for (from, conn, to) in self.iterate_connections() {
+            let column = match conn {
+                ConnType::Plain(trans) => Column::Plain(trans),
+                ConnType::Range(range) => Column::range(range),
+                ConnType::WildcardExcept(excluded) => {
+                    let mut excluded: Vec<_> = excluded.iter().collect();
+                    excluded.sort();
+                    Column::WildcardExcept(excluded)
+                }
+                ConnType::Wildcard => Column::Wildcard,
+                ConnType::Void => Column::Void,
+                ConnType::Predicate(_) => Column::Predicate,
+            };
+            table.add(from, column, to);
+        }
+        let start_set: HashSet<_> = self.start_state_set().collect();
+        table.render(
+            |state| start_set.contains(state),
+            |state| self.accept_state_set().contains(state),
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dfa::DFAutoBuilder;
+    use crate::nfa::NFAutoBuilder;
+
+    #[test]
+    fn dfa_table() {
+        let dfa = DFAutoBuilder::start(0)
+            .connect(0, 'b', 1)
+            .connect(0, 'a', 10)
+            .connect(10, 'a', 10)
+            .connect_range(10, '0'..='9', 1)
+            .connect_fallback(1, 0)
+            .accept(10)
+            .finalize();
+        assert_eq!(
+            dfa.to_table_string(),
+            concat!(
+                "      | 'a' | 'b' | ['0', '9'] | otherwise\n",
+                ">  0  | 10  | 1   | -          | -\n",
+                "   1  | -   | -   | -          | 0\n",
+                " * 10 | 10  | -   | 1          | -\n",
+            )
+        );
+    }
+
+    #[test]
+    fn nfa_table() {
+        let nfa = NFAutoBuilder::start('p')
+            .also_start('q')
+            .connect('p', 0, 'p')
+            .connect('p', 0, 'q')
+            .connect('p', 1, 'r')
+            .connect_void('q', 'r')
+            .connect_wildcard('r', 'p')
+            .accept('r')
+            .finalize();
+        assert_eq!(
+            nfa.to_table_string(),
+            concat!(
+                "       | 0          | 1     | ·     | ε\n",
+                ">  'p' | {'p', 'q'} | {'r'} | -     | -\n",
+                ">  'q' | -          | -     | -     | {'r'}\n",
+                " * 'r' | -          | -     | {'p'} | -\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::iter::Iterator;
+
+/// Borrowed view of an optional map, behaving like an empty map when absent.
+#[derive(Debug)]
+pub struct MapView<'bp, K, V>(Option<&'bp HashMap<K, V>>);
+
+impl<'bp, K, V> Clone for MapView<'bp, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'bp, K, V> Copy for MapView<'bp, K, V> {}
+
+impl<'bp, K, V> MapView<'bp, K, V>
+where
+    K: Hash + Eq,
+{
+    pub(crate) fn new(map: Option<&'bp HashMap<K, V>>) -> Self {
+        Self(map)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&'bp V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.and_then(|map| map.get(key))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'bp K, &'bp V)> {
+        self.0.into_iter().flat_map(|map| map.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.map_or(0, |map| map.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Borrowed view of an optional set, behaving like an empty set when absent.
+#[derive(Debug)]
+pub struct SetView<'bp, S>(Option<&'bp HashSet<S>>);
+
+impl<'bp, S> Clone for SetView<'bp, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'bp, S> Copy for SetView<'bp, S> {}
+
+impl<'bp, S> SetView<'bp, S>
+where
+    S: Hash + Eq,
+{
+    pub(crate) fn new(set: Option<&'bp HashSet<S>>) -> Self {
+        Self(set)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        S: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.is_some_and(|set| set.contains(value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'bp S> {
+        self.0.into_iter().flat_map(|set| set.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.map_or(0, |set| set.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,227 @@
+use crate::auto::Blueprint;
+use crate::dfa::DFAutoBlueprint;
+use crate::lcg::Lcg;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+// random walks per requested witness before giving up
+const WALK_TRY_FACTOR: usize = 64;
+
+/// Inputs accepted and rejected by a blueprint, as returned by
+/// `DFAutoBlueprint::witness_pairs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Witnesses<T> {
+    pub accepted: Vec<Vec<T>>,
+    pub rejected: Vec<Vec<T>>,
+}
+
+impl<T> Witnesses<T>
+where
+    T: Hash + Eq + Clone,
+{
+    // add `input` to the side given by running `blueprint` on it, unless that
+    // side is full or already has it
+    fn add<S>(
+        &mut self,
+        seen: &mut HashSet<Vec<T>>,
+        blueprint: &DFAutoBlueprint<S, T>,
+        input: Vec<T>,
+        count: usize,
+    ) where
+        S: Hash + Eq + Clone,
+    {
+        let side = if blueprint.accepts(&input) {
+            &mut self.accepted
+        } else {
+            &mut self.rejected
+        };
+        if side.len() < count && !seen.contains(&input) {
+            seen.insert(input.clone());
+            side.push(input);
+        }
+    }
+
+    fn is_full(&self, count: usize) -> bool {
+        self.accepted.len() >= count && self.rejected.len() >= count
+    }
+}
+
+impl<S, T> DFAutoBlueprint<S, T>
+where
+    S: Hash + Eq + Clone,
+    T: Hash + Eq + Clone,
+{
+    /// Up to `count` accepted and `count` rejected inputs over `alphabet`, no
+    /// longer than `max_len`, to be kept as regression fixtures. The shortest
+    /// input reaching every state, or leaving the blueprint, comes first, in
+    /// breadth-first order over `alphabet`; random walks from the start
+    /// state drawn from `seed` fill the rest. A walk takes transitions of
+    /// `alphabet` the blueprint has, or ends by taking one it lacks, so that
+    /// rejected inputs come close to accepted ones. Every input is run
+    /// through the blueprint to decide its side, and the result only depends
+    /// on the arguments. Fewer inputs are returned when random walks keep
+    /// missing a side.
+    pub fn witness_pairs(
+        &self,
+        alphabet: &[T],
+        count: usize,
+        max_len: usize,
+        seed: u64,
+    ) -> Witnesses<T> {
+        let mut witnesses = Witnesses {
+            accepted: Vec::new(),
+            rejected: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+
+        // shortest input reaching every state, `None` standing for inputs
+        // without a transition at some point
+        let mut reached = HashMap::new();
+        reached.insert(Some(self.start_state()), Vec::new());
+        let mut unresolved = VecDeque::new();
+        unresolved.push_back(Some(self.start_state()));
+        let mut shortest_list = vec![Vec::new()];
+        while let Some(state) = unresolved.pop_front() {
+            let input = reached[&state].clone();
+            if input.len() >= max_len {
+                continue;
+            }
+            for trans in alphabet {
+                let to = state.and_then(|state| self.next_state(state, trans));
+                if let Entry::Vacant(entry) = reached.entry(to) {
+                    let mut to_input = input.clone();
+                    to_input.push(trans.clone());
+                    shortest_list.push(to_input.clone());
+                    entry.insert(to_input);
+                    unresolved.push_back(to);
+                }
+            }
+        }
+        for input in shortest_list {
+            witnesses.add(&mut seen, self, input, count);
+        }
+
+        let mut rng = Lcg::new(seed);
+        if !alphabet.is_empty() {
+            for _ in 0..count.saturating_mul(2 * WALK_TRY_FACTOR) {
+                if witnesses.is_full(count) {
+                    break;
+                }
+                let input = self.random_walk(alphabet, rng.next(max_len + 1), &mut rng);
+                witnesses.add(&mut seen, self, input, count);
+            }
+        }
+        witnesses
+    }
+
+    // up to `len` transitions of `alphabet` from the start state, each drawn
+    // among the ones the current state has and, as one more choice, the ones
+    // it lacks, which end the walk
+    fn random_walk(&self, alphabet: &[T], len: usize, rng: &mut Lcg) -> Vec<T> {
+        let mut input = Vec::new();
+        let mut state = self.start_state();
+        while input.len() < len {
+            let (taken_list, missing_list): (Vec<_>, Vec<_>) = alphabet
+                .iter()
+                .partition(|trans| self.next_state(state, trans).is_some());
+            let choice_count = taken_list.len() + usize::from(!missing_list.is_empty());
+            let index = rng.next(choice_count);
+            match taken_list.get(index) {
+                Some(trans) => {
+                    input.push((*trans).clone());
+                    state = self.next_state(state, trans).unwrap();
+                }
+                None => {
+                    input.push(missing_list[rng.next(missing_list.len())].clone());
+                    break;
+                }
+            }
+        }
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::determinize;
+    use crate::re::Re;
+
+    #[test]
+    fn verified_witnesses() {
+        let re = Re::plain('a').plus() + Re::any_of("bc".chars()) + Re::plain('a').star();
+        let dfa = determinize(&re.compile()).unwrap().renumbered();
+        let alphabet: Vec<_> = "abcd".chars().collect();
+        let witnesses = dfa.witness_pairs(&alphabet, 10, 6, 1);
+        assert_eq!(witnesses.accepted.len(), 10);
+        assert_eq!(witnesses.rejected.len(), 10);
+        assert_eq!(witnesses.accepted[0], ['a', 'b']);
+        assert_eq!(witnesses.rejected[0], []);
+        for input in &witnesses.accepted {
+            assert!(dfa.accepts(input));
+            assert!(input.len() <= 6);
+        }
+        for input in &witnesses.rejected {
+            assert!(!dfa.accepts(input));
+            assert!(input.len() <= 6);
+        }
+        let mut all: Vec<_> = witnesses
+            .accepted
+            .iter()
+            .chain(&witnesses.rejected)
+            .collect();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 20);
+    }
+
+    #[test]
+    fn walks_follow_transitions() {
+        let dfa = determinize(&Re::literal("abcdef".chars()).compile())
+            .unwrap()
+            .renumbered();
+        let alphabet: Vec<_> = ('a'..='z').collect();
+        let witnesses = dfa.witness_pairs(&alphabet, 40, 6, 3);
+        assert_eq!(witnesses.accepted, ["abcdef".chars().collect::<Vec<_>>()]);
+        assert_eq!(witnesses.rejected.len(), 40);
+        // rejected inputs leave the literal on their last transition at most
+        for input in &witnesses.rejected {
+            let kept = &input[..input.len().saturating_sub(1)];
+            assert!("abcdef".starts_with(&kept.iter().collect::<String>()));
+        }
+    }
+
+    #[test]
+    fn reproducible() {
+        let dfa = determinize(&Re::any_of("ab".chars()).plus().compile())
+            .unwrap()
+            .renumbered();
+        let alphabet: Vec<_> = "abc".chars().collect();
+        let witnesses = dfa.witness_pairs(&alphabet, 10, 5, 7);
+        assert_eq!(witnesses, dfa.witness_pairs(&alphabet, 10, 5, 7));
+        assert_ne!(witnesses, dfa.witness_pairs(&alphabet, 10, 5, 8));
+
+        // only two inputs up to one transition, both rejected
+        let witnesses = dfa.witness_pairs(&['c'], 10, 1, 7);
+        assert!(witnesses.accepted.is_empty());
+        assert_eq!(witnesses.rejected, [vec![], vec!['c']]);
+        assert_eq!(
+            dfa.witness_pairs(&[], 10, 5, 7).rejected,
+            [Vec::<char>::new()]
+        );
+    }
+
+    #[test]
+    fn one_sided() {
+        // every input over the alphabet is accepted
+        let dfa = determinize(&Re::plain('a').star().compile())
+            .unwrap()
+            .renumbered();
+        let witnesses = dfa.witness_pairs(&['a'], 10, 3, 1);
+        assert!(witnesses.rejected.is_empty());
+        let mut accepted = witnesses.accepted;
+        accepted.sort();
+        assert_eq!(accepted, [vec![], vec!['a'], vec!['a'; 2], vec!['a'; 3]]);
+    }
+}